@@ -0,0 +1,65 @@
+use bencher::{benchmark_group, benchmark_main};
+
+use std::io::{self, Cursor, Read, Seek, SeekFrom, Write};
+use std::thread;
+use std::time::Duration;
+
+use bencher::Bencher;
+use getrandom::getrandom;
+use zip::read::ZipReaderConfig;
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+const SIZE: usize = 4 * 1024 * 1024;
+
+/// A reader with a fixed latency per read, like a file on a network filesystem
+struct LatencyReader<R>(R);
+
+impl<R: Read> Read for LatencyReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        thread::sleep(Duration::from_micros(50));
+        self.0.read(buf)
+    }
+}
+
+impl<R: Seek> Seek for LatencyReader<R> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.0.seek(pos)
+    }
+}
+
+fn generate_deflated_archive() -> Vec<u8> {
+    let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+    let options = FileOptions::default().compression_method(CompressionMethod::Deflated);
+    writer.start_file("data.bin", options).unwrap();
+    // Half random, so that the compressed data stays large
+    let mut bytes = vec![0u8; SIZE];
+    getrandom(&mut bytes[..SIZE / 2]).unwrap();
+    writer.write_all(&bytes).unwrap();
+    writer.finish().unwrap().into_inner()
+}
+
+fn read_with_buffer(bench: &mut Bencher, buffer_size: Option<usize>) {
+    let bytes = generate_deflated_archive();
+    let config = ZipReaderConfig::default().buffer_size(buffer_size);
+    let reader = LatencyReader(Cursor::new(bytes.as_slice()));
+    let mut archive = ZipArchive::with_config(reader, config).unwrap();
+
+    bench.iter(|| {
+        let mut file = archive.by_name("data.bin").unwrap();
+        io::copy(&mut file, &mut io::sink()).unwrap();
+    });
+
+    bench.bytes = SIZE as u64;
+}
+
+fn read_unbuffered(bench: &mut Bencher) {
+    read_with_buffer(bench, None);
+}
+
+fn read_buffered_256_kib(bench: &mut Bencher) {
+    read_with_buffer(bench, Some(256 * 1024));
+}
+
+benchmark_group!(benches, read_unbuffered, read_buffered_256_kib);
+benchmark_main!(benches);
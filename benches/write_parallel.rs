@@ -0,0 +1,51 @@
+use bencher::{benchmark_group, benchmark_main};
+
+use std::io::Cursor;
+
+use bencher::Bencher;
+use getrandom::getrandom;
+use zip::write::FileOptions;
+use zip::ZipWriter;
+
+const FILE_COUNT: usize = 16;
+const FILE_SIZE: usize = 1024 * 1024;
+
+fn generate_files() -> Vec<Vec<u8>> {
+    (0..FILE_COUNT)
+        .map(|_| {
+            // Half random, half zeros, so that compressing takes some time
+            let mut bytes = vec![0u8; FILE_SIZE];
+            getrandom(&mut bytes[..FILE_SIZE / 2]).unwrap();
+            bytes
+        })
+        .collect()
+}
+
+fn write_archive(files: &[Vec<u8>], threads: usize) -> Vec<u8> {
+    let mut writer = ZipWriter::new(Cursor::new(Vec::new())).with_parallelism(threads);
+    for (i, data) in files.iter().enumerate() {
+        writer
+            .add_entry(
+                format!("file{i}.dat"),
+                data.as_slice(),
+                FileOptions::default(),
+            )
+            .unwrap();
+    }
+    writer.finish().unwrap().into_inner()
+}
+
+fn write_serial(bench: &mut Bencher) {
+    let files = generate_files();
+    bench.iter(|| write_archive(&files, 1));
+    bench.bytes = (FILE_COUNT * FILE_SIZE) as u64;
+}
+
+fn write_parallel(bench: &mut Bencher) {
+    let files = generate_files();
+    bench.iter(|| write_archive(&files, 4));
+    bench.bytes = (FILE_COUNT * FILE_SIZE) as u64;
+}
+
+benchmark_group!(benches, write_serial, write_parallel);
+benchmark_main!(benches);
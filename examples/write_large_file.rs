@@ -0,0 +1,44 @@
+//! Write a file of more than 4 GiB of zeros, which compresses well.
+//!
+//! The file isn't marked with `FileOptions::large_file`: the writer switches it to ZIP64 once it
+//! turns out to be larger than 4 GiB.
+
+use std::io::prelude::*;
+use zip::write::FileOptions;
+
+fn main() {
+    std::process::exit(real_main());
+}
+
+fn real_main() -> i32 {
+    let args: Vec<_> = std::env::args().collect();
+    if args.len() < 2 {
+        println!("Usage: {} <filename>", args[0]);
+        return 1;
+    }
+
+    let filename = &*args[1];
+    match doit(filename) {
+        Ok(_) => println!("File written to {filename}"),
+        Err(e) => println!("Error: {e:?}"),
+    }
+
+    0
+}
+
+fn doit(filename: &str) -> zip::result::ZipResult<()> {
+    let file = std::fs::File::create(filename)?;
+    let mut zip = zip::ZipWriter::new(file);
+
+    let options = FileOptions::default().unix_permissions(0o644);
+    zip.start_file("huge-file-of-zeros", options)?;
+    let content = vec![0; 1 << 20];
+    let mut bytes_written = 0;
+    while bytes_written <= u32::MAX as u64 {
+        zip.write_all(&content)?;
+        bytes_written += content.len() as u64;
+    }
+
+    zip.finish()?;
+    Ok(())
+}
@@ -1,4 +1,4 @@
-//! Implementation of the AES decryption for zip files.
+//! Implementation of the AES encryption and decryption for zip files.
 //!
 //! This was implemented according to the [WinZip specification](https://www.winzip.com/win/en/aes_info.html).
 //! Note that using CRC with AES depends on the used encryption specification, AE-1 or AE-2.
@@ -9,7 +9,7 @@ use crate::types::AesMode;
 use constant_time_eq::constant_time_eq;
 use hmac::{Hmac, Mac};
 use sha1::Sha1;
-use std::io::{self, Read};
+use std::io::{self, Read, Write};
 
 /// The length of the password verifcation value in bytes
 const PWD_VERIFY_LENGTH: usize = 2;
@@ -18,6 +18,13 @@ const AUTH_CODE_LENGTH: usize = 10;
 /// The number of iterations used with PBKDF2
 const ITERATION_COUNT: u32 = 1000;
 
+/// Key material derived from the password, which is wiped when dropped if the `zeroize` feature
+/// is enabled
+#[cfg(feature = "zeroize")]
+type DerivedKey = zeroize::Zeroizing<Vec<u8>>;
+#[cfg(not(feature = "zeroize"))]
+type DerivedKey = Vec<u8>;
+
 /// Create a AesCipher depending on the used `AesMode` and the given `key`.
 ///
 /// # Panics
@@ -34,6 +41,17 @@ fn cipher_from_mode(aes_mode: AesMode, key: &[u8]) -> Box<dyn aes_ctr::AesCipher
     }
 }
 
+/// Derive the key material of a file from the password and salt: the encryption key, the HMAC key
+/// and the password verification value, in that order
+fn derive_key(aes_mode: AesMode, password: &[u8], salt: &[u8]) -> DerivedKey {
+    let key_length = aes_mode.key_length();
+    let mut derived_key = DerivedKey::from(vec![0; 2 * key_length + PWD_VERIFY_LENGTH]);
+
+    // use PBKDF2 with HMAC-Sha1 to derive the key
+    pbkdf2::pbkdf2::<Hmac<Sha1>>(password, salt, ITERATION_COUNT, &mut derived_key);
+    derived_key
+}
+
 // An aes encrypted file starts with a salt, whose length depends on the used aes mode
 // followed by a 2 byte password verification value
 // then the variable length encrypted data
@@ -45,15 +63,23 @@ pub struct AesReader<R> {
 }
 
 impl<R: Read> AesReader<R> {
-    pub fn new(reader: R, aes_mode: AesMode, compressed_size: u64) -> AesReader<R> {
+    /// Fails if `compressed_size` is too small to hold the salt, the password verification value
+    /// and the authentication code.
+    pub fn new(reader: R, aes_mode: AesMode, compressed_size: u64) -> io::Result<AesReader<R>> {
         let data_length = compressed_size
-            - (PWD_VERIFY_LENGTH + AUTH_CODE_LENGTH + aes_mode.salt_length()) as u64;
+            .checked_sub((PWD_VERIFY_LENGTH + AUTH_CODE_LENGTH + aes_mode.salt_length()) as u64)
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "AES encrypted file is too small to hold its header and authentication code",
+                )
+            })?;
 
-        Self {
+        Ok(Self {
             reader,
             aes_mode,
             data_length,
-        }
+        })
     }
 
     /// Read the AES header bytes and validate the password.
@@ -80,11 +106,8 @@ impl<R: Read> AesReader<R> {
 
         // derive a key from the password and salt
         // the length depends on the aes key length
-        let derived_key_len = 2 * key_length + PWD_VERIFY_LENGTH;
-        let mut derived_key: Vec<u8> = vec![0; derived_key_len];
-
-        // use PBKDF2 with HMAC-Sha1 to derive the key
-        pbkdf2::pbkdf2::<Hmac<Sha1>>(password, &salt, ITERATION_COUNT, &mut derived_key);
+        let derived_key = derive_key(self.aes_mode, password, &salt);
+        let derived_key_len = derived_key.len();
         let decrypt_key = &derived_key[0..key_length];
         let hmac_key = &derived_key[key_length..key_length * 2];
         let pwd_verify = &derived_key[derived_key_len - 2..];
@@ -140,6 +163,14 @@ impl<R: Read> Read for AesReaderValid<R> {
         // 2^32 bytes even on 32 bit systems.
         let bytes_to_read = self.data_remaining.min(buf.len() as u64) as usize;
         let read = self.reader.read(&mut buf[0..bytes_to_read])?;
+        if read == 0 && bytes_to_read > 0 {
+            // Without this, truncated data would be returned without its authentication code
+            // ever being checked
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "AES encrypted data ended before its authentication code",
+            ));
+        }
         self.data_remaining -= read as u64;
 
         // Update the hmac with the encrypted data
@@ -183,3 +214,65 @@ impl<R: Read> AesReaderValid<R> {
         self.reader
     }
 }
+
+/// A writer for aes encrypted files.
+///
+/// The salt and password verification value are written when it's created, and the
+/// authentication code when it's finished.
+pub struct AesWriter<W> {
+    writer: W,
+    cipher: Box<dyn aes_ctr::AesCipher>,
+    hmac: Hmac<Sha1>,
+    buffer: Vec<u8>,
+}
+
+impl<W: Write> AesWriter<W> {
+    /// Start encrypting with a key derived from `password` and a random salt.
+    pub fn new(mut writer: W, aes_mode: AesMode, password: &[u8]) -> io::Result<AesWriter<W>> {
+        let key_length = aes_mode.key_length();
+
+        let mut salt = vec![0; aes_mode.salt_length()];
+        getrandom::getrandom(&mut salt).map_err(io::Error::from)?;
+        let derived_key = derive_key(aes_mode, password, &salt);
+        let encrypt_key = &derived_key[0..key_length];
+        let hmac_key = &derived_key[key_length..key_length * 2];
+        let pwd_verify = &derived_key[key_length * 2..];
+
+        writer.write_all(&salt)?;
+        writer.write_all(pwd_verify)?;
+
+        Ok(AesWriter {
+            writer,
+            cipher: cipher_from_mode(aes_mode, encrypt_key),
+            hmac: Hmac::<Sha1>::new_from_slice(hmac_key).unwrap(),
+            buffer: Vec::new(),
+        })
+    }
+
+    /// Write the authentication code, returning the underlying writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        let auth_code = self.hmac.finalize_reset().into_bytes();
+        self.writer.write_all(&auth_code[0..AUTH_CODE_LENGTH])?;
+        Ok(self.writer)
+    }
+
+    /// Gets a mutable reference to the underlying writer.
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.writer
+    }
+}
+
+impl<W: Write> Write for AesWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buffer.clear();
+        self.buffer.extend_from_slice(buf);
+        self.cipher.crypt_in_place(&mut self.buffer);
+        self.hmac.update(&self.buffer);
+        self.writer.write_all(&self.buffer)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
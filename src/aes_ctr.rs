@@ -100,6 +100,14 @@ where
     }
 }
 
+#[cfg(feature = "zeroize")]
+impl<C: AesKind> Drop for AesCtrZipKeyStream<C> {
+    fn drop(&mut self) {
+        use zeroize::Zeroize;
+        self.buffer.zeroize();
+    }
+}
+
 impl<C> AesCipher for AesCtrZipKeyStream<C>
 where
     C: AesKind,
@@ -1,6 +1,7 @@
 //! Possible ZIP compression methods.
 
 use std::fmt;
+use std::ops::RangeInclusive;
 
 #[allow(deprecated)]
 /// Identifies the storage format used to compress a file within a ZIP archive.
@@ -34,6 +35,27 @@ pub enum CompressionMethod {
     /// Compress the file using ZStandard
     #[cfg(feature = "zstd")]
     Zstd,
+    /// Compress the file using XZ
+    #[cfg(feature = "xz")]
+    Xz,
+    /// Shrink, used by PKZIP 1.x (read only)
+    #[cfg(feature = "legacy-decompress")]
+    Shrink,
+    /// Reduce with compression factor 1, used by PKZIP 0.9x (read only)
+    #[cfg(feature = "legacy-decompress")]
+    Reduce1,
+    /// Reduce with compression factor 2, used by PKZIP 0.9x (read only)
+    #[cfg(feature = "legacy-decompress")]
+    Reduce2,
+    /// Reduce with compression factor 3, used by PKZIP 0.9x (read only)
+    #[cfg(feature = "legacy-decompress")]
+    Reduce3,
+    /// Reduce with compression factor 4, used by PKZIP 0.9x (read only)
+    #[cfg(feature = "legacy-decompress")]
+    Reduce4,
+    /// Implode, used by PKZIP 1.x (read only)
+    #[cfg(feature = "legacy-decompress")]
+    Implode,
     /// Unsupported compression method
     #[deprecated(since = "0.5.7", note = "use the constants instead")]
     Unsupported(u16),
@@ -42,11 +64,29 @@ pub enum CompressionMethod {
 /// All compression methods defined for the ZIP format
 impl CompressionMethod {
     pub const STORE: Self = CompressionMethod::Stored;
+    #[cfg(feature = "legacy-decompress")]
+    pub const SHRINK: Self = CompressionMethod::Shrink;
+    #[cfg(not(feature = "legacy-decompress"))]
     pub const SHRINK: Self = CompressionMethod::Unsupported(1);
+    #[cfg(feature = "legacy-decompress")]
+    pub const REDUCE_1: Self = CompressionMethod::Reduce1;
+    #[cfg(not(feature = "legacy-decompress"))]
     pub const REDUCE_1: Self = CompressionMethod::Unsupported(2);
+    #[cfg(feature = "legacy-decompress")]
+    pub const REDUCE_2: Self = CompressionMethod::Reduce2;
+    #[cfg(not(feature = "legacy-decompress"))]
     pub const REDUCE_2: Self = CompressionMethod::Unsupported(3);
+    #[cfg(feature = "legacy-decompress")]
+    pub const REDUCE_3: Self = CompressionMethod::Reduce3;
+    #[cfg(not(feature = "legacy-decompress"))]
     pub const REDUCE_3: Self = CompressionMethod::Unsupported(4);
+    #[cfg(feature = "legacy-decompress")]
+    pub const REDUCE_4: Self = CompressionMethod::Reduce4;
+    #[cfg(not(feature = "legacy-decompress"))]
     pub const REDUCE_4: Self = CompressionMethod::Unsupported(5);
+    #[cfg(feature = "legacy-decompress")]
+    pub const IMPLODE: Self = CompressionMethod::Implode;
+    #[cfg(not(feature = "legacy-decompress"))]
     pub const IMPLODE: Self = CompressionMethod::Unsupported(6);
     #[cfg(any(
         feature = "deflate",
@@ -69,12 +109,19 @@ impl CompressionMethod {
     pub const LZMA: Self = CompressionMethod::Unsupported(14);
     pub const IBM_ZOS_CMPSC: Self = CompressionMethod::Unsupported(16);
     pub const IBM_TERSE: Self = CompressionMethod::Unsupported(18);
+    /// The method ID used for Zstandard before APPNOTE assigned 93. It is read as `Zstd`.
+    #[cfg(feature = "zstd")]
+    pub const ZSTD_DEPRECATED: Self = CompressionMethod::Zstd;
+    #[cfg(not(feature = "zstd"))]
     pub const ZSTD_DEPRECATED: Self = CompressionMethod::Unsupported(20);
     #[cfg(feature = "zstd")]
     pub const ZSTD: Self = CompressionMethod::Zstd;
     #[cfg(not(feature = "zstd"))]
     pub const ZSTD: Self = CompressionMethod::Unsupported(93);
     pub const MP3: Self = CompressionMethod::Unsupported(94);
+    #[cfg(feature = "xz")]
+    pub const XZ: Self = CompressionMethod::Xz;
+    #[cfg(not(feature = "xz"))]
     pub const XZ: Self = CompressionMethod::Unsupported(95);
     pub const JPEG: Self = CompressionMethod::Unsupported(96);
     pub const WAVPACK: Self = CompressionMethod::Unsupported(97);
@@ -86,6 +133,8 @@ impl CompressionMethod {
 }
 impl CompressionMethod {
     /// Converts an u16 to its corresponding CompressionMethod
+    ///
+    /// Both 93 and the deprecated 20 are converted to `Zstd`.
     #[deprecated(
         since = "0.5.7",
         note = "use a constant to construct a compression method"
@@ -94,6 +143,18 @@ impl CompressionMethod {
         #[allow(deprecated)]
         match val {
             0 => CompressionMethod::Stored,
+            #[cfg(feature = "legacy-decompress")]
+            1 => CompressionMethod::Shrink,
+            #[cfg(feature = "legacy-decompress")]
+            2 => CompressionMethod::Reduce1,
+            #[cfg(feature = "legacy-decompress")]
+            3 => CompressionMethod::Reduce2,
+            #[cfg(feature = "legacy-decompress")]
+            4 => CompressionMethod::Reduce3,
+            #[cfg(feature = "legacy-decompress")]
+            5 => CompressionMethod::Reduce4,
+            #[cfg(feature = "legacy-decompress")]
+            6 => CompressionMethod::Implode,
             #[cfg(any(
                 feature = "deflate",
                 feature = "deflate-miniz",
@@ -103,7 +164,9 @@ impl CompressionMethod {
             #[cfg(feature = "bzip2")]
             12 => CompressionMethod::Bzip2,
             #[cfg(feature = "zstd")]
-            93 => CompressionMethod::Zstd,
+            20 | 93 => CompressionMethod::Zstd,
+            #[cfg(feature = "xz")]
+            95 => CompressionMethod::Xz,
             #[cfg(feature = "aes-crypto")]
             99 => CompressionMethod::Aes,
 
@@ -120,6 +183,18 @@ impl CompressionMethod {
         #[allow(deprecated)]
         match self {
             CompressionMethod::Stored => 0,
+            #[cfg(feature = "legacy-decompress")]
+            CompressionMethod::Shrink => 1,
+            #[cfg(feature = "legacy-decompress")]
+            CompressionMethod::Reduce1 => 2,
+            #[cfg(feature = "legacy-decompress")]
+            CompressionMethod::Reduce2 => 3,
+            #[cfg(feature = "legacy-decompress")]
+            CompressionMethod::Reduce3 => 4,
+            #[cfg(feature = "legacy-decompress")]
+            CompressionMethod::Reduce4 => 5,
+            #[cfg(feature = "legacy-decompress")]
+            CompressionMethod::Implode => 6,
             #[cfg(any(
                 feature = "deflate",
                 feature = "deflate-miniz",
@@ -132,10 +207,39 @@ impl CompressionMethod {
             CompressionMethod::Aes => 99,
             #[cfg(feature = "zstd")]
             CompressionMethod::Zstd => 93,
+            #[cfg(feature = "xz")]
+            CompressionMethod::Xz => 95,
 
             CompressionMethod::Unsupported(v) => v,
         }
     }
+
+    /// The compression levels that [`crate::write::FileOptions::compression_level`] accepts for
+    /// this method, when writing
+    ///
+    /// Returns `None` for methods that don't take a level, which only accept `None` as level.
+    pub fn level_range(&self) -> Option<RangeInclusive<i32>> {
+        match self {
+            #[cfg(any(
+                feature = "deflate",
+                feature = "deflate-miniz",
+                feature = "deflate-zlib"
+            ))]
+            CompressionMethod::Deflated => Some(
+                flate2::Compression::none().level() as i32
+                    ..=flate2::Compression::best().level() as i32,
+            ),
+            // Level 0 is rejected by libbzip2
+            #[cfg(feature = "bzip2")]
+            CompressionMethod::Bzip2 => Some(
+                bzip2::Compression::fast().level() as i32
+                    ..=bzip2::Compression::best().level() as i32,
+            ),
+            #[cfg(feature = "zstd")]
+            CompressionMethod::Zstd => Some(zstd::compression_level_range()),
+            _ => None,
+        }
+    }
 }
 
 impl fmt::Display for CompressionMethod {
@@ -167,6 +271,10 @@ mod test {
     #[test]
     fn from_eq_to() {
         for v in 0..(u16::MAX as u32 + 1) {
+            #[cfg(feature = "zstd")]
+            if v == 20 {
+                continue;
+            }
             #[allow(deprecated)]
             let from = CompressionMethod::from_u16(v as u16);
             #[allow(deprecated)]
@@ -175,6 +283,20 @@ mod test {
         }
     }
 
+    #[test]
+    #[cfg(feature = "zstd")]
+    fn zstd_ids() {
+        #[allow(deprecated)]
+        let legacy = CompressionMethod::from_u16(20);
+        #[allow(deprecated)]
+        let current = CompressionMethod::from_u16(93);
+        assert_eq!(legacy, CompressionMethod::Zstd);
+        assert_eq!(current, CompressionMethod::Zstd);
+        #[allow(deprecated)]
+        let id = CompressionMethod::Zstd.to_u16();
+        assert_eq!(id, 93);
+    }
+
     #[test]
     fn to_eq_from() {
         fn check_match(method: CompressionMethod) {
@@ -1,4 +1,4 @@
-//! Convert a string in IBM codepage 437 to UTF-8
+//! Convert a string in IBM codepage 437 to UTF-8, and back
 
 /// Trait to convert IBM codepage 437 to the target type
 pub trait FromCp437 {
@@ -35,6 +35,20 @@ impl FromCp437 for Vec<u8> {
     }
 }
 
+/// Encode a string in IBM codepage 437, replacing characters it lacks with `?`
+pub fn to_cp437(input: &str) -> Vec<u8> {
+    input
+        .chars()
+        .map(|c| {
+            if c.is_ascii() {
+                c as u8
+            } else {
+                (0x80..=0xff).find(|&b| to_char(b) == c).unwrap_or(b'?')
+            }
+        })
+        .collect()
+}
+
 fn to_char(input: u8) -> char {
     let output = match input {
         0x00..=0x7f => input as u32,
@@ -187,6 +201,7 @@ mod test {
     }
 
     #[test]
+    #[allow(unknown_lints, invalid_from_utf8)]
     fn example_slice() {
         use super::FromCp437;
         let data = b"Cura\x87ao";
@@ -194,6 +209,18 @@ mod test {
         assert_eq!(data.from_cp437(), "Curaçao");
     }
 
+    #[test]
+    fn encode() {
+        use super::FromCp437;
+        assert_eq!(super::to_cp437("Curaçao"), b"Cura\x87ao");
+        assert_eq!(super::to_cp437("╠══╣"), [0xCC, 0xCD, 0xCD, 0xB9]);
+        assert_eq!(super::to_cp437("日本.txt"), b"??.txt");
+        for i in 0x00..=0xff_u8 {
+            let decoded = vec![i].from_cp437();
+            assert_eq!(super::to_cp437(&decoded), [i]);
+        }
+    }
+
     #[test]
     fn example_vec() {
         use super::FromCp437;
@@ -0,0 +1,126 @@
+//! Comparing the files of two archives
+
+use crate::read::ZipArchive;
+use crate::result::ZipResult;
+use crate::types::AesVendorVersion;
+use std::collections::BTreeMap;
+use std::io::{self, Read, Seek};
+
+/// The differences between two archives, found with [`compare`] or [`compare_contents`]
+///
+/// The names have `/` separators, even if an archive has `\` ones, and are sorted.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ArchiveDiff {
+    /// Names of the files only in the second archive
+    pub added: Vec<String>,
+    /// Names of the files only in the first archive
+    pub removed: Vec<String>,
+    /// Names of the files in both archives, with different contents
+    pub modified: Vec<String>,
+}
+
+impl ArchiveDiff {
+    /// Whether the archives have the same files
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.modified.is_empty()
+    }
+}
+
+/// Size, CRC32 and index of a file, by name
+type Summary = BTreeMap<String, (u64, Option<u32>, usize)>;
+
+fn summary<R: Read + Seek>(archive: &ZipArchive<R>) -> Summary {
+    archive
+        .files()
+        .iter()
+        .enumerate()
+        .map(|(index, file)| {
+            // AE-2 encrypted files have no CRC32
+            let crc32 = match file.aes_mode {
+                Some((_, AesVendorVersion::Ae2)) => None,
+                _ => Some(file.crc32),
+            };
+            let name = file.file_name.replace('\\', "/");
+            (name, (file.uncompressed_size, crc32, index))
+        })
+        .collect()
+}
+
+/// Compare the files of two archives by name, size and CRC32, from their central directories
+///
+/// Nothing is decompressed, so the files of the same size and CRC32 count as the same: use
+/// [`compare_contents`] to check them byte by byte. The order of the files is ignored.
+pub fn compare<R1, R2>(a: &ZipArchive<R1>, b: &ZipArchive<R2>) -> ArchiveDiff
+where
+    R1: Read + Seek,
+    R2: Read + Seek,
+{
+    let a = summary(a);
+    let b = summary(b);
+    let mut diff = ArchiveDiff::default();
+    for (name, (size, crc32, _)) in &a {
+        match b.get(name) {
+            None => diff.removed.push(name.clone()),
+            Some((b_size, b_crc32, _)) => {
+                if size != b_size || crc32.zip(*b_crc32).map_or(false, |(a, b)| a != b) {
+                    diff.modified.push(name.clone());
+                }
+            }
+        }
+    }
+    diff.added = b
+        .keys()
+        .filter(|name| !a.contains_key(*name))
+        .cloned()
+        .collect();
+    diff
+}
+
+/// Compare the files of two archives like [`compare`], and also decompress those of the same
+/// size and CRC32 to compare them byte by byte.
+///
+/// Fails if one of those files can't be read, for example because it's encrypted.
+pub fn compare_contents<R1, R2>(
+    a: &mut ZipArchive<R1>,
+    b: &mut ZipArchive<R2>,
+) -> ZipResult<ArchiveDiff>
+where
+    R1: Read + Seek,
+    R2: Read + Seek,
+{
+    let mut diff = compare(a, b);
+    let a_summary = summary(a);
+    let b_summary = summary(b);
+    for (name, (_, _, a_index)) in &a_summary {
+        let b_index = match b_summary.get(name) {
+            Some((_, _, index)) if diff.modified.binary_search(name).is_err() => *index,
+            _ => continue,
+        };
+        if !same_contents(&mut a.by_index(*a_index)?, &mut b.by_index(b_index)?)? {
+            let position = diff.modified.binary_search(name).unwrap_err();
+            diff.modified.insert(position, name.clone());
+        }
+    }
+    Ok(diff)
+}
+
+/// Whether two readers give the same bytes
+fn same_contents(a: &mut impl Read, b: &mut impl Read) -> io::Result<bool> {
+    let mut a_buffer = [0; 8192];
+    let mut b_buffer = [0; 8192];
+    loop {
+        let length = a.read(&mut a_buffer)?;
+        if length == 0 {
+            return Ok(b.read(&mut b_buffer[..1])? == 0);
+        }
+        if let Err(e) = b.read_exact(&mut b_buffer[..length]) {
+            return match e.kind() {
+                io::ErrorKind::UnexpectedEof => Ok(false),
+                _ => Err(e),
+            };
+        }
+        if a_buffer[..length] != b_buffer[..length] {
+            return Ok(false);
+        }
+    }
+}
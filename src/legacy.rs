@@ -0,0 +1,142 @@
+//! Decompression of the methods used by PKZIP 1.x and earlier: Shrink, Reduce and Implode
+//!
+//! These are rare enough, and entries using them small enough, that an entry is decompressed as
+//! a whole on the first read instead of incrementally.
+
+use crate::compression::CompressionMethod;
+use std::io::{self, Read};
+
+mod implode;
+mod reduce;
+mod shrink;
+
+/// A legacy compression method, along with the options it was used with
+#[derive(Copy, Clone, Debug)]
+pub(crate) enum LegacyMethod {
+    Shrink,
+    /// Reduce, with a compression factor of 1 to 4
+    Reduce(u8),
+    Implode {
+        /// Whether an 8 KiB sliding window was used, instead of 4 KiB
+        large_window: bool,
+        /// Whether literals are encoded with a Shannon-Fano tree
+        literal_tree: bool,
+    },
+}
+
+impl LegacyMethod {
+    /// Get the legacy method for an entry's compression method and general purpose bit flags
+    pub fn new(method: CompressionMethod, flags: u16) -> LegacyMethod {
+        match method {
+            CompressionMethod::Reduce1 => LegacyMethod::Reduce(1),
+            CompressionMethod::Reduce2 => LegacyMethod::Reduce(2),
+            CompressionMethod::Reduce3 => LegacyMethod::Reduce(3),
+            CompressionMethod::Reduce4 => LegacyMethod::Reduce(4),
+            CompressionMethod::Implode => LegacyMethod::Implode {
+                large_window: flags & (1 << 1) != 0,
+                literal_tree: flags & (1 << 2) != 0,
+            },
+            _ => LegacyMethod::Shrink,
+        }
+    }
+}
+
+pub(crate) struct LegacyDecoder<R> {
+    reader: R,
+    method: LegacyMethod,
+    uncompressed_size: u64,
+    output: Option<io::Cursor<Vec<u8>>>,
+}
+
+impl<R: Read> LegacyDecoder<R> {
+    pub fn new(reader: R, method: LegacyMethod, uncompressed_size: u64) -> LegacyDecoder<R> {
+        LegacyDecoder {
+            reader,
+            method,
+            uncompressed_size,
+            output: None,
+        }
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    fn decompress(&mut self) -> io::Result<Vec<u8>> {
+        let mut input = Vec::new();
+        self.reader.read_to_end(&mut input)?;
+        let size = usize::try_from(self.uncompressed_size)
+            .map_err(|_| invalid_data("Entry is too large to decompress in memory"))?;
+        match self.method {
+            LegacyMethod::Shrink => shrink::unshrink(&input, size),
+            LegacyMethod::Reduce(factor) => reduce::unreduce(&input, size, factor),
+            LegacyMethod::Implode {
+                large_window,
+                literal_tree,
+            } => implode::explode(&input, size, large_window, literal_tree),
+        }
+    }
+}
+
+impl<R: Read> Read for LegacyDecoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.output.is_none() {
+            let output = self.decompress()?;
+            self.output = Some(io::Cursor::new(output));
+        }
+        self.output.as_mut().unwrap().read(buf)
+    }
+}
+
+fn invalid_data(message: &'static str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Reads bits from a byte slice, least significant bit first
+struct BitReader<'a> {
+    input: &'a [u8],
+    position: usize,
+    buffer: u32,
+    available: u8,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(input: &'a [u8]) -> BitReader<'a> {
+        BitReader {
+            input,
+            position: 0,
+            buffer: 0,
+            available: 0,
+        }
+    }
+
+    /// Read up to 16 bits
+    fn read_bits(&mut self, count: u8) -> io::Result<u16> {
+        while self.available < count {
+            let byte = *self
+                .input
+                .get(self.position)
+                .ok_or_else(|| invalid_data("Unexpected end of compressed data"))?;
+            self.position += 1;
+            self.buffer |= (byte as u32) << self.available;
+            self.available += 8;
+        }
+        let value = self.buffer & ((1 << count) - 1);
+        self.buffer >>= count;
+        self.available -= count;
+        Ok(value as u16)
+    }
+}
+
+/// Copy `length` bytes starting `distance` bytes back in `output`, up to `limit` bytes in total.
+///
+/// Positions before the start of the output read as zeros.
+fn copy_match(output: &mut Vec<u8>, distance: usize, length: usize, limit: usize) {
+    for _ in 0..length.min(limit - output.len()) {
+        let byte = match output.len().checked_sub(distance) {
+            Some(position) => output[position],
+            None => 0,
+        };
+        output.push(byte);
+    }
+}
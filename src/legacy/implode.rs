@@ -0,0 +1,114 @@
+//! Implode (method 6): a sliding window with Shannon-Fano coded literals, lengths and distances
+
+use super::{copy_match, invalid_data, BitReader};
+use std::io;
+
+const MAX_CODE_LENGTH: usize = 16;
+
+/// A Shannon-Fano tree, decoded as the canonical code with the same code lengths
+struct Tree {
+    /// Number of codes of each length
+    counts: [u16; MAX_CODE_LENGTH + 1],
+    /// Symbols ordered by code length, then value
+    symbols: Vec<u16>,
+}
+
+impl Tree {
+    /// Read the run-length encoded code lengths at the start of `input`, advancing past them
+    fn read(input: &mut &[u8], symbol_count: usize) -> io::Result<Tree> {
+        let (&run_count, rest) = input
+            .split_first()
+            .ok_or_else(|| invalid_data("Unexpected end of compressed data"))?;
+        let run_count = run_count as usize + 1;
+        if rest.len() < run_count {
+            return Err(invalid_data("Unexpected end of compressed data"));
+        }
+        let (runs, rest) = rest.split_at(run_count);
+        *input = rest;
+
+        let mut lengths = Vec::with_capacity(symbol_count);
+        for &run in runs {
+            let length = (run & 0x0f) as usize + 1;
+            let repeat = (run >> 4) as usize + 1;
+            lengths.extend(std::iter::repeat(length).take(repeat));
+        }
+        if lengths.len() != symbol_count {
+            return Err(invalid_data("Invalid Implode tree"));
+        }
+
+        let mut counts = [0u16; MAX_CODE_LENGTH + 1];
+        for &length in lengths.iter() {
+            counts[length] += 1;
+        }
+        let mut available = 1i32;
+        for &count in counts[1..].iter() {
+            available = available * 2 - count as i32;
+            if available < 0 {
+                return Err(invalid_data("Invalid Implode tree"));
+            }
+        }
+
+        let mut symbols: Vec<u16> = (0..symbol_count as u16).collect();
+        symbols.sort_by_key(|&symbol| lengths[symbol as usize]);
+        Ok(Tree { counts, symbols })
+    }
+
+    fn decode(&self, bits: &mut BitReader) -> io::Result<u16> {
+        // Codes are stored inverted, most significant bit first
+        let mut code = 0i32;
+        let mut first = 0i32;
+        let mut index = 0i32;
+        for &count in self.counts[1..].iter() {
+            code |= (bits.read_bits(1)? ^ 1) as i32;
+            let count = count as i32;
+            if code - first < count {
+                return Ok(self.symbols[(index + code - first) as usize]);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        Err(invalid_data("Invalid Implode code"))
+    }
+}
+
+pub(super) fn explode(
+    mut input: &[u8],
+    size: usize,
+    large_window: bool,
+    literal_tree: bool,
+) -> io::Result<Vec<u8>> {
+    let literals = if literal_tree {
+        Some(Tree::read(&mut input, 256)?)
+    } else {
+        None
+    };
+    let lengths = Tree::read(&mut input, 64)?;
+    let distances = Tree::read(&mut input, 64)?;
+
+    let distance_low_bits = if large_window { 7 } else { 6 };
+    let min_match = if literal_tree { 3 } else { 2 };
+
+    let mut bits = BitReader::new(input);
+    let mut output = Vec::new();
+    while output.len() < size {
+        if bits.read_bits(1)? == 1 {
+            let literal = match literals {
+                Some(ref tree) => tree.decode(&mut bits)? as u8,
+                None => bits.read_bits(8)? as u8,
+            };
+            output.push(literal);
+        } else {
+            let low = bits.read_bits(distance_low_bits)? as usize;
+            let high = distances.decode(&mut bits)? as usize;
+            let distance = (high << distance_low_bits | low) + 1;
+            let mut length = lengths.decode(&mut bits)? as usize;
+            if length == 63 {
+                length += bits.read_bits(8)? as usize;
+            }
+            copy_match(&mut output, distance, length + min_match, size);
+        }
+    }
+
+    Ok(output)
+}
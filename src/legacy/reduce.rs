@@ -0,0 +1,86 @@
+//! Reduce (methods 2 to 5): follower sets feeding a run-length and back-reference expander
+
+use super::{copy_match, invalid_data, BitReader};
+use std::io;
+
+/// Byte introducing a back-reference in the expanded stream
+const DLE: u8 = 144;
+
+/// Number of bits needed to index a follower set of the given size
+fn index_bits(set_size: usize) -> u8 {
+    let mut bits = 1;
+    while (1 << bits) < set_size {
+        bits += 1;
+    }
+    bits
+}
+
+pub(super) fn unreduce(input: &[u8], size: usize, factor: u8) -> io::Result<Vec<u8>> {
+    if !(1..=4).contains(&factor) {
+        return Err(invalid_data("Invalid Reduce compression factor"));
+    }
+    let mut bits = BitReader::new(input);
+    let mut output = Vec::new();
+
+    let mut followers = vec![Vec::new(); 256];
+    for set in followers.iter_mut().rev() {
+        let set_size = bits.read_bits(6)?;
+        for _ in 0..set_size {
+            set.push(bits.read_bits(8)? as u8);
+        }
+    }
+
+    let length_mask = 0x7f >> (factor - 1);
+    let distance_shift = 8 - factor;
+    let distance_mask = (1 << factor) - 1;
+
+    let mut last = 0u8;
+    let mut state = 0;
+    let mut value = 0u8;
+    let mut length = 0usize;
+    while output.len() < size {
+        let set: &Vec<u8> = &followers[last as usize];
+        let byte = if set.is_empty() || bits.read_bits(1)? == 1 {
+            bits.read_bits(8)? as u8
+        } else {
+            let index = bits.read_bits(index_bits(set.len()))? as usize;
+            *set.get(index)
+                .ok_or_else(|| invalid_data("Invalid Reduce follower index"))?
+        };
+        last = byte;
+
+        state = match state {
+            0 if byte == DLE => 1,
+            0 => {
+                output.push(byte);
+                0
+            }
+            1 if byte == 0 => {
+                output.push(DLE);
+                0
+            }
+            1 => {
+                value = byte;
+                length = (byte & length_mask) as usize;
+                if length == length_mask as usize {
+                    2
+                } else {
+                    3
+                }
+            }
+            2 => {
+                length += byte as usize;
+                3
+            }
+            _ => {
+                let distance = (((value >> distance_shift) & distance_mask) as usize) * 256
+                    + byte as usize
+                    + 1;
+                copy_match(&mut output, distance, length + 3, size);
+                0
+            }
+        };
+    }
+
+    Ok(output)
+}
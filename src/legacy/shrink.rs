@@ -0,0 +1,115 @@
+//! Shrink (method 1): LZW with code sizes of 9 to 13 bits and partial clearing
+
+use super::{invalid_data, BitReader};
+use std::io;
+
+const MAX_CODE_SIZE: u8 = 13;
+const TABLE_SIZE: usize = 1 << MAX_CODE_SIZE;
+/// Code announcing a code size increase or a partial clear
+const CONTROL_CODE: u16 = 256;
+const FREE: u16 = u16::MAX;
+
+pub(super) fn unshrink(input: &[u8], size: usize) -> io::Result<Vec<u8>> {
+    let mut bits = BitReader::new(input);
+    let mut output = Vec::new();
+    if size == 0 {
+        return Ok(output);
+    }
+
+    // Codes below 256 are literals; `parent` and `suffix` describe the others
+    let mut parent = vec![FREE; TABLE_SIZE];
+    let mut suffix = vec![0u8; TABLE_SIZE];
+    let mut code_size = 9;
+    let mut last_assigned = CONTROL_CODE as usize;
+    let mut string = Vec::new();
+
+    let mut previous = bits.read_bits(code_size)?;
+    if previous >= CONTROL_CODE {
+        return Err(invalid_data("Shrink stream does not start with a literal"));
+    }
+    output.push(previous as u8);
+
+    while output.len() < size {
+        let code = bits.read_bits(code_size)?;
+        if code == CONTROL_CODE {
+            match bits.read_bits(code_size)? {
+                1 if code_size < MAX_CODE_SIZE => code_size += 1,
+                2 => {
+                    partial_clear(&mut parent);
+                    last_assigned = CONTROL_CODE as usize;
+                }
+                _ => return Err(invalid_data("Invalid Shrink control code")),
+            }
+            continue;
+        }
+
+        let next_free = (last_assigned + 1..TABLE_SIZE).find(|&c| parent[c] == FREE);
+
+        string.clear();
+        let mut current = code as usize;
+        if current > CONTROL_CODE as usize && parent[current] == FREE {
+            // The code is the one about to be assigned, for the previous string followed by
+            // its own first byte.
+            if Some(current) != next_free {
+                return Err(invalid_data("Invalid Shrink code"));
+            }
+            string.push(first_byte(&parent, previous)?);
+            current = previous as usize;
+        }
+        while current > CONTROL_CODE as usize {
+            if parent[current] == FREE || string.len() >= TABLE_SIZE {
+                return Err(invalid_data("Invalid Shrink code"));
+            }
+            string.push(suffix[current]);
+            current = parent[current] as usize;
+        }
+        if current == CONTROL_CODE as usize {
+            return Err(invalid_data("Invalid Shrink code"));
+        }
+        let first = current as u8;
+        string.push(first);
+        output.extend(string.iter().rev().take(size - output.len()));
+
+        if let Some(slot) = next_free {
+            parent[slot] = previous;
+            suffix[slot] = first;
+            last_assigned = slot;
+        }
+        previous = code;
+    }
+
+    Ok(output)
+}
+
+fn first_byte(parent: &[u16], code: u16) -> io::Result<u8> {
+    let mut current = code as usize;
+    for _ in 0..TABLE_SIZE {
+        if current < CONTROL_CODE as usize {
+            return Ok(current as u8);
+        }
+        if parent[current] == FREE {
+            break;
+        }
+        current = parent[current] as usize;
+    }
+    Err(invalid_data("Invalid Shrink code"))
+}
+
+/// Free all codes that are not the prefix of another code
+fn partial_clear(parent: &mut [u16]) {
+    let mut has_child = vec![false; TABLE_SIZE];
+    for &p in parent[CONTROL_CODE as usize + 1..].iter() {
+        if p != FREE && p > CONTROL_CODE {
+            has_child[p as usize] = true;
+        }
+    }
+    for (code, p) in parent
+        .iter_mut()
+        .enumerate()
+        .skip(CONTROL_CODE as usize + 1)
+    {
+        if !has_child[code] {
+            *p = FREE;
+        }
+    }
+}
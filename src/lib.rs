@@ -26,7 +26,7 @@
 
 pub use crate::compression::{CompressionMethod, SUPPORTED_COMPRESSION_METHODS};
 pub use crate::read::ZipArchive;
-pub use crate::types::DateTime;
+pub use crate::types::{AesMode, AesVendorVersion, DateTime, DosAttributes, NtfsTimes, System};
 pub use crate::write::ZipWriter;
 
 #[cfg(feature = "aes-crypto")]
@@ -36,10 +36,15 @@ mod aes_ctr;
 mod compression;
 mod cp437;
 mod crc32;
+pub mod diff;
+#[cfg(feature = "legacy-decompress")]
+mod legacy;
+pub mod patch;
 pub mod read;
 pub mod result;
 mod spec;
 mod types;
+pub mod update;
 pub mod write;
 mod zipcrypto;
 
@@ -0,0 +1,58 @@
+//! Replacing files of an archive without recompressing the others
+
+use crate::read::ZipArchive;
+use crate::result::{ZipError, ZipResult};
+use crate::write::{FileOptions, ZipWriter};
+use std::io::{Read, Seek, Write};
+
+/// Write a copy of `src` to `dst`, with the data of the file named `name` replaced by
+/// `new_data`, and return `dst`.
+///
+/// The other files are copied with [`ZipWriter::raw_copy_file`], so they keep their compressed
+/// data, CRC32 and extra fields, and aren't decompressed. The new file is compressed according to
+/// `options`, and keeps its place among them. If several files are named `name`, only the last
+/// one is replaced, which is the one [`ZipArchive::by_name`] reads. The archive comment is kept,
+/// but not data prepended to `src`. Fails without writing anything if no file is named `name`.
+///
+/// ```no_run
+/// use std::fs::File;
+/// use zip::write::FileOptions;
+/// use zip::ZipArchive;
+///
+/// # fn main() -> zip::result::ZipResult<()> {
+/// let mut archive = ZipArchive::new(File::open("game.pak")?)?;
+/// let texture = File::open("mods/texture.png")?;
+/// zip::patch::replace_entry(
+///     &mut archive,
+///     File::create("game-modded.pak")?,
+///     "textures/texture.png",
+///     texture,
+///     FileOptions::default(),
+/// )?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn replace_entry<R, W, D>(
+    src: &mut ZipArchive<R>,
+    dst: W,
+    name: &str,
+    new_data: D,
+    options: FileOptions,
+) -> ZipResult<W>
+where
+    R: Read + Seek,
+    W: Write + Seek,
+    D: Read,
+{
+    let index = src.index_for_name(name).ok_or(ZipError::FileNotFound)?;
+    let mut writer = ZipWriter::new(dst);
+    writer.set_raw_comment(src.comment().to_vec());
+    for i in 0..index {
+        writer.raw_copy_file(src.by_index_raw(i)?)?;
+    }
+    writer.add_entry(name, new_data, options)?;
+    for i in index + 1..src.len() {
+        writer.raw_copy_file(src.by_index_raw(i)?)?;
+    }
+    writer.finish()
+}
@@ -5,15 +5,22 @@ use crate::aes::{AesReader, AesReaderValid};
 use crate::compression::CompressionMethod;
 use crate::cp437::FromCp437;
 use crate::crc32::Crc32Reader;
-use crate::result::{InvalidPassword, ZipError, ZipResult};
+#[cfg(feature = "legacy-decompress")]
+use crate::legacy::{LegacyDecoder, LegacyMethod};
+use crate::result::{ZipError, ZipResult};
 use crate::spec;
-use crate::types::{AesMode, AesVendorVersion, AtomicU64, DateTime, System, ZipFileData};
+use crate::types::{
+    AesMode, AesVendorVersion, AtomicU64, DateTime, DosAttributes, NtfsTimes, System, ZipFileData,
+};
 use crate::zipcrypto::{ZipCryptoReader, ZipCryptoReaderValid, ZipCryptoValidator};
+
+pub use crate::zipcrypto::ZipCryptoKeys;
 use byteorder::{LittleEndian, ReadBytesExt};
 use std::borrow::Cow;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::ffi::{OsStr, OsString};
 use std::io::{self, prelude::*};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
 #[cfg(any(
@@ -26,11 +33,21 @@ use flate2::read::DeflateDecoder;
 #[cfg(feature = "bzip2")]
 use bzip2::read::BzDecoder;
 
+#[cfg(feature = "xz")]
+use xz2::read::XzDecoder;
 #[cfg(feature = "zstd")]
 use zstd::stream::read::Decoder as ZstdDecoder;
 
-/// Provides high level API for reading from a stream.
-pub(crate) mod stream;
+mod diagnostic;
+mod split;
+pub mod stream;
+mod tree;
+mod validate;
+
+pub use diagnostic::ParseDiagnostic;
+pub use split::SplitReader;
+pub use tree::{TreeNode, ZipTree};
+pub use validate::{HeaderField, ValidationWarning};
 
 // Put the struct declaration in a private module to convince rustdoc to display ZipArchive nicely
 pub(crate) mod zip_archive {
@@ -40,7 +57,13 @@ pub(crate) mod zip_archive {
         pub(super) files: Vec<super::ZipFileData>,
         pub(super) names_map: super::HashMap<String, usize>,
         pub(super) offset: u64,
+        pub(super) directory_start: u64,
+        /// The size of the central directory, as recorded in its end record
+        pub(super) directory_size: u64,
+        /// Where parsing the central directory headers ended
+        pub(super) directory_end: u64,
         pub(super) comment: Vec<u8>,
+        pub(super) config: super::ZipReaderConfig,
     }
 
     /// ZIP archive reader
@@ -54,8 +77,8 @@ pub(crate) mod zip_archive {
     /// fn list_zip_contents(reader: impl Read + Seek) -> zip::result::ZipResult<()> {
     ///     let mut zip = zip::ZipArchive::new(reader)?;
     ///
-    ///     for i in 0..zip.len() {
-    ///         let mut file = zip.by_index(i)?;
+    ///     let mut entries = zip.entries();
+    ///     while let Some(mut file) = entries.next_entry()? {
     ///         println!("Filename: {}", file.name());
     ///         std::io::copy(&mut file, &mut std::io::stdout());
     ///     }
@@ -67,17 +90,420 @@ pub(crate) mod zip_archive {
     pub struct ZipArchive<R> {
         pub(super) reader: R,
         pub(super) shared: super::Arc<Shared>,
+        pub(super) decompressors: super::Decompressors,
+        #[cfg(feature = "zstd")]
+        pub(super) zstd_dictionary: Option<super::Arc<[u8]>>,
     }
 }
 
 pub use zip_archive::ZipArchive;
+
+/// A custom decompressor, registered with [`ZipArchive::register_decompressor`]
+///
+/// It is given the raw (but decrypted) data of an entry, and returns a reader for the
+/// decompressed data.
+pub type Decompressor = Arc<dyn Fn(Box<dyn Read + '_>) -> Box<dyn Read + '_> + Send + Sync>;
+
+/// The custom decompressors of an archive, by compression method ID
+#[derive(Clone, Default)]
+pub(crate) struct Decompressors(HashMap<u16, Decompressor>);
+
+impl Decompressors {
+    /// Get the custom decompressor for a compression method, if it is not otherwise supported
+    fn get(&self, method: CompressionMethod) -> Option<&Decompressor> {
+        #[allow(deprecated)]
+        match method {
+            CompressionMethod::Unsupported(id) => self.0.get(&id),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Debug for Decompressors {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_set().entries(self.0.keys()).finish()
+    }
+}
+
+/// Configuration for how a [`ZipArchive`] is parsed and which limits are enforced
+///
+/// The defaults match the behavior of [`ZipArchive::new`]. Options are set with the
+/// builder-style methods:
+///
+/// ```no_run
+/// # fn main() -> zip::result::ZipResult<()> {
+/// use zip::read::ZipReaderConfig;
+///
+/// let config = ZipReaderConfig::default()
+///     .max_entries(Some(10_000))
+///     .validate_entry_overlap(true);
+/// let file = std::fs::File::open("archive.zip")?;
+/// let archive = zip::ZipArchive::with_config(file, config)?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct ZipReaderConfig {
+    /// Fall back to the regular end of central directory record when the ZIP64 records are
+    /// missing or inconsistent, instead of failing. Default: `false`.
+    ///
+    /// This only applies when the regular record holds real values rather than the `0xFFFF`
+    /// and `0xFFFFFFFF` placeholders that defer to ZIP64.
+    pub lenient_zip64: bool,
+    /// The maximum number of entries the central directory may declare. Default: no limit.
+    pub max_entries: Option<usize>,
+    /// The maximum size of the central directory in bytes, which bounds the memory spent on
+    /// names, extra fields and comments. Default: no limit.
+    pub max_metadata_bytes: Option<u64>,
+    /// How entry names are decoded. Default: [`FileNameEncoding::Auto`].
+    pub file_name_encoding: FileNameEncoding,
+    /// Reject archives where the data of two entries overlaps, or runs into the central
+    /// directory. Default: `false`.
+    pub validate_entry_overlap: bool,
+    /// Use the offsets recorded in the central directory as-is. Default: `false`.
+    ///
+    /// By default, the difference between where the central directory is found and where it
+    /// claims to be is assumed to be data prepended to the archive (see
+    /// [`ZipArchive::offset`]), and all offsets are shifted accordingly.
+    pub trust_central_directory_offsets: bool,
+    /// The size of the buffer the data of entries is read through, before it is decrypted and
+    /// decompressed. Default: no buffer.
+    ///
+    /// The decompressors already read through buffers of their own, but larger buffers make
+    /// fewer and larger reads, which suits readers with a high latency per read, like network
+    /// filesystems. The buffer may read past the data of the entry.
+    pub buffer_size: Option<usize>,
+    /// The maximum number of bytes of trailing data after the archive comment, such as padding
+    /// or signature blocks appended by other tools. Default: no limit.
+    ///
+    /// The end of central directory is only searched for in the last 64 KiB of the archive, so
+    /// more trailing data is never accepted.
+    pub max_trailing_bytes: Option<u64>,
+}
+
+impl ZipReaderConfig {
+    /// Set whether inconsistent ZIP64 records fall back to the regular end of central directory
+    /// record
+    #[must_use]
+    pub fn lenient_zip64(mut self, lenient: bool) -> ZipReaderConfig {
+        self.lenient_zip64 = lenient;
+        self
+    }
+
+    /// Set the maximum number of entries the archive may contain
+    #[must_use]
+    pub fn max_entries(mut self, max: Option<usize>) -> ZipReaderConfig {
+        self.max_entries = max;
+        self
+    }
+
+    /// Set the maximum size of the central directory in bytes
+    #[must_use]
+    pub fn max_metadata_bytes(mut self, max: Option<u64>) -> ZipReaderConfig {
+        self.max_metadata_bytes = max;
+        self
+    }
+
+    /// Set the maximum number of bytes of trailing data after the archive comment
+    #[must_use]
+    pub fn max_trailing_bytes(mut self, max: Option<u64>) -> ZipReaderConfig {
+        self.max_trailing_bytes = max;
+        self
+    }
+
+    /// Set how entry names are decoded
+    #[must_use]
+    pub fn file_name_encoding(mut self, encoding: FileNameEncoding) -> ZipReaderConfig {
+        self.file_name_encoding = encoding;
+        self
+    }
+
+    /// Set whether overlapping entries are rejected
+    #[must_use]
+    pub fn validate_entry_overlap(mut self, validate: bool) -> ZipReaderConfig {
+        self.validate_entry_overlap = validate;
+        self
+    }
+
+    /// Set whether the offsets in the central directory are used without adjusting for
+    /// prepended data
+    #[must_use]
+    pub fn trust_central_directory_offsets(mut self, trust: bool) -> ZipReaderConfig {
+        self.trust_central_directory_offsets = trust;
+        self
+    }
+
+    /// Set the size of the buffer the data of entries is read through
+    #[must_use]
+    pub fn buffer_size(mut self, size: Option<usize>) -> ZipReaderConfig {
+        self.buffer_size = size;
+        self
+    }
+}
+
+impl Default for ZipReaderConfig {
+    fn default() -> Self {
+        Self {
+            lenient_zip64: false,
+            max_entries: None,
+            max_metadata_bytes: None,
+            file_name_encoding: FileNameEncoding::Auto,
+            validate_entry_overlap: false,
+            trust_central_directory_offsets: false,
+            buffer_size: None,
+            max_trailing_bytes: None,
+        }
+    }
+}
+
+/// The character encoding used to decode entry names
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum FileNameEncoding {
+    /// UTF-8 if the entry has the language encoding flag (bit 11) set, IBM codepage 437 otherwise
+    ///
+    /// The name of an Info-ZIP Unicode Path extra field is used instead, when it was written for
+    /// the same name.
+    Auto,
+    /// Always UTF-8, replacing invalid sequences
+    ///
+    /// Some tools write UTF-8 names without setting the language encoding flag.
+    Utf8,
+    /// Always IBM codepage 437
+    Cp437,
+}
+
+/// Default size of [`ExtractOptions::buffer_size`]
+const DEFAULT_EXTRACT_BUFFER_SIZE: usize = 64 * 1024;
+
+/// A function mapping the paths of extracted entries, set with [`ExtractOptions::map_name`]
+pub type NameMapper = Arc<dyn Fn(&Path) -> Option<PathBuf> + Send + Sync>;
+
+/// A function choosing the extended attributes to restore, set with
+/// [`ExtractOptions::xattr_filter`]
+pub type XattrFilter = Arc<dyn Fn(&OsStr) -> bool + Send + Sync>;
+
+/// Options for [`ZipArchive::extract_with_options`]
+#[derive(Clone)]
+#[non_exhaustive]
+pub struct ExtractOptions {
+    /// The algorithm of the digests of the extracted files. Default: no digests.
+    #[cfg(feature = "sha2")]
+    pub hasher: Option<HashAlgorithm>,
+    /// Set the extended attributes of the extracted files and directories. Default: `false`.
+    pub restore_xattrs: bool,
+    /// The function choosing the extended attributes to restore. Default: none, which restores
+    /// those in the `user` namespace.
+    pub xattr_filter: Option<XattrFilter>,
+    /// Seek over chunks of zeros instead of writing them. Default: `false`.
+    pub sparse: bool,
+    /// The size of the buffer the files are copied through. Default: 64 KiB.
+    pub buffer_size: usize,
+    /// The number of leading components removed from the paths of the entries. Default: 0.
+    pub strip_components: usize,
+    /// The function mapping the paths of the entries to those they are extracted to. Default:
+    /// none.
+    pub map_name: Option<NameMapper>,
+    /// Make the files with the MS-DOS read-only attribute read-only, on Windows. Default: `false`.
+    pub dos_read_only: bool,
+}
+
+impl ExtractOptions {
+    /// Compute a digest of each extracted file with `algorithm`, while it is written
+    ///
+    /// The digests are those of the decompressed and decrypted data, as written to disk, and are
+    /// returned in [`ExtractSummary::digests`].
+    #[cfg(feature = "sha2")]
+    #[must_use]
+    pub fn hasher(mut self, algorithm: HashAlgorithm) -> ExtractOptions {
+        self.hasher = Some(algorithm);
+        self
+    }
+
+    /// Set whether the extended attributes written with
+    /// [`FileOptions::xattrs`](crate::write::FileOptions::xattrs) are set on the extracted files
+    /// and directories
+    ///
+    /// This is only supported on Linux, Android and macOS, and does nothing elsewhere. Failing to
+    /// set an attribute doesn't stop the extraction: the errors are returned in
+    /// [`ExtractSummary::xattr_errors`].
+    ///
+    /// Only the attributes in the `user` namespace, such as `user.origin`, are restored, unless
+    /// [`ExtractOptions::xattr_filter`] is set. Other namespaces can grant privileges when
+    /// extracting as root, like `security.capability` for executables.
+    #[must_use]
+    pub fn restore_xattrs(mut self, restore: bool) -> ExtractOptions {
+        self.restore_xattrs = restore;
+        self
+    }
+
+    /// Restore the extended attributes for which `filter` returns `true`, instead of those in the
+    /// `user` namespace
+    ///
+    /// This only applies with [`ExtractOptions::restore_xattrs`]. On macOS, names have no
+    /// namespace, so no attribute is restored without a filter.
+    #[must_use]
+    pub fn xattr_filter<F>(mut self, filter: F) -> ExtractOptions
+    where
+        F: Fn(&OsStr) -> bool + Send + Sync + 'static,
+    {
+        self.xattr_filter = Some(Arc::new(filter));
+        self
+    }
+
+    /// Set whether the files are extracted as sparse files
+    ///
+    /// Chunks of [`ExtractOptions::buffer_size`] bytes that only hold zeros are then skipped with
+    /// a seek rather than written, so that filesystems supporting sparse files leave holes instead
+    /// of allocating them. This saves space and time for files such as disk images. The extracted
+    /// files have the same contents either way.
+    #[must_use]
+    pub fn sparse(mut self, sparse: bool) -> ExtractOptions {
+        self.sparse = sparse;
+        self
+    }
+
+    /// Set the size of the buffer the files are copied through, which is also the size of the
+    /// chunks of [`ExtractOptions::sparse`]
+    ///
+    /// Sizes of zero are replaced by 1.
+    #[must_use]
+    pub fn buffer_size(mut self, size: usize) -> ExtractOptions {
+        self.buffer_size = size;
+        self
+    }
+
+    /// Remove the first `count` components from the paths of the entries, like
+    /// `tar --strip-components`
+    ///
+    /// This applies to the paths given by [`ZipFile::enclosed_name`]. Entries with no more than
+    /// `count` components, such as the directory wrapping the others in source archives, are
+    /// skipped. Entries whose paths become the same are extracted in turn, each overwriting the
+    /// previous one.
+    #[must_use]
+    pub fn strip_components(mut self, count: usize) -> ExtractOptions {
+        self.strip_components = count;
+        self
+    }
+
+    /// Extract the entries to the paths returned by `mapper`, and skip those for which it returns
+    /// `None`
+    ///
+    /// `mapper` is given the relative path of each entry, after
+    /// [`ExtractOptions::strip_components`], and must return a relative path, which the entry is
+    /// extracted to in the directory. Extraction fails if it returns an absolute path or one with
+    /// `..` components.
+    ///
+    /// ```no_run
+    /// # fn main() -> zip::result::ZipResult<()> {
+    /// use std::path::Path;
+    /// use zip::read::ExtractOptions;
+    ///
+    /// let mut archive = zip::ZipArchive::new(std::fs::File::open("archive.zip")?)?;
+    /// let options = ExtractOptions::default().map_name(|path| {
+    ///     match path.strip_prefix("docs") {
+    ///         Ok(doc) => Some(Path::new("share/doc").join(doc)),
+    ///         Err(_) if path.ends_with(".DS_Store") => None,
+    ///         Err(_) => Some(path.to_path_buf()),
+    ///     }
+    /// });
+    /// archive.extract_with_options("out", &options)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[must_use]
+    pub fn map_name<F>(mut self, mapper: F) -> ExtractOptions
+    where
+        F: Fn(&Path) -> Option<PathBuf> + Send + Sync + 'static,
+    {
+        self.map_name = Some(Arc::new(mapper));
+        self
+    }
+
+    /// Set whether the files with the read-only attribute of
+    /// [`ZipFile::dos_attributes`] are made read-only
+    ///
+    /// This is only supported on Windows, and does nothing elsewhere, where the permissions of
+    /// [`ZipFile::unix_mode`] are set instead.
+    #[must_use]
+    pub fn dos_read_only(mut self, read_only: bool) -> ExtractOptions {
+        self.dos_read_only = read_only;
+        self
+    }
+}
+
+impl std::fmt::Debug for ExtractOptions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut debug = f.debug_struct("ExtractOptions");
+        #[cfg(feature = "sha2")]
+        debug.field("hasher", &self.hasher);
+        debug
+            .field("restore_xattrs", &self.restore_xattrs)
+            .field("xattr_filter", &self.xattr_filter.as_ref().map(|_| ".."))
+            .field("sparse", &self.sparse)
+            .field("buffer_size", &self.buffer_size)
+            .field("strip_components", &self.strip_components)
+            .field("map_name", &self.map_name.as_ref().map(|_| ".."))
+            .field("dos_read_only", &self.dos_read_only)
+            .finish()
+    }
+}
+
+impl Default for ExtractOptions {
+    fn default() -> Self {
+        Self {
+            #[cfg(feature = "sha2")]
+            hasher: None,
+            restore_xattrs: false,
+            xattr_filter: None,
+            sparse: false,
+            buffer_size: DEFAULT_EXTRACT_BUFFER_SIZE,
+            strip_components: 0,
+            map_name: None,
+            dos_read_only: false,
+        }
+    }
+}
+
+/// A hash algorithm for [`ExtractOptions::hasher`]
+#[cfg(feature = "sha2")]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum HashAlgorithm {
+    /// SHA-256
+    Sha256,
+}
+
+#[cfg(feature = "sha2")]
+impl HashAlgorithm {
+    fn digest(self) -> Box<dyn sha2::digest::DynDigest> {
+        match self {
+            HashAlgorithm::Sha256 => Box::new(sha2::Sha256::default()),
+        }
+    }
+}
+
+/// What [`ZipArchive::extract_with_options`] did
+#[derive(Debug, Default)]
+#[non_exhaustive]
+pub struct ExtractSummary {
+    /// The digests of the extracted files, by entry name, if [`ExtractOptions::hasher`] was set
+    ///
+    /// Directories and symlinks have no digest.
+    pub digests: BTreeMap<String, Vec<u8>>,
+    /// The errors setting extended attributes, by entry name, if
+    /// [`ExtractOptions::restore_xattrs`] was set
+    pub xattr_errors: Vec<(String, io::Error)>,
+}
+
 #[allow(clippy::large_enum_variant)]
 enum CryptoReader<'a> {
-    Plaintext(io::Take<&'a mut dyn Read>),
-    ZipCrypto(ZipCryptoReaderValid<io::Take<&'a mut dyn Read>>),
+    Plaintext(io::Take<Box<dyn Read + 'a>>),
+    ZipCrypto(ZipCryptoReaderValid<io::Take<Box<dyn Read + 'a>>>),
     #[cfg(feature = "aes-crypto")]
     Aes {
-        reader: AesReaderValid<io::Take<&'a mut dyn Read>>,
+        reader: AesReaderValid<io::Take<Box<dyn Read + 'a>>>,
         vendor_version: AesVendorVersion,
     },
 }
@@ -95,7 +521,7 @@ impl<'a> Read for CryptoReader<'a> {
 
 impl<'a> CryptoReader<'a> {
     /// Consumes this decoder, returning the underlying reader.
-    pub fn into_inner(self) -> io::Take<&'a mut dyn Read> {
+    pub fn into_inner(self) -> io::Take<Box<dyn Read + 'a>> {
         match self {
             CryptoReader::Plaintext(r) => r,
             CryptoReader::ZipCrypto(r) => r.into_inner(),
@@ -121,7 +547,7 @@ impl<'a> CryptoReader<'a> {
 
 enum ZipFileReader<'a> {
     NoReader,
-    Raw(io::Take<&'a mut dyn io::Read>),
+    Raw(io::Take<Box<dyn Read + 'a>>),
     Stored(Crc32Reader<CryptoReader<'a>>),
     #[cfg(any(
         feature = "deflate",
@@ -133,6 +559,11 @@ enum ZipFileReader<'a> {
     Bzip2(Crc32Reader<BzDecoder<CryptoReader<'a>>>),
     #[cfg(feature = "zstd")]
     Zstd(Crc32Reader<ZstdDecoder<'a, io::BufReader<CryptoReader<'a>>>>),
+    #[cfg(feature = "xz")]
+    Xz(Crc32Reader<XzDecoder<CryptoReader<'a>>>),
+    #[cfg(feature = "legacy-decompress")]
+    Legacy(Crc32Reader<LegacyDecoder<CryptoReader<'a>>>),
+    Custom(Crc32Reader<Box<dyn Read + 'a>>),
 }
 
 impl<'a> Read for ZipFileReader<'a> {
@@ -151,14 +582,21 @@ impl<'a> Read for ZipFileReader<'a> {
             ZipFileReader::Bzip2(r) => r.read(buf),
             #[cfg(feature = "zstd")]
             ZipFileReader::Zstd(r) => r.read(buf),
+            #[cfg(feature = "xz")]
+            ZipFileReader::Xz(r) => r.read(buf),
+            #[cfg(feature = "legacy-decompress")]
+            ZipFileReader::Legacy(r) => r.read(buf),
+            ZipFileReader::Custom(r) => r.read(buf),
         }
     }
 }
 
 impl<'a> ZipFileReader<'a> {
     /// Consumes this decoder, returning the underlying reader.
-    pub fn into_inner(self) -> io::Take<&'a mut dyn Read> {
-        match self {
+    ///
+    /// Custom decompressors own the reader they were given, so it can't be returned.
+    pub fn into_inner(self) -> io::Result<io::Take<Box<dyn Read + 'a>>> {
+        Ok(match self {
             ZipFileReader::NoReader => panic!("ZipFileReader was in an invalid state"),
             ZipFileReader::Raw(r) => r,
             ZipFileReader::Stored(r) => r.into_inner().into_inner(),
@@ -172,7 +610,17 @@ impl<'a> ZipFileReader<'a> {
             ZipFileReader::Bzip2(r) => r.into_inner().into_inner().into_inner(),
             #[cfg(feature = "zstd")]
             ZipFileReader::Zstd(r) => r.into_inner().finish().into_inner().into_inner(),
-        }
+            #[cfg(feature = "xz")]
+            ZipFileReader::Xz(r) => r.into_inner().into_inner().into_inner(),
+            #[cfg(feature = "legacy-decompress")]
+            ZipFileReader::Legacy(r) => r.into_inner().into_inner().into_inner(),
+            ZipFileReader::Custom(_) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::Other,
+                    "Custom decompressors cannot be unwrapped",
+                ))
+            }
+        })
     }
 }
 
@@ -180,13 +628,28 @@ impl<'a> ZipFileReader<'a> {
 pub struct ZipFile<'a> {
     data: Cow<'a, ZipFileData>,
     crypto_reader: Option<CryptoReader<'a>>,
+    decompressor: Option<Decompressor>,
+    #[cfg(feature = "zstd")]
+    zstd_dictionary: Option<Arc<[u8]>>,
     reader: ZipFileReader<'a>,
 }
 
+/// Seek to the data of a file, and limit `reader` to it, reading through a buffer of
+/// `buffer_size` bytes if there is one
 fn find_content<'a>(
     data: &ZipFileData,
-    reader: &'a mut (impl Read + Seek),
-) -> ZipResult<io::Take<&'a mut dyn Read>> {
+    mut reader: impl Read + Seek + 'a,
+    buffer_size: Option<usize>,
+) -> ZipResult<io::Take<Box<dyn Read + 'a>>> {
+    seek_to_data_start(data, &mut reader).map_err(|e| e.with_entry(&data.file_name))?;
+    let reader: Box<dyn Read + 'a> = match buffer_size {
+        Some(size) => Box::new(io::BufReader::with_capacity(size, reader)),
+        None => Box::new(reader),
+    };
+    Ok(reader.take(data.compressed_size))
+}
+
+fn seek_to_data_start(data: &ZipFileData, reader: &mut (impl Read + Seek)) -> ZipResult<()> {
     // Parse local header
     reader.seek(io::SeekFrom::Start(data.header_start))?;
     let signature = reader.read_u32::<LittleEndian>()?;
@@ -201,29 +664,59 @@ fn find_content<'a>(
     let data_start = data.header_start + magic_and_header + file_name_length + extra_field_length;
     data.data_start.store(data_start);
 
-    reader.seek(io::SeekFrom::Start(data_start))?;
-    Ok((reader as &mut dyn Read).take(data.compressed_size))
+    reader.seek(io::SeekFrom::Current(file_name_length as i64))?;
+    let mut extra_field = vec![0; extra_field_length as usize];
+    reader.read_exact(&mut extra_field)?;
+    data.data_alignment
+        .store(padded_data_alignment(&extra_field, data_start));
+    Ok(())
+}
+
+/// Alignment of data starting at `data_start`, after a local header with the extra field
+/// `extra_field`: the largest power of two that `data_start` is a multiple of, if a padding field
+/// aligns it, and 1 otherwise
+///
+/// Padding fields have the IDs used by `zipalign` and by [`crate::ZipWriter`]. A 20-byte one may
+/// only be the room that the writer reserves for a ZIP64 field, so it isn't taken as aligning.
+pub(crate) fn padded_data_alignment(mut extra_field: &[u8], data_start: u64) -> u64 {
+    let mut padded = false;
+    while extra_field.len() >= 4 {
+        let kind = u16::from_le_bytes([extra_field[0], extra_field[1]]);
+        let size = 4 + u16::from_le_bytes([extra_field[2], extra_field[3]]) as usize;
+        padded |= matches!(kind, 0x0000 | 0x617a | 0xd935) && size != 20;
+        extra_field = &extra_field[size.min(extra_field.len())..];
+    }
+    if padded {
+        // The largest alignment that `FileOptions::alignment` takes
+        1 << data_start.trailing_zeros().min(15)
+    } else {
+        1
+    }
+}
+
+/// What an encrypted file is decrypted with
+#[derive(Clone, Copy)]
+enum DecryptionKey<'a> {
+    Password(&'a [u8]),
+    ZipCrypto(ZipCryptoKeys),
 }
 
 #[allow(clippy::too_many_arguments)]
 fn make_crypto_reader<'a>(
-    compression_method: crate::compression::CompressionMethod,
     crc32: u32,
     last_modified_time: DateTime,
     using_data_descriptor: bool,
-    reader: io::Take<&'a mut dyn io::Read>,
-    password: Option<&[u8]>,
+    reader: io::Take<Box<dyn Read + 'a>>,
+    key: Option<DecryptionKey>,
     aes_info: Option<(AesMode, AesVendorVersion)>,
     #[cfg(feature = "aes-crypto")] compressed_size: u64,
-) -> ZipResult<Result<CryptoReader<'a>, InvalidPassword>> {
-    #[allow(deprecated)]
-    {
-        if let CompressionMethod::Unsupported(_) = compression_method {
-            return unsupported_zip_error("Compression method not supported");
+) -> ZipResult<CryptoReader<'a>> {
+    let reader = match (key, aes_info) {
+        (Some(DecryptionKey::ZipCrypto(_)), Some(_)) => {
+            return Err(ZipError::UnsupportedArchive(
+                "AES encrypted files cannot be decrypted with ZipCrypto keys",
+            ))
         }
-    }
-
-    let reader = match (password, aes_info) {
         #[cfg(not(feature = "aes-crypto"))]
         (Some(_), Some(_)) => {
             return Err(ZipError::UnsupportedArchive(
@@ -231,40 +724,51 @@ fn make_crypto_reader<'a>(
             ))
         }
         #[cfg(feature = "aes-crypto")]
-        (Some(password), Some((aes_mode, vendor_version))) => {
-            match AesReader::new(reader, aes_mode, compressed_size).validate(password)? {
-                None => return Ok(Err(InvalidPassword)),
+        (Some(DecryptionKey::Password(password)), Some((aes_mode, vendor_version))) => {
+            match AesReader::new(reader, aes_mode, compressed_size)?.validate(password)? {
+                None => return Err(ZipError::InvalidPassword),
                 Some(r) => CryptoReader::Aes {
                     reader: r,
                     vendor_version,
                 },
             }
         }
-        (Some(password), None) => {
+        (Some(key), None) => {
             let validator = if using_data_descriptor {
                 ZipCryptoValidator::InfoZipMsdosTime(last_modified_time.timepart())
             } else {
                 ZipCryptoValidator::PkzipCrc32(crc32)
             };
-            match ZipCryptoReader::new(reader, password).validate(validator)? {
-                None => return Ok(Err(InvalidPassword)),
+            let reader = match key {
+                DecryptionKey::Password(password) => ZipCryptoReader::new(reader, password),
+                DecryptionKey::ZipCrypto(keys) => ZipCryptoReader::with_keys(reader, keys),
+            };
+            match reader.validate(validator)? {
+                None => return Err(ZipError::InvalidPassword),
                 Some(r) => CryptoReader::ZipCrypto(r),
             }
         }
-        (None, Some(_)) => return Ok(Err(InvalidPassword)),
+        (None, Some(_)) => return Err(ZipError::UnsupportedArchive(ZipError::PASSWORD_REQUIRED)),
         (None, None) => CryptoReader::Plaintext(reader),
     };
-    Ok(Ok(reader))
+    Ok(reader)
 }
 
-fn make_reader(
-    compression_method: CompressionMethod,
-    crc32: u32,
-    reader: CryptoReader,
-) -> ZipFileReader {
+fn make_reader<'a>(
+    data: &ZipFileData,
+    reader: CryptoReader<'a>,
+    decompressor: Option<&Decompressor>,
+    #[cfg(feature = "zstd")] zstd_dictionary: Option<&[u8]>,
+) -> ZipFileReader<'a> {
     let ae2_encrypted = reader.is_ae2_encrypted();
+    let crc32 = data.crc32;
 
-    match compression_method {
+    if let Some(decompressor) = decompressor {
+        let custom_reader = decompressor(Box::new(reader));
+        return ZipFileReader::Custom(Crc32Reader::new(custom_reader, crc32, ae2_encrypted));
+    }
+
+    match data.compression_method {
         CompressionMethod::Stored => {
             ZipFileReader::Stored(Crc32Reader::new(reader, crc32, ae2_encrypted))
         }
@@ -284,129 +788,322 @@ fn make_reader(
         }
         #[cfg(feature = "zstd")]
         CompressionMethod::Zstd => {
-            let zstd_reader = ZstdDecoder::new(reader).unwrap();
+            // The dictionary was checked when it was set
+            let zstd_reader = match zstd_dictionary {
+                Some(dictionary) => {
+                    ZstdDecoder::with_dictionary(io::BufReader::new(reader), dictionary).unwrap()
+                }
+                None => ZstdDecoder::new(reader).unwrap(),
+            };
             ZipFileReader::Zstd(Crc32Reader::new(zstd_reader, crc32, ae2_encrypted))
         }
+        #[cfg(feature = "xz")]
+        CompressionMethod::Xz => {
+            let xz_reader = XzDecoder::new(reader);
+            ZipFileReader::Xz(Crc32Reader::new(xz_reader, crc32, ae2_encrypted))
+        }
+        #[cfg(feature = "legacy-decompress")]
+        method @ (CompressionMethod::Shrink
+        | CompressionMethod::Reduce1
+        | CompressionMethod::Reduce2
+        | CompressionMethod::Reduce3
+        | CompressionMethod::Reduce4
+        | CompressionMethod::Implode) => {
+            let method = LegacyMethod::new(method, data.flags);
+            let legacy_reader = LegacyDecoder::new(reader, method, data.uncompressed_size);
+            ZipFileReader::Legacy(Crc32Reader::new(legacy_reader, crc32, ae2_encrypted))
+        }
         _ => panic!("Compression method not supported"),
     }
 }
 
+impl<R: Read + io::Seek> ZipArchive<SplitReader<R>> {
+    /// Read a split (spanned) ZIP archive from its parts, in order
+    ///
+    /// For archives written by WinZip, 7-Zip or Info-ZIP, this is `.z01`, `.z02`, ... followed by
+    /// the `.zip` file. Use [`ZipArchive::open_split`] to find the parts from the path of the
+    /// `.zip` file.
+    pub fn new_split(parts: Vec<R>) -> ZipResult<ZipArchive<SplitReader<R>>> {
+        Self::split_with_config(parts, Default::default())
+    }
+
+    /// Read a split (spanned) ZIP archive from its parts using the given [`ZipReaderConfig`]
+    pub fn split_with_config(
+        parts: Vec<R>,
+        config: ZipReaderConfig,
+    ) -> ZipResult<ZipArchive<SplitReader<R>>> {
+        let reader = SplitReader::new(parts)?;
+        let part_starts = reader.part_starts().to_vec();
+        Self::read_central_directory(reader, config, Some(&part_starts), &mut Vec::new())
+    }
+}
+
+impl ZipArchive<SplitReader<std::fs::File>> {
+    /// Open a split (spanned) ZIP archive, given the path of its last part
+    ///
+    /// The other parts are expected next to it, with the same name and the extensions `.z01`,
+    /// `.z02` and so on. An archive that was not split is opened as a single part.
+    pub fn open_split<P: AsRef<Path>>(
+        path: P,
+    ) -> ZipResult<ZipArchive<SplitReader<std::fs::File>>> {
+        let reader = SplitReader::open(path)?;
+        let part_starts = reader.part_starts().to_vec();
+        Self::read_central_directory(
+            reader,
+            Default::default(),
+            Some(&part_starts),
+            &mut Vec::new(),
+        )
+    }
+}
+
 impl<R: Read + io::Seek> ZipArchive<R> {
-    /// Get the directory start offset and number of files. This is done in a
-    /// separate function to ease the control flow design.
+    /// Get the directory start offset, recorded directory size and number of files. This is done
+    /// in a separate function to ease the control flow design.
     pub(crate) fn get_directory_counts(
         reader: &mut R,
         footer: &spec::CentralDirectoryEnd,
         cde_start_pos: u64,
-    ) -> ZipResult<(u64, u64, usize)> {
-        // See if there's a ZIP64 footer. The ZIP64 locator if present will
-        // have its signature 20 bytes in front of the standard footer. The
-        // standard footer, in turn, is 22+N bytes large, where N is the
-        // comment length. Therefore:
-        let zip64locator = if reader
-            .seek(io::SeekFrom::End(
-                -(20 + 22 + footer.zip_file_comment.len() as i64),
-            ))
-            .is_ok()
-        {
-            match spec::Zip64CentralDirectoryEndLocator::parse(reader) {
-                Ok(loc) => Some(loc),
-                Err(ZipError::InvalidArchive(_)) => {
-                    // No ZIP64 header; that's actually fine. We're done here.
-                    None
-                }
-                Err(e) => {
-                    // Yikes, a real problem
-                    return Err(e);
+        zip64_locator: Option<spec::Zip64CentralDirectoryEndLocator>,
+        config: &ZipReaderConfig,
+        diagnostics: &mut Vec<ParseDiagnostic>,
+    ) -> ZipResult<(u64, u64, u64, usize)> {
+        if let Some(locator64) = zip64_locator {
+            // If we got here, this is indeed a ZIP64 file.
+            match Self::get_zip64_directory_counts(reader, footer, cde_start_pos, locator64, config)
+            {
+                Err(_) if config.lenient_zip64 && !footer.record_too_small() => {
+                    // The regular footer holds real values, so it can stand in for the
+                    // broken ZIP64 records.
+                    diagnostics.push(ParseDiagnostic::Zip64EndIgnored);
                 }
+                result => return result,
             }
+        }
+
+        // Some zip files have data prepended to them, resulting in the
+        // offsets all being too small. Get the amount of error by comparing
+        // the actual file position we found the CDE at with the offset
+        // recorded in the CDE.
+        let archive_offset = if config.trust_central_directory_offsets {
+            0
         } else {
-            // Empty Zip files will have nothing else so this error might be fine. If
-            // not, we'll find out soon.
-            None
+            cde_start_pos
+                .checked_sub(footer.central_directory_size as u64)
+                .and_then(|x| x.checked_sub(footer.central_directory_offset as u64))
+                .ok_or(ZipError::InvalidArchive(
+                    "Invalid central directory size or offset",
+                ))?
         };
 
-        match zip64locator {
-            None => {
-                // Some zip files have data prepended to them, resulting in the
-                // offsets all being too small. Get the amount of error by comparing
-                // the actual file position we found the CDE at with the offset
-                // recorded in the CDE.
-                let archive_offset = cde_start_pos
-                    .checked_sub(footer.central_directory_size as u64)
-                    .and_then(|x| x.checked_sub(footer.central_directory_offset as u64))
-                    .ok_or(ZipError::InvalidArchive(
-                        "Invalid central directory size or offset",
-                    ))?;
-
-                let directory_start = footer.central_directory_offset as u64 + archive_offset;
-                let number_of_files = footer.number_of_files_on_this_disk as usize;
-                Ok((archive_offset, directory_start, number_of_files))
+        let directory_start = footer.central_directory_offset as u64 + archive_offset;
+        let number_of_files = footer.number_of_files_on_this_disk as usize;
+        Ok((
+            archive_offset,
+            directory_start,
+            footer.central_directory_size as u64,
+            number_of_files,
+        ))
+    }
+
+    /// Get the directory start offset, recorded directory size and number of files of a split
+    /// archive whose parts start at `part_starts`.
+    fn get_split_directory_counts(
+        reader: &mut R,
+        footer: &spec::CentralDirectoryEnd,
+        zip64_locator: Option<spec::Zip64CentralDirectoryEndLocator>,
+        part_starts: &[u64],
+    ) -> ZipResult<(u64, u64, usize)> {
+        if let Some(locator64) = zip64_locator {
+            if locator64.number_of_disks as usize != part_starts.len() {
+                return Err(ZipError::InvalidArchive(
+                    "Number of parts does not match the archive",
+                ));
             }
-            Some(locator64) => {
-                // If we got here, this is indeed a ZIP64 file.
+            let footer64_start = part_start(part_starts, locator64.disk_with_central_directory)?
+                .checked_add(locator64.end_of_central_directory_offset)
+                .ok_or(ZipError::InvalidArchive(
+                    "Invalid ZIP64 central directory end offset",
+                ))?;
+            let (footer64, _) = spec::Zip64CentralDirectoryEnd::find_and_parse(
+                reader,
+                footer64_start,
+                footer64_start,
+            )?;
+            let directory_start = part_start(part_starts, footer64.disk_with_central_directory)?
+                .checked_add(footer64.central_directory_offset)
+                .ok_or(ZipError::InvalidArchive(
+                    "Invalid central directory size or offset",
+                ))?;
+            return Ok((
+                directory_start,
+                footer64.central_directory_size,
+                footer64.number_of_files as usize,
+            ));
+        }
 
-                if !footer.record_too_small()
-                    && footer.disk_number as u32 != locator64.disk_with_central_directory
-                {
-                    return unsupported_zip_error(
-                        "Support for multi-disk files is not implemented",
-                    );
-                }
+        if footer.disk_number as usize + 1 != part_starts.len() {
+            return Err(ZipError::InvalidArchive(
+                "Number of parts does not match the archive",
+            ));
+        }
+        let directory_start = part_start(part_starts, footer.disk_with_central_directory as u32)?
+            + footer.central_directory_offset as u64;
+        Ok((
+            directory_start,
+            footer.central_directory_size as u64,
+            footer.number_of_files as usize,
+        ))
+    }
 
-                // We need to reassess `archive_offset`. We know where the ZIP64
-                // central-directory-end structure *should* be, but unfortunately we
-                // don't know how to precisely relate that location to our current
-                // actual offset in the file, since there may be junk at its
-                // beginning. Therefore we need to perform another search, as in
-                // read::CentralDirectoryEnd::find_and_parse, except now we search
-                // forward.
-
-                let search_upper_bound = cde_start_pos
-                    .checked_sub(60) // minimum size of Zip64CentralDirectoryEnd + Zip64CentralDirectoryEndLocator
-                    .ok_or(ZipError::InvalidArchive(
-                        "File cannot contain ZIP64 central directory end",
-                    ))?;
-                let (footer, archive_offset) = spec::Zip64CentralDirectoryEnd::find_and_parse(
-                    reader,
-                    locator64.end_of_central_directory_offset,
-                    search_upper_bound,
-                )?;
-
-                if footer.disk_number != footer.disk_with_central_directory {
-                    return unsupported_zip_error(
-                        "Support for multi-disk files is not implemented",
-                    );
-                }
+    fn get_zip64_directory_counts(
+        reader: &mut R,
+        footer: &spec::CentralDirectoryEnd,
+        cde_start_pos: u64,
+        locator64: spec::Zip64CentralDirectoryEndLocator,
+        config: &ZipReaderConfig,
+    ) -> ZipResult<(u64, u64, u64, usize)> {
+        if !footer.record_too_small()
+            && footer.disk_number as u32 != locator64.disk_with_central_directory
+        {
+            return unsupported_zip_error("Support for multi-disk files is not implemented");
+        }
 
-                let directory_start = footer
-                    .central_directory_offset
-                    .checked_add(archive_offset)
-                    .ok_or({
-                        ZipError::InvalidArchive("Invalid central directory size or offset")
-                    })?;
-
-                Ok((
-                    archive_offset,
-                    directory_start,
-                    footer.number_of_files as usize,
-                ))
-            }
+        // We need to reassess `archive_offset`. We know where the ZIP64
+        // central-directory-end structure *should* be, but unfortunately we
+        // don't know how to precisely relate that location to our current
+        // actual offset in the file, since there may be junk at its
+        // beginning. Therefore we need to perform another search, as in
+        // read::CentralDirectoryEnd::find_and_parse, except now we search
+        // forward.
+
+        let search_upper_bound = if config.trust_central_directory_offsets {
+            locator64.end_of_central_directory_offset
+        } else {
+            cde_start_pos
+                .checked_sub(60) // minimum size of Zip64CentralDirectoryEnd + Zip64CentralDirectoryEndLocator
+                .ok_or(ZipError::InvalidArchive(
+                    "File cannot contain ZIP64 central directory end",
+                ))?
+        };
+        let (footer, archive_offset) = spec::Zip64CentralDirectoryEnd::find_and_parse(
+            reader,
+            locator64.end_of_central_directory_offset,
+            search_upper_bound,
+        )?;
+
+        if footer.disk_number != footer.disk_with_central_directory {
+            return unsupported_zip_error("Support for multi-disk files is not implemented");
         }
+
+        let directory_start = footer
+            .central_directory_offset
+            .checked_add(archive_offset)
+            .ok_or(ZipError::InvalidArchive(
+                "Invalid central directory size or offset",
+            ))?;
+
+        Ok((
+            archive_offset,
+            directory_start,
+            footer.central_directory_size,
+            footer.number_of_files as usize,
+        ))
     }
 
     /// Read a ZIP archive, collecting the files it contains
     ///
     /// This uses the central directory record of the ZIP file, and ignores local file headers
-    pub fn new(mut reader: R) -> ZipResult<ZipArchive<R>> {
-        let (footer, cde_start_pos) = spec::CentralDirectoryEnd::find_and_parse(&mut reader)?;
+    pub fn new(reader: R) -> ZipResult<ZipArchive<R>> {
+        Self::with_config(reader, Default::default())
+    }
 
-        if !footer.record_too_small() && footer.disk_number != footer.disk_with_central_directory {
-            return unsupported_zip_error("Support for multi-disk files is not implemented");
+    /// Read a ZIP archive using the given [`ZipReaderConfig`], collecting the files it contains
+    ///
+    /// This uses the central directory record of the ZIP file, and ignores local file headers
+    pub fn with_config(reader: R, config: ZipReaderConfig) -> ZipResult<ZipArchive<R>> {
+        Self::read_central_directory(reader, config, None, &mut Vec::new())
+    }
+
+    /// Read a ZIP archive like [`ZipArchive::new`], and also return what was unusual about it
+    ///
+    /// Reading is identical, but the problems that are otherwise silently worked around, like
+    /// truncated extra fields or names decoded as CP437, are returned as [`ParseDiagnostic`]s.
+    ///
+    /// ```no_run
+    /// # fn main() -> zip::result::ZipResult<()> {
+    /// let file = std::fs::File::open("odd.zip")?;
+    /// let (archive, diagnostics) = zip::ZipArchive::new_with_diagnostics(file)?;
+    /// for diagnostic in &diagnostics {
+    ///     eprintln!("warning: {}", diagnostic);
+    /// }
+    /// println!("{} entries", archive.len());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn new_with_diagnostics(reader: R) -> ZipResult<(ZipArchive<R>, Vec<ParseDiagnostic>)> {
+        let mut diagnostics = Vec::new();
+        let archive =
+            Self::read_central_directory(reader, Default::default(), None, &mut diagnostics)?;
+        Ok((archive, diagnostics))
+    }
+
+    /// Read the central directory, collecting what was worked around into `diagnostics`. For
+    /// split archives, `part_starts` holds the offset in `reader` at which each part starts.
+    fn read_central_directory(
+        mut reader: R,
+        config: ZipReaderConfig,
+        part_starts: Option<&[u64]>,
+        diagnostics: &mut Vec<ParseDiagnostic>,
+    ) -> ZipResult<ZipArchive<R>> {
+        let (footer, cde_start_pos, zip64_locator) =
+            spec::CentralDirectoryEnd::find_and_parse(&mut reader, config.max_trailing_bytes)?;
+        let footer_end = cde_start_pos + 22 + footer.zip_file_comment.len() as u64;
+        let trailing = reader.seek(io::SeekFrom::End(0))? - footer_end;
+        if trailing > 0 {
+            diagnostics.push(ParseDiagnostic::TrailingData { length: trailing });
         }
 
-        let (archive_offset, directory_start, number_of_files) =
-            Self::get_directory_counts(&mut reader, &footer, cde_start_pos)?;
+        // Some old writers let the 16-bit number of files wrap around past 65535 without writing
+        // ZIP64 records, so more central headers may follow the ones it counts
+        let count_may_wrap = part_starts.is_none() && zip64_locator.is_none();
+
+        let (archive_offset, directory_start, directory_size, number_of_files) = match part_starts {
+            Some(part_starts) => {
+                let (directory_start, directory_size, number_of_files) =
+                    Self::get_split_directory_counts(
+                        &mut reader,
+                        &footer,
+                        zip64_locator,
+                        part_starts,
+                    )?;
+                (0, directory_start, directory_size, number_of_files)
+            }
+            None => {
+                if !footer.record_too_small()
+                    && footer.disk_number != footer.disk_with_central_directory
+                {
+                    return unsupported_zip_error(
+                        "Support for multi-disk files is not implemented",
+                    );
+                }
+                Self::get_directory_counts(
+                    &mut reader,
+                    &footer,
+                    cde_start_pos,
+                    zip64_locator,
+                    &config,
+                    diagnostics,
+                )?
+            }
+        };
+
+        if config
+            .max_entries
+            .map_or(false, |max| number_of_files > max)
+        {
+            return unsupported_zip_error("Number of entries exceeds the configured maximum");
+        }
 
         // If the parsed number of files is greater than the offset then
         // something fishy is going on and we shouldn't trust number_of_files.
@@ -425,57 +1122,304 @@ impl<R: Read + io::Seek> ZipArchive<R> {
             ));
         }
 
-        for _ in 0..number_of_files {
-            let file = central_header_to_zip_file(&mut reader, archive_offset)?;
+        while files.len() < number_of_files
+            || (count_may_wrap && has_central_header_before(&mut reader, cde_start_pos)?)
+        {
+            if config.max_entries.map_or(false, |max| files.len() >= max) {
+                return unsupported_zip_error("Number of entries exceeds the configured maximum");
+            }
+            let mut file = central_header_to_zip_file(&mut reader, archive_offset)?;
+            if let Some(part_starts) = part_starts {
+                file.header_start = part_start(part_starts, file.disk_number)?
+                    .checked_add(file.header_start)
+                    .ok_or(ZipError::InvalidArchive("Archive header is too large"))?;
+            }
+            if let Some(max) = config.max_metadata_bytes {
+                if reader.stream_position()? - directory_start > max {
+                    return unsupported_zip_error(
+                        "Central directory size exceeds the configured maximum",
+                    );
+                }
+            }
+            diagnostic::extra_field_diagnostics(files.len(), &file.extra_field, diagnostics);
+            match config.file_name_encoding {
+                FileNameEncoding::Auto => {
+                    if file.flags & (1 << 11) == 0
+                        && !file.file_name_raw.is_ascii()
+                        && !diagnostic::has_unicode_path(&file.extra_field, &file.file_name_raw)
+                    {
+                        diagnostics.push(ParseDiagnostic::Cp437Name { index: files.len() });
+                    }
+                }
+                FileNameEncoding::Utf8 => {
+                    file.file_name = String::from_utf8_lossy(&file.file_name_raw).into_owned()
+                }
+                FileNameEncoding::Cp437 => file.file_name = file.file_name_raw.clone().from_cp437(),
+            }
             names_map.insert(file.file_name.clone(), files.len());
             files.push(file);
         }
 
+        let directory_end = reader.stream_position()?;
+        if footer.number_of_files != 0xFFFF && footer.number_of_files as usize != files.len() {
+            diagnostics.push(ParseDiagnostic::EntryCountMismatch {
+                recorded: footer.number_of_files as u64,
+                parsed: files.len() as u64,
+            });
+        }
+
+        if config.validate_entry_overlap {
+            validate_entry_overlap(&files, directory_start)?;
+        }
+
         let shared = Arc::new(zip_archive::Shared {
             files,
             names_map,
             offset: archive_offset,
+            directory_start,
+            directory_size,
+            directory_end,
             comment: footer.zip_file_comment,
+            config,
         });
 
-        Ok(ZipArchive { reader, shared })
+        Ok(ZipArchive {
+            reader,
+            shared,
+            decompressors: Decompressors::default(),
+            #[cfg(feature = "zstd")]
+            zstd_dictionary: None,
+        })
+    }
+
+    /// Look for structural inconsistencies that lenient readers, including this one, accept, for
+    /// example to reject dubious uploads
+    ///
+    /// This checks each entry for general purpose flags and versions needed to extract that this
+    /// crate doesn't implement, and compares its central header with its local header. It also
+    /// compares the size of the central directory with the one recorded in its end record. Entries
+    /// whose local header can't be read are reported without the comparison, and the others are
+    /// still checked.
+    ///
+    /// ```no_run
+    /// # fn main() -> zip::result::ZipResult<()> {
+    /// let mut archive = zip::ZipArchive::new(std::fs::File::open("upload.zip")?)?;
+    /// let warnings = archive.validate_strict()?;
+    /// for warning in &warnings {
+    ///     eprintln!("Rejected: {}", warning);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn validate_strict(&mut self) -> ZipResult<Vec<ValidationWarning>> {
+        let mut warnings = Vec::new();
+        for (i, file) in self.shared.files.iter().enumerate() {
+            validate::validate_entry(&mut self.reader, i, file, &mut warnings)?;
+        }
+        let actual = self.shared.directory_end - self.shared.directory_start;
+        if actual != self.shared.directory_size {
+            warnings.push(ValidationWarning::CentralDirectorySize {
+                recorded: self.shared.directory_size,
+                actual,
+            });
+        }
+        Ok(warnings)
     }
+
     /// Extract a Zip archive into a directory, overwriting files if they
     /// already exist. Paths are sanitized with [`ZipFile::enclosed_name`].
     ///
+    /// On unix, the permissions of the files are set from their unix mode. On Windows, files with
+    /// the read-only attribute are only made read-only with [`ExtractOptions::dos_read_only`].
+    ///
+    /// On unix, symlinks are extracted as symlinks. Their target must be a relative path that
+    /// stays in the directory, with `..` components only at its start. Elsewhere, they are
+    /// extracted as files containing their target.
+    ///
+    /// No entry is written through a symlink, whether it was extracted or was already in the
+    /// directory: this fails with [`ZipError::SymlinkInPath`] instead. Symlinks already at the
+    /// path of a symlink entry are replaced.
+    ///
     /// Extraction is not atomic; If an error is encountered, some of the files
     /// may be left on disk.
+    ///
+    /// Errors are reported as [`ZipError::Entry`], with the name of the entry that failed.
     pub fn extract<P: AsRef<Path>>(&mut self, directory: P) -> ZipResult<()> {
-        use std::fs;
+        self.extract_with_options(directory, &ExtractOptions::default())?;
+        Ok(())
+    }
 
+    /// Extract a Zip archive into a directory like [`ZipArchive::extract`], with options
+    ///
+    /// ```no_run
+    /// # #[cfg(feature = "sha2")]
+    /// # fn main() -> zip::result::ZipResult<()> {
+    /// use zip::read::{ExtractOptions, HashAlgorithm};
+    ///
+    /// let mut archive = zip::ZipArchive::new(std::fs::File::open("archive.zip")?)?;
+    /// let options = ExtractOptions::default().hasher(HashAlgorithm::Sha256);
+    /// let summary = archive.extract_with_options("out", &options)?;
+    /// for (name, digest) in &summary.digests {
+    ///     println!("{name}: {digest:02x?}");
+    /// }
+    /// # Ok(())
+    /// # }
+    /// # #[cfg(not(feature = "sha2"))]
+    /// # fn main() {}
+    /// ```
+    pub fn extract_with_options<P: AsRef<Path>>(
+        &mut self,
+        directory: P,
+        options: &ExtractOptions,
+    ) -> ZipResult<ExtractSummary> {
+        let mut summary = ExtractSummary::default();
         for i in 0..self.len() {
-            let mut file = self.by_index(i)?;
-            let filepath = file
-                .enclosed_name()
-                .ok_or(ZipError::InvalidArchive("Invalid file path"))?;
+            self.extract_file(i, directory.as_ref(), options, &mut summary)
+                .map_err(|e| e.with_entry(&self.shared.files[i].file_name))?;
+        }
+        Ok(summary)
+    }
 
-            let outpath = directory.as_ref().join(filepath);
+    fn extract_file(
+        &mut self,
+        file_number: usize,
+        directory: &Path,
+        options: &ExtractOptions,
+        summary: &mut ExtractSummary,
+    ) -> ZipResult<()> {
+        use std::fs;
 
-            if file.name().ends_with('/') {
-                fs::create_dir_all(&outpath)?;
-            } else {
+        let filepath: PathBuf = self.shared.files[file_number]
+            .enclosed_name()
+            .ok_or(ZipError::InvalidArchive("Invalid file path"))?
+            .components()
+            .skip(options.strip_components)
+            .collect();
+        if filepath.as_os_str().is_empty() {
+            return Ok(());
+        }
+        let filepath = match &options.map_name {
+            Some(mapper) => match mapper(&filepath) {
+                Some(mapped) => relative_path(&mapped)?,
+                None => return Ok(()),
+            },
+            None => filepath,
+        };
+
+        let mut file = self.by_index(file_number)?;
+        let outpath = directory.join(&filepath);
+        // Symlinks replace the file at their path, and other files are never written through one
+        check_no_symlinks(directory, &filepath, !(cfg!(unix) && file.is_symlink()))?;
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::ffi::OsStrExt;
+
+            if file.is_symlink() {
+                let mut target = Vec::new();
+                file.read_to_end(&mut target)?;
+                let target = Path::new(std::ffi::OsStr::from_bytes(&target));
+                if !symlink_target_enclosed(&filepath, target) {
+                    return Err(ZipError::InvalidArchive(
+                        "Symlink target escapes the extraction directory",
+                    ));
+                }
                 if let Some(p) = outpath.parent() {
-                    if !p.exists() {
-                        fs::create_dir_all(p)?;
-                    }
+                    fs::create_dir_all(p)?;
                 }
-                let mut outfile = fs::File::create(&outpath)?;
-                io::copy(&mut file, &mut outfile)?;
+                if outpath.symlink_metadata().is_ok() {
+                    fs::remove_file(&outpath)?;
+                }
+                std::os::unix::fs::symlink(target, &outpath)?;
+                return Ok(());
             }
-            // Get and Set permissions
-            #[cfg(unix)]
-            {
-                use std::os::unix::fs::PermissionsExt;
-                if let Some(mode) = file.unix_mode() {
-                    fs::set_permissions(&outpath, fs::Permissions::from_mode(mode))?;
+        }
+
+        if file.name().ends_with('/') {
+            fs::create_dir_all(&outpath)?;
+        } else {
+            if let Some(p) = outpath.parent() {
+                if !p.exists() {
+                    fs::create_dir_all(p)?;
                 }
             }
+            let outfile = create_without_following(&outpath)?;
+            if let Some(digest) = copy_file(&mut file, outfile, options)? {
+                summary.digests.insert(file.name().to_owned(), digest);
+            }
         }
+        // Before the permissions, which may make the file read-only
+        if options.restore_xattrs {
+            for (name, value) in file.xattrs() {
+                let restore = match &options.xattr_filter {
+                    Some(filter) => filter(name),
+                    None => name
+                        .to_str()
+                        .map_or(false, |name| name.starts_with("user.")),
+                };
+                if !restore {
+                    continue;
+                }
+                if let Err(e) = set_xattr(&outpath, name, value) {
+                    summary.xattr_errors.push((file.name().to_owned(), e));
+                }
+            }
+        }
+        // Get and Set permissions
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            if let Some(mode) = file.unix_mode() {
+                fs::set_permissions(&outpath, fs::Permissions::from_mode(mode))?;
+            }
+        }
+        #[cfg(windows)]
+        if options.dos_read_only && file.dos_attributes().read_only && !file.is_dir() {
+            let mut permissions = fs::metadata(&outpath)?.permissions();
+            permissions.set_readonly(true);
+            fs::set_permissions(&outpath, permissions)?;
+        }
+        Ok(())
+    }
+
+    /// Register a decompressor for entries using a compression method this crate does not
+    /// support, such as a proprietary one.
+    ///
+    /// The decompressor is used by [`ZipArchive::by_index`], [`ZipArchive::by_name`] and
+    /// [`ZipArchive::extract`] for entries with the given compression method ID. The CRC-32 of
+    /// the decompressed data is still checked. Methods that are supported cannot be overridden.
+    ///
+    /// ```no_run
+    /// # fn main() -> zip::result::ZipResult<()> {
+    /// use std::io::Read;
+    /// use std::sync::Arc;
+    ///
+    /// let file = std::fs::File::open("archive.zip")?;
+    /// let mut archive = zip::ZipArchive::new(file)?;
+    /// archive.register_decompressor(
+    ///     0x4242,
+    ///     Arc::new(|reader: Box<dyn Read + '_>| -> Box<dyn Read + '_> {
+    ///         // Wrap `reader` in the decoder for method 0x4242
+    ///         reader
+    ///     }),
+    /// );
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn register_decompressor(&mut self, method_id: u16, decompressor: Decompressor) {
+        self.decompressors.0.insert(method_id, decompressor);
+    }
+
+    /// Decompress Zstandard entries with a dictionary, such as one given to
+    /// [`FileOptions::zstd_dictionary`](crate::write::FileOptions::zstd_dictionary) when writing
+    ///
+    /// The ZIP format has no way to refer to a dictionary, so only readers that are given the
+    /// dictionary this way can read such entries. Entries compressed without a dictionary can
+    /// still be read. Fails if the dictionary is invalid.
+    #[cfg(feature = "zstd")]
+    pub fn set_zstd_dictionary(&mut self, dictionary: Arc<[u8]>) -> ZipResult<()> {
+        zstd::stream::raw::Decoder::with_dictionary(&dictionary)?;
+        self.zstd_dictionary = Some(dictionary);
         Ok(())
     }
 
@@ -489,6 +1433,26 @@ impl<R: Read + io::Seek> ZipArchive<R> {
         self.len() == 0
     }
 
+    /// The metadata of the files, from the central directory
+    pub(crate) fn files(&self) -> &[ZipFileData] {
+        &self.shared.files
+    }
+
+    /// The index of the file named `name`
+    pub(crate) fn index_for_name(&self, name: &str) -> Option<usize> {
+        self.shared.names_map.get(name).copied()
+    }
+
+    /// Go through the files of the archive, in central directory order
+    ///
+    /// See the example of [`ZipArchive`], and [`ZipArchive::into_entries`] for an [`Iterator`].
+    pub fn entries(&mut self) -> Entries<'_, R> {
+        Entries {
+            archive: self,
+            next: 0,
+        }
+    }
+
     /// Get the offset from the beginning of the underlying reader that this zip begins at, in bytes.
     ///
     /// Normally this value is zero, but if the zip has arbitrary data prepended to it, then this value will be the size
@@ -502,11 +1466,79 @@ impl<R: Read + io::Seek> ZipArchive<R> {
         &self.shared.comment
     }
 
+    /// Get the configuration this archive was read with.
+    pub fn config(&self) -> &ZipReaderConfig {
+        &self.shared.config
+    }
+
     /// Returns an iterator over all the file and directory names in this archive.
     pub fn file_names(&self) -> impl Iterator<Item = &str> {
         self.shared.names_map.keys().map(|s| s.as_str())
     }
 
+    /// Get the entries of this archive as a tree of directories and files, for example to
+    /// display them like a file browser would
+    ///
+    /// ```no_run
+    /// # fn main() -> zip::result::ZipResult<()> {
+    /// let archive = zip::ZipArchive::new(std::fs::File::open("photos.zip")?)?;
+    /// let tree = archive.tree();
+    /// for node in tree.children("2023/") {
+    ///     let kind = if node.is_dir() { "directory" } else { "file" };
+    ///     println!("{} ({})", node.name(), kind);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn tree(&self) -> ZipTree<'_> {
+        ZipTree::new(&self.shared.files)
+    }
+
+    /// Iterate over the indices of the files whose names start with `prefix`, in central
+    /// directory order, to be used with [`ZipArchive::by_index`]
+    ///
+    /// ```no_run
+    /// # fn main() -> zip::result::ZipResult<()> {
+    /// let mut archive = zip::ZipArchive::new(std::fs::File::open("game.pak")?)?;
+    /// let textures: Vec<usize> = archive.indices_with_prefix("assets/textures/").collect();
+    /// for i in textures {
+    ///     let texture = archive.by_index(i)?;
+    ///     println!("{}: {} bytes", texture.name(), texture.size());
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn indices_with_prefix<'a>(&'a self, prefix: &'a str) -> impl Iterator<Item = usize> + 'a {
+        self.shared
+            .files
+            .iter()
+            .enumerate()
+            .filter(move |(_, file)| file.file_name.starts_with(prefix))
+            .map(|(i, _)| i)
+    }
+
+    /// Iterate over the indices of the files whose names match the glob `pattern`, in central
+    /// directory order, to be used with [`ZipArchive::by_index`]
+    ///
+    /// The syntax is the one of [`globset`](https://docs.rs/globset), where `*` and `?` don't
+    /// match `/`, but `**` does: `assets/**/*.png` matches all the PNG files under `assets/`.
+    /// Backslashes in names are treated as `/`. Fails if `pattern` is invalid.
+    #[cfg(feature = "glob")]
+    pub fn indices_matching(&self, pattern: &str) -> ZipResult<impl Iterator<Item = usize> + '_> {
+        let matcher = globset::GlobBuilder::new(pattern)
+            .literal_separator(true)
+            .build()
+            .map_err(|e| ZipError::Io(io::Error::new(io::ErrorKind::InvalidInput, e)))?
+            .compile_matcher();
+        Ok(self
+            .shared
+            .files
+            .iter()
+            .enumerate()
+            .filter(move |(_, file)| matcher.is_match(file.file_name.replace('\\', "/")))
+            .map(|(i, _)| i))
+    }
+
     /// Search for a file entry by name, decrypt with given password
     ///
     /// # Warning
@@ -523,21 +1555,21 @@ impl<R: Read + io::Seek> ZipArchive<R> {
     pub fn by_name_decrypt<'a>(
         &'a mut self,
         name: &str,
-        password: &[u8],
-    ) -> ZipResult<Result<ZipFile<'a>, InvalidPassword>> {
-        self.by_name_with_optional_password(name, Some(password))
+        password: impl AsRef<[u8]>,
+    ) -> ZipResult<ZipFile<'a>> {
+        self.by_name_with_optional_password(name, Some(DecryptionKey::Password(password.as_ref())))
     }
 
     /// Search for a file entry by name
     pub fn by_name<'a>(&'a mut self, name: &str) -> ZipResult<ZipFile<'a>> {
-        Ok(self.by_name_with_optional_password(name, None)?.unwrap())
+        self.by_name_with_optional_password(name, None)
     }
 
     fn by_name_with_optional_password<'a>(
         &'a mut self,
         name: &str,
-        password: Option<&[u8]>,
-    ) -> ZipResult<Result<ZipFile<'a>, InvalidPassword>> {
+        password: Option<DecryptionKey>,
+    ) -> ZipResult<ZipFile<'a>> {
         let index = match self.shared.names_map.get(name) {
             Some(index) => *index,
             None => {
@@ -563,21 +1595,106 @@ impl<R: Read + io::Seek> ZipArchive<R> {
     pub fn by_index_decrypt<'a>(
         &'a mut self,
         file_number: usize,
-        password: &[u8],
-    ) -> ZipResult<Result<ZipFile<'a>, InvalidPassword>> {
-        self.by_index_with_optional_password(file_number, Some(password))
+        password: impl AsRef<[u8]>,
+    ) -> ZipResult<ZipFile<'a>> {
+        self.by_index_with_optional_password(
+            file_number,
+            Some(DecryptionKey::Password(password.as_ref())),
+        )
+    }
+
+    /// Get a contained file by index, decrypting it with ZipCrypto keys instead of a password
+    ///
+    /// This is for ZipCrypto encrypted files whose password is unknown, but whose internal keys
+    /// were recovered, for example with a known-plaintext attack. The keys are checked against the
+    /// encryption header of the file, just like a password would be.
+    pub fn by_index_decrypt_with_keys(
+        &mut self,
+        file_number: usize,
+        keys: ZipCryptoKeys,
+    ) -> ZipResult<ZipFile<'_>> {
+        self.by_index_with_optional_password(file_number, Some(DecryptionKey::ZipCrypto(keys)))
+    }
+
+    /// Search for a file entry by name, decrypting it with the first of the given passwords that
+    /// works
+    ///
+    /// See [`ZipArchive::find_password`] for how the passwords are checked.
+    pub fn by_name_decrypt_any<'a, P: AsRef<[u8]>>(
+        &'a mut self,
+        name: &str,
+        passwords: &[P],
+    ) -> ZipResult<ZipFile<'a>> {
+        let index = match self.shared.names_map.get(name) {
+            Some(index) => *index,
+            None => {
+                return Err(ZipError::FileNotFound);
+            }
+        };
+        self.by_index_decrypt_any(index, passwords)
+    }
+
+    /// Get a contained file by index, decrypting it with the first of the given passwords that
+    /// works
+    ///
+    /// See [`ZipArchive::find_password`] for how the passwords are checked, and to find out which
+    /// of them was used.
+    pub fn by_index_decrypt_any<'a, P: AsRef<[u8]>>(
+        &'a mut self,
+        file_number: usize,
+        passwords: &[P],
+    ) -> ZipResult<ZipFile<'a>> {
+        match self.find_password(file_number, passwords)? {
+            Some(index) => self.by_index_with_optional_password(
+                file_number,
+                Some(DecryptionKey::Password(passwords[index].as_ref())),
+            ),
+            None => Err(ZipError::InvalidPassword),
+        }
+    }
+
+    /// Find the index of the first of the given passwords that decrypts a contained file
+    ///
+    /// Each password is first checked against the encryption header of the file. ZipCrypto lets
+    /// about 1 in 256 wrong passwords through that check, so for ZipCrypto a password that passes
+    /// it is confirmed by decrypting and decompressing the whole file and checking its CRC-32.
+    /// A file that fails this for every password, including a corrupt one, yields `None`.
+    ///
+    /// For a file that is not encrypted, the first password is reported as working.
+    pub fn find_password<P: AsRef<[u8]>>(
+        &mut self,
+        file_number: usize,
+        passwords: &[P],
+    ) -> ZipResult<Option<usize>> {
+        for (index, password) in passwords.iter().enumerate() {
+            let mut file = match self.by_index_with_optional_password(
+                file_number,
+                Some(DecryptionKey::Password(password.as_ref())),
+            ) {
+                Ok(file) => file,
+                Err(ZipError::InvalidPassword) => continue,
+                Err(e) => return Err(e),
+            };
+            if file.data.encrypted
+                && file.data.aes_mode.is_none()
+                && io::copy(&mut file, &mut io::sink()).is_err()
+            {
+                continue;
+            }
+            return Ok(Some(index));
+        }
+        Ok(None)
     }
 
     /// Get a contained file by index
     pub fn by_index(&mut self, file_number: usize) -> ZipResult<ZipFile<'_>> {
-        Ok(self
-            .by_index_with_optional_password(file_number, None)?
-            .unwrap())
+        self.by_index_with_optional_password(file_number, None)
     }
 
     /// Get a contained file by index without decompressing it
     pub fn by_index_raw(&mut self, file_number: usize) -> ZipResult<ZipFile<'_>> {
         let reader = &mut self.reader;
+        let buffer_size = self.shared.config.buffer_size;
         self.shared
             .files
             .get(file_number)
@@ -585,7 +1702,10 @@ impl<R: Read + io::Seek> ZipArchive<R> {
             .and_then(move |data| {
                 Ok(ZipFile {
                     crypto_reader: None,
-                    reader: ZipFileReader::Raw(find_content(data, reader)?),
+                    decompressor: None,
+                    #[cfg(feature = "zstd")]
+                    zstd_dictionary: None,
+                    reader: ZipFileReader::Raw(find_content(data, reader, buffer_size)?),
                     data: Cow::Borrowed(data),
                 })
             })
@@ -594,40 +1714,22 @@ impl<R: Read + io::Seek> ZipArchive<R> {
     fn by_index_with_optional_password<'a>(
         &'a mut self,
         file_number: usize,
-        mut password: Option<&[u8]>,
-    ) -> ZipResult<Result<ZipFile<'a>, InvalidPassword>> {
+        password: Option<DecryptionKey>,
+    ) -> ZipResult<ZipFile<'a>> {
         let data = self
             .shared
             .files
             .get(file_number)
             .ok_or(ZipError::FileNotFound)?;
-
-        match (password, data.encrypted) {
-            (None, true) => return Err(ZipError::UnsupportedArchive(ZipError::PASSWORD_REQUIRED)),
-            (Some(_), false) => password = None, //Password supplied, but none needed! Discard.
-            _ => {}
-        }
-        let limit_reader = find_content(data, &mut self.reader)?;
-
-        match make_crypto_reader(
-            data.compression_method,
-            data.crc32,
-            data.last_modified_time,
-            data.using_data_descriptor,
-            limit_reader,
+        open_file(
+            Cow::Borrowed(data),
+            &mut self.reader,
+            self.shared.config.buffer_size,
             password,
-            data.aes_mode,
-            #[cfg(feature = "aes-crypto")]
-            data.compressed_size,
-        ) {
-            Ok(Ok(crypto_reader)) => Ok(Ok(ZipFile {
-                crypto_reader: Some(crypto_reader),
-                reader: ZipFileReader::NoReader,
-                data: Cow::Borrowed(data),
-            })),
-            Err(e) => Err(e),
-            Ok(Err(e)) => Ok(Err(e)),
-        }
+            &self.decompressors,
+            #[cfg(feature = "zstd")]
+            self.zstd_dictionary.clone(),
+        )
     }
 
     /// Unwrap and return the inner reader object
@@ -638,10 +1740,387 @@ impl<R: Read + io::Seek> ZipArchive<R> {
     }
 }
 
+impl<R: Read + io::Seek + Clone + 'static> ZipArchive<R> {
+    /// Get a contained file by index, reading it through a clone of the archive's reader
+    ///
+    /// Unlike [`ZipArchive::by_index`], the file doesn't borrow the archive, so several files can
+    /// be read at the same time, and files can outlive the archive. Each file seeks its own
+    /// reader, so readers sharing a position, such as clones of a `File` opened with
+    /// [`std::fs::File::try_clone`], can't be used.
+    ///
+    /// [`ZipArchive::into_entries`] iterates over all the files this way.
+    pub fn by_index_owned(&self, file_number: usize) -> ZipResult<OwnedZipFile> {
+        let data = self
+            .shared
+            .files
+            .get(file_number)
+            .ok_or(ZipError::FileNotFound)?;
+        let file = open_file(
+            Cow::Owned(data.clone()),
+            self.reader.clone(),
+            self.shared.config.buffer_size,
+            None,
+            &self.decompressors,
+            #[cfg(feature = "zstd")]
+            self.zstd_dictionary.clone(),
+        )?;
+        Ok(OwnedZipFile { file })
+    }
+
+    /// Iterate over the files of the archive, in central directory order
+    ///
+    /// Each file is opened with [`ZipArchive::by_index_owned`], so it can be kept after the
+    /// iteration moves on. Use [`ZipArchive::entries`] for readers that can't be cloned.
+    ///
+    /// ```no_run
+    /// use std::io::{self, Read};
+    ///
+    /// fn main() -> zip::result::ZipResult<()> {
+    ///     let data = std::fs::read("archive.zip")?;
+    ///     let archive = zip::ZipArchive::new(io::Cursor::new(std::sync::Arc::<[u8]>::from(data)))?;
+    ///
+    ///     let mut files = Vec::new();
+    ///     for file in archive.into_entries() {
+    ///         files.push(file?);
+    ///     }
+    ///     let mut contents = String::new();
+    ///     for mut file in files {
+    ///         file.read_to_string(&mut contents)?;
+    ///     }
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn into_entries(self) -> IntoEntries<R> {
+        IntoEntries {
+            archive: self,
+            next: 0,
+        }
+    }
+}
+
+/// Iterator over the files of an archive, returned by [`ZipArchive::entries`]
+///
+/// Each file borrows the archive, so this can't implement [`Iterator`]. Call
+/// [`Entries::next_entry`] in a `while let` loop instead.
+pub struct Entries<'a, R> {
+    archive: &'a mut ZipArchive<R>,
+    next: usize,
+}
+
+impl<'a, R: Read + io::Seek> Entries<'a, R> {
+    /// Get the next file, or `None` after the last one
+    ///
+    /// Errors are those of [`ZipArchive::by_index`]: in particular, encrypted files are reported
+    /// as [`ZipError::PASSWORD_REQUIRED`]. Iteration can continue after an error.
+    pub fn next_entry(&mut self) -> ZipResult<Option<ZipFile<'_>>> {
+        if self.next == self.archive.len() {
+            return Ok(None);
+        }
+        self.next += 1;
+        self.archive.by_index(self.next - 1).map(Some)
+    }
+}
+
+/// Iterator over the files of an archive, returned by [`ZipArchive::into_entries`]
+///
+/// Encrypted files are yielded as [`ZipError::PASSWORD_REQUIRED`] errors.
+pub struct IntoEntries<R> {
+    archive: ZipArchive<R>,
+    next: usize,
+}
+
+impl<R: Read + io::Seek + Clone + 'static> Iterator for IntoEntries<R> {
+    type Item = ZipResult<OwnedZipFile>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.next == self.archive.len() {
+            return None;
+        }
+        self.next += 1;
+        Some(self.archive.by_index_owned(self.next - 1))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.archive.len() - self.next;
+        (remaining, Some(remaining))
+    }
+}
+
+impl<R: Read + io::Seek + Clone + 'static> ExactSizeIterator for IntoEntries<R> {}
+
+/// Set up reading a file, with `reader` positioned anywhere in the archive
+fn open_file<'a>(
+    data: Cow<'a, ZipFileData>,
+    reader: impl Read + Seek + 'a,
+    buffer_size: Option<usize>,
+    mut password: Option<DecryptionKey>,
+    decompressors: &Decompressors,
+    #[cfg(feature = "zstd")] zstd_dictionary: Option<Arc<[u8]>>,
+) -> ZipResult<ZipFile<'a>> {
+    match (password, data.encrypted) {
+        (None, true) => return Err(ZipError::UnsupportedArchive(ZipError::PASSWORD_REQUIRED)),
+        (Some(_), false) => password = None, //Password supplied, but none needed! Discard.
+        _ => {}
+    }
+    let decompressor = decompressors.get(data.compression_method).cloned();
+    #[allow(deprecated)]
+    {
+        if let (CompressionMethod::Unsupported(_), None) = (data.compression_method, &decompressor)
+        {
+            return unsupported_zip_error("Compression method not supported");
+        }
+    }
+    let limit_reader = find_content(&data, reader, buffer_size)?;
+
+    let crypto_reader = make_crypto_reader(
+        data.crc32,
+        data.last_modified_time,
+        data.using_data_descriptor,
+        limit_reader,
+        password,
+        data.aes_mode,
+        #[cfg(feature = "aes-crypto")]
+        data.compressed_size,
+    )
+    // Password errors are left as they are, so that they can be matched on
+    .map_err(|e| match e {
+        ZipError::Io(_) => e.with_entry(&data.file_name),
+        e => e,
+    })?;
+    Ok(ZipFile {
+        crypto_reader: Some(crypto_reader),
+        decompressor,
+        #[cfg(feature = "zstd")]
+        zstd_dictionary,
+        reader: ZipFileReader::NoReader,
+        data,
+    })
+}
+
+/// Copy the data of an extracted file to `outfile`, and return its digest if `options` asks for
+/// one
+fn copy_file(
+    file: &mut ZipFile,
+    mut outfile: std::fs::File,
+    options: &ExtractOptions,
+) -> io::Result<Option<Vec<u8>>> {
+    #[cfg(feature = "sha2")]
+    let mut digest = options.hasher.map(HashAlgorithm::digest);
+    let mut buffer = vec![0; options.buffer_size.max(1)];
+    let mut length = 0;
+    loop {
+        let read = match file.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(read) => read,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        let chunk = &buffer[..read];
+        #[cfg(feature = "sha2")]
+        if let Some(digest) = digest.as_mut() {
+            digest.update(chunk);
+        }
+        if options.sparse && chunk.iter().all(|&byte| byte == 0) {
+            outfile.seek(io::SeekFrom::Current(read as i64))?;
+        } else {
+            outfile.write_all(chunk)?;
+        }
+        length += read as u64;
+    }
+    if options.sparse {
+        // Skipped zeros at the end don't extend the file
+        outfile.set_len(length)?;
+    }
+    #[cfg(feature = "sha2")]
+    let digest = digest.map(|digest| digest.finalize().into_vec());
+    #[cfg(not(feature = "sha2"))]
+    let digest = None;
+    Ok(digest)
+}
+
+/// Check that a path given by [`ExtractOptions::map_name`] stays in the extraction directory, and
+/// remove its `.` components
+fn relative_path(path: &Path) -> ZipResult<PathBuf> {
+    let mut relative = PathBuf::new();
+    for component in path.components() {
+        match component {
+            std::path::Component::Normal(name) => relative.push(name),
+            std::path::Component::CurDir => {}
+            _ => {
+                return Err(ZipError::Io(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "Mapped path must be relative, without `..` components",
+                )))
+            }
+        }
+    }
+    if relative.as_os_str().is_empty() {
+        return Err(ZipError::Io(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "Mapped path is empty",
+        )));
+    }
+    Ok(relative)
+}
+
+/// Fail if writing `filepath` in `directory` would go through a symlink, in one of its ancestors
+/// under `directory`, or at the path itself if `check_path`
+fn check_no_symlinks(directory: &Path, filepath: &Path, check_path: bool) -> ZipResult<()> {
+    // Component by component, since a trailing separator makes `symlink_metadata` follow links
+    let mut path = directory.to_path_buf();
+    let mut components = filepath.components().peekable();
+    while let Some(component) = components.next() {
+        if components.peek().is_none() && !check_path {
+            break;
+        }
+        path.push(component);
+        if path
+            .symlink_metadata()
+            .map_or(false, |metadata| metadata.file_type().is_symlink())
+        {
+            return Err(ZipError::SymlinkInPath(path));
+        }
+    }
+    Ok(())
+}
+
+/// Create or truncate a file, failing if the last component of its path is a symlink where the
+/// platform allows it, in case one was created since [`check_no_symlinks`]
+fn create_without_following(path: &Path) -> io::Result<std::fs::File> {
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.custom_flags(libc::O_NOFOLLOW);
+    }
+    options.open(path)
+}
+
+/// Set an extended attribute of a file, without following symlinks
+#[cfg(any(target_os = "linux", target_os = "android", target_os = "macos"))]
+fn set_xattr(path: &Path, name: &OsString, value: &[u8]) -> io::Result<()> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let invalid = |_| io::Error::new(io::ErrorKind::InvalidInput, "Path or name contains a NUL");
+    let path = CString::new(path.as_os_str().as_bytes()).map_err(invalid)?;
+    let name = CString::new(name.as_bytes()).map_err(invalid)?;
+    let value_ptr = value.as_ptr() as *const libc::c_void;
+    // SAFETY: the path and name are NUL-terminated, and the value is `value.len()` bytes long
+    #[cfg(not(target_os = "macos"))]
+    let result =
+        unsafe { libc::lsetxattr(path.as_ptr(), name.as_ptr(), value_ptr, value.len(), 0) };
+    #[cfg(target_os = "macos")]
+    let result = unsafe {
+        libc::setxattr(
+            path.as_ptr(),
+            name.as_ptr(),
+            value_ptr,
+            value.len(),
+            0,
+            libc::XATTR_NOFOLLOW,
+        )
+    };
+    if result == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android", target_os = "macos")))]
+fn set_xattr(_path: &Path, _name: &OsString, _value: &[u8]) -> io::Result<()> {
+    Ok(())
+}
+
 fn unsupported_zip_error<T>(detail: &'static str) -> ZipResult<T> {
     Err(ZipError::UnsupportedArchive(detail))
 }
 
+/// Returns whether a central header starts at the current position of `reader`, before `end`,
+/// without moving it
+fn has_central_header_before<R: Read + Seek>(reader: &mut R, end: u64) -> ZipResult<bool> {
+    let pos = reader.stream_position()?;
+    if pos + 4 > end {
+        return Ok(false);
+    }
+    let signature = reader.read_u32::<LittleEndian>()?;
+    reader.seek(io::SeekFrom::Start(pos))?;
+    Ok(signature == spec::CENTRAL_DIRECTORY_HEADER_SIGNATURE)
+}
+
+/// Get the offset at which a part of a split archive starts
+fn part_start(part_starts: &[u64], disk: u32) -> ZipResult<u64> {
+    part_starts
+        .get(disk as usize)
+        .copied()
+        .ok_or(ZipError::InvalidArchive("Disk number out of range"))
+}
+
+/// Check that no two entries share bytes, and that no entry runs into the central directory.
+///
+/// The extent of an entry is estimated from below as its fixed-size local header, its name and
+/// its compressed data, so only overlaps that can't be explained by the local extra field or a
+/// data descriptor are detected.
+fn validate_entry_overlap(files: &[ZipFileData], directory_start: u64) -> ZipResult<()> {
+    let mut spans: Vec<(u64, u64)> = files
+        .iter()
+        .map(|file| {
+            let end = file
+                .header_start
+                .saturating_add(30 + file.file_name_raw.len() as u64)
+                .saturating_add(file.compressed_size);
+            (file.header_start, end)
+        })
+        .collect();
+    spans.sort_unstable();
+
+    if spans.windows(2).any(|pair| pair[0].1 > pair[1].0) {
+        return Err(ZipError::InvalidArchive("Overlapping entries in archive"));
+    }
+    if spans
+        .last()
+        .map_or(false, |&(_, end)| end > directory_start)
+    {
+        return Err(ZipError::InvalidArchive(
+            "Entry data overlaps the central directory",
+        ));
+    }
+    Ok(())
+}
+
+/// Whether the target of a symlink extracted to `link` stays in the extraction directory
+///
+/// `..` components are only allowed at the start of the target, so that they can't follow other
+/// symlinks out of the directory.
+#[cfg(unix)]
+fn symlink_target_enclosed(link: &Path, target: &Path) -> bool {
+    use std::path::Component;
+
+    let mut depth = 0usize;
+    for component in link.parent().into_iter().flat_map(Path::components) {
+        match component {
+            Component::Normal(_) => depth += 1,
+            Component::ParentDir => depth = depth.saturating_sub(1),
+            _ => (),
+        }
+    }
+    let mut leading = true;
+    for component in target.components() {
+        match component {
+            Component::ParentDir if leading => match depth.checked_sub(1) {
+                Some(parent_depth) => depth = parent_depth,
+                None => return false,
+            },
+            Component::Normal(_) => leading = false,
+            Component::CurDir => (),
+            _ => return false,
+        }
+    }
+    true
+}
+
 /// Parse a central directory entry to collect the information for the file.
 pub(crate) fn central_header_to_zip_file<R: Read + io::Seek>(
     reader: &mut R,
@@ -679,7 +2158,7 @@ fn central_header_to_zip_file_inner<R: Read>(
     let file_name_length = reader.read_u16::<LittleEndian>()? as usize;
     let extra_field_length = reader.read_u16::<LittleEndian>()? as usize;
     let file_comment_length = reader.read_u16::<LittleEndian>()? as usize;
-    let _disk_number = reader.read_u16::<LittleEndian>()?;
+    let disk_number = reader.read_u16::<LittleEndian>()?;
     let _internal_file_attributes = reader.read_u16::<LittleEndian>()?;
     let external_file_attributes = reader.read_u32::<LittleEndian>()?;
     let offset = reader.read_u32::<LittleEndian>()? as u64;
@@ -705,10 +2184,12 @@ fn central_header_to_zip_file_inner<R: Read>(
         version_made_by: version_made_by as u8,
         encrypted,
         using_data_descriptor,
+        flags,
         compression_method: {
             #[allow(deprecated)]
             CompressionMethod::from_u16(compression_method)
         },
+        compression_method_id: compression_method,
         compression_level: None,
         last_modified_time: DateTime::from_msdos(last_mod_date, last_mod_time),
         crc32,
@@ -717,13 +2198,22 @@ fn central_header_to_zip_file_inner<R: Read>(
         file_name,
         file_name_raw,
         extra_field,
+        alignment_padding: Vec::new(),
         file_comment,
         header_start: offset,
+        disk_number: disk_number as u32,
         central_header_start,
         data_start: AtomicU64::new(0),
+        data_alignment: AtomicU64::new(1),
         external_attributes: external_file_attributes,
         large_file: false,
         aes_mode: None,
+        unix_mtime: None,
+        unix_atime: None,
+        ntfs_times: None,
+        unix_ownership: None,
+        xattrs: Vec::new(),
+        legacy_name_compat: false,
     };
 
     match parse_extra_field(&mut result) {
@@ -771,6 +2261,10 @@ fn parse_extra_field(file: &mut ZipFileData) -> ZipResult<()> {
                     file.header_start = reader.read_u64::<LittleEndian>()?;
                     len_left -= 8;
                 }
+                if file.disk_number == u16::MAX as u32 {
+                    file.disk_number = reader.read_u32::<LittleEndian>()?;
+                    len_left -= 4;
+                }
             }
             0x9901 => {
                 // AES
@@ -802,18 +2296,153 @@ fn parse_extra_field(file: &mut ZipFileData) -> ZipResult<()> {
                     #[allow(deprecated)]
                     CompressionMethod::from_u16(compression_method)
                 };
+                len_left = 0;
+            }
+            0x000a => {
+                // NTFS, with attributes after a reserved field
+                if len_left >= 4 {
+                    reader.read_u32::<LittleEndian>()?;
+                    len_left -= 4;
+                }
+                while len_left >= 4 {
+                    let tag = reader.read_u16::<LittleEndian>()?;
+                    let size = reader.read_u16::<LittleEndian>()? as i64;
+                    len_left -= 4;
+                    if tag == 0x0001 && size == 24 && len_left >= 24 {
+                        file.ntfs_times = Some(NtfsTimes {
+                            mtime: reader.read_u64::<LittleEndian>()?,
+                            atime: reader.read_u64::<LittleEndian>()?,
+                            ctime: reader.read_u64::<LittleEndian>()?,
+                        });
+                    } else {
+                        reader.seek(io::SeekFrom::Current(size.min(len_left)))?;
+                    }
+                    len_left -= size.min(len_left);
+                }
+            }
+            0x7075 if len_left >= 5 => {
+                // Info-ZIP Unicode Path, valid while the CRC matches the name field
+                let version = reader.read_u8()?;
+                let crc32 = reader.read_u32::<LittleEndian>()?;
+                let mut name = vec![0; len_left as usize - 5];
+                reader.read_exact(&mut name)?;
+                len_left = 0;
+                if version == 1 && crc32 == crc32fast::hash(&file.file_name_raw) {
+                    if let Ok(name) = String::from_utf8(name) {
+                        file.file_name = name;
+                    }
+                }
+            }
+            0x5455 if len_left >= 1 => {
+                // Extended timestamp
+                let flags = reader.read_u8()?;
+                len_left -= 1;
+                if flags & 0x01 != 0 && len_left >= 4 {
+                    file.unix_mtime = Some(reader.read_i32::<LittleEndian>()? as i64);
+                    len_left -= 4;
+                }
+                // The central header only has the modification time
+                if flags & 0x02 != 0 && len_left >= 4 {
+                    file.unix_atime = Some(reader.read_i32::<LittleEndian>()? as i64);
+                    len_left -= 4;
+                }
+            }
+            0x7875 if len_left >= 1 => {
+                // Info-ZIP new Unix, with IDs of variable size
+                let version = reader.read_u8()?;
+                len_left -= 1;
+                if version == 1 {
+                    if let (Some(uid), Some(gid)) = (
+                        read_unix_id(&mut reader, &mut len_left)?,
+                        read_unix_id(&mut reader, &mut len_left)?,
+                    ) {
+                        file.unix_ownership = Some((uid, gid));
+                    }
+                }
+            }
+            0x7861 => {
+                // Extended attributes, specific to this crate
+                read_xattrs(&mut reader, &mut len_left, &mut file.xattrs)?;
+            }
+            _ => {
+                // Other fields are ignored
+            }
+        }
+
+        // We could also check for < 0 to check for errors
+        if len_left > 0 {
+            reader.seek(io::SeekFrom::Current(len_left))?;
+        }
+    }
+    Ok(())
+}
+
+/// Read the names and values of an extended attributes extra field into `xattrs`
+///
+/// They are ignored if an attribute doesn't fit in the field.
+fn read_xattrs(
+    reader: &mut io::Cursor<&Vec<u8>>,
+    len_left: &mut i64,
+    xattrs: &mut Vec<(OsString, Vec<u8>)>,
+) -> ZipResult<()> {
+    let mut read = Vec::new();
+    while *len_left > 0 {
+        let mut parts = [Vec::new(), Vec::new()];
+        for part in parts.iter_mut() {
+            if *len_left < 2 {
+                return Ok(());
             }
-            _ => {
-                // Other fields are ignored
+            let length = reader.read_u16::<LittleEndian>()? as i64;
+            *len_left -= 2;
+            if length > *len_left {
+                return Ok(());
             }
+            part.resize(length as usize, 0);
+            reader.read_exact(part)?;
+            *len_left -= length;
         }
+        let [name, value] = parts;
+        read.push((os_string_from_bytes(name), value));
+    }
+    *xattrs = read;
+    Ok(())
+}
 
-        // We could also check for < 0 to check for errors
-        if len_left > 0 {
-            reader.seek(io::SeekFrom::Current(len_left))?;
+/// Name of an extended attribute from its bytes: the bytes themselves on Unix, and their UTF-8
+/// decoding elsewhere
+fn os_string_from_bytes(name: Vec<u8>) -> OsString {
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStringExt;
+        OsString::from_vec(name)
+    }
+    #[cfg(not(unix))]
+    OsString::from(String::from_utf8_lossy(&name).into_owned())
+}
+
+/// Read a user or group ID of the Info-ZIP new Unix extra field, with its size before it
+///
+/// This gives `None` for IDs that don't fit in the field or in a `u32`.
+fn read_unix_id(reader: &mut io::Cursor<&Vec<u8>>, len_left: &mut i64) -> ZipResult<Option<u32>> {
+    if *len_left < 1 {
+        return Ok(None);
+    }
+    let size = reader.read_u8()? as i64;
+    *len_left -= 1;
+    if size > *len_left {
+        return Ok(None);
+    }
+    let mut id = 0u64;
+    for i in 0..size {
+        let byte = reader.read_u8()? as u64;
+        if i < 8 {
+            id |= byte << (8 * i);
+        } else if byte != 0 {
+            id = u64::MAX;
         }
     }
-    Ok(())
+    *len_left -= size;
+    Ok(u32::try_from(id).ok())
 }
 
 /// Methods for retrieving information on zip files
@@ -822,7 +2451,13 @@ impl<'a> ZipFile<'a> {
         if let ZipFileReader::NoReader = self.reader {
             let data = &self.data;
             let crypto_reader = self.crypto_reader.take().expect("Invalid reader state");
-            self.reader = make_reader(data.compression_method, data.crc32, crypto_reader)
+            self.reader = make_reader(
+                data,
+                crypto_reader,
+                self.decompressor.as_ref(),
+                #[cfg(feature = "zstd")]
+                self.zstd_dictionary.as_deref(),
+            )
         }
         &mut self.reader
     }
@@ -835,6 +2470,10 @@ impl<'a> ZipFile<'a> {
         &mut self.reader
     }
 
+    pub(crate) fn get_metadata(&self) -> &ZipFileData {
+        &self.data
+    }
+
     /// Get the version of the file
     pub fn version_made_by(&self) -> (u8, u8) {
         (
@@ -931,18 +2570,42 @@ impl<'a> ZipFile<'a> {
     pub fn last_modified(&self) -> DateTime {
         self.data.last_modified_time
     }
+
+    /// Get the modification time of the extended timestamp extra field, in seconds since the
+    /// Unix epoch
+    ///
+    /// Unlike [`ZipFile::last_modified`], this has a precision of one second and a time zone.
+    pub fn unix_mtime(&self) -> Option<i64> {
+        self.data.unix_mtime
+    }
+
+    /// Get the access time of the extended timestamp extra field, in seconds since the Unix epoch
+    ///
+    /// This is only stored in the local header, so it's only known for files read from a stream.
+    pub fn unix_atime(&self) -> Option<i64> {
+        self.data.unix_atime
+    }
+
+    /// Get the times of the NTFS extra field
+    pub fn ntfs_times(&self) -> Option<NtfsTimes> {
+        self.data.ntfs_times
+    }
     /// Returns whether the file is actually a directory
     pub fn is_dir(&self) -> bool {
         self.name()
             .chars()
-            .rev()
-            .next()
+            .next_back()
             .map_or(false, |c| c == '/' || c == '\\')
     }
 
     /// Returns whether the file is a regular file
     pub fn is_file(&self) -> bool {
-        !self.is_dir()
+        !self.is_dir() && !self.is_symlink()
+    }
+
+    /// Returns whether the file is a symbolic link, whose data is the path it points to
+    pub fn is_symlink(&self) -> bool {
+        self.data.is_symlink()
     }
 
     /// Get unix mode for the file
@@ -950,6 +2613,28 @@ impl<'a> ZipFile<'a> {
         self.data.unix_mode()
     }
 
+    /// Get the user ID of the file's owner, from an Info-ZIP new Unix extra field
+    pub fn unix_uid(&self) -> Option<u32> {
+        self.data.unix_ownership.map(|(uid, _)| uid)
+    }
+
+    /// Get the group ID of the file, from an Info-ZIP new Unix extra field
+    pub fn unix_gid(&self) -> Option<u32> {
+        self.data.unix_ownership.map(|(_, gid)| gid)
+    }
+
+    /// Get the extended attributes of the file, as names and values, written with
+    /// [`FileOptions::xattrs`](crate::write::FileOptions::xattrs)
+    pub fn xattrs(&self) -> &[(OsString, Vec<u8>)] {
+        &self.data.xattrs
+    }
+
+    /// Get the MS-DOS attributes of the file, which archives made by MS-DOS and Windows have,
+    /// and those made by Info-ZIP on unix. Other archives leave them all unset.
+    pub fn dos_attributes(&self) -> DosAttributes {
+        DosAttributes::from_bits(self.data.external_attributes)
+    }
+
     /// Get the CRC32 hash of the original file
     pub fn crc32(&self) -> u32 {
         self.data.crc32
@@ -981,35 +2666,75 @@ impl<'a> Read for ZipFile<'a> {
     }
 }
 
-impl<'a> Drop for ZipFile<'a> {
-    fn drop(&mut self) {
+impl<'a> ZipFile<'a> {
+    /// Skip the rest of the data of a file read with [`read_zipfile_from_stream`], so that the
+    /// next file can be read.
+    ///
+    /// Dropping the file does the same, but ignores errors. If skipping fails, the stream is left
+    /// in the middle of the file, and reading the next file will most likely fail as well.
+    pub fn finish(mut self) -> ZipResult<()> {
+        self.skip_remaining_data()?;
+        Ok(())
+    }
+
+    fn skip_remaining_data(&mut self) -> io::Result<()> {
         // self.data is Owned, this reader is constructed by a streaming reader.
         // In this case, we want to exhaust the reader so that the next file is accessible.
         if let Cow::Owned(_) = self.data {
-            let mut buffer = [0; 1 << 16];
-
             // Get the inner `Take` reader so all decryption, decompression and CRC calculation is skipped.
-            let mut reader: std::io::Take<&mut dyn std::io::Read> = match &mut self.reader {
-                ZipFileReader::NoReader => {
-                    let innerreader = ::std::mem::replace(&mut self.crypto_reader, None);
-                    innerreader.expect("Invalid reader state").into_inner()
-                }
+            let mut reader: io::Take<Box<dyn Read + 'a>> = match &mut self.reader {
+                ZipFileReader::NoReader => match self.crypto_reader.take() {
+                    Some(innerreader) => innerreader.into_inner(),
+                    // Already skipped
+                    None => return Ok(()),
+                },
                 reader => {
                     let innerreader = ::std::mem::replace(reader, ZipFileReader::NoReader);
-                    innerreader.into_inner()
+                    innerreader.into_inner()?
                 }
             };
 
-            loop {
-                match reader.read(&mut buffer) {
-                    Ok(0) => break,
-                    Ok(_) => (),
-                    Err(e) => {
-                        panic!("Could not consume all of the output of the current ZipFile: {e:?}")
-                    }
-                }
-            }
+            io::copy(&mut reader, &mut io::sink())?;
         }
+        Ok(())
+    }
+}
+
+impl<'a> Drop for ZipFile<'a> {
+    fn drop(&mut self) {
+        // Errors are ignored, since panicking while unwinding would abort the process.
+        // `ZipFile::finish` reports them instead.
+        let _ = self.skip_remaining_data();
+    }
+}
+
+/// A file of an archive that owns its reader, returned by [`ZipArchive::by_index_owned`]
+///
+/// The metadata of the file is available through [`ZipFile`]'s methods.
+pub struct OwnedZipFile {
+    file: ZipFile<'static>,
+}
+
+impl std::ops::Deref for OwnedZipFile {
+    type Target = ZipFile<'static>;
+
+    fn deref(&self) -> &ZipFile<'static> {
+        &self.file
+    }
+}
+
+impl Read for OwnedZipFile {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.file.read(buf)
+    }
+}
+
+impl Drop for OwnedZipFile {
+    fn drop(&mut self) {
+        // The data is owned, like that of a streamed file, but the rest of the file doesn't need
+        // to be skipped: drop the readers so that `ZipFile`'s drop finds nothing to skip.
+        self.file.crypto_reader = None;
+        self.file.reader = ZipFileReader::NoReader;
     }
 }
 
@@ -1031,7 +2756,27 @@ impl<'a> Drop for ZipFile<'a> {
 /// * `external_attributes`: `unix_mode()`: will return None
 pub fn read_zipfile_from_stream<'a, R: io::Read>(
     reader: &'a mut R,
+) -> ZipResult<Option<ZipFile<'a>>> {
+    read_zipfile_from_stream_with_password(reader, None)
+}
+
+/// Read ZipFile structures from a non-seekable reader, decrypting encrypted files with the given
+/// password
+///
+/// This works like [`read_zipfile_from_stream`], and files that are not encrypted are read the
+/// same way. An encrypted file whose password check fails yields [`ZipError::InvalidPassword`];
+/// the stream can't be read any further after that.
+pub fn read_zipfile_from_stream_decrypt<R: io::Read>(
+    reader: &mut R,
+    password: impl AsRef<[u8]>,
 ) -> ZipResult<Option<ZipFile<'_>>> {
+    read_zipfile_from_stream_with_password(reader, Some(password.as_ref()))
+}
+
+fn read_zipfile_from_stream_with_password<'a, R: io::Read>(
+    reader: &'a mut R,
+    password: Option<&[u8]>,
+) -> ZipResult<Option<ZipFile<'a>>> {
     let signature = reader.read_u32::<LittleEndian>()?;
 
     match signature {
@@ -1040,13 +2785,66 @@ pub fn read_zipfile_from_stream<'a, R: io::Read>(
         _ => return Err(ZipError::InvalidArchive("Invalid local file header")),
     }
 
+    let result = read_local_header(reader)?;
+    let key = match (password, result.encrypted) {
+        (None, true) => return unsupported_zip_error("Encrypted files are not supported"),
+        (Some(password), true) => Some(DecryptionKey::Password(password)),
+        (_, false) => None,
+    };
+    if result.using_data_descriptor {
+        return unsupported_zip_error("The file length is not available in the local header");
+    }
+
+    #[allow(deprecated)]
+    {
+        if let CompressionMethod::Unsupported(_) = result.compression_method {
+            return unsupported_zip_error("Compression method not supported");
+        }
+    }
+
+    let limit_reader = (Box::new(reader) as Box<dyn Read + 'a>).take(result.compressed_size);
+
+    // Files with a data descriptor were rejected above, so ZipCrypto always checks the CRC
+    let crypto_reader = make_crypto_reader(
+        result.crc32,
+        result.last_modified_time,
+        false,
+        limit_reader,
+        key,
+        result.aes_mode,
+        #[cfg(feature = "aes-crypto")]
+        result.compressed_size,
+    )?;
+
+    let reader = make_reader(
+        &result,
+        crypto_reader,
+        None,
+        #[cfg(feature = "zstd")]
+        None,
+    );
+    Ok(Some(ZipFile {
+        data: Cow::Owned(result),
+        crypto_reader: None,
+        decompressor: None,
+        #[cfg(feature = "zstd")]
+        zstd_dictionary: None,
+        reader,
+    }))
+}
+
+/// Parse a local file header, after its signature
+///
+/// Its offsets are left at 0, and the fields only in the central header have default values.
+pub(crate) fn read_local_header<R: Read>(reader: &mut R) -> ZipResult<ZipFileData> {
     let version_made_by = reader.read_u16::<LittleEndian>()?;
     let flags = reader.read_u16::<LittleEndian>()?;
     let encrypted = flags & 1 == 1;
     let is_utf8 = flags & (1 << 11) != 0;
     let using_data_descriptor = flags & (1 << 3) != 0;
+    let compression_method_id = reader.read_u16::<LittleEndian>()?;
     #[allow(deprecated)]
-    let compression_method = CompressionMethod::from_u16(reader.read_u16::<LittleEndian>()?);
+    let compression_method = CompressionMethod::from_u16(compression_method_id);
     let last_mod_time = reader.read_u16::<LittleEndian>()?;
     let last_mod_date = reader.read_u16::<LittleEndian>()?;
     let crc32 = reader.read_u32::<LittleEndian>()?;
@@ -1070,7 +2868,9 @@ pub fn read_zipfile_from_stream<'a, R: io::Read>(
         version_made_by: version_made_by as u8,
         encrypted,
         using_data_descriptor,
+        flags,
         compression_method,
+        compression_method_id,
         compression_level: None,
         last_modified_time: DateTime::from_msdos(last_mod_date, last_mod_time),
         crc32,
@@ -1079,11 +2879,14 @@ pub fn read_zipfile_from_stream<'a, R: io::Read>(
         file_name,
         file_name_raw,
         extra_field,
+        alignment_padding: Vec::new(),
         file_comment: String::new(), // file comment is only available in the central directory
         // header_start and data start are not available, but also don't matter, since seeking is
         // not available.
         header_start: 0,
+        disk_number: 0,
         data_start: AtomicU64::new(0),
+        data_alignment: AtomicU64::new(1),
         central_header_start: 0,
         // The external_attributes field is only available in the central directory.
         // We set this to zero, which should be valid as the docs state 'If input came
@@ -1091,42 +2894,19 @@ pub fn read_zipfile_from_stream<'a, R: io::Read>(
         external_attributes: 0,
         large_file: false,
         aes_mode: None,
+        unix_mtime: None,
+        unix_atime: None,
+        ntfs_times: None,
+        unix_ownership: None,
+        xattrs: Vec::new(),
+        legacy_name_compat: false,
     };
 
     match parse_extra_field(&mut result) {
         Ok(..) | Err(ZipError::Io(..)) => {}
         Err(e) => return Err(e),
     }
-
-    if encrypted {
-        return unsupported_zip_error("Encrypted files are not supported");
-    }
-    if using_data_descriptor {
-        return unsupported_zip_error("The file length is not available in the local header");
-    }
-
-    let limit_reader = (reader as &'a mut dyn io::Read).take(result.compressed_size);
-
-    let result_crc32 = result.crc32;
-    let result_compression_method = result.compression_method;
-    let crypto_reader = make_crypto_reader(
-        result_compression_method,
-        result_crc32,
-        result.last_modified_time,
-        result.using_data_descriptor,
-        limit_reader,
-        None,
-        None,
-        #[cfg(feature = "aes-crypto")]
-        result.compressed_size,
-    )?
-    .unwrap();
-
-    Ok(Some(ZipFile {
-        data: Cow::Owned(result),
-        crypto_reader: None,
-        reader: make_reader(result_compression_method, result_crc32, crypto_reader),
-    }))
+    Ok(result)
 }
 
 #[cfg(test)]
@@ -1283,4 +3063,619 @@ mod test {
         let reader = ZipArchive::new(io::Cursor::new(v));
         assert!(reader.is_err());
     }
+
+    #[test]
+    fn config_max_entries() {
+        use super::{ZipArchive, ZipReaderConfig};
+        use crate::result::ZipError;
+        use std::io;
+
+        let v = include_bytes!("../tests/data/files_and_dirs.zip").to_vec();
+        let len = ZipArchive::new(io::Cursor::new(v.clone())).unwrap().len();
+
+        let config = ZipReaderConfig::default().max_entries(Some(len));
+        assert!(ZipArchive::with_config(io::Cursor::new(v.clone()), config).is_ok());
+
+        let config = ZipReaderConfig::default().max_entries(Some(len - 1));
+        match ZipArchive::with_config(io::Cursor::new(v), config) {
+            Err(ZipError::UnsupportedArchive(_)) => {}
+            other => panic!("unexpected result: {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn config_max_metadata_bytes() {
+        use super::{ZipArchive, ZipReaderConfig};
+        use std::io;
+
+        let v = include_bytes!("../tests/data/files_and_dirs.zip").to_vec();
+        let config = ZipReaderConfig::default().max_metadata_bytes(Some(16));
+        assert!(ZipArchive::with_config(io::Cursor::new(v), config).is_err());
+    }
+
+    #[test]
+    fn config_trust_central_directory_offsets() {
+        use super::{ZipArchive, ZipReaderConfig};
+        use crate::write::{FileOptions, ZipWriter};
+        use std::io::{self, Read, Write};
+
+        let mut writer = ZipWriter::new(io::Cursor::new(Vec::new()));
+        writer.start_file("test", FileOptions::default()).unwrap();
+        writer.write_all(b"hello").unwrap();
+        let mut v = writer.finish().unwrap().into_inner();
+
+        // Put padding between the central directory and its end record, which makes the
+        // recorded offsets look like they are off by the size of the padding.
+        let cde_start = v.len() - 22;
+        assert_eq!(v[cde_start..cde_start + 4], [0x50, 0x4b, 0x05, 0x06]);
+        v.splice(cde_start..cde_start, [0; 16]);
+
+        assert!(ZipArchive::new(io::Cursor::new(v.clone())).is_err());
+
+        let config = ZipReaderConfig::default().trust_central_directory_offsets(true);
+        let mut archive = ZipArchive::with_config(io::Cursor::new(v), config).unwrap();
+        assert_eq!(archive.offset(), 0);
+        let mut contents = String::new();
+        archive
+            .by_index(0)
+            .unwrap()
+            .read_to_string(&mut contents)
+            .unwrap();
+        assert_eq!(contents, "hello");
+    }
+
+    #[test]
+    fn config_file_name_encoding() {
+        use super::{FileNameEncoding, ZipArchive, ZipReaderConfig};
+        use crate::write::{FileOptions, ZipWriter};
+        use std::io::{self, Write};
+
+        let mut writer = ZipWriter::new(io::Cursor::new(Vec::new()));
+        writer.start_file("Ç.txt", FileOptions::default()).unwrap();
+        writer.write_all(b"hello").unwrap();
+        let v = writer.finish().unwrap().into_inner();
+
+        let archive = ZipArchive::new(io::Cursor::new(v.clone())).unwrap();
+        assert_eq!(archive.file_names().collect::<Vec<_>>(), ["Ç.txt"]);
+
+        // "Ç" is encoded as 0xC3 0x87 in UTF-8
+        let config = ZipReaderConfig::default().file_name_encoding(FileNameEncoding::Cp437);
+        let mut archive = ZipArchive::with_config(io::Cursor::new(v), config).unwrap();
+        assert_eq!(archive.file_names().collect::<Vec<_>>(), ["├ç.txt"]);
+        assert!(archive.by_name("├ç.txt").is_ok());
+    }
+
+    #[test]
+    fn config_validate_entry_overlap() {
+        use super::{ZipArchive, ZipReaderConfig};
+        use crate::write::{FileOptions, ZipWriter};
+        use std::io::{self, Write};
+
+        let mut writer = ZipWriter::new(io::Cursor::new(Vec::new()));
+        for name in ["first", "second"] {
+            writer.start_file(name, FileOptions::default()).unwrap();
+            writer.write_all(b"some file contents").unwrap();
+        }
+        let mut v = writer.finish().unwrap().into_inner();
+
+        let config = ZipReaderConfig::default().validate_entry_overlap(true);
+        assert!(ZipArchive::with_config(io::Cursor::new(v.clone()), config.clone()).is_ok());
+
+        // Point the second entry's local header offset at the first entry
+        let second = ZipArchive::new(io::Cursor::new(v.clone()))
+            .unwrap()
+            .by_index(1)
+            .unwrap()
+            .central_header_start() as usize;
+        v[second + 42..second + 46].copy_from_slice(&[0; 4]);
+
+        assert!(ZipArchive::new(io::Cursor::new(v.clone())).is_ok());
+        assert!(ZipArchive::with_config(io::Cursor::new(v), config).is_err());
+    }
+
+    #[test]
+    fn split_archive() {
+        use super::ZipArchive;
+        use std::io::{self, Read};
+
+        let parts = vec![
+            io::Cursor::new(include_bytes!("../tests/data/split.z01").to_vec()),
+            io::Cursor::new(include_bytes!("../tests/data/split.zip").to_vec()),
+        ];
+        let mut archive = ZipArchive::new_split(parts).unwrap();
+        assert_eq!(archive.len(), 2);
+
+        let mut contents = String::new();
+        archive
+            .by_name("hello.txt")
+            .unwrap()
+            .read_to_string(&mut contents)
+            .unwrap();
+        assert_eq!(contents, "hello from a split archive\n");
+
+        // This entry starts in the first part and ends in the second; reading it checks its CRC.
+        let mut file = archive.by_name("random.bin").unwrap();
+        let mut contents = Vec::new();
+        file.read_to_end(&mut contents).unwrap();
+        assert_eq!(contents.len(), 70000);
+    }
+
+    #[test]
+    fn split_archive_from_path() {
+        use super::ZipArchive;
+
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/split.zip");
+        let archive = ZipArchive::open_split(path).unwrap();
+        assert_eq!(archive.len(), 2);
+
+        // Without its first part, the archive is rejected instead of misread
+        let last_part = include_bytes!("../tests/data/split.zip").to_vec();
+        assert!(ZipArchive::new_split(vec![std::io::Cursor::new(last_part)]).is_err());
+    }
+
+    #[test]
+    fn register_decompressor() {
+        use super::ZipArchive;
+        use crate::compression::CompressionMethod;
+        use crate::write::{FileOptions, ZipWriter};
+        use std::io::{self, Read, Write};
+        use std::sync::Arc;
+
+        /// A stand-in for a proprietary codec
+        struct XorReader<R>(R);
+
+        impl<R: Read> Read for XorReader<R> {
+            fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                let count = self.0.read(buf)?;
+                buf[..count].iter_mut().for_each(|byte| *byte ^= 0x5a);
+                Ok(count)
+            }
+        }
+
+        const XOR_METHOD: u16 = 0x7a7a;
+        let contents = b"compressed with a proprietary codec";
+        let encoded: Vec<u8> = contents.iter().map(|byte| byte ^ 0x5a).collect();
+
+        let mut writer = ZipWriter::new(io::Cursor::new(Vec::new()));
+        let options = FileOptions::default().compression_method(CompressionMethod::Stored);
+        writer.start_file("data.txt", options).unwrap();
+        writer.write_all(&encoded).unwrap();
+        let mut v = writer.finish().unwrap().into_inner();
+
+        // Mark the entry as using the XOR method, with the CRC-32 of the decoded data
+        let central = ZipArchive::new(io::Cursor::new(v.clone()))
+            .unwrap()
+            .by_index(0)
+            .unwrap()
+            .central_header_start() as usize;
+        let crc32 = crc32fast::hash(contents);
+        for (method, crc) in [(8, 14), (central + 10, central + 16)] {
+            v[method..method + 2].copy_from_slice(&XOR_METHOD.to_le_bytes());
+            v[crc..crc + 4].copy_from_slice(&crc32.to_le_bytes());
+        }
+
+        let mut archive = ZipArchive::new(io::Cursor::new(v)).unwrap();
+        assert!(archive.by_index(0).is_err());
+
+        // A decompressor that doesn't decode is caught by the CRC check
+        archive.register_decompressor(XOR_METHOD, Arc::new(|reader| reader));
+        let mut decoded = Vec::new();
+        assert!(archive
+            .by_index(0)
+            .unwrap()
+            .read_to_end(&mut decoded)
+            .is_err());
+
+        archive.register_decompressor(XOR_METHOD, Arc::new(|reader| Box::new(XorReader(reader))));
+        let mut decoded = Vec::new();
+        archive
+            .by_name("data.txt")
+            .unwrap()
+            .read_to_end(&mut decoded)
+            .unwrap();
+        assert_eq!(decoded, contents);
+
+        // Owned files skip their remaining data when dropped, which can't bypass the decompressor
+        let mut file = archive.by_index_owned(0).unwrap();
+        let mut start = [0; 8];
+        file.read_exact(&mut start).unwrap();
+        assert_eq!(start, contents[..8]);
+        drop(file);
+
+        let directory = tempfile::tempdir().unwrap();
+        archive.extract(directory.path()).unwrap();
+        let extracted = std::fs::read(directory.path().join("data.txt")).unwrap();
+        assert_eq!(extracted, contents);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn extract_symlinks() {
+        use super::ZipArchive;
+        use crate::write::{FileOptions, ZipWriter};
+        use std::io::{self, Write};
+
+        let mut writer = ZipWriter::new(io::Cursor::new(Vec::new()));
+        writer
+            .start_file("dir/file.txt", FileOptions::default())
+            .unwrap();
+        writer.write_all(b"file contents").unwrap();
+        writer
+            .add_symlink("link", "dir/file.txt", FileOptions::default())
+            .unwrap();
+        writer
+            .add_symlink(
+                "dir/sub/link",
+                "../../dir/./file.txt",
+                FileOptions::default(),
+            )
+            .unwrap();
+        let mut archive = ZipArchive::new(writer.finish().unwrap()).unwrap();
+        assert!(archive.by_name("link").unwrap().is_symlink());
+        assert!(!archive.by_name("link").unwrap().is_file());
+        assert!(!archive.by_name("dir/file.txt").unwrap().is_symlink());
+
+        let directory = tempfile::tempdir().unwrap();
+        archive.extract(directory.path()).unwrap();
+        for link in ["link", "dir/sub/link"] {
+            let link = directory.path().join(link);
+            assert!(link.symlink_metadata().unwrap().file_type().is_symlink());
+            assert_eq!(std::fs::read(link).unwrap(), b"file contents");
+        }
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn extract_dos_read_only() {
+        use super::{ExtractOptions, ZipArchive};
+        use crate::types::DosAttributes;
+        use crate::write::{FileOptions, ZipWriter};
+        use std::io;
+
+        let read_only = FileOptions::default().dos_attributes(DosAttributes {
+            read_only: true,
+            ..DosAttributes::default()
+        });
+        let mut writer = ZipWriter::new(io::Cursor::new(Vec::new()));
+        writer.start_file("read-only.txt", read_only).unwrap();
+        writer
+            .start_file("writable.txt", FileOptions::default())
+            .unwrap();
+        let mut archive = ZipArchive::new(writer.finish().unwrap()).unwrap();
+
+        for apply in [false, true] {
+            let temp = tempfile::tempdir().unwrap();
+            let directory = temp.path();
+            let options = ExtractOptions::default().dos_read_only(apply);
+            archive.extract_with_options(directory, &options).unwrap();
+            let is_read_only = |name: &str| {
+                std::fs::metadata(directory.join(name))
+                    .unwrap()
+                    .permissions()
+                    .readonly()
+            };
+            assert_eq!(is_read_only("read-only.txt"), apply);
+            assert!(!is_read_only("writable.txt"));
+
+            let path = directory.join("read-only.txt");
+            let mut permissions = std::fs::metadata(&path).unwrap().permissions();
+            permissions.set_readonly(false);
+            std::fs::set_permissions(&path, permissions).unwrap();
+        }
+    }
+
+    #[test]
+    fn buffer_sizes() {
+        use super::{ExtractOptions, ZipArchive, ZipReaderConfig};
+        use crate::write::{FileOptions, ZipWriter};
+        use std::io::{self, Read, Write};
+
+        let contents = "Contents of the file\n".repeat(1000);
+        let mut writer = ZipWriter::new(io::Cursor::new(Vec::new()));
+        writer
+            .start_file("file.txt", FileOptions::default())
+            .unwrap();
+        writer.write_all(contents.as_bytes()).unwrap();
+        writer
+            .start_file("after.txt", FileOptions::default())
+            .unwrap();
+        writer.write_all(b"after").unwrap();
+        let archive = writer.finish().unwrap();
+
+        // Buffers reading past the data of an entry don't affect the next one
+        let config = ZipReaderConfig::default().buffer_size(Some(7));
+        let mut archive = ZipArchive::with_config(archive, config).unwrap();
+        for (name, expected) in [("file.txt", contents.as_str()), ("after.txt", "after")] {
+            let mut data = String::new();
+            archive
+                .by_name(name)
+                .unwrap()
+                .read_to_string(&mut data)
+                .unwrap();
+            assert_eq!(data, expected);
+        }
+
+        let directory = tempfile::tempdir().unwrap();
+        for size in [0, 1, 1000, 1 << 20] {
+            let options = ExtractOptions::default().buffer_size(size);
+            archive
+                .extract_with_options(directory.path(), &options)
+                .unwrap();
+            let extracted = std::fs::read_to_string(directory.path().join("file.txt")).unwrap();
+            assert_eq!(extracted, contents);
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn extract_escaping_symlinks() {
+        use super::ZipArchive;
+        use crate::write::{FileOptions, ZipWriter};
+        use std::io::{self, Write};
+
+        let archives = [
+            vec![("dir/link", "../..")],
+            vec![("link", "/etc")],
+            vec![("link", "dir/../..")],
+            vec![("dir", "."), ("dir/link", "..")],
+        ];
+        for links in &archives {
+            let mut writer = ZipWriter::new(io::Cursor::new(Vec::new()));
+            for (name, target) in links {
+                writer
+                    .add_symlink(*name, *target, FileOptions::default())
+                    .unwrap();
+            }
+            writer
+                .start_file("file.txt", FileOptions::default())
+                .unwrap();
+            writer.write_all(b"file contents").unwrap();
+            let mut archive = ZipArchive::new(writer.finish().unwrap()).unwrap();
+
+            let directory = tempfile::tempdir().unwrap();
+            assert!(archive.extract(directory.path()).is_err());
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn extract_through_planted_symlinks() {
+        use super::stream::ZipStreamReader;
+        use super::ZipArchive;
+        use crate::result::ZipError;
+        use crate::write::{FileOptions, ZipWriter};
+        use std::fs;
+        use std::io::{self, Write};
+
+        let root = tempfile::tempdir().unwrap();
+        let (directory, outside) = (root.path().join("directory"), root.path().join("outside"));
+        fs::create_dir_all(&directory).unwrap();
+        fs::create_dir_all(&outside).unwrap();
+        fs::write(outside.join("target.txt"), b"outside").unwrap();
+        std::os::unix::fs::symlink(&outside, directory.join("dir")).unwrap();
+        std::os::unix::fs::symlink(outside.join("target.txt"), directory.join("file.txt")).unwrap();
+
+        for name in ["dir/file.txt", "file.txt", "dir/"] {
+            let mut writer = ZipWriter::new(io::Cursor::new(Vec::new()));
+            if name.ends_with('/') {
+                writer.add_directory(name, FileOptions::default()).unwrap();
+            } else {
+                writer.start_file(name, FileOptions::default()).unwrap();
+                writer.write_all(b"written through a symlink").unwrap();
+            }
+            let archive = writer.finish().unwrap().into_inner();
+
+            let error = ZipArchive::new(io::Cursor::new(archive.as_slice()))
+                .unwrap()
+                .extract(&directory)
+                .unwrap_err();
+            match error {
+                ZipError::Entry { source, .. } => {
+                    assert!(matches!(*source, ZipError::SymlinkInPath(_)), "{source}")
+                }
+                _ => panic!("expected an error naming the entry"),
+            }
+            let error = ZipStreamReader::new(archive.as_slice())
+                .extract(&directory)
+                .unwrap_err();
+            assert!(matches!(error, ZipError::SymlinkInPath(_)), "{error}");
+        }
+        assert!(!outside.join("file.txt").exists());
+        assert_eq!(fs::read(outside.join("target.txt")).unwrap(), b"outside");
+    }
+
+    #[test]
+    fn entry_name_in_errors() {
+        use super::ZipArchive;
+        use crate::result::ZipError;
+        use crate::write::{FileOptions, ZipWriter};
+        use std::io::{self, Write};
+
+        let mut writer = ZipWriter::new(io::Cursor::new(Vec::new()));
+        for name in ["first.txt", "dir/second.txt"] {
+            writer.start_file(name, FileOptions::default()).unwrap();
+            writer.write_all(b"some file contents").unwrap();
+        }
+        let mut v = writer.finish().unwrap().into_inner();
+
+        // Corrupt the local header signature of the second entry
+        let second = ZipArchive::new(io::Cursor::new(v.clone()))
+            .unwrap()
+            .by_index(1)
+            .unwrap()
+            .header_start() as usize;
+        v[second] = 0;
+
+        let mut archive = ZipArchive::new(io::Cursor::new(v)).unwrap();
+        assert!(archive.by_index(0).is_ok());
+        match archive.by_index(1) {
+            Err(ZipError::Entry { name, source }) => {
+                assert_eq!(name, "dir/second.txt");
+                assert!(matches!(*source, ZipError::InvalidArchive(_)));
+            }
+            _ => panic!("expected an error naming the entry"),
+        }
+
+        let directory = tempfile::tempdir().unwrap();
+        let error = archive.extract(directory.path()).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "error in entry `dir/second.txt`: invalid Zip archive: Invalid local file header"
+        );
+    }
+
+    #[test]
+    fn stream_read_error_while_skipping() {
+        use super::read_zipfile_from_stream;
+        use crate::write::{FileOptions, ZipWriter};
+        use crate::CompressionMethod;
+        use std::io::{self, Read, Write};
+
+        /// Fails once, when reading past `fail_at`
+        struct FlakyReader {
+            inner: io::Cursor<Vec<u8>>,
+            fail_at: Option<u64>,
+        }
+
+        impl Read for FlakyReader {
+            fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                if let Some(fail_at) = self.fail_at {
+                    if self.inner.position() + buf.len() as u64 > fail_at {
+                        self.fail_at = None;
+                        return Err(io::Error::new(io::ErrorKind::Other, "connection reset"));
+                    }
+                }
+                self.inner.read(buf)
+            }
+        }
+
+        let mut writer = ZipWriter::new(io::Cursor::new(Vec::new()));
+        let options = FileOptions::default().compression_method(CompressionMethod::Stored);
+        writer.start_file("large", options).unwrap();
+        writer.write_all(&[0; 100_000]).unwrap();
+        writer.start_file("small", options).unwrap();
+        writer.write_all(b"small").unwrap();
+        let v = writer.finish().unwrap().into_inner();
+
+        let flaky = || FlakyReader {
+            inner: io::Cursor::new(v.clone()),
+            fail_at: Some(50_000),
+        };
+
+        // Dropping the file doesn't panic; the stream is then out of place
+        let mut reader = flaky();
+        drop(read_zipfile_from_stream(&mut reader).unwrap().unwrap());
+        assert!(read_zipfile_from_stream(&mut reader).is_err());
+
+        // `finish` reports the error
+        let mut reader = flaky();
+        let file = read_zipfile_from_stream(&mut reader).unwrap().unwrap();
+        assert!(file.finish().is_err());
+
+        let mut reader = io::Cursor::new(v);
+        let file = read_zipfile_from_stream(&mut reader).unwrap().unwrap();
+        file.finish().unwrap();
+        let mut contents = String::new();
+        read_zipfile_from_stream(&mut reader)
+            .unwrap()
+            .unwrap()
+            .read_to_string(&mut contents)
+            .unwrap();
+        assert_eq!(contents, "small");
+    }
+
+    #[test]
+    fn by_index_owned() {
+        use super::ZipArchive;
+        use crate::write::{FileOptions, ZipWriter};
+        use std::io::{self, Read, Write};
+        use std::sync::Arc;
+
+        let mut writer = ZipWriter::new(io::Cursor::new(Vec::new()));
+        for (name, contents) in [("first", b"first file"), ("other", b"other file")] {
+            writer.start_file(name, FileOptions::default()).unwrap();
+            writer.write_all(contents).unwrap();
+        }
+        let data: Arc<[u8]> = writer.finish().unwrap().into_inner().into();
+        let archive = ZipArchive::new(io::Cursor::new(data)).unwrap();
+
+        let mut first = archive.by_index_owned(0).unwrap();
+        let mut other = archive.by_index_owned(1).unwrap();
+        assert_eq!(first.name(), "first");
+        assert_eq!(other.name(), "other");
+
+        let mut buf1 = [0; 6];
+        let mut buf2 = [0; 6];
+        first.read_exact(&mut buf1).unwrap();
+        other.read_exact(&mut buf2).unwrap();
+        assert_eq!(&buf1, b"first ");
+        assert_eq!(&buf2, b"other ");
+
+        let mut rest1 = String::new();
+        let mut rest2 = String::new();
+        other.read_to_string(&mut rest2).unwrap();
+        first.read_to_string(&mut rest1).unwrap();
+        assert_eq!(rest1, "file");
+        assert_eq!(rest2, "file");
+
+        assert!(matches!(
+            archive.by_index_owned(2),
+            Err(crate::result::ZipError::FileNotFound)
+        ));
+    }
+
+    #[test]
+    fn entries() {
+        use super::ZipArchive;
+        use crate::result::ZipError;
+        use crate::write::{FileOptions, ZipWriter};
+        use std::io::{self, Read, Write};
+
+        let mut writer = ZipWriter::new(io::Cursor::new(Vec::new()));
+        writer.start_file("first", FileOptions::default()).unwrap();
+        writer.write_all(b"first file").unwrap();
+        let encrypted = FileOptions::default().with_deprecated_encryption(b"password");
+        writer.start_file("encrypted", encrypted).unwrap();
+        writer.write_all(b"secret").unwrap();
+        writer.start_file("last", FileOptions::default()).unwrap();
+        writer.write_all(b"last file").unwrap();
+        let data = writer.finish().unwrap().into_inner();
+
+        let mut archive = ZipArchive::new(io::Cursor::new(data)).unwrap();
+        let mut results = Vec::new();
+        let mut entries = archive.entries();
+        loop {
+            match entries.next_entry() {
+                Ok(Some(mut file)) => {
+                    let mut contents = String::new();
+                    file.read_to_string(&mut contents).unwrap();
+                    results.push(Ok((file.name().to_owned(), contents)));
+                }
+                Ok(None) => break,
+                Err(e) => results.push(Err(e)),
+            }
+        }
+        assert_eq!(results.len(), 3);
+        assert_eq!(
+            results[0].as_ref().unwrap(),
+            &("first".to_owned(), "first file".to_owned())
+        );
+        assert!(matches!(
+            results[1],
+            Err(ZipError::UnsupportedArchive(ZipError::PASSWORD_REQUIRED))
+        ));
+        assert_eq!(
+            results[2].as_ref().unwrap(),
+            &("last".to_owned(), "last file".to_owned())
+        );
+
+        let entries = archive.into_entries();
+        assert_eq!(entries.len(), 3);
+        let names: Vec<_> = entries
+            .map(|file| file.map(|file| file.name().to_owned()))
+            .collect();
+        assert_eq!(names[0].as_ref().unwrap(), "first");
+        assert!(names[1].is_err());
+        assert_eq!(names[2].as_ref().unwrap(), "last");
+    }
 }
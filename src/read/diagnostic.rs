@@ -0,0 +1,128 @@
+use std::fmt;
+
+/// The IDs of the extra fields that this crate reads
+const KNOWN_EXTRA_FIELDS: [u16; 7] = [0x0001, 0x000a, 0x5455, 0x7075, 0x7861, 0x7875, 0x9901];
+
+/// Something unusual that was worked around while reading the central directory, collected by
+/// [`ZipArchive::new_with_diagnostics`](crate::ZipArchive::new_with_diagnostics)
+///
+/// Entries are identified by their index, to be used with
+/// [`ZipArchive::by_index`](crate::ZipArchive::by_index).
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ParseDiagnostic {
+    /// The extra data of the entry ends in the middle of a field, which was ignored with the
+    /// rest of the extra data
+    TruncatedExtraField {
+        /// The index of the entry
+        index: usize,
+    },
+    /// The entry has an extra field that this crate doesn't read, which was skipped
+    UnknownExtraField {
+        /// The index of the entry
+        index: usize,
+        /// The ID of the extra field
+        id: u16,
+    },
+    /// The name of the entry isn't marked as UTF-8 and isn't ASCII, and no Info-ZIP Unicode Path
+    /// field replaces it, so it was decoded as CP437
+    Cp437Name {
+        /// The index of the entry
+        index: usize,
+    },
+    /// The ZIP64 end of central directory couldn't be read, so the regular one was used instead,
+    /// as allowed by [`ZipReaderConfig::lenient_zip64`](super::ZipReaderConfig::lenient_zip64)
+    Zip64EndIgnored,
+    /// Data follows the archive comment, which was ignored
+    TrailingData {
+        /// The number of bytes after the archive comment
+        length: u64,
+    },
+    /// The number of entries recorded in the end of central directory differs from the number
+    /// of entries read
+    EntryCountMismatch {
+        /// The number recorded in the end of central directory
+        recorded: u64,
+        /// The number of entries read
+        parsed: u64,
+    },
+}
+
+impl fmt::Display for ParseDiagnostic {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseDiagnostic::TruncatedExtraField { index } => {
+                write!(fmt, "extra field truncated in entry {index}")
+            }
+            ParseDiagnostic::UnknownExtraField { index, id } => {
+                write!(
+                    fmt,
+                    "unknown extra field {id:#06x} skipped in entry {index}"
+                )
+            }
+            ParseDiagnostic::Cp437Name { index } => {
+                write!(fmt, "name of entry {index} decoded as CP437")
+            }
+            ParseDiagnostic::Zip64EndIgnored => write!(
+                fmt,
+                "ZIP64 end of central directory unreadable, regular one used instead"
+            ),
+            ParseDiagnostic::TrailingData { length } => {
+                write!(
+                    fmt,
+                    "{length} bytes of trailing data after the archive comment"
+                )
+            }
+            ParseDiagnostic::EntryCountMismatch { recorded, parsed } => write!(
+                fmt,
+                "end of central directory records {recorded} entries, but {parsed} were read"
+            ),
+        }
+    }
+}
+
+/// Collect the diagnostics about the layout of the extra data of the entry `index`
+pub(super) fn extra_field_diagnostics(
+    index: usize,
+    extra_field: &[u8],
+    diagnostics: &mut Vec<ParseDiagnostic>,
+) {
+    let mut rest = extra_field;
+    while !rest.is_empty() {
+        if rest.len() < 4 {
+            diagnostics.push(ParseDiagnostic::TruncatedExtraField { index });
+            return;
+        }
+        let id = u16::from_le_bytes([rest[0], rest[1]]);
+        let len = u16::from_le_bytes([rest[2], rest[3]]) as usize;
+        if !KNOWN_EXTRA_FIELDS.contains(&id) {
+            diagnostics.push(ParseDiagnostic::UnknownExtraField { index, id });
+        }
+        if rest.len() - 4 < len {
+            diagnostics.push(ParseDiagnostic::TruncatedExtraField { index });
+            return;
+        }
+        rest = &rest[4 + len..];
+    }
+}
+
+/// Whether the extra data of an entry has an Info-ZIP Unicode Path field that replaces the name
+/// `name_raw`, as read by the central directory parser
+pub(super) fn has_unicode_path(extra_field: &[u8], name_raw: &[u8]) -> bool {
+    let mut rest = extra_field;
+    while rest.len() >= 4 {
+        let id = u16::from_le_bytes([rest[0], rest[1]]);
+        let len = (u16::from_le_bytes([rest[2], rest[3]]) as usize).min(rest.len() - 4);
+        let data = &rest[4..4 + len];
+        if id == 0x7075
+            && data.len() >= 5
+            && data[0] == 1
+            && data[1..5] == crc32fast::hash(name_raw).to_le_bytes()
+            && std::str::from_utf8(&data[5..]).is_ok()
+        {
+            return true;
+        }
+        rest = &rest[4 + len..];
+    }
+    false
+}
@@ -0,0 +1,164 @@
+use std::fs;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::Path;
+
+/// Reader over the parts of a split (spanned) archive
+///
+/// The parts are presented as a single contiguous stream, in the order they were given. Reads
+/// that cross the end of one part continue into the next.
+///
+/// Use [`ZipArchive::new_split`](crate::ZipArchive::new_split) to open a split archive, which
+/// also takes care of the per-part offsets recorded in the central directory.
+#[derive(Debug)]
+pub struct SplitReader<R> {
+    parts: Vec<R>,
+    starts: Vec<u64>,
+    len: u64,
+    pos: u64,
+    /// The part whose cursor is known to match `pos`
+    current: Option<usize>,
+}
+
+impl<R: Seek> SplitReader<R> {
+    /// Create a reader over the given parts, in order
+    ///
+    /// For archives written by WinZip, 7-Zip or Info-ZIP, this is `.z01`, `.z02`, ... followed
+    /// by the `.zip` file.
+    pub fn new(mut parts: Vec<R>) -> io::Result<SplitReader<R>> {
+        let mut starts = Vec::with_capacity(parts.len());
+        let mut len = 0;
+        for part in parts.iter_mut() {
+            starts.push(len);
+            len += part.seek(SeekFrom::End(0))?;
+        }
+        Ok(SplitReader {
+            parts,
+            starts,
+            len,
+            pos: 0,
+            current: None,
+        })
+    }
+
+    /// Get the offset at which each part starts
+    pub fn part_starts(&self) -> &[u64] {
+        &self.starts
+    }
+
+    /// Unwrap and return the parts
+    pub fn into_inner(self) -> Vec<R> {
+        self.parts
+    }
+
+    fn part_at(&self, pos: u64) -> usize {
+        // The last part starting at or before `pos` holds it, skipping over empty parts.
+        self.starts
+            .iter()
+            .rposition(|&start| start <= pos)
+            .unwrap_or(0)
+    }
+}
+
+impl SplitReader<fs::File> {
+    /// Open the parts of a split archive, given the path of its last part
+    ///
+    /// The other parts are expected next to it, with the same name and the extensions `.z01`,
+    /// `.z02` and so on.
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<SplitReader<fs::File>> {
+        let path = path.as_ref();
+        let mut parts = Vec::new();
+        for number in 1.. {
+            let part_path = path.with_extension(format!("z{:02}", number));
+            if !part_path.is_file() {
+                break;
+            }
+            parts.push(fs::File::open(part_path)?);
+        }
+        parts.push(fs::File::open(path)?);
+        SplitReader::new(parts)
+    }
+}
+
+impl<R: Read + Seek> Read for SplitReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.pos >= self.len || buf.is_empty() {
+            return Ok(0);
+        }
+
+        let index = self.part_at(self.pos);
+        let offset = self.pos - self.starts[index];
+        if self.current != Some(index) {
+            self.parts[index].seek(SeekFrom::Start(offset))?;
+            self.current = Some(index);
+        }
+
+        let part_end = self.starts.get(index + 1).copied().unwrap_or(self.len);
+        let limit = buf.len().min((part_end - self.pos) as usize);
+        let count = self.parts[index].read(&mut buf[..limit])?;
+        self.pos += count as u64;
+        if count == 0 {
+            // The part is shorter than it was when measured
+            self.current = None;
+        }
+        Ok(count)
+    }
+}
+
+impl<R: Read + Seek> Seek for SplitReader<R> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let new_pos = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(offset) => checked_add_signed(self.len, offset),
+            SeekFrom::Current(offset) => checked_add_signed(self.pos, offset),
+        };
+        let new_pos = new_pos.ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "invalid seek to a negative or overflowing position",
+            )
+        })?;
+        if new_pos != self.pos {
+            self.pos = new_pos;
+            self.current = None;
+        }
+        Ok(new_pos)
+    }
+}
+
+fn checked_add_signed(base: u64, offset: i64) -> Option<u64> {
+    if offset >= 0 {
+        base.checked_add(offset as u64)
+    } else {
+        base.checked_sub(offset.unsigned_abs())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::SplitReader;
+    use std::io::{self, Read, Seek, SeekFrom};
+
+    #[test]
+    fn read_across_parts() {
+        let parts = vec![
+            io::Cursor::new(b"abc".to_vec()),
+            io::Cursor::new(Vec::new()),
+            io::Cursor::new(b"defg".to_vec()),
+        ];
+        let mut reader = SplitReader::new(parts).unwrap();
+        assert_eq!(reader.part_starts(), [0, 3, 3]);
+
+        let mut contents = String::new();
+        reader.read_to_string(&mut contents).unwrap();
+        assert_eq!(contents, "abcdefg");
+
+        let mut buf = [0; 3];
+        reader.seek(SeekFrom::End(-5)).unwrap();
+        reader.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"cde");
+        reader.seek(SeekFrom::Current(-3)).unwrap();
+        reader.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"cde");
+        assert!(reader.seek(SeekFrom::Current(-6)).is_err());
+    }
+}
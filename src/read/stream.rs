@@ -1,10 +1,48 @@
+//! Provides high level API for reading from a stream.
+//!
+//! [`ZipStreamReader`] reads an archive front to back from any [`Read`], without seeking: first
+//! the entries from their local headers, then the central directory. Since the local headers
+//! hold less than the central directory, some metadata is only available at the end, through
+//! [`ZipStreamVisitor::visit_additional_metadata`]:
+//!
+//! - the comment of an entry
+//! - its external attributes, and so [`ZipFile::unix_mode`]
+//!
+//! Entries that are encrypted, or whose sizes are only known from a data descriptor after their
+//! data, cannot be streamed and fail with [`ZipError::UnsupportedArchive`].
+//!
+//! ```no_run
+//! use zip::read::stream::{ZipStreamFileMetadata, ZipStreamReader, ZipStreamVisitor};
+//! use zip::read::ZipFile;
+//! use zip::result::ZipResult;
+//!
+//! struct Lister;
+//!
+//! impl ZipStreamVisitor for Lister {
+//!     fn visit_file(&mut self, file: &mut ZipFile<'_>) -> ZipResult<()> {
+//!         println!("{}: {} bytes", file.name(), file.size());
+//!         Ok(())
+//!     }
+//!
+//!     fn visit_additional_metadata(&mut self, metadata: &ZipStreamFileMetadata) -> ZipResult<()> {
+//!         println!("{}: {:?}", metadata.name(), metadata.unix_mode());
+//!         Ok(())
+//!     }
+//! }
+//!
+//! # fn main() -> ZipResult<()> {
+//! ZipStreamReader::new(std::io::stdin()).visit(&mut Lister)?;
+//! # Ok(())
+//! # }
+//! ```
+
 use std::fs;
 use std::io::{self, Read};
 use std::path::Path;
 
 use super::{
-    central_header_to_zip_file_inner, read_zipfile_from_stream, spec, ZipError, ZipFile,
-    ZipFileData, ZipResult,
+    central_header_to_zip_file_inner, check_no_symlinks, create_without_following,
+    read_zipfile_from_stream, spec, ZipError, ZipFile, ZipFileData, ZipResult,
 };
 
 use byteorder::{LittleEndian, ReadBytesExt};
@@ -22,29 +60,36 @@ impl<R> ZipStreamReader<R> {
 
 impl<R: Read> ZipStreamReader<R> {
     fn parse_central_directory(&mut self) -> ZipResult<Option<ZipStreamFileMetadata>> {
-        // Give archive_offset and central_header_start dummy value 0, since
-        // they are not used in the output.
-        let archive_offset = 0;
-        let central_header_start = 0;
-
-        // Parse central header
         let signature = self.0.read_u32::<LittleEndian>()?;
         if signature != spec::CENTRAL_DIRECTORY_HEADER_SIGNATURE {
             Ok(None)
         } else {
-            central_header_to_zip_file_inner(&mut self.0, archive_offset, central_header_start)
-                .map(ZipStreamFileMetadata)
-                .map(Some)
+            self.parse_central_header().map(Some)
         }
     }
 
-    /// Iteraate over the stream and extract all file and their
+    /// Parse a central header, after its signature
+    fn parse_central_header(&mut self) -> ZipResult<ZipStreamFileMetadata> {
+        // Give archive_offset and central_header_start dummy value 0, since
+        // they are not used in the output.
+        let archive_offset = 0;
+        let central_header_start = 0;
+
+        central_header_to_zip_file_inner(&mut self.0, archive_offset, central_header_start)
+            .map(ZipStreamFileMetadata)
+    }
+
+    /// Iterate over the stream and extract all file and their
     /// metadata.
+    ///
+    /// Data of an entry that `visit_file` does not read is skipped.
     pub fn visit<V: ZipStreamVisitor>(mut self, visitor: &mut V) -> ZipResult<()> {
         while let Some(mut file) = read_zipfile_from_stream(&mut self.0)? {
             visitor.visit_file(&mut file)?;
         }
 
+        // Reading the entries stops after the signature of the first central header
+        visitor.visit_additional_metadata(&self.parse_central_header()?)?;
         while let Some(metadata) = self.parse_central_directory()? {
             visitor.visit_additional_metadata(&metadata)?;
         }
@@ -55,6 +100,9 @@ impl<R: Read> ZipStreamReader<R> {
     /// Extract a Zip archive into a directory, overwriting files if they
     /// already exist. Paths are sanitized with [`ZipFile::enclosed_name`].
     ///
+    /// No entry is written through a symlink already in the directory: this fails with
+    /// [`ZipError::SymlinkInPath`] instead.
+    ///
     /// Extraction is not atomic; If an error is encountered, some of the files
     /// may be left on disk.
     pub fn extract<P: AsRef<Path>>(self, directory: P) -> ZipResult<()> {
@@ -66,6 +114,7 @@ impl<R: Read> ZipStreamReader<R> {
                     .ok_or(ZipError::InvalidArchive("Invalid file path"))?;
 
                 let outpath = self.0.join(filepath);
+                check_no_symlinks(self.0, filepath, true)?;
 
                 if file.name().ends_with('/') {
                     fs::create_dir_all(&outpath)?;
@@ -73,7 +122,7 @@ impl<R: Read> ZipStreamReader<R> {
                     if let Some(p) = outpath.parent() {
                         fs::create_dir_all(p)?;
                     }
-                    let mut outfile = fs::File::create(&outpath)?;
+                    let mut outfile = create_without_following(&outpath)?;
                     io::copy(file, &mut outfile)?;
                 }
 
@@ -184,8 +233,7 @@ impl ZipStreamFileMetadata {
     pub fn is_dir(&self) -> bool {
         self.name()
             .chars()
-            .rev()
-            .next()
+            .next_back()
             .map_or(false, |c| c == '/' || c == '\\')
     }
 
@@ -369,4 +417,92 @@ mod test {
         .visit(&mut DummyVisitor)
         .unwrap_err();
     }
+
+    /// A reader that can't seek, like a pipe or a socket
+    struct Unseekable<R>(R);
+
+    impl<R: Read> Read for Unseekable<R> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.0.read(buf)
+        }
+    }
+
+    fn multi_entry_archive() -> Vec<u8> {
+        use crate::write::{FileOptions, ZipWriter};
+        use crate::CompressionMethod;
+        use std::io::Write;
+
+        let mut writer = ZipWriter::new(io::Cursor::new(Vec::new()));
+        let stored = FileOptions::default().compression_method(CompressionMethod::Stored);
+        writer.add_directory("dir/", stored).unwrap();
+        writer.start_file("dir/first.txt", stored).unwrap();
+        writer.write_all(&b"first entry\n".repeat(100)).unwrap();
+        writer
+            .start_file("second.txt", FileOptions::default())
+            .unwrap();
+        writer.write_all(&b"second entry\n".repeat(100)).unwrap();
+        writer
+            .start_file("third.txt", stored.unix_permissions(0o640))
+            .unwrap();
+        writer.write_all(b"third entry").unwrap();
+        writer.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn count_entries_unseekable() {
+        let mut visitor = CounterVisitor::default();
+        ZipStreamReader::new(Unseekable(io::Cursor::new(multi_entry_archive())))
+            .visit(&mut visitor)
+            .unwrap();
+        assert_eq!(visitor, CounterVisitor(4, 4));
+    }
+
+    #[test]
+    fn skip_unread_data_unseekable() {
+        /// Reads a few bytes of the first file only, and checks the last file
+        #[derive(Default)]
+        struct V {
+            files: Vec<String>,
+            third: Vec<u8>,
+            modes: Vec<Option<u32>>,
+        }
+        impl ZipStreamVisitor for V {
+            fn visit_file(&mut self, file: &mut ZipFile<'_>) -> ZipResult<()> {
+                // Not available until the central directory
+                assert_eq!(file.unix_mode(), None);
+                match file.name() {
+                    "dir/first.txt" => {
+                        let mut start = [0; 5];
+                        file.read_exact(&mut start)?;
+                        assert_eq!(&start, b"first");
+                    }
+                    "third.txt" => {
+                        file.read_to_end(&mut self.third)?;
+                    }
+                    _ => {}
+                }
+                self.files.push(file.name().to_owned());
+                Ok(())
+            }
+
+            fn visit_additional_metadata(
+                &mut self,
+                metadata: &ZipStreamFileMetadata,
+            ) -> ZipResult<()> {
+                self.modes.push(metadata.unix_mode());
+                Ok(())
+            }
+        }
+
+        let mut visitor = V::default();
+        ZipStreamReader::new(Unseekable(io::Cursor::new(multi_entry_archive())))
+            .visit(&mut visitor)
+            .unwrap();
+        assert_eq!(
+            visitor.files,
+            ["dir/", "dir/first.txt", "second.txt", "third.txt"]
+        );
+        assert_eq!(visitor.third, b"third entry");
+        assert_eq!(visitor.modes[3], Some(0o100640));
+    }
 }
@@ -0,0 +1,169 @@
+use crate::types::ZipFileData;
+use std::collections::HashMap;
+
+/// Split a name into its path components, treating `\` as `/` and skipping empty components
+fn components(name: &str) -> impl Iterator<Item = &str> {
+    name.split(['/', '\\'])
+        .filter(|component| !component.is_empty())
+}
+
+#[derive(Debug)]
+struct Node<'a> {
+    name: &'a str,
+    index: Option<usize>,
+    is_dir: bool,
+    parent: usize,
+    children: Vec<usize>,
+}
+
+/// The entries of an archive as a tree of directories and files, built by
+/// [`ZipArchive::tree`](crate::ZipArchive::tree)
+///
+/// Names are split on `/` and `\`, and empty components are skipped, so that `a//b` is `b` in
+/// the directory `a`. Entries whose name has no component, like `/`, are left out.
+///
+/// Parent directories without an entry of their own are *implicit*: they are in the tree, but
+/// have no entry index. Children are in the order in which they first appear in the central
+/// directory.
+///
+/// When several entries have the same name, the node refers to the last one, like
+/// [`ZipArchive::by_name`](crate::ZipArchive::by_name). A file and a directory with the same
+/// name (`a` and `a/`, or `a` and `a/b`) are two sibling nodes; path lookups find the directory.
+#[derive(Debug)]
+pub struct ZipTree<'a> {
+    /// The root is the first node
+    nodes: Vec<Node<'a>>,
+}
+
+impl<'a> ZipTree<'a> {
+    pub(crate) fn new(files: &'a [ZipFileData]) -> ZipTree<'a> {
+        let mut tree = ZipTree {
+            nodes: vec![Node {
+                name: "",
+                index: None,
+                is_dir: true,
+                parent: 0,
+                children: Vec::new(),
+            }],
+        };
+        let mut directories = HashMap::new();
+        let mut files_by_name = HashMap::new();
+        for (i, file) in files.iter().enumerate() {
+            let is_dir = file.file_name.ends_with(['/', '\\']);
+            let mut components = components(&file.file_name).peekable();
+            let mut parent = 0;
+            while let Some(component) = components.next() {
+                let is_last = components.peek().is_none();
+                let id = if is_last && !is_dir {
+                    *files_by_name
+                        .entry((parent, component))
+                        .or_insert_with(|| tree.push(parent, component, false))
+                } else {
+                    *directories
+                        .entry((parent, component))
+                        .or_insert_with(|| tree.push(parent, component, true))
+                };
+                if is_last {
+                    tree.nodes[id].index = Some(i);
+                }
+                parent = id;
+            }
+        }
+        tree
+    }
+
+    fn push(&mut self, parent: usize, name: &'a str, is_dir: bool) -> usize {
+        let id = self.nodes.len();
+        self.nodes.push(Node {
+            name,
+            index: None,
+            is_dir,
+            parent,
+            children: Vec::new(),
+        });
+        self.nodes[parent].children.push(id);
+        id
+    }
+
+    fn node(&self, id: usize) -> TreeNode<'_> {
+        TreeNode { tree: self, id }
+    }
+
+    /// Get the root directory, which is implicit
+    pub fn root(&self) -> TreeNode<'_> {
+        self.node(0)
+    }
+
+    /// Get the node at `path`, with the same normalization as the names of the entries
+    ///
+    /// The empty path is the root. If both a file and a directory are at `path`, this is the
+    /// directory.
+    pub fn get(&self, path: &str) -> Option<TreeNode<'_>> {
+        let mut node = self.root();
+        let mut components = components(path).peekable();
+        while let Some(component) = components.next() {
+            let is_last = components.peek().is_none();
+            let directory = node
+                .children()
+                .find(|child| child.is_dir() && child.name() == component);
+            node = match directory {
+                Some(directory) => directory,
+                None if is_last => node.children().find(|child| child.name() == component)?,
+                None => return None,
+            };
+        }
+        Some(node)
+    }
+
+    /// Iterate over the children of the directory at `path`
+    ///
+    /// This is empty if there is no directory at `path`.
+    pub fn children(&self, path: &str) -> impl Iterator<Item = TreeNode<'_>> {
+        self.get(path)
+            .filter(TreeNode::is_dir)
+            .into_iter()
+            .flat_map(|node| node.children())
+    }
+}
+
+/// A file or directory of a [`ZipTree`]
+#[derive(Clone, Copy, Debug)]
+pub struct TreeNode<'a> {
+    tree: &'a ZipTree<'a>,
+    id: usize,
+}
+
+impl<'a> TreeNode<'a> {
+    /// Get the last component of the path of this node, which is empty for the root
+    pub fn name(&self) -> &'a str {
+        self.tree.nodes[self.id].name
+    }
+
+    /// Get the index of the entry of this node, to be used with
+    /// [`ZipArchive::by_index`](crate::ZipArchive::by_index), or `None` for implicit directories
+    pub fn index(&self) -> Option<usize> {
+        self.tree.nodes[self.id].index
+    }
+
+    /// Returns whether this node is a directory
+    pub fn is_dir(&self) -> bool {
+        self.tree.nodes[self.id].is_dir
+    }
+
+    /// Get the directory containing this node, or `None` for the root
+    pub fn parent(&self) -> Option<TreeNode<'a>> {
+        match self.id {
+            0 => None,
+            id => Some(self.tree.node(self.tree.nodes[id].parent)),
+        }
+    }
+
+    /// Iterate over the children of this node, which is empty for files
+    pub fn children(&self) -> impl Iterator<Item = TreeNode<'a>> + 'a {
+        let tree = self.tree;
+        tree.nodes[self.id]
+            .children
+            .iter()
+            .map(move |&id| tree.node(id))
+    }
+}
@@ -0,0 +1,180 @@
+use super::read_local_header;
+use crate::result::{ZipError, ZipResult};
+use crate::spec;
+use crate::types::ZipFileData;
+use byteorder::{LittleEndian, ReadBytesExt};
+use std::fmt;
+use std::io::{self, Read, Seek};
+
+/// The general purpose flag bits this crate implements: encryption (bit 0), compression options
+/// (bits 1 and 2), data descriptor (bit 3) and UTF-8 names (bit 11)
+const SUPPORTED_FLAGS: u16 = 0b1000_0000_1111;
+
+/// The highest version needed to extract that this crate implements, 6.3
+const MAX_VERSION_NEEDED: u16 = 63;
+
+/// A field of the local header of an entry
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum HeaderField {
+    /// The compression method
+    CompressionMethod,
+    /// The CRC32 of the uncompressed data
+    Crc32,
+    /// The compressed size
+    CompressedSize,
+    /// The uncompressed size
+    UncompressedSize,
+    /// The raw file name
+    FileName,
+}
+
+/// A structural inconsistency found by
+/// [`ZipArchive::validate_strict`](crate::ZipArchive::validate_strict)
+///
+/// Entries are identified by their index, to be used with
+/// [`ZipArchive::by_index`](crate::ZipArchive::by_index).
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ValidationWarning {
+    /// The entry has general purpose flag bits that this crate doesn't implement, such as
+    /// patched data (bit 5) or strong encryption (bit 6)
+    UnsupportedFlags {
+        /// The index of the entry
+        index: usize,
+        /// The unsupported bits
+        flags: u16,
+    },
+    /// The entry needs a higher version to extract than the one this crate implements, 6.3
+    VersionNeeded {
+        /// The index of the entry
+        index: usize,
+        /// The version needed to extract, as recorded in the central header
+        version: u16,
+    },
+    /// A field of the local header of the entry differs from its central header
+    HeaderMismatch {
+        /// The index of the entry
+        index: usize,
+        /// The field that differs
+        field: HeaderField,
+    },
+    /// The local header of the entry is missing, or ends past the end of the archive
+    UnreadableLocalHeader {
+        /// The index of the entry
+        index: usize,
+    },
+    /// The size of the central directory recorded in its end record differs from the size of
+    /// its headers
+    CentralDirectorySize {
+        /// The size recorded in the end record
+        recorded: u64,
+        /// The size of the headers
+        actual: u64,
+    },
+}
+
+impl fmt::Display for ValidationWarning {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ValidationWarning::UnsupportedFlags { index, flags } => {
+                write!(fmt, "entry {index} has unsupported flags {flags:#06x}")
+            }
+            ValidationWarning::VersionNeeded { index, version } => write!(
+                fmt,
+                "entry {index} needs version {}.{} to extract",
+                version / 10,
+                version % 10
+            ),
+            ValidationWarning::HeaderMismatch { index, field } => write!(
+                fmt,
+                "entry {index} has a different {field:?} in its local and central headers"
+            ),
+            ValidationWarning::UnreadableLocalHeader { index } => {
+                write!(fmt, "entry {index} has no readable local header")
+            }
+            ValidationWarning::CentralDirectorySize { recorded, actual } => write!(
+                fmt,
+                "central directory is {actual} bytes, but its end record says {recorded}"
+            ),
+        }
+    }
+}
+
+/// Check the entry `index`, whose central header is `file`, against its local header, and
+/// collect what is wrong with it into `warnings`
+pub(super) fn validate_entry<R: Read + Seek>(
+    reader: &mut R,
+    index: usize,
+    file: &ZipFileData,
+    warnings: &mut Vec<ValidationWarning>,
+) -> ZipResult<()> {
+    let flags = file.flags & !SUPPORTED_FLAGS;
+    if flags != 0 {
+        warnings.push(ValidationWarning::UnsupportedFlags { index, flags });
+    }
+
+    // The upper byte is unused, but some writers put the host system there, as in version made by
+    reader.seek(io::SeekFrom::Start(file.central_header_start + 6))?;
+    let version = reader.read_u16::<LittleEndian>()?;
+    if version & 0xFF > MAX_VERSION_NEEDED {
+        warnings.push(ValidationWarning::VersionNeeded { index, version });
+    }
+
+    let local = match read_local_header_at(reader, file.header_start)? {
+        Some(local) => local,
+        None => {
+            warnings.push(ValidationWarning::UnreadableLocalHeader { index });
+            return Ok(());
+        }
+    };
+    let mut mismatches = vec![(
+        HeaderField::CompressionMethod,
+        local.compression_method_id != file.compression_method_id,
+    )];
+    // With a data descriptor, these are only known after the data
+    if !local.using_data_descriptor {
+        mismatches.extend([
+            (HeaderField::Crc32, local.crc32 != file.crc32),
+            (
+                HeaderField::CompressedSize,
+                local.compressed_size != file.compressed_size,
+            ),
+            (
+                HeaderField::UncompressedSize,
+                local.uncompressed_size != file.uncompressed_size,
+            ),
+        ]);
+    }
+    mismatches.push((
+        HeaderField::FileName,
+        local.file_name_raw != file.file_name_raw,
+    ));
+    warnings.extend(
+        mismatches
+            .into_iter()
+            .filter(|(_, mismatch)| *mismatch)
+            .map(|(field, _)| ValidationWarning::HeaderMismatch { index, field }),
+    );
+    Ok(())
+}
+
+/// Read the local header at `header_start`, or `None` if there is no local header there or it is
+/// cut short
+fn read_local_header_at<R: Read + Seek>(
+    reader: &mut R,
+    header_start: u64,
+) -> ZipResult<Option<ZipFileData>> {
+    let result = reader
+        .seek(io::SeekFrom::Start(header_start))
+        .map_err(ZipError::from)
+        .and_then(|_| match reader.read_u32::<LittleEndian>()? {
+            spec::LOCAL_FILE_HEADER_SIGNATURE => read_local_header(reader),
+            _ => Err(ZipError::InvalidArchive("Invalid local file header")),
+        });
+    match result {
+        Ok(local) => Ok(Some(local)),
+        Err(ZipError::Io(e)) if e.kind() != io::ErrorKind::UnexpectedEof => Err(ZipError::Io(e)),
+        Err(_) => Ok(None),
+    }
+}
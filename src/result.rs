@@ -3,24 +3,39 @@
 use std::error::Error;
 use std::fmt;
 use std::io;
+use std::path::PathBuf;
 
 /// Generic result type with ZipError as its error variant
 pub type ZipResult<T> = Result<T, ZipError>;
 
 /// The given password is wrong
+#[deprecated(
+    since = "0.7.0",
+    note = "decrypting methods return `ZipError::InvalidPassword` instead"
+)]
 #[derive(Debug)]
 pub struct InvalidPassword;
 
+#[allow(deprecated)]
 impl fmt::Display for InvalidPassword {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
         write!(fmt, "invalid password for file in archive")
     }
 }
 
+#[allow(deprecated)]
 impl Error for InvalidPassword {}
 
+#[allow(deprecated)]
+impl From<InvalidPassword> for ZipError {
+    fn from(_: InvalidPassword) -> ZipError {
+        ZipError::InvalidPassword
+    }
+}
+
 /// Error type for Zip
 #[derive(Debug)]
+#[non_exhaustive]
 pub enum ZipError {
     /// An Error caused by I/O
     Io(io::Error),
@@ -33,6 +48,25 @@ pub enum ZipError {
 
     /// The requested file could not be found in the archive
     FileNotFound,
+
+    /// The password provided is incorrect
+    ///
+    /// A missing password is reported as [`ZipError::UnsupportedArchive`] with
+    /// [`ZipError::PASSWORD_REQUIRED`] instead.
+    InvalidPassword,
+
+    /// Extracting an entry would write through the symlink at this path
+    ///
+    /// The symlink may have been extracted from the archive, or already be in the destination.
+    SymlinkInPath(PathBuf),
+
+    /// An error that occurred while reading or extracting a specific entry
+    Entry {
+        /// The name of the entry, as stored in the archive
+        name: String,
+        /// The error itself
+        source: Box<ZipError>,
+    },
 }
 
 impl From<io::Error> for ZipError {
@@ -48,6 +82,11 @@ impl fmt::Display for ZipError {
             ZipError::InvalidArchive(err) => write!(fmt, "invalid Zip archive: {err}"),
             ZipError::UnsupportedArchive(err) => write!(fmt, "unsupported Zip archive: {err}"),
             ZipError::FileNotFound => write!(fmt, "specified file not found in archive"),
+            ZipError::InvalidPassword => write!(fmt, "invalid password for file in archive"),
+            ZipError::SymlinkInPath(path) => {
+                write!(fmt, "refusing to write through symlink {}", path.display())
+            }
+            ZipError::Entry { name, source } => write!(fmt, "error in entry `{name}`: {source}"),
         }
     }
 }
@@ -56,12 +95,26 @@ impl Error for ZipError {
     fn source(&self) -> Option<&(dyn Error + 'static)> {
         match self {
             ZipError::Io(err) => Some(err),
+            ZipError::Entry { source, .. } => Some(source),
             _ => None,
         }
     }
 }
 
 impl ZipError {
+    /// Attach the name of the entry this error occurred in
+    ///
+    /// Errors that already name an entry are returned unchanged.
+    pub fn with_entry(self, name: impl Into<String>) -> ZipError {
+        match self {
+            ZipError::Entry { .. } => self,
+            source => ZipError::Entry {
+                name: name.into(),
+                source: Box::new(source),
+            },
+        }
+    }
+
     /// The text used as an error when a password is required and not supplied
     ///
     /// ```rust,no_run
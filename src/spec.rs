@@ -5,6 +5,7 @@ use std::io::prelude::*;
 
 pub const LOCAL_FILE_HEADER_SIGNATURE: u32 = 0x04034b50;
 pub const CENTRAL_DIRECTORY_HEADER_SIGNATURE: u32 = 0x02014b50;
+pub const DATA_DESCRIPTOR_SIGNATURE: u32 = 0x08074b50;
 const CENTRAL_DIRECTORY_END_SIGNATURE: u32 = 0x06054b50;
 pub const ZIP64_CENTRAL_DIRECTORY_END_SIGNATURE: u32 = 0x06064b50;
 const ZIP64_CENTRAL_DIRECTORY_END_LOCATOR_SIGNATURE: u32 = 0x07064b50;
@@ -61,40 +62,111 @@ impl CentralDirectoryEnd {
         })
     }
 
+    /// Find the end of central directory record by searching backwards from the end of the
+    /// file, and parse it along with the ZIP64 locator that precedes it, if there is one.
+    ///
+    /// The signature may also occur inside the archive comment or the data of the last entry, so
+    /// a candidate is only accepted if its comment ends exactly at the end of the file. If no
+    /// candidate does, because there is trailing garbage after the archive, the candidate whose
+    /// comment ends closest to the end of the file is used, as long as at most
+    /// `max_trailing_bytes` follow it. Among equally close ones, the one closest to the end of
+    /// the file wins.
+    ///
+    /// The end of the file is read in growing chunks, so archives without a long comment don't
+    /// need the whole 64 KiB search window to be read.
     pub fn find_and_parse<T: Read + io::Seek>(
         reader: &mut T,
-    ) -> ZipResult<(CentralDirectoryEnd, u64)> {
+        max_trailing_bytes: Option<u64>,
+    ) -> ZipResult<(
+        CentralDirectoryEnd,
+        u64,
+        Option<Zip64CentralDirectoryEndLocator>,
+    )> {
         const HEADER_SIZE: u64 = 22;
-        const BYTES_BETWEEN_MAGIC_AND_COMMENT_SIZE: u64 = HEADER_SIZE - 6;
+        const LOCATOR_SIZE: u64 = 20;
+        const COMMENT_SIZE_OFFSET: usize = HEADER_SIZE as usize - 2;
+        const MAX_WINDOW: u64 = LOCATOR_SIZE + HEADER_SIZE + u16::MAX as u64;
+        const WINDOWS: [u64; 3] = [1024, 16 * 1024, MAX_WINDOW];
         let file_length = reader.seek(io::SeekFrom::End(0))?;
 
-        let search_upper_bound = file_length.saturating_sub(HEADER_SIZE + ::std::u16::MAX as u64);
+        let search_upper_bound = file_length.saturating_sub(HEADER_SIZE + u16::MAX as u64);
 
         if file_length < HEADER_SIZE {
             return Err(ZipError::InvalidArchive("Invalid zip header"));
         }
 
-        let mut pos = file_length - HEADER_SIZE;
-        while pos >= search_upper_bound {
-            reader.seek(io::SeekFrom::Start(pos))?;
-            if reader.read_u32::<LittleEndian>()? == CENTRAL_DIRECTORY_END_SIGNATURE {
-                reader.seek(io::SeekFrom::Current(
-                    BYTES_BETWEEN_MAGIC_AND_COMMENT_SIZE as i64,
-                ))?;
-                let cde_start_pos = reader.seek(io::SeekFrom::Start(pos))?;
-                return CentralDirectoryEnd::parse(reader).map(|cde| (cde, cde_start_pos));
+        // `buffer` holds the end of the file, starting at `buffer_start`
+        let mut buffer = Vec::new();
+        let mut buffer_start = file_length;
+        let mut next_pos = Some(file_length - HEADER_SIZE);
+        // The best candidate so far, and the number of bytes after its comment
+        let mut best_match: Option<(u64, u64)> = None;
+        for &window in WINDOWS.iter() {
+            let new_start = file_length.saturating_sub(window);
+            if new_start < buffer_start {
+                let mut chunk = vec![0; (buffer_start - new_start) as usize];
+                reader.seek(io::SeekFrom::Start(new_start))?;
+                reader.read_exact(&mut chunk)?;
+                chunk.extend_from_slice(&buffer);
+                buffer = chunk;
+                buffer_start = new_start;
+            }
+
+            let lowest_pos = buffer_start.max(search_upper_bound);
+            while let Some(pos) = next_pos.filter(|&pos| pos >= lowest_pos) {
+                let record = &buffer[(pos - buffer_start) as usize..];
+                if record[..4] == CENTRAL_DIRECTORY_END_SIGNATURE.to_le_bytes() {
+                    let comment_length = u16::from_le_bytes([
+                        record[COMMENT_SIZE_OFFSET],
+                        record[COMMENT_SIZE_OFFSET + 1],
+                    ]) as u64;
+                    let trailing = file_length.checked_sub(pos + HEADER_SIZE + comment_length);
+                    if let Some(trailing) = trailing.filter(|&trailing| {
+                        max_trailing_bytes.map_or(true, |max| trailing <= max)
+                            && best_match.map_or(true, |(_, best)| trailing < best)
+                    }) {
+                        best_match = Some((pos, trailing));
+                        if trailing == 0 {
+                            break;
+                        }
+                    }
+                }
+                next_pos = pos.checked_sub(1);
+            }
+
+            let exact_match = best_match.map_or(false, |(_, trailing)| trailing == 0);
+            if exact_match || buffer_start <= search_upper_bound {
+                break;
             }
-            pos = match pos.checked_sub(1) {
-                Some(p) => p,
-                None => break,
-            };
         }
-        Err(ZipError::InvalidArchive(
+
+        let (cde_start_pos, _) = best_match.ok_or(ZipError::InvalidArchive(
             "Could not find central directory end",
-        ))
+        ))?;
+        let cde =
+            CentralDirectoryEnd::parse(&mut &buffer[(cde_start_pos - buffer_start) as usize..])?;
+
+        let zip64_locator = if cde_start_pos < LOCATOR_SIZE {
+            None
+        } else if cde_start_pos - LOCATOR_SIZE >= buffer_start {
+            let start = (cde_start_pos - LOCATOR_SIZE - buffer_start) as usize;
+            Zip64CentralDirectoryEndLocator::parse(&mut &buffer[start..]).ok()
+        } else {
+            reader.seek(io::SeekFrom::Start(cde_start_pos - LOCATOR_SIZE))?;
+            match Zip64CentralDirectoryEndLocator::parse(reader) {
+                Ok(locator) => Some(locator),
+                Err(ZipError::InvalidArchive(_)) => None,
+                Err(e) => return Err(e),
+            }
+        };
+
+        Ok((cde, cde_start_pos, zip64_locator))
     }
 
     pub fn write<T: Write>(&self, writer: &mut T) -> ZipResult<()> {
+        if self.zip_file_comment.len() > u16::MAX as usize {
+            return Err(ZipError::InvalidArchive("Archive comment is too long"));
+        }
         writer.write_u32::<LittleEndian>(CENTRAL_DIRECTORY_END_SIGNATURE)?;
         writer.write_u16::<LittleEndian>(self.disk_number)?;
         writer.write_u16::<LittleEndian>(self.disk_with_central_directory)?;
@@ -217,3 +289,157 @@ impl Zip64CentralDirectoryEnd {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::CentralDirectoryEnd;
+    use crate::result::ZipError;
+    use crate::write::ZipWriter;
+    use std::io;
+
+    fn archive_with_comment(comment: &[u8]) -> Vec<u8> {
+        let mut writer = ZipWriter::new(io::Cursor::new(Vec::new()));
+        writer.set_raw_comment(comment.to_vec());
+        writer.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn signature_in_comment() {
+        let comment = b"PK\x05\x06 marks the end of the central directory";
+        let v = archive_with_comment(comment);
+
+        let (cde, pos, _) =
+            CentralDirectoryEnd::find_and_parse(&mut io::Cursor::new(&v), None).unwrap();
+        assert_eq!(pos, 0);
+        assert_eq!(cde.zip_file_comment, comment);
+    }
+
+    #[test]
+    fn binary_comment() {
+        let comment: Vec<u8> = (0..=255).collect();
+        let v = archive_with_comment(&comment);
+
+        let archive = crate::ZipArchive::new(io::Cursor::new(v)).unwrap();
+        assert_eq!(archive.comment(), comment.as_slice());
+    }
+
+    #[test]
+    fn comment_too_long() {
+        let mut writer = ZipWriter::new(io::Cursor::new(Vec::new()));
+        writer.set_raw_comment(vec![b'x'; 70_000]);
+        assert!(matches!(writer.finish(), Err(ZipError::InvalidArchive(_))));
+    }
+
+    #[test]
+    fn trailing_garbage() {
+        let mut v = archive_with_comment(b"comment");
+        v.extend_from_slice(b"garbage after the archive");
+
+        let (cde, pos, _) =
+            CentralDirectoryEnd::find_and_parse(&mut io::Cursor::new(&v), None).unwrap();
+        assert_eq!(pos, 0);
+        assert_eq!(cde.zip_file_comment, b"comment");
+
+        let max = Some(b"garbage after the archive".len() as u64);
+        assert!(CentralDirectoryEnd::find_and_parse(&mut io::Cursor::new(&v), max).is_ok());
+        let max = max.map(|max| max - 1);
+        assert!(CentralDirectoryEnd::find_and_parse(&mut io::Cursor::new(&v), max).is_err());
+    }
+
+    #[test]
+    fn trailing_garbage_best_fit() {
+        // The comment starts with an empty record, which is closer to the end of the file than
+        // the real one, but whose comment ends farther from it
+        let mut comment = archive_with_comment(b"");
+        comment.extend_from_slice(b" and the rest of the comment");
+        let mut v = archive_with_comment(&comment);
+        v.extend_from_slice(b"garbage");
+
+        let (cde, pos, _) =
+            CentralDirectoryEnd::find_and_parse(&mut io::Cursor::new(&v), None).unwrap();
+        assert_eq!(pos, 0);
+        assert_eq!(cde.zip_file_comment, comment);
+    }
+
+    /// Reader that counts the bytes read through it
+    struct CountingReader<R> {
+        inner: R,
+        bytes_read: usize,
+    }
+
+    impl<R: io::Read> io::Read for CountingReader<R> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let count = self.inner.read(buf)?;
+            self.bytes_read += count;
+            Ok(count)
+        }
+    }
+
+    impl<R: io::Seek> io::Seek for CountingReader<R> {
+        fn seek(&mut self, pos: io::SeekFrom) -> io::Result<u64> {
+            self.inner.seek(pos)
+        }
+    }
+
+    #[test]
+    fn small_archive() {
+        use crate::write::FileOptions;
+        use crate::CompressionMethod;
+        use std::io::Write;
+
+        let options = FileOptions::default().compression_method(CompressionMethod::Stored);
+        let mut writer = ZipWriter::new(io::Cursor::new(Vec::new()));
+        writer.start_file("test", options).unwrap();
+        writer.write_all(&[b'x'; 100_000]).unwrap();
+        let v = writer.finish().unwrap().into_inner();
+
+        let mut reader = CountingReader {
+            inner: io::Cursor::new(&v),
+            bytes_read: 0,
+        };
+        let (cde, pos, locator) = CentralDirectoryEnd::find_and_parse(&mut reader, None).unwrap();
+        assert_eq!(pos as usize, v.len() - 22);
+        assert_eq!(cde.number_of_files, 1);
+        assert!(locator.is_none());
+        assert_eq!(reader.bytes_read, 1024);
+    }
+
+    #[test]
+    fn max_length_comment() {
+        let mut comment = vec![b'x'; u16::MAX as usize];
+        // Put a signature at the end of the comment, in the first chunk that is searched
+        comment[u16::MAX as usize - 22..u16::MAX as usize - 18].copy_from_slice(b"PK\x05\x06");
+        let v = archive_with_comment(&comment);
+
+        let (cde, pos, _) =
+            CentralDirectoryEnd::find_and_parse(&mut io::Cursor::new(&v), None).unwrap();
+        assert_eq!(pos, 0);
+        assert_eq!(cde.zip_file_comment, comment);
+    }
+
+    #[test]
+    fn zip64_locator() {
+        use super::Zip64CentralDirectoryEndLocator;
+
+        let mut v = Vec::new();
+        Zip64CentralDirectoryEndLocator {
+            disk_with_central_directory: 0,
+            end_of_central_directory_offset: 1234,
+            number_of_disks: 1,
+        }
+        .write(&mut v)
+        .unwrap();
+        v.extend_from_slice(&archive_with_comment(b"comment"));
+
+        let (_, pos, locator) =
+            CentralDirectoryEnd::find_and_parse(&mut io::Cursor::new(&v), None).unwrap();
+        assert_eq!(pos, 20);
+        assert_eq!(locator.unwrap().end_of_central_directory_offset, 1234);
+    }
+
+    #[test]
+    fn no_record() {
+        let v = vec![0; 100];
+        assert!(CentralDirectoryEnd::find_and_parse(&mut io::Cursor::new(&v), None).is_err());
+    }
+}
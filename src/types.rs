@@ -1,20 +1,23 @@
 //! Types that specify what is contained in a ZIP.
 use std::path;
 
+use std::convert::TryFrom;
+use std::ffi::OsString;
 #[cfg(not(any(
     all(target_arch = "arm", target_pointer_width = "32"),
     target_arch = "mips",
     target_arch = "powerpc"
 )))]
 use std::sync::atomic;
-#[cfg(not(feature = "time"))]
-use std::time::SystemTime;
+use std::time::{SystemTime, UNIX_EPOCH};
 #[cfg(doc)]
 use {crate::read::ZipFile, crate::write::FileOptions};
 
 mod ffi {
+    pub const S_IFMT: u32 = 0o0170000;
     pub const S_IFDIR: u32 = 0o0040000;
     pub const S_IFREG: u32 = 0o0100000;
+    pub const S_IFLNK: u32 = 0o0120000;
 }
 
 #[cfg(any(
@@ -49,20 +52,23 @@ mod atomic {
     }
 }
 
-#[cfg(feature = "time")]
 use crate::result::DateTimeRangeError;
 #[cfg(feature = "time")]
 use time::{error::ComponentRange, Date, Month, OffsetDateTime, PrimitiveDateTime, Time};
 
+/// The system that made a file, which determines how its external attributes are read
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum System {
+    /// MS-DOS and Windows, with FAT attributes
     Dos = 0,
+    /// Unix, with a unix mode
     Unix = 3,
+    /// Any other system
     Unknown,
 }
 
 impl System {
-    pub fn from_u8(system: u8) -> System {
+    pub(crate) fn from_u8(system: u8) -> System {
         use self::System::*;
 
         match system {
@@ -88,8 +94,10 @@ impl System {
 /// be used for user-facing descriptions. This also means [`DateTime::to_time`] returns an
 /// [`OffsetDateTime`] (which is the equivalent of chrono's `NaiveDateTime`).
 ///
-/// Modern zip files store more precise timestamps, which are ignored by [`crate::read::ZipArchive`],
-/// so keep in mind that these timestamps are unreliable. [We're working on this](https://github.com/zip-rs/zip/issues/156#issuecomment-652981904).
+/// Modern zip files store more precise timestamps in extra fields, so keep in mind that these
+/// timestamps are unreliable. The extended timestamp field, in seconds since the Unix epoch, is
+/// read with [`ZipFile::unix_mtime`](crate::read::ZipFile::unix_mtime) and written with
+/// [`FileOptions::unix_mtime`](crate::write::FileOptions::unix_mtime).
 #[derive(Debug, Clone, Copy)]
 pub struct DateTime {
     year: u16,
@@ -172,6 +180,40 @@ impl DateTime {
         }
     }
 
+    /// Converts a number of seconds since the Unix epoch to a DateTime, in UTC
+    ///
+    /// Returns `Err` when the time is before 1980 or after 2107.
+    pub(crate) fn from_unix_seconds(seconds: i64) -> Result<DateTime, DateTimeRangeError> {
+        // Civil date from days since the epoch, in the proleptic Gregorian calendar
+        let days = seconds.div_euclid(86400);
+        let time = seconds.rem_euclid(86400);
+        let z = days + 719468;
+        let era = z.div_euclid(146097);
+        let day_of_era = z - era * 146097;
+        let year_of_era =
+            (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let shifted_month = (5 * day_of_year + 2) / 153;
+        let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+        let month = if shifted_month < 10 {
+            shifted_month + 3
+        } else {
+            shifted_month - 9
+        };
+        let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+        if !(1980..=2107).contains(&year) {
+            return Err(DateTimeRangeError);
+        }
+        Ok(DateTime {
+            year: year as u16,
+            month: month as u8,
+            day: day as u8,
+            hour: (time / 3600) as u8,
+            minute: (time % 3600 / 60) as u8,
+            second: (time % 60) as u8,
+        })
+    }
+
     #[cfg(feature = "time")]
     /// Converts a OffsetDateTime object to a DateTime
     ///
@@ -252,6 +294,22 @@ impl DateTime {
     }
 }
 
+/// Converts a [`SystemTime`] to a DateTime in UTC, truncating it to the second
+///
+/// Fails for times before 1980 or after 2107. When written, the seconds are also truncated to an
+/// even number.
+impl TryFrom<SystemTime> for DateTime {
+    type Error = DateTimeRangeError;
+
+    fn try_from(time: SystemTime) -> Result<Self, Self::Error> {
+        let seconds = time
+            .duration_since(UNIX_EPOCH)
+            .map_err(|_| DateTimeRangeError)?
+            .as_secs();
+        DateTime::from_unix_seconds(i64::try_from(seconds).map_err(|_| DateTimeRangeError)?)
+    }
+}
+
 #[cfg(feature = "time")]
 impl TryFrom<OffsetDateTime> for DateTime {
     type Error = DateTimeRangeError;
@@ -316,8 +374,19 @@ pub struct ZipFileData {
     pub encrypted: bool,
     /// True if the file uses a data-descriptor section
     pub using_data_descriptor: bool,
+    /// General purpose bit flags, as read from the archive
+    ///
+    /// Some of these are options of the compression method. When writing, the flags are
+    /// derived from the other fields instead.
+    #[cfg_attr(not(feature = "legacy-decompress"), allow(dead_code))]
+    pub flags: u16,
     /// Compression method used to store the file
     pub compression_method: crate::compression::CompressionMethod,
+    /// Compression method ID written to the headers
+    ///
+    /// This is kept as it was read, so that a raw copy writes the same ID even when several IDs
+    /// map to one `compression_method`, like the two IDs used for Zstandard.
+    pub compression_method_id: u16,
     /// Compression level to store the file
     pub compression_level: Option<i32>,
     /// Last modified time. This will only have a 2 second precision.
@@ -334,22 +403,47 @@ pub struct ZipFileData {
     pub file_name_raw: Vec<u8>,
     /// Extra field usually used for storage expansion
     pub extra_field: Vec<u8>,
+    /// Padding field aligning the data, written after the extra field of the local header
+    ///
+    /// This is only used when writing.
+    pub alignment_padding: Vec<u8>,
     /// File comment
     pub file_comment: String,
     /// Specifies where the local header of the file starts
     pub header_start: u64,
+    /// Number of the disk (part of a split archive) on which the local header starts
+    pub disk_number: u32,
     /// Specifies where the central header of the file starts
     ///
     /// Note that when this is not known, it is set to 0
     pub central_header_start: u64,
     /// Specifies where the compressed data of the file starts
     pub data_start: AtomicU64,
+    /// Alignment of the data of the file, if a padding field of its local header aligns it
+    ///
+    /// This is 1 if the data isn't padded, or until the local header is read.
+    pub data_alignment: AtomicU64,
     /// External file attributes
     pub external_attributes: u32,
     /// Reserve local ZIP64 extra field
     pub large_file: bool,
     /// AES mode if applicable
     pub aes_mode: Option<(AesMode, AesVendorVersion)>,
+    /// Modification time of the extended timestamp extra field, in seconds since the Unix epoch
+    pub unix_mtime: Option<i64>,
+    /// Access time of the extended timestamp extra field, which is only in the local header
+    pub unix_atime: Option<i64>,
+    /// Times of the NTFS extra field
+    pub ntfs_times: Option<NtfsTimes>,
+    /// User and group IDs of the Info-ZIP new Unix extra field
+    pub unix_ownership: Option<(u32, u32)>,
+    /// Extended attributes, as names and values, of the crate's extended attributes extra field
+    pub xattrs: Vec<(OsString, Vec<u8>)>,
+    /// Whether to write the name and comment in IBM codepage 437, with the name also in an
+    /// Info-ZIP Unicode Path extra field
+    ///
+    /// This is only used when writing.
+    pub legacy_name_compat: bool,
 }
 
 impl ZipFileData {
@@ -414,7 +508,7 @@ impl ZipFileData {
                 };
                 if 0x01 == (self.external_attributes & 0x01) {
                     // Read-only bit; strip write permissions
-                    mode &= 0o0555;
+                    mode &= !0o0222;
                 }
                 Some(mode)
             }
@@ -422,6 +516,12 @@ impl ZipFileData {
         }
     }
 
+    /// Whether the unix mode of the file marks it as a symlink
+    pub(crate) fn is_symlink(&self) -> bool {
+        self.unix_mode()
+            .map_or(false, |mode| mode & ffi::S_IFMT == ffi::S_IFLNK)
+    }
+
     pub fn zip64_extension(&self) -> bool {
         self.uncompressed_size > 0xFFFFFFFF
             || self.compressed_size > 0xFFFFFFFF
@@ -431,6 +531,9 @@ impl ZipFileData {
     pub fn version_needed(&self) -> u16 {
         // higher versions matched first
         match (self.zip64_extension(), self.compression_method) {
+            #[cfg(feature = "xz")]
+            (_, crate::compression::CompressionMethod::Xz) => 63,
+            _ if self.aes_mode.is_some() => 51,
             #[cfg(feature = "bzip2")]
             (_, crate::compression::CompressionMethod::Bzip2) => 46,
             (true, _) => 45,
@@ -443,27 +546,32 @@ impl ZipFileData {
 ///
 /// According to the [specification](https://www.winzip.com/win/en/aes_info.html#winzip11) AE-2
 /// does not make use of the CRC check.
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum AesVendorVersion {
+    /// AE-1, which stores the CRC of the file
     Ae1,
+    /// AE-2, which doesn't store the CRC of the file, as it could leak information
     Ae2,
 }
 
 /// AES variant used.
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum AesMode {
+    /// AES with a 128-bit key
     Aes128,
+    /// AES with a 192-bit key
     Aes192,
+    /// AES with a 256-bit key
     Aes256,
 }
 
 #[cfg(feature = "aes-crypto")]
 impl AesMode {
-    pub fn salt_length(&self) -> usize {
+    pub(crate) fn salt_length(&self) -> usize {
         self.key_length() / 2
     }
 
-    pub fn key_length(&self) -> usize {
+    pub(crate) fn key_length(&self) -> usize {
         match self {
             Self::Aes128 => 16,
             Self::Aes192 => 24,
@@ -472,6 +580,72 @@ impl AesMode {
     }
 }
 
+/// Times of the NTFS extra field of a file, read by Windows Explorer
+///
+/// The times are Windows `FILETIME` values: intervals of 100 nanoseconds since 1601-01-01 UTC.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct NtfsTimes {
+    /// Last modification time
+    pub mtime: u64,
+    /// Last access time
+    pub atime: u64,
+    /// Creation time
+    pub ctime: u64,
+}
+
+impl NtfsTimes {
+    /// Seconds between 1601-01-01, the `FILETIME` epoch, and the Unix epoch
+    const UNIX_EPOCH_OFFSET: u64 = 11_644_473_600;
+
+    /// Converts a [`SystemTime`] to a `FILETIME` value
+    ///
+    /// Times before 1601 are converted to 0.
+    pub fn filetime(time: SystemTime) -> u64 {
+        let intervals = |duration: std::time::Duration| {
+            duration.as_secs() as u128 * 10_000_000 + duration.subsec_nanos() as u128 / 100
+        };
+        let offset = Self::UNIX_EPOCH_OFFSET as u128 * 10_000_000;
+        let filetime = match time.duration_since(UNIX_EPOCH) {
+            Ok(after) => offset + intervals(after),
+            Err(before) => offset.saturating_sub(intervals(before.duration())),
+        };
+        filetime.min(u64::MAX as u128) as u64
+    }
+}
+
+/// MS-DOS attributes of a file, in the low byte of its external attributes
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct DosAttributes {
+    /// The file can't be written
+    pub read_only: bool,
+    /// The file isn't listed by default
+    pub hidden: bool,
+    /// The file belongs to the operating system
+    pub system: bool,
+    /// The file changed since it was last backed up
+    pub archive: bool,
+}
+
+impl DosAttributes {
+    pub(crate) const MASK: u32 = 0x27;
+
+    pub(crate) fn from_bits(bits: u32) -> DosAttributes {
+        DosAttributes {
+            read_only: bits & 0x01 != 0,
+            hidden: bits & 0x02 != 0,
+            system: bits & 0x04 != 0,
+            archive: bits & 0x20 != 0,
+        }
+    }
+
+    pub(crate) fn to_bits(self) -> u32 {
+        self.read_only as u32
+            | (self.hidden as u32) << 1
+            | (self.system as u32) << 2
+            | (self.archive as u32) << 5
+    }
+}
+
 #[cfg(test)]
 mod test {
     #[test]
@@ -492,7 +666,9 @@ mod test {
             version_made_by: 0,
             encrypted: false,
             using_data_descriptor: false,
+            flags: 0,
             compression_method: crate::compression::CompressionMethod::Stored,
+            compression_method_id: 0,
             compression_level: None,
             last_modified_time: DateTime::default(),
             crc32: 0,
@@ -501,13 +677,22 @@ mod test {
             file_name: file_name.clone(),
             file_name_raw: file_name.into_bytes(),
             extra_field: Vec::new(),
+            alignment_padding: Vec::new(),
             file_comment: String::new(),
             header_start: 0,
+            disk_number: 0,
             data_start: AtomicU64::new(0),
+            data_alignment: AtomicU64::new(1),
             central_header_start: 0,
             external_attributes: 0,
             large_file: false,
             aes_mode: None,
+            unix_mtime: None,
+            unix_atime: None,
+            ntfs_times: None,
+            unix_ownership: None,
+            xattrs: Vec::new(),
+            legacy_name_compat: false,
         };
         assert_eq!(
             data.file_name_sanitized(),
@@ -594,6 +779,65 @@ mod test {
         );
     }
 
+    #[test]
+    fn unix_seconds_conversion() {
+        use super::DateTime;
+        let dt = DateTime::from_unix_seconds(1_700_000_001).unwrap();
+        assert_eq!(
+            (dt.year(), dt.month(), dt.day()),
+            (2023, 11, 14),
+            "{:?}",
+            dt
+        );
+        assert_eq!((dt.hour(), dt.minute(), dt.second()), (22, 13, 21));
+
+        let dt = DateTime::from_unix_seconds(951_782_400).unwrap();
+        assert_eq!((dt.year(), dt.month(), dt.day()), (2000, 2, 29));
+
+        assert!(DateTime::from_unix_seconds(315_532_800).is_ok());
+        assert!(DateTime::from_unix_seconds(315_532_799).is_err());
+        assert!(DateTime::from_unix_seconds(0).is_err());
+        assert!(DateTime::from_unix_seconds(-1).is_err());
+    }
+
+    #[test]
+    fn filetime_conversion() {
+        use super::NtfsTimes;
+        use std::time::{Duration, UNIX_EPOCH};
+        assert_eq!(NtfsTimes::filetime(UNIX_EPOCH), 116_444_736_000_000_000);
+        assert_eq!(
+            NtfsTimes::filetime(UNIX_EPOCH + Duration::new(1, 500)),
+            116_444_736_010_000_005
+        );
+        assert_eq!(
+            NtfsTimes::filetime(UNIX_EPOCH - Duration::from_secs(11_644_473_600)),
+            0
+        );
+        assert_eq!(
+            NtfsTimes::filetime(UNIX_EPOCH - Duration::from_secs(11_644_473_601)),
+            0
+        );
+    }
+
+    #[test]
+    fn datetime_try_from_system_time() {
+        use super::DateTime;
+        use std::convert::TryFrom;
+        use std::time::{Duration, UNIX_EPOCH};
+
+        // 2023-11-14 22:13:21.7 UTC is truncated to the second
+        let dt = DateTime::try_from(UNIX_EPOCH + Duration::from_millis(1_700_000_001_700)).unwrap();
+        assert_eq!((dt.year(), dt.month(), dt.day()), (2023, 11, 14));
+        assert_eq!((dt.hour(), dt.minute(), dt.second()), (22, 13, 21));
+
+        let dt = DateTime::try_from(UNIX_EPOCH + Duration::from_secs(315_532_800)).unwrap();
+        assert_eq!((dt.year(), dt.month(), dt.day()), (1980, 1, 1));
+
+        assert!(DateTime::try_from(UNIX_EPOCH).is_err());
+        assert!(DateTime::try_from(UNIX_EPOCH - Duration::from_secs(86400)).is_err());
+        assert!(DateTime::try_from(UNIX_EPOCH + Duration::from_secs(4_354_819_200)).is_err());
+    }
+
     #[test]
     fn time_out_of_bounds() {
         use super::DateTime;
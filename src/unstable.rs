@@ -8,13 +8,17 @@ pub mod write {
     /// Unstable methods for [`FileOptions`].
     pub trait FileOptionsExt {
         /// Write the file with the given password using the deprecated ZipCrypto algorithm.
-        /// 
+        ///
         /// This is not recommended for new archives, as ZipCrypto is not secure.
+        ///
+        /// The keys derived from the password are kept in the options, which are `Copy`. With the
+        /// `zeroize` feature, the writer wipes its own keys once the file is written, but the
+        /// options and their copies aren't wiped.
         fn with_deprecated_encryption(self, password: &[u8]) -> Self;
     }
-    impl FileOptionsExt for FileOptions {
+    impl FileOptionsExt for FileOptions<'_> {
         fn with_deprecated_encryption(self, password: &[u8]) -> Self {
             self.with_deprecated_encryption(password)
         }
     }
-}
\ No newline at end of file
+}
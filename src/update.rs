@@ -0,0 +1,199 @@
+//! Updating an archive from the files it was made of
+
+use crate::read::ZipArchive;
+use crate::result::ZipResult;
+use crate::write::{DirectoryTreeOptions, FileOptions, TreeEntry, ZipWriter};
+use std::collections::HashSet;
+use std::fs;
+use std::io::{Read, Seek, Write};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+/// Options for [`sync_directory`]
+#[derive(Copy, Clone, Default)]
+pub struct SyncOptions<'k> {
+    file_options: FileOptions<'k>,
+    force_crc_check: bool,
+}
+
+impl<'k> SyncOptions<'k> {
+    /// Set the options of the new and changed files, such as their compression method
+    ///
+    /// The last modified time and, on Unix, the permissions are replaced by those of each file,
+    /// and the modification time is also written with [`FileOptions::unix_mtime`].
+    #[must_use]
+    pub fn file_options(mut self, options: FileOptions<'k>) -> SyncOptions<'k> {
+        self.file_options = options;
+        self
+    }
+
+    /// Set whether to compare the CRC32 of the files with those in the archive, rather than
+    /// their modification times
+    ///
+    /// Every file is then read, but a file whose modification time changed without its contents
+    /// changing is copied. The sizes are compared either way. The default is `false`.
+    #[must_use]
+    pub fn force_crc_check(mut self, force: bool) -> SyncOptions<'k> {
+        self.force_crc_check = force;
+        self
+    }
+}
+
+/// The names of the files written by [`sync_directory`], by what happened to them
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SyncReport {
+    /// Files and directories that didn't change, copied from the archive
+    pub copied: Vec<String>,
+    /// Files that changed, compressed again
+    pub updated: Vec<String>,
+    /// Files and directories that weren't in the archive
+    pub added: Vec<String>,
+    /// Files and directories of the archive that aren't in the directory anymore
+    pub removed: Vec<String>,
+}
+
+/// Write to `writer` the files and directories of `src`, recursively, taking those that didn't
+/// change from `archive`, like `zip -FS`.
+///
+/// The names are the paths relative to `src`, with `/` separators, in the order of
+/// [`ZipWriter::add_directory_tree`]. A file is copied with [`ZipWriter::raw_copy_file`], without
+/// being compressed again, if the archive has a file of the same name and size, and the same
+/// modification time: the one of its extended timestamp if it has one, or else its last modified
+/// time. The other files are added with [`ZipWriter::add_entry`]. Symlinks, FIFOs, sockets and
+/// devices are skipped. Files of `archive` missing from `src` are left out.
+///
+/// ```no_run
+/// use std::fs::File;
+/// use std::path::Path;
+/// use zip::update::{sync_directory, SyncOptions};
+/// use zip::{ZipArchive, ZipWriter};
+///
+/// # fn main() -> zip::result::ZipResult<()> {
+/// let mut old = ZipArchive::new(File::open("backup.zip")?)?;
+/// let mut writer = ZipWriter::new(File::create("backup.zip.new")?);
+/// let src = Path::new("documents");
+/// let report = sync_directory(&mut old, &mut writer, src, SyncOptions::default())?;
+/// writer.finish()?;
+/// std::fs::rename("backup.zip.new", "backup.zip")?;
+/// println!("{} files changed", report.updated.len());
+/// # Ok(())
+/// # }
+/// ```
+pub fn sync_directory<R, W>(
+    archive: &mut ZipArchive<R>,
+    writer: &mut ZipWriter<W>,
+    src: &Path,
+    options: SyncOptions,
+) -> ZipResult<SyncReport>
+where
+    R: Read + Seek,
+    W: Write + Seek,
+{
+    let mut report = SyncReport::default();
+    let mut written = HashSet::new();
+    for (name, path, metadata) in directory_entries(src)? {
+        let mut file_options = options.file_options.with_fs_metadata(&metadata);
+        if let Some(mtime) = unix_mtime(&metadata) {
+            file_options = file_options.unix_mtime(mtime);
+        }
+
+        let list = match archive.index_for_name(&name) {
+            Some(index) if unchanged(archive, index, &path, &metadata, &file_options, options)? => {
+                writer.raw_copy_file(archive.by_index_raw(index)?)?;
+                &mut report.copied
+            }
+            index => {
+                if metadata.is_dir() {
+                    writer.add_directory(name.as_str(), file_options)?;
+                } else {
+                    writer.add_entry(name.as_str(), fs::File::open(&path)?, file_options)?;
+                }
+                match index {
+                    Some(_) => &mut report.updated,
+                    None => &mut report.added,
+                }
+            }
+        };
+        list.push(name.clone());
+        written.insert(name);
+    }
+    report.removed = archive
+        .files()
+        .iter()
+        .map(|file| file.file_name.clone())
+        .filter(|name| !written.contains(name))
+        .collect();
+    Ok(report)
+}
+
+/// Whether the file of `archive` at `index` has the same contents as the one at `path`
+fn unchanged<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+    index: usize,
+    path: &Path,
+    metadata: &fs::Metadata,
+    file_options: &FileOptions,
+    options: SyncOptions,
+) -> ZipResult<bool> {
+    let file = archive.by_index_raw(index)?;
+    if metadata.is_dir() || file.is_dir() {
+        return Ok(metadata.is_dir() && file.is_dir());
+    }
+    if file.size() != metadata.len() {
+        return Ok(false);
+    }
+    if options.force_crc_check {
+        let crc32 = file.crc32();
+        drop(file);
+        let mut hasher = crc32fast::Hasher::new();
+        let mut source = fs::File::open(path)?;
+        let mut buffer = [0; 8192];
+        loop {
+            match source.read(&mut buffer)? {
+                0 => break,
+                length => hasher.update(&buffer[..length]),
+            }
+        }
+        return Ok(hasher.finalize() == crc32);
+    }
+    Ok(match file.unix_mtime() {
+        Some(file_mtime) => Some(file_mtime) == unix_mtime(metadata),
+        None => {
+            let (time, file_time) = (file_options.modified_time(), file.last_modified());
+            (time.datepart(), time.timepart()) == (file_time.datepart(), file_time.timepart())
+        }
+    })
+}
+
+/// The modification time of a file in seconds since the epoch, if it fits in an extended timestamp
+fn unix_mtime(metadata: &fs::Metadata) -> Option<i64> {
+    metadata
+        .modified()
+        .ok()
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map(|duration| duration.as_secs() as i64)
+        .filter(|seconds| *seconds <= i32::MAX as i64)
+}
+
+/// The files and directories in `dir`, recursively, with their names, paths and metadata, in the
+/// order of [`ZipWriter::add_directory_tree`]
+fn directory_entries(dir: &Path) -> ZipResult<Vec<(String, PathBuf, fs::Metadata)>> {
+    let mut entries = Vec::new();
+    DirectoryTreeOptions::default().walk(dir, &mut |entry| {
+        match entry {
+            TreeEntry::Directory {
+                name,
+                path,
+                metadata,
+            }
+            | TreeEntry::File {
+                name,
+                path,
+                metadata,
+            } => entries.push((name, path, metadata)),
+            TreeEntry::Symlink { .. } => {}
+        }
+        Ok(())
+    })?;
+    Ok(entries)
+}
@@ -1,17 +1,26 @@
 //! Types for creating ZIP archives
 
 use crate::compression::CompressionMethod;
-use crate::read::{central_header_to_zip_file, ZipArchive, ZipFile};
+use crate::read::{
+    central_header_to_zip_file, padded_data_alignment, read_local_header, ZipArchive, ZipFile,
+    ZipReaderConfig,
+};
 use crate::result::{ZipError, ZipResult};
 use crate::spec;
-use crate::types::{AtomicU64, DateTime, System, ZipFileData, DEFAULT_VERSION};
+use crate::types::{
+    AesMode, AesVendorVersion, AtomicU64, DateTime, DosAttributes, NtfsTimes, System, ZipFileData,
+    DEFAULT_VERSION,
+};
 use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
 use crc32fast::Hasher;
-use std::convert::TryInto;
+use std::borrow::Cow;
+use std::collections::HashSet;
 use std::default::Default;
+use std::ffi::{OsStr, OsString};
 use std::io;
 use std::io::prelude::*;
 use std::mem;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 #[cfg(any(
     feature = "deflate",
@@ -29,25 +38,51 @@ use time::OffsetDateTime;
 #[cfg(feature = "zstd")]
 use zstd::stream::write::Encoder as ZstdEncoder;
 
+#[cfg(any(feature = "deflate", feature = "deflate-miniz"))]
+mod deflate;
+
+#[cfg(feature = "parallel")]
+mod parallel;
+
+#[cfg(feature = "tokio")]
+pub mod tokio;
+
 enum MaybeEncrypted<W> {
     Unencrypted(W),
     Encrypted(crate::zipcrypto::ZipCryptoWriter<W>),
+    #[cfg(feature = "aes-crypto")]
+    Aes(crate::aes::AesWriter<W>),
 }
 impl<W: Write> Write for MaybeEncrypted<W> {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
         match self {
             MaybeEncrypted::Unencrypted(w) => w.write(buf),
             MaybeEncrypted::Encrypted(w) => w.write(buf),
+            #[cfg(feature = "aes-crypto")]
+            MaybeEncrypted::Aes(w) => w.write(buf),
         }
     }
     fn flush(&mut self) -> io::Result<()> {
         match self {
             MaybeEncrypted::Unencrypted(w) => w.flush(),
             MaybeEncrypted::Encrypted(w) => w.flush(),
+            #[cfg(feature = "aes-crypto")]
+            MaybeEncrypted::Aes(w) => w.flush(),
+        }
+    }
+}
+impl<W: Write> MaybeEncrypted<W> {
+    /// The writer of the archive, which receives the data once encrypted
+    fn get_plain_mut(&mut self) -> &mut W {
+        match self {
+            MaybeEncrypted::Unencrypted(w) => w,
+            MaybeEncrypted::Encrypted(w) => &mut w.writer,
+            #[cfg(feature = "aes-crypto")]
+            MaybeEncrypted::Aes(w) => w.get_mut(),
         }
     }
 }
-enum GenericZipWriter<W: Write + io::Seek> {
+enum GenericZipWriter<W: Write> {
     Closed,
     Storer(MaybeEncrypted<W>),
     #[cfg(any(
@@ -56,6 +91,8 @@ enum GenericZipWriter<W: Write + io::Seek> {
         feature = "deflate-zlib"
     ))]
     Deflater(DeflateEncoder<MaybeEncrypted<W>>),
+    #[cfg(any(feature = "deflate", feature = "deflate-miniz"))]
+    DeflaterWithStrategy(deflate::StrategyEncoder<MaybeEncrypted<W>>),
     #[cfg(feature = "bzip2")]
     Bzip2(BzEncoder<MaybeEncrypted<W>>),
     #[cfg(feature = "zstd")]
@@ -101,6 +138,25 @@ pub(crate) mod zip_writer {
         pub(super) writing_to_central_extra_field_only: bool,
         pub(super) writing_raw: bool,
         pub(super) comment: Vec<u8>,
+        pub(super) central_directory_sort: super::SortOrder,
+        pub(super) duplicate_policy: super::Duplicate,
+        /// Offset of the start of the archive, after any data prepended to it, which the
+        /// offsets in the central directory are relative to
+        pub(super) archive_offset: u64,
+        /// Offset past the furthest byte written before seeking back, which the archive must
+        /// end at or after so that nothing written before is left after it
+        pub(super) high_water_mark: u64,
+        /// Whether `inner` can't seek, so that files are followed by data descriptors instead
+        /// of their local headers being updated
+        pub(super) streaming: bool,
+        /// Names of the files in `files`
+        pub(super) names: HashSet<String>,
+        pub(super) progress: Option<super::ProgressCallback>,
+        pub(super) last_entry: Option<super::EntryStats>,
+        /// The data of the file being written, if it may be stored
+        pub(super) store_if_larger: Option<super::StoreIfLarger>,
+        #[cfg(feature = "parallel")]
+        pub(super) pending: super::parallel::Pending,
     }
 }
 pub use zip_writer::ZipWriter;
@@ -112,30 +168,316 @@ struct ZipWriterStats {
     bytes_written: u64,
 }
 
-struct ZipRawValues {
-    crc32: u32,
-    compressed_size: u64,
-    uncompressed_size: u64,
+/// Writer that can't seek, for [`ZipWriter::new_streaming`]
+///
+/// It counts the bytes written to `W`, so that the archive knows its offsets. Seeking to where it
+/// is is the only seek supported.
+#[derive(Debug)]
+pub struct StreamWriter<W: Write> {
+    inner: W,
+    bytes_written: u64,
+}
+
+impl<W: Write> StreamWriter<W> {
+    /// Unwrap the writer
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+impl<W: Write> Write for StreamWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let count = self.inner.write(buf)?;
+        self.bytes_written += count as u64;
+        Ok(count)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl<W: Write> io::Seek for StreamWriter<W> {
+    fn seek(&mut self, pos: io::SeekFrom) -> io::Result<u64> {
+        match pos {
+            io::SeekFrom::Current(0) => Ok(self.bytes_written),
+            io::SeekFrom::Start(offset) if offset == self.bytes_written => Ok(offset),
+            _ => Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "Streaming writers can't seek",
+            )),
+        }
+    }
+}
+
+/// The default size up to which files are kept in memory for [`FileOptions::store_if_larger`]
+const DEFAULT_STORE_IF_LARGER_BUFFER: usize = 16 << 20;
+
+/// The data of a file being written with [`FileOptions::store_if_larger`], and what is needed to
+/// store it instead
+struct StoreIfLarger {
+    data: Vec<u8>,
+    buffer_size: usize,
+    alignment: u16,
+    alignment_padding_id: u16,
+}
+
+/// The number of bytes written to a file between calls to the progress callback
+const PROGRESS_INTERVAL: u64 = 1 << 20;
+
+type ProgressCallback = Box<dyn FnMut(WriteProgress) + Send>;
+
+/// Progress of a file being written, passed to the callback set with
+/// [`ZipWriter::set_progress_callback`]
+#[derive(Copy, Clone, Debug)]
+#[non_exhaustive]
+pub struct WriteProgress<'a> {
+    /// Name of the file
+    pub name: &'a str,
+    /// Number of bytes of the file written so far, before compression
+    pub uncompressed_bytes: u64,
+    /// Number of bytes of compressed, and possibly encrypted, data written to the archive so far
+    pub compressed_bytes: u64,
+    /// Whether the file is finished, so that the numbers of bytes are those of the whole file
+    pub finished_entry: bool,
+}
+
+/// Where a file was written in the archive, and its sizes, given by
+/// [`ZipWriter::last_entry_stats`]
+///
+/// The offsets are from the start of the writer, like those of [`ZipFile`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct EntryStats {
+    /// Name of the file
+    pub name: String,
+    /// Offset of the local header of the file
+    pub header_start: u64,
+    /// Offset of the data of the file, after its local header
+    pub data_start: u64,
+    /// Size of the data of the file in the archive
+    pub compressed_size: u64,
+    /// Size of the file
+    pub uncompressed_size: u64,
+    /// CRC32 of the file, which is 0 for AE-2 encrypted files
+    pub crc32: u32,
+}
+
+impl EntryStats {
+    fn new(file: &ZipFileData) -> EntryStats {
+        EntryStats {
+            name: file.file_name.clone(),
+            header_start: file.header_start,
+            data_start: file.data_start.load(),
+            compressed_size: file.compressed_size,
+            uncompressed_size: file.uncompressed_size,
+            crc32: file.crc32,
+        }
+    }
+}
+
+/// Values of a file whose data is already compressed, for [`ZipWriter::add_raw_entry`]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ZipRawValues {
+    /// CRC32 of the uncompressed data
+    pub crc32: u32,
+    /// Size of the compressed data
+    pub compressed_size: u64,
+    /// Size of the uncompressed data
+    pub uncompressed_size: u64,
+}
+
+/// Order of the entries in the central directory, set with
+/// [`ZipWriter::set_central_directory_sort`]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SortOrder {
+    /// The order in which the files were added
+    Insertion,
+    /// By name, comparing the UTF-8 bytes of the names
+    ByName,
+}
+
+impl Default for SortOrder {
+    fn default() -> SortOrder {
+        SortOrder::Insertion
+    }
+}
+
+/// What [`ZipWriter`] does when a file is added with the name of a file already in the archive,
+/// set with [`ZipWriter::set_duplicate_policy`]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Duplicate {
+    /// Add the file, so that the archive has several files with the same name. Readers differ
+    /// in which one they use: [`ZipArchive`] uses the last one.
+    Allow,
+    /// Fail to add the file, without writing anything
+    Error,
+    /// Remove the earlier file from the central directory. Its data stays in the archive, unused.
+    Replace,
+}
+
+impl Default for Duplicate {
+    fn default() -> Duplicate {
+        Duplicate::Allow
+    }
+}
+
+/// What [`ZipWriter::add_directory_tree`] does with symlinks
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SymlinkPolicy {
+    /// Add symlinks as symlinks, with [`ZipWriter::add_symlink`]
+    Store,
+    /// Add the files and directories that symlinks point to, under the name of the symlink
+    Follow,
+}
+
+impl Default for SymlinkPolicy {
+    fn default() -> SymlinkPolicy {
+        SymlinkPolicy::Store
+    }
+}
+
+/// Strategy of the deflate compressor, set with [`DeflateOptions`]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Strategy {
+    /// Look for matches as usual
+    Default,
+    /// Only use matches of at least 5 bytes, for data with many small, near-random values
+    Filtered,
+    /// Don't look for matches, only Huffman-code the bytes, for data that is already compressed
+    HuffmanOnly,
+    /// Only look for runs of the same byte, which is fast
+    Rle,
+}
+
+impl Default for Strategy {
+    fn default() -> Strategy {
+        Strategy::Default
+    }
+}
+
+/// Settings of the deflate compressor, set with `FileOptions::deflate_options`
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct DeflateOptions {
+    /// The compression level, from 0 to 9. Default: 6.
+    pub level: i32,
+    /// The strategy of the compressor. Default: [`Strategy::Default`].
+    pub strategy: Strategy,
+}
+
+impl Default for DeflateOptions {
+    fn default() -> DeflateOptions {
+        DeflateOptions {
+            level: 6,
+            strategy: Strategy::Default,
+        }
+    }
+}
+
+/// A field of the extra data of a file, written with [`ZipWriter::start_file_with_extra_fields`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ExtraField {
+    /// Header ID, identifying the kind of field
+    pub id: u16,
+    /// Data of the field, without its header
+    pub data: Vec<u8>,
+}
+
+impl ExtraField {
+    /// Create a field from its header ID and data
+    pub fn new(id: u16, data: Vec<u8>) -> ExtraField {
+        ExtraField { id, data }
+    }
+}
+
+/// Copy the extra data of a file, except for the fields which the writer manages
+fn extra_data_without_managed_fields(mut data: &[u8]) -> ZipResult<Vec<u8>> {
+    let mut result = Vec::new();
+    while data.len() >= 4 {
+        let kind = u16::from_le_bytes([data[0], data[1]]);
+        let size = 4 + u16::from_le_bytes([data[2], data[3]]) as usize;
+        if size > data.len() {
+            return Err(ZipError::InvalidArchive(
+                "Extra data field exceeds extra data",
+            ));
+        }
+        if !matches!(
+            kind,
+            0x0001 | 0x000a | 0x5455 | 0x7075 | 0x7861 | 0x7875 | 0x9901
+        ) {
+            result.extend_from_slice(&data[..size]);
+        }
+        data = &data[size..];
+    }
+    Ok(result)
+}
+
+/// Serialize extra fields, checking that they fit in an extra field of `reserved` more bytes
+fn extra_fields_to_bytes(fields: &[ExtraField], reserved: usize) -> ZipResult<Vec<u8>> {
+    let mut data = Vec::new();
+    for field in fields {
+        let size = u16::try_from(field.data.len()).map_err(|_| {
+            ZipError::Io(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Extra data exceeds extra field",
+            ))
+        })?;
+        data.write_u16::<LittleEndian>(field.id)?;
+        data.write_u16::<LittleEndian>(size)?;
+        data.extend_from_slice(&field.data);
+    }
+    if data.len() + reserved > u16::MAX as usize {
+        return Err(ZipError::Io(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Extra data exceeds extra field",
+        )));
+    }
+    validate_extra_data(&data)?;
+    Ok(data)
 }
 
 /// Metadata for a file to be written
 #[derive(Copy, Clone)]
-pub struct FileOptions {
+pub struct FileOptions<'k> {
     compression_method: CompressionMethod,
     compression_level: Option<i32>,
     last_modified_time: DateTime,
     permissions: Option<u32>,
-    large_file: bool,
+    dir_permissions: Option<u32>,
+    /// Whether the file is 4 GiB or larger, if the caller knows
+    large_file: Option<bool>,
+    /// Whether to reserve room for a local ZIP64 extra field, unless `large_file` is set, for a
+    /// file whose size isn't known when its local header is written
+    reserve_zip64: bool,
     encrypt_with: Option<crate::zipcrypto::ZipCryptoKeys>,
+    aes_encryption: Option<(AesMode, &'k [u8])>,
+    aes_vendor_version: AesVendorVersion,
+    legacy_zstd_method_id: bool,
+    alignment: u16,
+    alignment_padding_id: u16,
+    comment: &'k str,
+    unix_mtime: Option<i64>,
+    unix_atime: Option<i64>,
+    ntfs_times: Option<NtfsTimes>,
+    unix_ownership: Option<(u32, u32)>,
+    xattrs: &'k [(OsString, Vec<u8>)],
+    legacy_name_compat: bool,
+    system: System,
+    version_made_by: u8,
+    dos_attributes: Option<DosAttributes>,
+    zstd_dictionary: Option<&'k [u8]>,
+    deflate_options: Option<DeflateOptions>,
+    store_if_larger: bool,
+    store_if_larger_buffer: usize,
 }
 
-impl FileOptions {
+impl<'k> FileOptions<'k> {
     /// Set the compression method for the new file
     ///
     /// The default is `CompressionMethod::Deflated`. If the deflate compression feature is
     /// disabled, `CompressionMethod::Stored` becomes the default.
     #[must_use]
-    pub fn compression_method(mut self, method: CompressionMethod) -> FileOptions {
+    pub fn compression_method(mut self, method: CompressionMethod) -> FileOptions<'k> {
         self.compression_method = method;
         self
     }
@@ -144,13 +486,17 @@ impl FileOptions {
     ///
     /// `None` value specifies default compression level.
     ///
-    /// Range of values depends on compression method:
+    /// Range of values depends on compression method, and is given by
+    /// [`CompressionMethod::level_range`]:
     /// * `Deflated`: 0 - 9. Default is 6
-    /// * `Bzip2`: 0 - 9. Default is 6
-    /// * `Zstd`: -7 - 22, with zero being mapped to default level. Default is 3
+    /// * `Bzip2`: 1 - 9. Default is 6
+    /// * `Zstd`: the range of `zstd::compression_level_range()`, with zero being mapped to default
+    ///   level. Default is 3
     /// * others: only `None` is allowed
+    ///
+    /// Other values make starting the file fail, before anything is written.
     #[must_use]
-    pub fn compression_level(mut self, level: Option<i32>) -> FileOptions {
+    pub fn compression_level(mut self, level: Option<i32>) -> FileOptions<'k> {
         self.compression_level = level;
         self
     }
@@ -160,11 +506,53 @@ impl FileOptions {
     /// The default is the current timestamp if the 'time' feature is enabled, and 1980-01-01
     /// otherwise
     #[must_use]
-    pub fn last_modified_time(mut self, mod_time: DateTime) -> FileOptions {
+    pub fn last_modified_time(mut self, mod_time: DateTime) -> FileOptions<'k> {
         self.last_modified_time = mod_time;
         self
     }
 
+    /// Set the last modified time from a [`SystemTime`]
+    ///
+    /// The time is converted to UTC with [`DateTime::try_from`], and clamped to the range it
+    /// supports: times before 1980 become 1980-01-01 00:00:00, and times after 2107 become
+    /// 2107-12-31 23:59:58.
+    #[must_use]
+    pub fn last_modified_time_from(self, time: SystemTime) -> FileOptions<'k> {
+        let mod_time = DateTime::try_from(time).unwrap_or_else(|_| {
+            if time < UNIX_EPOCH + Duration::from_secs(315_532_800) {
+                DateTime::default()
+            } else {
+                DateTime::from_date_and_time(2107, 12, 31, 23, 59, 58).unwrap()
+            }
+        });
+        self.last_modified_time(mod_time)
+    }
+
+    /// Construct options with the last modified time and, on Unix, the permissions of a file
+    ///
+    /// The other options have their default values. The last modified time is set with
+    /// [`FileOptions::last_modified_time_from`], if the platform provides it.
+    pub fn from_fs_metadata(metadata: &std::fs::Metadata) -> FileOptions<'k> {
+        FileOptions::default().with_fs_metadata(metadata)
+    }
+
+    pub(crate) fn modified_time(&self) -> DateTime {
+        self.last_modified_time
+    }
+
+    pub(crate) fn with_fs_metadata(mut self, metadata: &std::fs::Metadata) -> FileOptions<'k> {
+        if let Ok(modified) = metadata.modified() {
+            self = self.last_modified_time_from(modified);
+        }
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            self = self.unix_permissions(metadata.permissions().mode());
+            self.dir_permissions = None;
+        }
+        self
+    }
+
     /// Set the permissions for the new file.
     ///
     /// The format is represented with unix-style permissions.
@@ -175,28 +563,283 @@ impl FileOptions {
     /// higher file mode bits. So it cannot be used to denote an entry as a directory,
     /// symlink, or other special file type.
     #[must_use]
-    pub fn unix_permissions(mut self, mode: u32) -> FileOptions {
+    pub fn unix_permissions(mut self, mode: u32) -> FileOptions<'k> {
         self.permissions = Some(mode & 0o777);
         self
     }
 
+    /// Set the permissions of the directories added with these options.
+    ///
+    /// This takes precedence over [`FileOptions::unix_permissions`] for directories, so that the
+    /// same options can give `0o644` to files and `0o755` to directories. Like
+    /// `unix_permissions`, only the permissions bits are kept.
+    #[must_use]
+    pub fn dir_permissions(mut self, mode: u32) -> FileOptions<'k> {
+        self.dir_permissions = Some(mode & 0o777);
+        self
+    }
+
     /// Set whether the new file's compressed and uncompressed size is less than 4 GiB.
     ///
-    /// If set to `false` and the file exceeds the limit, an I/O error is thrown. If set to `true`,
-    /// readers will require ZIP64 support and if the file does not exceed the limit, 20 B are
-    /// wasted. The default is `false`.
+    /// If set to `true`, readers will require ZIP64 support and if the file does not exceed the
+    /// limit, 20 B are wasted. If set to `false` and the file exceeds the limit, an I/O error is
+    /// thrown. If not set, [`ZipWriter::start_file`] reserves these 20 B in a padding extra field,
+    /// which becomes the ZIP64 extra field if the file exceeds the limit: set it to `false` for
+    /// files that must have no extra field, like the `mimetype` file of an EPUB or OpenDocument
+    /// archive.
+    #[must_use]
+    pub fn large_file(mut self, large: bool) -> FileOptions<'k> {
+        self.large_file = Some(large);
+        self
+    }
+
+    /// Set whether Zstandard-compressed files are written with the deprecated method ID 20
+    ///
+    /// Some older tools only recognize Zstandard under this ID. The default is `false`, which
+    /// writes the ID 93 assigned by APPNOTE.
+    #[cfg(feature = "zstd")]
+    #[must_use]
+    pub fn legacy_zstd_method_id(mut self, legacy: bool) -> FileOptions<'k> {
+        self.legacy_zstd_method_id = legacy;
+        self
+    }
+
+    /// Set the alignment of the new file's data, which must be a power of two
+    ///
+    /// The data is aligned by padding the local header with an extra field filled with zeroes,
+    /// like `zipalign` does for Android packages: memory-mapped archives can then use stored files
+    /// in place. The default is `1`, which doesn't align the data.
+    ///
+    /// Alignment isn't supported with [`ZipWriter::start_file_with_extra_data`], whose extra data
+    /// can be aligned with [`ZipWriter::start_file_aligned`].
+    #[must_use]
+    pub fn alignment(mut self, alignment: u16) -> FileOptions<'k> {
+        self.alignment = alignment;
+        self
+    }
+
+    /// Set the header ID of the extra field padding the local header for [`FileOptions::alignment`]
+    ///
+    /// The default is `0xd935`, the ID used by Android's `apksigner`. Some tools expect `0x0000`
+    /// instead.
+    #[must_use]
+    pub fn alignment_padding_id(mut self, id: u16) -> FileOptions<'k> {
+        self.alignment_padding_id = id;
+        self
+    }
+
+    /// Encrypt the new file with AES, with the given key length and password
+    ///
+    /// The file is compressed before it's encrypted, and readers need support for the WinZip AES
+    /// extension. This replaces the deprecated ZipCrypto encryption, if it was set.
+    #[cfg(feature = "aes-crypto")]
+    #[must_use]
+    pub fn encrypt_with_aes(mut self, mode: AesMode, password: &'k [u8]) -> FileOptions<'k> {
+        self.encrypt_with = None;
+        self.aes_encryption = Some((mode, password));
+        self
+    }
+
+    /// Set the AES encryption specification used by [`FileOptions::encrypt_with_aes`]
+    ///
+    /// The default is AE-2, which doesn't store the CRC of the file, as the CRC of small files can
+    /// leak their content. With AE-1, the CRC is stored and checked, which some older readers need.
+    #[cfg(feature = "aes-crypto")]
+    #[must_use]
+    pub fn aes_vendor_version(mut self, version: AesVendorVersion) -> FileOptions<'k> {
+        self.aes_vendor_version = version;
+        self
+    }
+
+    /// Set the comment of the new file, which is stored in the central directory
+    ///
+    /// The comment must be shorter than 64 KiB. The default is an empty comment.
+    #[must_use]
+    pub fn comment(mut self, comment: &'k str) -> FileOptions<'k> {
+        self.comment = comment;
+        self
+    }
+
+    /// Set the modification time of the new file, in seconds since the Unix epoch
+    ///
+    /// This is stored in an extended timestamp extra field, with a precision of one second, and
+    /// also replaces the time set with [`FileOptions::last_modified_time`] when it's between 1980
+    /// and 2107. The time must fit in an `i32`.
+    #[must_use]
+    pub fn unix_mtime(mut self, mtime: i64) -> FileOptions<'k> {
+        self.unix_mtime = Some(mtime);
+        self
+    }
+
+    /// Set the access time of the new file, in seconds since the Unix epoch
+    ///
+    /// This is stored in the extended timestamp extra field of the local header only. The time
+    /// must fit in an `i32`.
+    #[must_use]
+    pub fn unix_atime(mut self, atime: i64) -> FileOptions<'k> {
+        self.unix_atime = Some(atime);
+        self
+    }
+
+    /// Set the modification, access and creation times of the new file, for Windows
+    ///
+    /// They are stored in an NTFS extra field, in both the local and central headers, and can be
+    /// set alongside [`FileOptions::unix_mtime`]. Use [`NtfsTimes::filetime`] to convert times
+    /// from [`SystemTime`](std::time::SystemTime).
+    #[must_use]
+    pub fn ntfs_times(mut self, times: NtfsTimes) -> FileOptions<'k> {
+        self.ntfs_times = Some(times);
+        self
+    }
+
+    /// Set the user and group IDs of the owner of the new file
+    ///
+    /// They are stored in an Info-ZIP new Unix extra field, in both the local and central
+    /// headers, which `unzip` uses to restore the ownership when run as root.
+    #[must_use]
+    pub fn unix_ownership(mut self, uid: u32, gid: u32) -> FileOptions<'k> {
+        self.unix_ownership = Some((uid, gid));
+        self
+    }
+
+    /// Set the extended attributes of the new file, as names and values
+    ///
+    /// They are stored in both the local and central headers, in an extra field specific to this
+    /// crate, with ID 0x7861. Its data is, for each attribute, the length of its name as a
+    /// little-endian `u16`, its name, the length of its value as a little-endian `u16`, and its
+    /// value. [`ExtractOptions::restore_xattrs`](crate::read::ExtractOptions::restore_xattrs)
+    /// restores them. Starting the file fails if they don't fit in the extra field.
+    #[must_use]
+    pub fn xattrs(mut self, xattrs: &'k [(OsString, Vec<u8>)]) -> FileOptions<'k> {
+        self.xattrs = xattrs;
+        self
+    }
+
+    /// Write the name of the new file for readers ignoring the UTF-8 flag, like old Windows tools
+    ///
+    /// The name and comment are then written in IBM codepage 437, with `?` for the characters it
+    /// lacks, and without the UTF-8 flag. A name that isn't ASCII is also written in an Info-ZIP
+    /// Unicode Path extra field, which readers supporting it use instead. The default is `false`.
+    #[must_use]
+    pub fn legacy_name_compat(mut self, legacy: bool) -> FileOptions<'k> {
+        self.legacy_name_compat = legacy;
+        self
+    }
+
+    /// Set the system and the version of the specification that the new file is made by
+    ///
+    /// The version is the major version times 10 plus the minor version. The default is
+    /// [`System::Unix`] and `46`, for version 4.6. With [`System::Dos`], the external attributes
+    /// are FAT attributes instead of a unix mode, which only keep whether the file is a directory,
+    /// and whether it is read-only because the owner can't write it. Symlinks can't be written
+    /// with `System::Dos`, and no file can be written with [`System::Unknown`].
+    #[must_use]
+    pub fn made_by(mut self, system: System, version: u8) -> FileOptions<'k> {
+        self.system = system;
+        self.version_made_by = version;
+        self
+    }
+
+    /// Set the MS-DOS attributes of the new file, which Windows reads.
+    ///
+    /// They are written in the low byte of the external attributes, whatever the system set with
+    /// [`FileOptions::made_by`], like Info-ZIP does on unix. With [`System::Unix`], the unix
+    /// permissions are kept beside them. With [`System::Dos`], they replace the read-only and
+    /// archive attributes derived from the permissions. The directory attribute is still set
+    /// for directories.
+    #[must_use]
+    pub fn dos_attributes(mut self, attributes: DosAttributes) -> FileOptions<'k> {
+        self.dos_attributes = Some(attributes);
+        self
+    }
+
+    /// Compress the file with a Zstandard dictionary, when its compression method is `Zstd`
+    ///
+    /// The ZIP format has no way to refer to a dictionary, so the file can only be read by
+    /// software that is given the same dictionary, such as a
+    /// [`ZipArchive`] with [`ZipArchive::set_zstd_dictionary`]. This is meant for archives of
+    /// many small, similar files, which compress better with a dictionary trained on them, for
+    /// example with `zstd::dict::from_samples`.
+    #[cfg(feature = "zstd")]
+    #[must_use]
+    pub fn zstd_dictionary(mut self, dictionary: &'k [u8]) -> FileOptions<'k> {
+        self.zstd_dictionary = Some(dictionary);
+        self
+    }
+
+    /// Set the level and strategy of the deflate compressor
+    ///
+    /// These take precedence over [`FileOptions::compression_level`]. They are only meant for the
+    /// `Deflated` compression method: other methods ignore them, and fail a debug assertion.
+    ///
+    /// Strategies other than [`Strategy::Default`] need the `deflate` or `deflate-miniz` feature,
+    /// as zlib's are out of reach through flate2. With only `deflate-zlib`, starting a file with
+    /// one of them fails.
+    #[cfg(any(
+        feature = "deflate",
+        feature = "deflate-miniz",
+        feature = "deflate-zlib"
+    ))]
+    #[must_use]
+    pub fn deflate_options(mut self, options: DeflateOptions) -> FileOptions<'k> {
+        self.deflate_options = Some(options);
+        self
+    }
+
+    /// Set whether to store the new file without compression if compressing it doesn't make it
+    /// smaller, like Info-ZIP does for already compressed files such as images and videos
+    ///
+    /// Its data is kept in memory while it's written, up to the size set with
+    /// [`FileOptions::store_if_larger_buffer`], so that it can be written again. A file larger
+    /// than that stays compressed. The local header gets a padding extra field if the stored data
+    /// is shorter than the compressed data, so that the next file still starts right after it.
+    /// The file stays compressed when that padding can't be added: if it would only be 1 to 3
+    /// bytes, or would break the [alignment](FileOptions::alignment) of the data.
+    /// This only applies to [`ZipWriter::start_file`] and [`ZipWriter::add_entry`], and not to
+    /// encrypted files. The default is `false`.
+    #[must_use]
+    pub fn store_if_larger(mut self, store: bool) -> FileOptions<'k> {
+        self.store_if_larger = store;
+        self
+    }
+
+    /// Set the size up to which the data of files is kept in memory for
+    /// [`FileOptions::store_if_larger`]
+    ///
+    /// The default is 16 MiB.
     #[must_use]
-    pub fn large_file(mut self, large: bool) -> FileOptions {
-        self.large_file = large;
+    pub fn store_if_larger_buffer(mut self, size: usize) -> FileOptions<'k> {
+        self.store_if_larger_buffer = size;
         self
     }
-    pub(crate) fn with_deprecated_encryption(mut self, password: &[u8]) -> FileOptions {
+
+    pub(crate) fn with_deprecated_encryption(mut self, password: &[u8]) -> FileOptions<'k> {
         self.encrypt_with = Some(crate::zipcrypto::ZipCryptoKeys::derive(password));
+        self.aes_encryption = None;
         self
     }
 }
 
-impl Default for FileOptions {
+impl FileOptions<'_> {
+    /// Check the compression level, or the level of the deflate options, before writing anything
+    fn check_compression_level(&self) -> ZipResult<()> {
+        let level = match self.deflate_options {
+            Some(deflate_options) if self.compression_method == CompressionMethod::DEFLATE => {
+                Some(deflate_options.level)
+            }
+            _ => self.compression_level,
+        };
+        validate_compression_level(self.compression_method, level)
+    }
+
+    /// The unix mode of a directory entry: the directory permissions, or `0o755` if none are
+    /// set, with the directory type bits
+    fn directory_mode(&self) -> u32 {
+        self.dir_permissions.or(self.permissions).unwrap_or(0o755) | 0o40000
+    }
+}
+
+impl Default for FileOptions<'_> {
     /// Construct a new FileOptions object
     fn default() -> Self {
         Self {
@@ -218,50 +861,313 @@ impl Default for FileOptions {
             #[cfg(not(feature = "time"))]
             last_modified_time: DateTime::default(),
             permissions: None,
-            large_file: false,
+            dir_permissions: None,
+            large_file: None,
+            reserve_zip64: false,
             encrypt_with: None,
+            aes_encryption: None,
+            aes_vendor_version: AesVendorVersion::Ae2,
+            legacy_zstd_method_id: false,
+            alignment: 1,
+            alignment_padding_id: 0xd935,
+            comment: "",
+            unix_mtime: None,
+            unix_atime: None,
+            ntfs_times: None,
+            unix_ownership: None,
+            xattrs: &[],
+            legacy_name_compat: false,
+            system: System::Unix,
+            version_made_by: DEFAULT_VERSION,
+            dos_attributes: None,
+            zstd_dictionary: None,
+            deflate_options: None,
+            store_if_larger: false,
+            store_if_larger_buffer: DEFAULT_STORE_IF_LARGER_BUFFER,
         }
     }
 }
 
-impl<W: Write + io::Seek> Write for ZipWriter<W> {
-    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-        if !self.writing_to_file {
-            return Err(io::Error::new(
-                io::ErrorKind::Other,
-                "No file has been started",
-            ));
-        }
-        match self.inner.ref_mut() {
-            Some(ref mut w) => {
-                if self.writing_to_extra_field {
-                    self.files.last_mut().unwrap().extra_field.write(buf)
-                } else {
-                    let write_result = w.write(buf);
-                    if let Ok(count) = write_result {
-                        self.stats.update(&buf[0..count]);
-                        if self.stats.bytes_written > spec::ZIP64_BYTES_THR
-                            && !self.files.last_mut().unwrap().large_file
-                        {
-                            let _inner = mem::replace(&mut self.inner, GenericZipWriter::Closed);
-                            return Err(io::Error::new(
-                                io::ErrorKind::Other,
-                                "Large file option has not been set",
-                            ));
-                        }
-                    }
-                    write_result
-                }
-            }
-            None => Err(io::Error::new(
-                io::ErrorKind::BrokenPipe,
-                "ZipWriter was already closed",
-            )),
-        }
-    }
+/// Options for [`ZipWriter::add_directory_tree`]
+#[derive(Copy, Clone)]
+pub struct DirectoryTreeOptions<'k> {
+    file_options: FileOptions<'k>,
+    prefix: &'k str,
+    add_directories: bool,
+    symlinks: SymlinkPolicy,
+    include_hidden: bool,
+    unix_ownership: bool,
+    filter: Option<&'k dyn Fn(&std::path::Path) -> bool>,
+}
 
-    fn flush(&mut self) -> io::Result<()> {
-        match self.inner.ref_mut() {
+impl<'k> DirectoryTreeOptions<'k> {
+    /// Set the options of the files, such as their compression method
+    ///
+    /// The last modified time and, on Unix, the permissions are replaced by those of each file.
+    #[must_use]
+    pub fn file_options(mut self, options: FileOptions<'k>) -> DirectoryTreeOptions<'k> {
+        self.file_options = options;
+        self
+    }
+
+    /// Set a directory to add the files to, inside the archive
+    ///
+    /// The default is `""`, for the root of the archive. No directory entry is added for the
+    /// prefix itself.
+    #[must_use]
+    pub fn prefix(mut self, prefix: &'k str) -> DirectoryTreeOptions<'k> {
+        self.prefix = prefix;
+        self
+    }
+
+    /// Set whether to add entries for directories
+    ///
+    /// The default is `true`. Without them, empty directories are lost.
+    #[must_use]
+    pub fn add_directories(mut self, add: bool) -> DirectoryTreeOptions<'k> {
+        self.add_directories = add;
+        self
+    }
+
+    /// Set what to do with symlinks
+    ///
+    /// The default is [`SymlinkPolicy::Store`].
+    #[must_use]
+    pub fn symlinks(mut self, policy: SymlinkPolicy) -> DirectoryTreeOptions<'k> {
+        self.symlinks = policy;
+        self
+    }
+
+    /// Set whether to add hidden files and directories, whose name starts with `.`
+    ///
+    /// The default is `true`.
+    #[must_use]
+    pub fn include_hidden(mut self, include: bool) -> DirectoryTreeOptions<'k> {
+        self.include_hidden = include;
+        self
+    }
+
+    /// Set whether to store the user and group IDs of the files, on Unix
+    ///
+    /// They are written with [`FileOptions::unix_ownership`]. The default is `false`, and this
+    /// has no effect on other platforms.
+    #[must_use]
+    pub fn unix_ownership(mut self, store: bool) -> DirectoryTreeOptions<'k> {
+        self.unix_ownership = store;
+        self
+    }
+
+    /// Only add the files and directories for which `filter` returns `true`
+    ///
+    /// `filter` gets their path relative to the directory being added. The contents of a
+    /// directory that is filtered out are skipped too.
+    #[must_use]
+    pub fn filter(
+        mut self,
+        filter: &'k dyn Fn(&std::path::Path) -> bool,
+    ) -> DirectoryTreeOptions<'k> {
+        self.filter = Some(filter);
+        self
+    }
+
+    /// Walk the directory `src` recursively, as [`ZipWriter::add_directory_tree`] adds it, and pass
+    /// each file, directory and symlink to `visit`
+    ///
+    /// Names start with the prefix, and those of directories end with `/`. Directories are visited
+    /// whether or not [`DirectoryTreeOptions::add_directories`] is set, right before their
+    /// contents. Symlinks are only visited with [`SymlinkPolicy::Store`].
+    pub(crate) fn walk(
+        &self,
+        src: &std::path::Path,
+        visit: &mut dyn FnMut(TreeEntry) -> ZipResult<()>,
+    ) -> ZipResult<()> {
+        let mut prefix = self.prefix.to_owned();
+        if !prefix.is_empty() && !prefix.ends_with('/') {
+            prefix.push('/');
+        }
+        let mut ancestors = vec![src.canonicalize()?];
+        self.walk_directory(
+            src,
+            std::path::Path::new(""),
+            &prefix,
+            &mut ancestors,
+            visit,
+        )
+    }
+
+    /// Walk the contents of the directory at `path`, whose names in the archive start with
+    /// `prefix`
+    ///
+    /// `ancestors` are the canonical paths of the directories being walked, to detect symlink
+    /// loops.
+    fn walk_directory(
+        &self,
+        path: &std::path::Path,
+        relative: &std::path::Path,
+        prefix: &str,
+        ancestors: &mut Vec<std::path::PathBuf>,
+        visit: &mut dyn FnMut(TreeEntry) -> ZipResult<()>,
+    ) -> ZipResult<()> {
+        let mut entries = std::fs::read_dir(path)?.collect::<io::Result<Vec<_>>>()?;
+        entries.sort_by_key(|entry| entry.file_name());
+        for entry in entries {
+            let relative = relative.join(entry.file_name());
+            if !self.include_hidden && entry.file_name().to_string_lossy().starts_with('.') {
+                continue;
+            }
+            if let Some(filter) = self.filter {
+                if !filter(&relative) {
+                    continue;
+                }
+            }
+            let name = format!("{prefix}{}", path_to_zip_name(&relative)?);
+            let path = entry.path();
+
+            let mut metadata = std::fs::symlink_metadata(&path)?;
+            if metadata.file_type().is_symlink() {
+                if self.symlinks == SymlinkPolicy::Store {
+                    let target = std::fs::read_link(&path)?;
+                    let target = target.to_str().ok_or_else(|| {
+                        ZipError::Io(io::Error::new(
+                            io::ErrorKind::InvalidData,
+                            "Symlink target is not valid UTF-8",
+                        ))
+                    })?;
+                    let target = target.replace(std::path::MAIN_SEPARATOR, "/");
+                    visit(TreeEntry::Symlink {
+                        name,
+                        target,
+                        metadata,
+                    })?;
+                    continue;
+                }
+                metadata = std::fs::metadata(&path)?;
+            }
+
+            if metadata.is_dir() {
+                let canonical = path.canonicalize()?;
+                if ancestors.contains(&canonical) {
+                    return Err(ZipError::Io(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        "Symlink points to a directory containing it",
+                    )));
+                }
+                visit(TreeEntry::Directory {
+                    name: name + "/",
+                    path: path.clone(),
+                    metadata,
+                })?;
+                ancestors.push(canonical);
+                self.walk_directory(&path, &relative, prefix, ancestors, visit)?;
+                ancestors.pop();
+            } else if metadata.is_file() {
+                visit(TreeEntry::File {
+                    name,
+                    path,
+                    metadata,
+                })?;
+            }
+            // Opening a FIFO would block until something writes to it
+        }
+        Ok(())
+    }
+
+    /// Options for a file, with its metadata
+    fn file_options_for(&self, metadata: &std::fs::Metadata) -> FileOptions<'k> {
+        let options = self.file_options.with_fs_metadata(metadata);
+        #[cfg(unix)]
+        if self.unix_ownership {
+            use std::os::unix::fs::MetadataExt;
+            return options.unix_ownership(metadata.uid(), metadata.gid());
+        }
+        options
+    }
+}
+
+/// A file, directory or symlink found by [`DirectoryTreeOptions::walk`]
+pub(crate) enum TreeEntry {
+    Directory {
+        name: String,
+        path: std::path::PathBuf,
+        metadata: std::fs::Metadata,
+    },
+    File {
+        name: String,
+        path: std::path::PathBuf,
+        metadata: std::fs::Metadata,
+    },
+    Symlink {
+        name: String,
+        target: String,
+        metadata: std::fs::Metadata,
+    },
+}
+
+impl Default for DirectoryTreeOptions<'_> {
+    fn default() -> Self {
+        DirectoryTreeOptions {
+            file_options: FileOptions::default(),
+            prefix: "",
+            add_directories: true,
+            symlinks: SymlinkPolicy::Store,
+            include_hidden: true,
+            unix_ownership: false,
+            filter: None,
+        }
+    }
+}
+
+impl<W: Write + io::Seek> Write for ZipWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if !self.writing_to_file {
+            return Err(io::Error::new(
+                io::ErrorKind::Other,
+                "No file has been started",
+            ));
+        }
+        match self.inner.ref_mut() {
+            Some(ref mut w) => {
+                if self.writing_to_extra_field {
+                    self.files.last_mut().unwrap().extra_field.write(buf)
+                } else {
+                    let write_result = w.write(buf);
+                    if let Ok(count) = write_result {
+                        self.stats.update(&buf[0..count]);
+                        if let Some(copy) = self.store_if_larger.as_mut() {
+                            if copy.data.len() + count <= copy.buffer_size {
+                                copy.data.extend_from_slice(&buf[0..count]);
+                            } else {
+                                self.store_if_larger = None;
+                            }
+                        }
+                        if self.stats.bytes_written > spec::ZIP64_BYTES_THR
+                            && !can_use_zip64(self.files.last().unwrap())
+                        {
+                            let _inner = mem::replace(&mut self.inner, GenericZipWriter::Closed);
+                            return Err(io::Error::new(
+                                io::ErrorKind::Other,
+                                "Large file option has not been set",
+                            ));
+                        }
+                        if !self.writing_raw
+                            && self.stats.bytes_written / PROGRESS_INTERVAL
+                                > (self.stats.bytes_written - count as u64) / PROGRESS_INTERVAL
+                        {
+                            self.report_progress(false)?;
+                        }
+                    }
+                    write_result
+                }
+            }
+            None => Err(io::Error::new(
+                io::ErrorKind::BrokenPipe,
+                "ZipWriter was already closed",
+            )),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self.inner.ref_mut() {
             Some(ref mut w) => w.flush(),
             None => Err(io::Error::new(
                 io::ErrorKind::BrokenPipe,
@@ -278,10 +1184,306 @@ impl ZipWriterStats {
     }
 }
 
+/// The FAT attributes of a file with the unix `mode`: directory or archive, and read-only
+fn dos_attributes(mode: u32) -> u32 {
+    let mut attributes = if mode & 0o170000 == 0o040000 {
+        0x10
+    } else {
+        0x20
+    };
+    if mode & 0o200 == 0 {
+        attributes |= 0x01;
+    }
+    attributes
+}
+
+/// Describe a new file, whose local header starts at `header_start`
+fn new_file_data(
+    name: String,
+    options: &FileOptions,
+    raw_values: ZipRawValues,
+    header_start: u64,
+) -> ZipResult<ZipFileData> {
+    if !options.alignment.is_power_of_two() {
+        return Err(ZipError::Io(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "Alignment must be a power of two",
+        )));
+    }
+    let i32_range = i32::MIN as i64..=i32::MAX as i64;
+    if !options
+        .unix_mtime
+        .iter()
+        .chain(options.unix_atime.iter())
+        .all(|time| i32_range.contains(time))
+    {
+        return Err(ZipError::Io(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "Extended timestamps must fit in an i32",
+        )));
+    }
+    let last_modified_time = options
+        .unix_mtime
+        .and_then(|mtime| DateTime::from_unix_seconds(mtime).ok())
+        .unwrap_or(options.last_modified_time);
+    if options.comment.len() > u16::MAX as usize {
+        return Err(ZipError::Io(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "File comment is too long",
+        )));
+    }
+    let permissions = options.permissions.unwrap_or(0o100644);
+    let external_attributes = match options.system {
+        System::Unix => permissions << 16,
+        System::Dos if permissions & 0o170000 == 0o120000 => {
+            return Err(ZipError::Io(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Symlinks can only be written with System::Unix",
+            )));
+        }
+        System::Dos => dos_attributes(permissions),
+        System::Unknown => {
+            return Err(ZipError::Io(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Files can't be written with System::Unknown",
+            )));
+        }
+    };
+    let external_attributes = match options.dos_attributes {
+        Some(attributes) => external_attributes & !DosAttributes::MASK | attributes.to_bits(),
+        None => external_attributes,
+    };
+    let compression_method_id = if options.aes_encryption.is_some() {
+        #[allow(deprecated)]
+        CompressionMethod::AES.to_u16()
+    } else if options.legacy_zstd_method_id && options.compression_method == CompressionMethod::ZSTD
+    {
+        20
+    } else {
+        #[allow(deprecated)]
+        options.compression_method.to_u16()
+    };
+    let mut file = ZipFileData {
+        system: options.system,
+        version_made_by: options.version_made_by,
+        encrypted: options.encrypt_with.is_some() || options.aes_encryption.is_some(),
+        using_data_descriptor: false,
+        flags: 0,
+        compression_method: options.compression_method,
+        compression_method_id,
+        compression_level: options.compression_level,
+        last_modified_time,
+        crc32: raw_values.crc32,
+        compressed_size: raw_values.compressed_size,
+        uncompressed_size: raw_values.uncompressed_size,
+        file_name: name,
+        file_name_raw: Vec::new(), // Never used for saving
+        extra_field: Vec::new(),
+        alignment_padding: Vec::new(),
+        file_comment: options.comment.to_owned(),
+        header_start,
+        disk_number: 0,
+        data_start: AtomicU64::new(0),
+        data_alignment: AtomicU64::new(1),
+        central_header_start: 0,
+        external_attributes,
+        large_file: options.large_file.unwrap_or(false),
+        aes_mode: options
+            .aes_encryption
+            .map(|(mode, _)| (mode, options.aes_vendor_version)),
+        unix_mtime: options.unix_mtime,
+        unix_atime: options.unix_atime,
+        ntfs_times: options.ntfs_times,
+        unix_ownership: options.unix_ownership,
+        xattrs: options.xattrs.to_vec(),
+        legacy_name_compat: options.legacy_name_compat,
+    };
+    pad_local_header(
+        &mut file,
+        options.alignment as u64,
+        options.alignment_padding_id,
+        options.reserve_zip64 && options.large_file.is_none(),
+    );
+    local_extra_length(&file)?;
+    // The ZIP64 field of the central header depends on where the file ends up
+    central_extra_length(&file, 28)?;
+    Ok(file)
+}
+
+/// Set the alignment padding of `file`, so that its data is aligned to `alignment` after its local
+/// header at `file.header_start`
+///
+/// If `reserve_zip64`, the padding starts with room for a ZIP64 field, which
+/// [`use_reserved_zip64`] takes from it, if the header has room for it.
+fn pad_local_header(file: &mut ZipFileData, alignment: u64, padding_id: u16, reserve_zip64: bool) {
+    let other_length = managed_local_extra_length(file) as u64 + file.extra_field.len() as u64;
+    let header_end = file.header_start + 30 + encoded_name(file).len() as u64 + other_length;
+    // Length of a padding field starting with `reserved` bytes, and aligning the data
+    let padding_length = |reserved: u64| match (header_end + reserved) % alignment {
+        0 => reserved,
+        _ => reserved + 4 + (alignment - (header_end + reserved + 4) % alignment) % alignment,
+    };
+    let mut length = padding_length(0);
+    if reserve_zip64 {
+        let reserved_length = padding_length(20);
+        if other_length + reserved_length <= u16::MAX as u64 {
+            length = reserved_length;
+        }
+    }
+    let padding = &mut file.alignment_padding;
+    padding.clear();
+    if length > 0 {
+        padding.extend_from_slice(&padding_id.to_le_bytes());
+        padding.extend_from_slice(&((length - 4) as u16).to_le_bytes());
+        padding.resize(length as usize, 0);
+    }
+}
+
+/// Read the local header at `header_start` and find the end of the data of its file, for
+/// [`ZipWriter::resume`]
+///
+/// Gives `None` if there is no local header there, or if the file is cut short before `end`.
+fn read_complete_entry<R: Read + io::Seek>(
+    reader: &mut R,
+    header_start: u64,
+    end: u64,
+) -> ZipResult<Option<(ZipFileData, u64)>> {
+    reader.seek(io::SeekFrom::Start(header_start))?;
+    match reader.read_u32::<LittleEndian>() {
+        Ok(spec::LOCAL_FILE_HEADER_SIGNATURE) => {}
+        Ok(_) => return Ok(None),
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e.into()),
+    }
+    let mut file = match read_local_header(reader) {
+        Ok(file) => file,
+        Err(ZipError::Io(e)) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    let data_start = reader.stream_position()?;
+    let data_end = if file.using_data_descriptor {
+        // The sizes in the descriptor take 8 bytes iff the local header has a ZIP64 field
+        // (APPNOTE 4.3.9.2)
+        let zip64 = has_zip64_field(&file.extra_field);
+        match find_data_descriptor(reader, &mut file, data_start, end, zip64)? {
+            Some(descriptor_end) => descriptor_end,
+            None => return Ok(None),
+        }
+    } else if data_start + file.compressed_size <= end {
+        data_start + file.compressed_size
+    } else {
+        return Ok(None);
+    };
+    // A file being written has sizes of 0 in its local header until it's finished, so it is only
+    // complete if another header follows
+    if data_end + 4 <= end {
+        reader.seek(io::SeekFrom::Start(data_end))?;
+        let signature = reader.read_u32::<LittleEndian>()?;
+        if signature != spec::LOCAL_FILE_HEADER_SIGNATURE
+            && signature != spec::CENTRAL_DIRECTORY_HEADER_SIGNATURE
+        {
+            return Ok(None);
+        }
+    }
+
+    // The permissions and comment are only in the central header
+    file.system = System::Unix;
+    file.version_made_by = DEFAULT_VERSION;
+    file.external_attributes = if file.file_name.ends_with('/') {
+        (0o40755 << 16) | 0x10
+    } else {
+        0o100644 << 16
+    };
+    file.extra_field = extra_data_without_managed_fields(&file.extra_field)?;
+    file.large_file = file.compressed_size.max(file.uncompressed_size) > spec::ZIP64_BYTES_THR;
+    file.header_start = header_start;
+    *file.data_start.get_mut() = data_start;
+    Ok(Some((file, data_end)))
+}
+
+/// Whether the extra field `data` has a ZIP64 field
+fn has_zip64_field(mut data: &[u8]) -> bool {
+    while data.len() >= 4 {
+        let kind = u16::from_le_bytes([data[0], data[1]]);
+        let size = 4 + u16::from_le_bytes([data[2], data[3]]) as usize;
+        if kind == 0x0001 {
+            return true;
+        }
+        data = &data[size.min(data.len())..];
+    }
+    false
+}
+
+/// Find the data descriptor of `file`, whose data starts at `data_start`, and take the CRC32 and
+/// sizes from it
+///
+/// The descriptor is recognized by its signature, followed by a compressed size matching its
+/// position, of 8 bytes if `zip64` and 4 bytes otherwise. Gives the end of the descriptor, or
+/// `None` if there is none before `end`.
+fn find_data_descriptor<R: Read + io::Seek>(
+    reader: &mut R,
+    file: &mut ZipFileData,
+    data_start: u64,
+    end: u64,
+    zip64: bool,
+) -> ZipResult<Option<u64>> {
+    let signature = spec::DATA_DESCRIPTOR_SIGNATURE.to_le_bytes();
+    let descriptor_length = if zip64 { 24 } else { 16 };
+    let mut buffer = vec![0; 64 * 1024];
+    let mut position = data_start;
+    while position + descriptor_length <= end {
+        reader.seek(io::SeekFrom::Start(position))?;
+        let length = (end - position).min(buffer.len() as u64) as usize;
+        reader.read_exact(&mut buffer[..length])?;
+        let candidate = match buffer[..length]
+            .windows(signature.len())
+            .position(|window| window == signature)
+        {
+            Some(index) => position + index as u64,
+            None => {
+                // The signature may start in the last bytes
+                position += length.saturating_sub(signature.len() - 1).max(1) as u64;
+                continue;
+            }
+        };
+        if candidate + descriptor_length > end {
+            break;
+        }
+        let compressed_size = candidate - data_start;
+        reader.seek(io::SeekFrom::Start(candidate + 4))?;
+        let crc32 = reader.read_u32::<LittleEndian>()?;
+        let sizes = if zip64 {
+            (
+                reader.read_u64::<LittleEndian>()?,
+                reader.read_u64::<LittleEndian>()?,
+            )
+        } else {
+            (
+                reader.read_u32::<LittleEndian>()? as u64,
+                reader.read_u32::<LittleEndian>()? as u64,
+            )
+        };
+        if sizes.0 == compressed_size {
+            file.crc32 = crc32;
+            file.compressed_size = compressed_size;
+            file.uncompressed_size = sizes.1;
+            return Ok(Some(candidate + descriptor_length));
+        }
+        position = candidate + 1;
+    }
+    Ok(None)
+}
+
 impl<A: Read + Write + io::Seek> ZipWriter<A> {
     /// Initializes the archive from an existing ZIP archive, making it ready for append.
+    ///
+    /// The new files are written over the old central directory, which is written again with
+    /// them on finish. Data prepended to the archive, such as the stub of a self-extracting
+    /// archive, is kept, and the offsets stay relative to the start of the archive after it. The
+    /// archive comment is kept, unless replaced with [`ZipWriter::set_comment`].
     pub fn new_append(mut readwriter: A) -> ZipResult<ZipWriter<A>> {
-        let (footer, cde_start_pos) = spec::CentralDirectoryEnd::find_and_parse(&mut readwriter)?;
+        let (footer, cde_start_pos, zip64_locator) =
+            spec::CentralDirectoryEnd::find_and_parse(&mut readwriter, None)?;
 
         if footer.disk_number != footer.disk_with_central_directory {
             return Err(ZipError::UnsupportedArchive(
@@ -289,8 +1491,15 @@ impl<A: Read + Write + io::Seek> ZipWriter<A> {
             ));
         }
 
-        let (archive_offset, directory_start, number_of_files) =
-            ZipArchive::get_directory_counts(&mut readwriter, &footer, cde_start_pos)?;
+        let (archive_offset, directory_start, _, number_of_files) =
+            ZipArchive::get_directory_counts(
+                &mut readwriter,
+                &footer,
+                cde_start_pos,
+                zip64_locator,
+                &ZipReaderConfig::default(),
+                &mut Vec::new(),
+            )?;
 
         if readwriter
             .seek(io::SeekFrom::Start(directory_start))
@@ -302,22 +1511,173 @@ impl<A: Read + Write + io::Seek> ZipWriter<A> {
         }
 
         let files = (0..number_of_files)
-            .map(|_| central_header_to_zip_file(&mut readwriter, archive_offset))
-            .collect::<Result<Vec<_>, _>>()?;
+            .map(|_| {
+                let mut file = central_header_to_zip_file(&mut readwriter, archive_offset)?;
+                // The central header is rewritten with fresh ZIP64 and AES fields
+                file.extra_field = extra_data_without_managed_fields(&file.extra_field)?;
+                Ok(file)
+            })
+            .collect::<ZipResult<Vec<_>>>()?;
 
         let _ = readwriter.seek(io::SeekFrom::Start(directory_start)); // seek directory_start to overwrite it
 
         Ok(ZipWriter {
             inner: GenericZipWriter::Storer(MaybeEncrypted::Unencrypted(readwriter)),
-            files,
             stats: Default::default(),
             writing_to_file: false,
             writing_to_extra_field: false,
             writing_to_central_extra_field_only: false,
+            names: files.iter().map(|file| file.file_name.clone()).collect(),
+            files,
             comment: footer.zip_file_comment,
             writing_raw: true, // avoid recomputing the last file's header
+            central_directory_sort: SortOrder::Insertion,
+            duplicate_policy: Duplicate::Allow,
+            archive_offset,
+            high_water_mark: 0,
+            streaming: false,
+            progress: None,
+            last_entry: None,
+            store_if_larger: None,
+            #[cfg(feature = "parallel")]
+            pending: Default::default(),
         })
     }
+
+    /// Continue writing an archive that was cut short before its central directory, for example
+    /// by a crash.
+    ///
+    /// The local headers are read from the start of `readwriter`, and the files whose data is
+    /// complete are kept: the last one must be followed by the end of `readwriter` or by another
+    /// header, as the sizes of a file are only written in its local header once it's finished.
+    /// Data descriptors are found by their signature. New files are written
+    /// after the last complete file, over a file that was cut short and anything else after it.
+    /// The permissions and comments are only in the central directory, so the files get the
+    /// default ones. Like with [`ZipWriter::abort_file`], the writer isn't truncated: if the
+    /// archive would end before the end of `readwriter`, zeros are written before the central
+    /// directory so that it ends there.
+    ///
+    /// ```no_run
+    /// use std::fs::OpenOptions;
+    /// use zip::ZipWriter;
+    ///
+    /// # fn main() -> zip::result::ZipResult<()> {
+    /// let file = OpenOptions::new().read(true).write(true).open("partial.zip")?;
+    /// let mut zip = ZipWriter::resume(file)?;
+    /// println!("{} files were written before the crash", zip.entry_count());
+    /// zip.finish()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn resume(mut readwriter: A) -> ZipResult<ZipWriter<A>> {
+        let end = readwriter.seek(io::SeekFrom::End(0))?;
+        let mut files = Vec::new();
+        let mut offset = 0;
+        while let Some((file, file_end)) = read_complete_entry(&mut readwriter, offset, end)? {
+            files.push(file);
+            offset = file_end;
+        }
+        readwriter.seek(io::SeekFrom::Start(offset))?;
+
+        let mut writer = ZipWriter::new(readwriter);
+        writer.high_water_mark = end;
+        writer.names = files.iter().map(|file| file.file_name.clone()).collect();
+        writer.files = files;
+        // avoid recomputing the last file's header
+        writer.writing_raw = true;
+        Ok(writer)
+    }
+
+    /// Write the central directory, and return a [`ZipArchive`] reading the finished archive.
+    ///
+    /// This saves rewinding the writer and calling [`ZipArchive::new`], to read back an archive
+    /// that was just written, for instance in memory.
+    pub fn finish_into_readable(mut self) -> ZipResult<ZipArchive<A>> {
+        ZipArchive::new(self.finish()?)
+    }
+
+    /// Add a copy of a file already written to the archive, with a new name.
+    ///
+    /// The compressed data of the file is read back and written again, after a new local header,
+    /// aligned like the data of the source as with [`ZipWriter::raw_copy_file_rename`]. Fails if
+    /// no file is named `src_name`, or if a file is already named `dest_name`.
+    pub fn deep_copy_file(&mut self, src_name: &str, dest_name: &str) -> ZipResult<()> {
+        self.finish_file()?;
+        let mut data = self.copy_file_data(src_name, dest_name)?;
+        let src_header_start = data.header_start;
+        let writer = self.inner.get_plain();
+
+        // The data start isn't known for files read by `new_append`
+        writer.seek(io::SeekFrom::Start(src_header_start + 26))?;
+        let name_length = writer.read_u16::<LittleEndian>()? as u64;
+        let extra_field_length = writer.read_u16::<LittleEndian>()? as u64;
+        let mut read_position = src_header_start + 30 + name_length + extra_field_length;
+        writer.seek(io::SeekFrom::Current(name_length as i64))?;
+        let mut extra_field = vec![0; extra_field_length as usize];
+        writer.read_exact(&mut extra_field)?;
+        let alignment = padded_data_alignment(&extra_field, read_position);
+
+        data.header_start = writer.seek(io::SeekFrom::End(0))?;
+        pad_local_header(&mut data, alignment, 0xd935, false);
+        self.write_local_header(data)?;
+        self.writing_raw = true;
+
+        let writer = self.inner.get_plain();
+        let data = self.files.last().unwrap();
+        let mut write_position = data.data_start.load();
+        let mut remaining = data.compressed_size;
+        let mut buffer = vec![0; 1 << 16];
+        while remaining > 0 {
+            let chunk = &mut buffer[..remaining.min(1 << 16) as usize];
+            writer.seek(io::SeekFrom::Start(read_position))?;
+            writer.read_exact(chunk)?;
+            writer.seek(io::SeekFrom::Start(write_position))?;
+            writer.write_all(chunk)?;
+            read_position += chunk.len() as u64;
+            write_position += chunk.len() as u64;
+            remaining -= chunk.len() as u64;
+        }
+        if data.using_data_descriptor {
+            write_data_descriptor(writer, data)?;
+        }
+        Ok(())
+    }
+}
+
+impl<W: Write> ZipWriter<StreamWriter<W>> {
+    /// Initializes an archive written to a writer that can't seek, such as a socket or a pipe.
+    ///
+    /// The CRC and sizes of each file are written in a data descriptor following its data. As the
+    /// size of a file isn't known when its local header is written, files are always written
+    /// with ZIP64 extra fields and 8-byte sizes in their data descriptors, whatever
+    /// [`FileOptions::large_file`] is set to, and can grow past 4 GiB. Offsets in the archive are
+    /// counted from the first byte written, so `inner` should be at its start.
+    ///
+    /// Files encrypted with the deprecated ZipCrypto method aren't supported, and neither is
+    /// [`FileOptions::store_if_larger`]. The methods that seek back, such as
+    /// [`ZipWriter::checkpoint`] and [`ZipWriter::start_file_with_extra_data`], fail, and
+    /// [`ZipWriter::abort_file`] leaves the data of the file in the writer, unreferenced.
+    ///
+    /// ```
+    /// # fn main() -> zip::result::ZipResult<()> {
+    /// use std::io::Write;
+    /// use zip::write::FileOptions;
+    ///
+    /// let mut zip = zip::ZipWriter::new_streaming(Vec::new());
+    /// zip.start_file("hello_world.txt", FileOptions::default())?;
+    /// zip.write_all(b"Hello, World!")?;
+    /// let archive: Vec<u8> = zip.finish()?.into_inner();
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn new_streaming(inner: W) -> ZipWriter<StreamWriter<W>> {
+        let mut writer = ZipWriter::new(StreamWriter {
+            inner,
+            bytes_written: 0,
+        });
+        writer.streaming = true;
+        writer
+    }
 }
 
 impl<W: Write + io::Seek> ZipWriter<W> {
@@ -334,9 +1694,35 @@ impl<W: Write + io::Seek> ZipWriter<W> {
             writing_to_central_extra_field_only: false,
             writing_raw: false,
             comment: Vec::new(),
+            central_directory_sort: SortOrder::Insertion,
+            duplicate_policy: Duplicate::Allow,
+            archive_offset: 0,
+            high_water_mark: 0,
+            streaming: false,
+            names: HashSet::new(),
+            progress: None,
+            last_entry: None,
+            store_if_larger: None,
+            #[cfg(feature = "parallel")]
+            pending: Default::default(),
         }
     }
 
+    /// Initializes the archive after the data of `prefix`, such as the stub of a self-extracting
+    /// archive.
+    ///
+    /// The prefix is copied to `inner`, and the archive starts after it: the offsets in the
+    /// central directory are relative to the start of the archive, like those of an archive
+    /// that data was prepended to. [`ZipArchive::offset`] gives the length of the prefix. Info-ZIP
+    /// reads these archives too, but warns about the extra bytes at the beginning.
+    pub fn new_with_prefix<R: Read>(mut inner: W, mut prefix: R) -> ZipResult<ZipWriter<W>> {
+        io::copy(&mut prefix, &mut inner)?;
+        let archive_offset = inner.stream_position()?;
+        let mut writer = ZipWriter::new(inner);
+        writer.archive_offset = archive_offset;
+        Ok(writer)
+    }
+
     /// Set ZIP archive comment.
     pub fn set_comment<S>(&mut self, comment: S)
     where
@@ -348,110 +1734,374 @@ impl<W: Write + io::Seek> ZipWriter<W> {
     /// Set ZIP archive comment.
     ///
     /// This sets the raw bytes of the comment. The comment
-    /// is typically expected to be encoded in UTF-8
+    /// is typically expected to be encoded in UTF-8, and must be shorter than 64 KiB for the
+    /// archive to be finished.
     pub fn set_raw_comment(&mut self, comment: Vec<u8>) {
         self.comment = comment;
     }
 
-    /// Start a new file for with the requested options.
-    fn start_entry<S>(
-        &mut self,
-        name: S,
-        options: FileOptions,
-        raw_values: Option<ZipRawValues>,
-    ) -> ZipResult<()>
-    where
-        S: Into<String>,
-    {
-        self.finish_file()?;
-
-        let raw_values = raw_values.unwrap_or(ZipRawValues {
-            crc32: 0,
-            compressed_size: 0,
-            uncompressed_size: 0,
-        });
-
-        {
-            let writer = self.inner.get_plain();
-            let header_start = writer.stream_position()?;
-
-            let permissions = options.permissions.unwrap_or(0o100644);
-            let mut file = ZipFileData {
-                system: System::Unix,
-                version_made_by: DEFAULT_VERSION,
-                encrypted: options.encrypt_with.is_some(),
-                using_data_descriptor: false,
-                compression_method: options.compression_method,
-                compression_level: options.compression_level,
-                last_modified_time: options.last_modified_time,
-                crc32: raw_values.crc32,
-                compressed_size: raw_values.compressed_size,
-                uncompressed_size: raw_values.uncompressed_size,
-                file_name: name.into(),
-                file_name_raw: Vec::new(), // Never used for saving
-                extra_field: Vec::new(),
-                file_comment: String::new(),
-                header_start,
-                data_start: AtomicU64::new(0),
-                central_header_start: 0,
-                external_attributes: permissions << 16,
-                large_file: options.large_file,
-                aes_mode: None,
-            };
-            write_local_file_header(writer, &file)?;
-
-            let header_end = writer.stream_position()?;
-            self.stats.start = header_end;
-            *file.data_start.get_mut() = header_end;
+    /// Set the order of the entries in the central directory, which is written by
+    /// [`ZipWriter::finish`]
+    ///
+    /// The files themselves stay in the order they were written. Readers find them through the
+    /// offsets in the central directory, but list them in its order.
+    pub fn set_central_directory_sort(&mut self, order: SortOrder) {
+        self.central_directory_sort = order;
+    }
 
-            self.stats.bytes_written = 0;
-            self.stats.hasher = Hasher::new();
+    /// Set what happens when a file is added with the name of a file already in the archive.
+    ///
+    /// The policy applies to the files started, added or raw-copied into the archive, but not to
+    /// [`ZipWriter::shallow_copy_file`] and [`ZipWriter::deep_copy_file`], which always fail. The
+    /// default is [`Duplicate::Allow`].
+    pub fn set_duplicate_policy(&mut self, policy: Duplicate) {
+        self.duplicate_policy = policy;
+    }
 
-            self.files.push(file);
+    /// Apply the duplicate policy to a new file named `name`, before writing anything for it
+    fn check_duplicate(&mut self, name: &str) -> ZipResult<()> {
+        #[cfg(feature = "parallel")]
+        let pending = self.pending.contains(name);
+        #[cfg(not(feature = "parallel"))]
+        let pending = false;
+        if !pending && !self.names.contains(name) {
+            return Ok(());
         }
-        if let Some(keys) = options.encrypt_with {
-            let mut zipwriter = crate::zipcrypto::ZipCryptoWriter { writer: core::mem::replace(&mut self.inner, GenericZipWriter::Closed).unwrap(), buffer: vec![], keys };
-            let mut crypto_header = [0u8; 12];
-
-            zipwriter.write_all(&crypto_header)?;
-            self.inner = GenericZipWriter::Storer(MaybeEncrypted::Encrypted(zipwriter));
+        match self.duplicate_policy {
+            Duplicate::Allow => {}
+            Duplicate::Error => {
+                return Err(ZipError::Io(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("A file named {name} already exists"),
+                )));
+            }
+            Duplicate::Replace => {
+                self.files.retain(|file| file.file_name != name);
+                #[cfg(feature = "parallel")]
+                self.pending.remove(name);
+            }
         }
         Ok(())
     }
 
-    fn finish_file(&mut self) -> ZipResult<()> {
-        if self.writing_to_extra_field {
-            // Implicitly calling [`ZipWriter::end_extra_data`] for empty files.
-            self.end_extra_data()?;
-        }
-        self.inner.switch_to(CompressionMethod::Stored, None)?;
-        match core::mem::replace(&mut self.inner, GenericZipWriter::Closed) {
-            GenericZipWriter::Storer(MaybeEncrypted::Encrypted(writer)) => {
+    /// Call `callback` with the progress of the files written, every MiB of data written to a
+    /// file, and once the file is finished.
+    ///
+    /// The files copied with [`ZipWriter::raw_copy_file`] and its variants, and the files
+    /// compressed on other threads by [`ZipWriter::add_entry`], are only reported once finished.
+    /// Files copied within the archive aren't reported. Encrypted data only counts as compressed
+    /// bytes once written to the archive, which is when the file is finished for ZipCrypto.
+    ///
+    /// Finding how much compressed data was written seeks the writer to its current position,
+    /// which flushes a [`std::io::BufWriter`].
+    ///
+    /// The callback is called after the data it reports is written and accounted for. If it
+    /// panics, the panic propagates to the caller of the `ZipWriter` method, and the writer can
+    /// still be used: the file being written can be aborted with [`ZipWriter::abort_file`], or
+    /// the archive finished.
+    pub fn set_progress_callback<F>(&mut self, callback: F)
+    where
+        F: FnMut(WriteProgress) + Send + 'static,
+    {
+        self.progress = Some(Box::new(callback));
+    }
+
+    /// The offset in the writer after the data written so far, where the next local header
+    /// starts if no file is being written.
+    ///
+    /// While a file is being written, the data held by its compressor or encryption isn't
+    /// counted, so the offset grows in steps.
+    pub fn current_offset(&mut self) -> ZipResult<u64> {
+        match self.inner.get_mut() {
+            Some(writer) => Ok(writer.get_plain_mut().stream_position()?),
+            None => Err(ZipError::Io(io::Error::new(
+                io::ErrorKind::BrokenPipe,
+                "ZipWriter was already closed",
+            ))),
+        }
+    }
+
+    /// The number of files in the archive so far, including the one being written
+    pub fn entry_count(&self) -> usize {
+        #[cfg(feature = "parallel")]
+        let pending = self.pending.len();
+        #[cfg(not(feature = "parallel"))]
+        let pending = 0;
+        self.files.len() + pending
+    }
+
+    /// Where the last file finished was written, and its final sizes and CRC32
+    ///
+    /// A file is finished when the next one is started, or when the archive is finished.
+    pub fn last_entry_stats(&self) -> Option<&EntryStats> {
+        self.last_entry.as_ref()
+    }
+
+    /// Record the stats of the last file, which was just finished, and report its progress
+    fn entry_finished(&mut self) -> io::Result<()> {
+        self.last_entry = self.files.last().map(EntryStats::new);
+        self.report_progress(true)
+    }
+
+    /// Pass the progress of the last file to the progress callback, if there is one
+    fn report_progress(&mut self, finished_entry: bool) -> io::Result<()> {
+        let (callback, file) = match (self.progress.as_mut(), self.files.last()) {
+            (Some(callback), Some(file)) => (callback, file),
+            _ => return Ok(()),
+        };
+        let progress = if finished_entry {
+            WriteProgress {
+                name: &file.file_name,
+                uncompressed_bytes: file.uncompressed_size,
+                compressed_bytes: file.compressed_size,
+                finished_entry,
+            }
+        } else {
+            let writer = match self.inner.get_mut() {
+                Some(writer) => writer.get_plain_mut(),
+                None => return Ok(()),
+            };
+            WriteProgress {
+                name: &file.file_name,
+                uncompressed_bytes: self.stats.bytes_written,
+                compressed_bytes: writer.stream_position()? - self.stats.start,
+                finished_entry,
+            }
+        };
+        callback(progress);
+        Ok(())
+    }
+
+    /// Compress the files added with [`ZipWriter::add_entry`] on up to `threads` threads.
+    ///
+    /// The files are still written in the order they were added, with the same bytes as without
+    /// parallelism. Each file is held in memory, compressed and uncompressed, until it is written.
+    /// Encrypted and aligned files are compressed on the calling thread, after the files before
+    /// them.
+    #[cfg(feature = "parallel")]
+    pub fn with_parallelism(mut self, threads: usize) -> ZipWriter<W> {
+        self.pending.parallelism = threads;
+        self
+    }
+
+    /// Start a new file for with the requested options.
+    fn start_entry<S>(
+        &mut self,
+        name: S,
+        options: FileOptions,
+        raw_values: Option<ZipRawValues>,
+    ) -> ZipResult<()>
+    where
+        S: Into<String>,
+    {
+        self.finish_file()?;
+        let name = name.into();
+        self.check_duplicate(&name)?;
+        self.store_if_larger = None;
+
+        let raw_values = raw_values.unwrap_or(ZipRawValues {
+            crc32: 0,
+            compressed_size: 0,
+            uncompressed_size: 0,
+        });
+
+        // Without seeking, the CRC and sizes of a file follow its data, and as its size isn't known
+        // yet, it's written in ZIP64
+        let using_data_descriptor = self.streaming && !name.ends_with('/');
+        let mut options = options;
+        if using_data_descriptor {
+            if options.encrypt_with.is_some() {
+                return Err(ZipError::UnsupportedArchive(
+                    "Encrypted files cannot be written without seeking",
+                ));
+            }
+            options.large_file = Some(true);
+        }
+
+        let header_start = self.inner.get_plain().stream_position()?;
+        let mut file = new_file_data(name, &options, raw_values, header_start)?;
+        file.using_data_descriptor = using_data_descriptor;
+        self.write_local_header(file)?;
+        self.writing_raw = false;
+
+        if let Some(keys) = options.encrypt_with {
+            let mut zipwriter = crate::zipcrypto::ZipCryptoWriter {
+                writer: core::mem::replace(&mut self.inner, GenericZipWriter::Closed).unwrap(),
+                buffer: vec![],
+                keys: keys.into(),
+            };
+            let crypto_header = [0u8; 12];
+
+            zipwriter.write_all(&crypto_header)?;
+            self.inner = GenericZipWriter::Storer(MaybeEncrypted::Encrypted(zipwriter));
+        }
+        #[cfg(feature = "aes-crypto")]
+        if let Some((mode, password)) = options.aes_encryption {
+            let writer = mem::replace(&mut self.inner, GenericZipWriter::Closed).unwrap();
+            let aes_writer = crate::aes::AesWriter::new(writer, mode, password)?;
+            self.inner = GenericZipWriter::Storer(MaybeEncrypted::Aes(aes_writer));
+        }
+        Ok(())
+    }
+
+    /// Add a copy of a file already written to the archive, with a new name, sharing its data.
+    ///
+    /// Only a central directory header is added, pointing at the local header and data of the
+    /// file. This saves space, but some readers reject archives with overlapping files, or with a
+    /// local header whose name differs from the central one: [`ZipWriter::deep_copy_file`]
+    /// avoids this. Fails if no file is named `src_name`, or if a file is already named
+    /// `dest_name`.
+    pub fn shallow_copy_file(&mut self, src_name: &str, dest_name: &str) -> ZipResult<()> {
+        self.finish_file()?;
+        let data = self.copy_file_data(src_name, dest_name)?;
+        self.names.insert(data.file_name.clone());
+        self.files.push(data);
+        // The local header is the one of the source file
+        self.writing_raw = true;
+        Ok(())
+    }
+
+    /// Remove the files named `name` from the archive.
+    ///
+    /// They are only removed from the central directory: their local headers and data stay in
+    /// the archive, unused, so that nothing after them is moved. This works for the files of an
+    /// archive opened with [`ZipWriter::new_append`] as well as for new ones. Fails if no file is
+    /// named `name`.
+    pub fn remove_entry(&mut self, name: &str) -> ZipResult<()> {
+        self.finish_file()?;
+        if !self.names.remove(name) {
+            return Err(ZipError::FileNotFound);
+        }
+        self.files.retain(|file| file.file_name != name);
+        Ok(())
+    }
+
+    /// Rename the files named `old_name` to `new_name`.
+    ///
+    /// Only the central directory headers are rewritten. The local headers keep the old name,
+    /// which readers using the central directory, like [`ZipArchive`], ignore. The duplicate
+    /// policy applies to `new_name`. Fails if no file is named `old_name`.
+    pub fn rename_entry(&mut self, old_name: &str, new_name: &str) -> ZipResult<()> {
+        self.finish_file()?;
+        if !self.names.contains(old_name) {
+            return Err(ZipError::FileNotFound);
+        }
+        if old_name == new_name {
+            return Ok(());
+        }
+        self.check_duplicate(new_name)?;
+        for file in self.files.iter_mut() {
+            if file.file_name == old_name {
+                file.file_name = new_name.to_owned();
+                file.file_name_raw = Vec::new();
+            }
+        }
+        self.names.remove(old_name);
+        self.names.insert(new_name.to_owned());
+        Ok(())
+    }
+
+    /// Metadata for a copy of the file named `src_name`, named `dest_name`
+    fn copy_file_data(&self, src_name: &str, dest_name: &str) -> ZipResult<ZipFileData> {
+        if self.files.iter().any(|file| file.file_name == dest_name) {
+            return Err(ZipError::Io(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "A file with the destination name already exists",
+            )));
+        }
+        let mut data = self
+            .files
+            .iter()
+            .find(|file| file.file_name == src_name)
+            .ok_or(ZipError::FileNotFound)?
+            .clone();
+        data.file_name = dest_name.to_owned();
+        data.file_name_raw = Vec::new();
+        data.central_header_start = 0;
+        Ok(data)
+    }
+
+    /// Write the local header of a new file, and start counting its data
+    fn write_local_header(&mut self, mut file: ZipFileData) -> ZipResult<()> {
+        let writer = self.inner.get_plain();
+        write_local_file_header(writer, &file)?;
+
+        let header_end = writer.stream_position()?;
+        self.stats.start = header_end;
+        *file.data_start.get_mut() = header_end;
+
+        self.stats.bytes_written = 0;
+        self.stats.hasher = Hasher::new();
+
+        self.names.insert(file.file_name.clone());
+        self.files.push(file);
+        Ok(())
+    }
+
+    fn finish_file(&mut self) -> ZipResult<()> {
+        #[cfg(feature = "parallel")]
+        self.write_pending()?;
+        if self.writing_to_extra_field {
+            // Implicitly calling [`ZipWriter::end_extra_data`] for empty files.
+            self.end_extra_data()?;
+        }
+        self.inner.switch_to(CompressionMethod::Stored, None)?;
+        match core::mem::replace(&mut self.inner, GenericZipWriter::Closed) {
+            GenericZipWriter::Storer(MaybeEncrypted::Encrypted(writer)) => {
                 let crc32 = self.stats.hasher.clone().finalize();
-                self.inner = GenericZipWriter::Storer(MaybeEncrypted::Unencrypted(writer.finish(crc32)?))
+                self.inner =
+                    GenericZipWriter::Storer(MaybeEncrypted::Unencrypted(writer.finish(crc32)?))
+            }
+            #[cfg(feature = "aes-crypto")]
+            GenericZipWriter::Storer(MaybeEncrypted::Aes(writer)) => {
+                self.inner = GenericZipWriter::Storer(MaybeEncrypted::Unencrypted(writer.finish()?))
             }
             GenericZipWriter::Storer(w) => self.inner = GenericZipWriter::Storer(w),
-            _ => unreachable!()
+            _ => unreachable!(),
         }
         let writer = self.inner.get_plain();
 
-        if !self.writing_raw {
+        let finished_entry = !self.writing_raw;
+        if finished_entry {
             let file = match self.files.last_mut() {
                 None => return Ok(()),
                 Some(f) => f,
             };
-            file.crc32 = self.stats.hasher.clone().finalize();
+            file.crc32 = match file.aes_mode {
+                Some((_, AesVendorVersion::Ae2)) => 0,
+                _ => self.stats.hasher.clone().finalize(),
+            };
             file.uncompressed_size = self.stats.bytes_written;
 
             let file_end = writer.stream_position()?;
             file.compressed_size = file_end - self.stats.start;
+            if self.streaming {
+                if file.using_data_descriptor {
+                    write_data_descriptor(writer, file)?;
+                }
+            } else {
+                if !file.large_file
+                    && file.compressed_size.max(file.uncompressed_size) > spec::ZIP64_BYTES_THR
+                    && use_reserved_zip64(file)
+                {
+                    writer.seek(io::SeekFrom::Start(file.header_start))?;
+                    write_local_file_header(writer, file)?;
+                }
+                if let Some(copy) = self.store_if_larger.take() {
+                    store_if_larger(writer, file, copy)?;
+                }
 
-            update_local_file_header(writer, file)?;
-            writer.seek(io::SeekFrom::Start(file_end))?;
+                update_local_file_header(writer, file)?;
+                writer.seek(io::SeekFrom::Start(file_end))?;
+            }
         }
 
         self.writing_to_file = false;
-        self.writing_raw = false;
+        // The header is final, even if finishing again
+        self.writing_raw = true;
+        if finished_entry {
+            self.entry_finished()?;
+        }
         Ok(())
     }
 
@@ -466,27 +2116,67 @@ impl<W: Write + io::Seek> ZipWriter<W> {
             options.permissions = Some(0o644);
         }
         *options.permissions.as_mut().unwrap() |= 0o100000;
+        options.check_compression_level()?;
+        options.reserve_zip64 = true;
         self.start_entry(name, options, None)?;
-        self.inner
-            .switch_to(options.compression_method, options.compression_level)?;
+        self.inner.switch_to_options(&options)?;
         self.writing_to_file = true;
+        if options.store_if_larger
+            && !self.streaming
+            && options.compression_method != CompressionMethod::Stored
+            && options.encrypt_with.is_none()
+            && options.aes_encryption.is_none()
+        {
+            self.store_if_larger = Some(StoreIfLarger {
+                data: Vec::new(),
+                buffer_size: options.store_if_larger_buffer,
+                alignment: options.alignment,
+                alignment_padding_id: options.alignment_padding_id,
+            });
+        }
         Ok(())
     }
 
-    /// Starts a file, taking a Path as argument.
+    /// Add a file with the contents of `reader`.
     ///
-    /// This function ensures that the '/' path separator is used. It also ignores all non 'Normal'
-    /// Components, such as a starting '/' or '..' and '.'.
-    #[deprecated(
-        since = "0.5.7",
-        note = "by stripping `..`s from the path, the meaning of paths can change. Use `start_file` instead."
-    )]
+    /// This is the same as [`ZipWriter::start_file`] followed by copying `reader` to the file,
+    /// except with [`ZipWriter::with_parallelism`], which compresses the file on another thread.
+    pub fn add_entry<S, R>(
+        &mut self,
+        name: S,
+        mut reader: R,
+        mut options: FileOptions,
+    ) -> ZipResult<()>
+    where
+        S: Into<String>,
+        R: Read,
+    {
+        if options.permissions.is_none() {
+            options.permissions = Some(0o644);
+        }
+        *options.permissions.as_mut().unwrap() |= 0o100000;
+        options.check_compression_level()?;
+        #[cfg(feature = "parallel")]
+        if self.can_queue(&options) {
+            return self.queue_entry(name.into(), reader, options);
+        }
+        self.start_file(name, options)?;
+        io::copy(&mut reader, self)?;
+        Ok(())
+    }
+
+    /// Starts a file, taking a relative Path as argument.
+    ///
+    /// The name of the file is the path with `/` separators. Both `/` and `\` are separators, on
+    /// every platform, so that Windows paths give the same name everywhere. `.` components are
+    /// skipped. Fails if the path is absolute, starts with a drive like `C:`, has a `..`
+    /// component, or isn't valid UTF-8.
     pub fn start_file_from_path(
         &mut self,
         path: &std::path::Path,
         options: FileOptions,
     ) -> ZipResult<()> {
-        self.start_file(path_to_string(path), options)
+        self.start_file(path_to_zip_name(path)?, options)
     }
 
     /// Create an aligned file in the archive and start writing its' contents.
@@ -589,16 +2279,94 @@ impl<W: Write + io::Seek> ZipWriter<W> {
     where
         S: Into<String>,
     {
+        if self.streaming {
+            return Err(ZipError::Io(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "Extra data isn't supported without seeking",
+            )));
+        }
+        if options.alignment > 1 {
+            return Err(ZipError::Io(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Alignment isn't supported with extra data",
+            )));
+        }
+        if options.aes_encryption.is_some() {
+            return Err(ZipError::Io(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "AES encryption isn't supported with extra data",
+            )));
+        }
+        if options.zstd_dictionary.is_some() {
+            return Err(ZipError::Io(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Zstandard dictionaries aren't supported with extra data",
+            )));
+        }
+        if options.deflate_options.is_some() {
+            return Err(ZipError::Io(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Deflate options aren't supported with extra data",
+            )));
+        }
         if options.permissions.is_none() {
             options.permissions = Some(0o644);
         }
         *options.permissions.as_mut().unwrap() |= 0o100000;
+        options.check_compression_level()?;
         self.start_entry(name, options, None)?;
         self.writing_to_file = true;
         self.writing_to_extra_field = true;
         Ok(self.files.last().unwrap().data_start.load())
     }
 
+    /// Create a file in the archive with the given extra fields, and start writing its contents.
+    ///
+    /// The local header of the file gets `local_extra`, and its central directory header gets
+    /// `central_extra`. The fields are checked like those written with
+    /// [`ZipWriter::start_file_with_extra_data`]: in particular, the ZIP64 (0x0001) and AES
+    /// (0x9901) fields are managed by the writer and can't be given. Both sets of fields must fit
+    /// in an extra field along with the ZIP64 field, when there is one.
+    ///
+    /// Returns the starting offset of the file data.
+    ///
+    /// ```
+    /// use std::io::{Cursor, Write};
+    /// use zip::write::{ExtraField, FileOptions};
+    /// use zip::{ZipArchive, ZipWriter};
+    ///
+    /// # fn main() -> zip::result::ZipResult<()> {
+    /// let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    /// let provenance = ExtraField::new(0xcafe, b"built by CI".to_vec());
+    /// zip.start_file_with_extra_fields("file.txt", FileOptions::default(), &[], &[provenance])?;
+    /// zip.write_all(b"file data")?;
+    ///
+    /// let mut zip = ZipArchive::new(zip.finish()?)?;
+    /// assert_eq!(&zip.by_index(0)?.extra_data()[4..], b"built by CI");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn start_file_with_extra_fields<S>(
+        &mut self,
+        name: S,
+        options: FileOptions,
+        local_extra: &[ExtraField],
+        central_extra: &[ExtraField],
+    ) -> ZipResult<u64>
+    where
+        S: Into<String>,
+    {
+        // Largest ZIP64 fields of the local and central headers
+        let local_extra = extra_fields_to_bytes(local_extra, 20)?;
+        let central_extra = extra_fields_to_bytes(central_extra, 28)?;
+
+        self.start_file_with_extra_data(name, options)?;
+        self.files.last_mut().unwrap().extra_field = local_extra;
+        self.end_local_start_central_extra_data()?;
+        self.files.last_mut().unwrap().extra_field = central_extra;
+        self.end_extra_data()
+    }
+
     /// End local and start central extra data. Requires [`ZipWriter::start_file_with_extra_data`].
     ///
     /// Returns the final starting offset of the file data.
@@ -623,8 +2391,12 @@ impl<W: Write + io::Seek> ZipWriter<W> {
         }
         let file = self.files.last_mut().unwrap();
 
-        validate_extra_data(file)?;
-
+        validate_extra_data(&file.extra_field)?;
+        let extra_field_length = if self.writing_to_central_extra_field_only {
+            central_extra_length(file, 28)?
+        } else {
+            local_extra_length(file)?
+        };
         let data_start = file.data_start.get_mut();
 
         if !self.writing_to_central_extra_field_only {
@@ -639,8 +2411,6 @@ impl<W: Write + io::Seek> ZipWriter<W> {
             *data_start = header_end;
 
             // Update extra field length in local file header.
-            let extra_field_length =
-                if file.large_file { 20 } else { 0 } + file.extra_field.len() as u16;
             writer.seek(io::SeekFrom::Start(file.header_start + 28))?;
             writer.write_u16::<LittleEndian>(extra_field_length)?;
             writer.seek(io::SeekFrom::Start(header_end))?;
@@ -657,7 +2427,13 @@ impl<W: Write + io::Seek> ZipWriter<W> {
     /// Add a new file using the already compressed data from a ZIP file being read and renames it, this
     /// allows faster copies of the `ZipFile` since there is no need to decompress and compress it again.
     /// Any `ZipFile` metadata is copied and not checked, for example the file CRC.
-
+    ///
+    /// Only the name changes, along with the header fields that depend on it or on the new position
+    /// of the file, such as the UTF-8 flag and the ZIP64 fields. If the local header of the source
+    /// has a padding field aligning its data, like those written for [`FileOptions::alignment`],
+    /// the data of the copy gets the same alignment: the largest power of two that the data of the
+    /// source starts at a multiple of. See [`ZipWriter::raw_copy_file`].
+    ///
     /// ```no_run
     /// use std::fs::File;
     /// use std::io::{Read, Seek, Write};
@@ -684,26 +2460,112 @@ impl<W: Write + io::Seek> ZipWriter<W> {
     where
         S: Into<String>,
     {
-        let mut options = FileOptions::default()
-            .large_file(file.compressed_size().max(file.size()) > spec::ZIP64_BYTES_THR)
-            .last_modified_time(file.last_modified())
-            .compression_method(file.compression());
-        if let Some(perms) = file.unix_mode() {
-            options = options.unix_permissions(perms);
-        }
-
-        let raw_values = ZipRawValues {
-            crc32: file.crc32(),
-            compressed_size: file.compressed_size(),
-            uncompressed_size: file.size(),
-        };
+        self.finish_file()?;
+        let name = name.into();
+        self.check_duplicate(&name)?;
 
-        self.start_entry(name, options, Some(raw_values))?;
+        let source = file.get_metadata();
+        let mut data = source.clone();
+        data.file_name = name;
+        data.file_name_raw = Vec::new();
+        data.extra_field = extra_data_without_managed_fields(&source.extra_field)?;
+        data.large_file =
+            source.compressed_size.max(source.uncompressed_size) > spec::ZIP64_BYTES_THR;
+        data.header_start = self.inner.get_plain().stream_position()?;
+        data.disk_number = 0;
+        data.central_header_start = 0;
+        // The default padding ID, as the source's isn't kept
+        pad_local_header(&mut data, source.data_alignment.load(), 0xd935, false);
+        self.write_local_header(data)?;
         self.writing_to_file = true;
         self.writing_raw = true;
 
         io::copy(file.get_raw_reader(), self)?;
 
+        // The sizes are also in the local header, but a ZipCrypto header checks the time instead of
+        // the CRC when there is a data descriptor, so keep it
+        let data = self.files.last().unwrap();
+        if data.using_data_descriptor {
+            write_data_descriptor(self.inner.get_plain(), data)?;
+        }
+        self.entry_finished()?;
+
+        Ok(())
+    }
+
+    /// Add a new file whose data is already compressed with the compression method of `options`,
+    /// such as deflated data cached by a build system.
+    ///
+    /// The local header is written from `raw_values`, then `compressed_size` bytes of `data` are
+    /// copied as they are. Nothing is checked against the data, except its size: a wrong CRC32
+    /// or uncompressed size only shows when the file is read. Encryption isn't supported, and
+    /// stored files need the same compressed and uncompressed size.
+    ///
+    /// ```
+    /// use std::io::{Cursor, Write};
+    /// use zip::write::{FileOptions, ZipRawValues};
+    /// use zip::ZipWriter;
+    ///
+    /// # fn main() -> zip::result::ZipResult<()> {
+    /// let data = b"Hello, World!";
+    /// let raw_values = ZipRawValues {
+    ///     crc32: crc32fast::hash(data),
+    ///     compressed_size: data.len() as u64,
+    ///     uncompressed_size: data.len() as u64,
+    /// };
+    /// let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    /// let options = FileOptions::default().compression_method(zip::CompressionMethod::Stored);
+    /// zip.add_raw_entry("hello.txt", options, raw_values, &data[..])?;
+    /// zip.finish()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn add_raw_entry<S, R>(
+        &mut self,
+        name: S,
+        options: FileOptions,
+        raw_values: ZipRawValues,
+        data: R,
+    ) -> ZipResult<()>
+    where
+        S: Into<String>,
+        R: Read,
+    {
+        if options.encrypt_with.is_some() || options.aes_encryption.is_some() {
+            return Err(ZipError::Io(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Raw entries can't be encrypted",
+            )));
+        }
+        if options.compression_method == CompressionMethod::Stored
+            && raw_values.compressed_size != raw_values.uncompressed_size
+        {
+            return Err(ZipError::Io(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Stored files must have the same compressed and uncompressed size",
+            )));
+        }
+        self.finish_file()?;
+        let name = name.into();
+        self.check_duplicate(&name)?;
+
+        let header_start = self.inner.get_plain().stream_position()?;
+        let mut file = new_file_data(name, &options, raw_values, header_start)?;
+        file.large_file |=
+            raw_values.compressed_size.max(raw_values.uncompressed_size) > spec::ZIP64_BYTES_THR;
+        self.write_local_header(file)?;
+        self.writing_to_file = true;
+        self.writing_raw = true;
+
+        let copied = io::copy(&mut data.take(raw_values.compressed_size), self)?;
+        if copied != raw_values.compressed_size {
+            self.abort_file()?;
+            return Err(ZipError::Io(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "Raw data is shorter than its compressed size",
+            )));
+        }
+        self.entry_finished()?;
         Ok(())
     }
 
@@ -711,6 +2573,10 @@ impl<W: Write + io::Seek> ZipWriter<W> {
     /// copies of the `ZipFile` since there is no need to decompress and compress it again. Any `ZipFile`
     /// metadata is copied and not checked, for example the file CRC.
     ///
+    /// The extra fields are copied too, except for the ZIP64 field, which is rewritten as needed.
+    /// Encrypted files stay encrypted with the same password: get them with
+    /// [`ZipArchive::by_index_raw`](crate::ZipArchive::by_index_raw), which needs no password.
+    ///
     /// ```no_run
     /// use std::fs::File;
     /// use std::io::{Read, Seek, Write};
@@ -735,17 +2601,63 @@ impl<W: Write + io::Seek> ZipWriter<W> {
         self.raw_copy_file_rename(file, name)
     }
 
+    /// Add all the files of another archive, in order, copying their compressed data like
+    /// [`ZipWriter::raw_copy_file`], and return their names.
+    ///
+    /// The duplicate policy applies to each file. With [`Duplicate::Error`], this fails without
+    /// adding anything if a name is already in this archive, or twice in `source`. Encrypted
+    /// files stay encrypted with the same password.
+    ///
+    /// ```no_run
+    /// use std::fs::File;
+    /// use zip::{ZipArchive, ZipWriter};
+    ///
+    /// fn bundle(parts: &[&str]) -> zip::result::ZipResult<()> {
+    ///     let mut bundle = ZipWriter::new(File::create("bundle.zip")?);
+    ///     for part in parts {
+    ///         let mut source = ZipArchive::new(File::open(part)?)?;
+    ///         bundle.merge_archive(&mut source)?;
+    ///     }
+    ///     bundle.finish()?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn merge_archive<R>(&mut self, source: &mut ZipArchive<R>) -> ZipResult<Vec<String>>
+    where
+        R: Read + io::Seek,
+    {
+        self.finish_file()?;
+        let mut names = Vec::with_capacity(source.len());
+        let mut new_names = HashSet::new();
+        for i in 0..source.len() {
+            let name = source.by_index_raw(i)?.name().to_owned();
+            let duplicate = self.names.contains(&name) || !new_names.insert(name.clone());
+            if duplicate && self.duplicate_policy == Duplicate::Error {
+                return Err(ZipError::Io(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("A file named {name} already exists"),
+                )));
+            }
+            names.push(name);
+        }
+        for i in 0..source.len() {
+            self.raw_copy_file(source.by_index_raw(i)?)?;
+        }
+        Ok(names)
+    }
+
     /// Add a directory entry.
     ///
+    /// The entry is marked as a directory in its unix mode, with the permissions set by
+    /// [`FileOptions::dir_permissions`] or else [`FileOptions::unix_permissions`], and `0o755`
+    /// by default.
+    ///
     /// As directories have no content, you must not call [`ZipWriter::write`] before adding a new file.
     pub fn add_directory<S>(&mut self, name: S, mut options: FileOptions) -> ZipResult<()>
     where
         S: Into<String>,
     {
-        if options.permissions.is_none() {
-            options.permissions = Some(0o755);
-        }
-        *options.permissions.as_mut().unwrap() |= 0o40000;
+        options.permissions = Some(options.directory_mode());
         options.compression_method = CompressionMethod::Stored;
 
         let name_as_string = name.into();
@@ -776,23 +2688,146 @@ impl<W: Write + io::Seek> ZipWriter<W> {
         self.add_directory(path_to_string(path), options)
     }
 
-    /// Finish the last file and write all other zip-structures
-    ///
-    /// This will return the writer, but one should normally not append any data to the end of the file.
-    /// Note that the zipfile will also be finished on drop.
-    pub fn finish(&mut self) -> ZipResult<W> {
-        self.finalize()?;
-        let inner = mem::replace(&mut self.inner, GenericZipWriter::Closed);
-        Ok(inner.unwrap())
+    /// Stop the compression and encryption of the current file, to write to the plain writer,
+    /// without finishing the file
+    fn switch_to_plain(&mut self) -> ZipResult<()> {
+        self.inner.switch_to(CompressionMethod::Stored, None)?;
+        self.inner = match mem::replace(&mut self.inner, GenericZipWriter::Closed) {
+            GenericZipWriter::Storer(MaybeEncrypted::Encrypted(writer)) => {
+                GenericZipWriter::Storer(MaybeEncrypted::Unencrypted(writer.finish(0)?))
+            }
+            #[cfg(feature = "aes-crypto")]
+            GenericZipWriter::Storer(MaybeEncrypted::Aes(writer)) => {
+                GenericZipWriter::Storer(MaybeEncrypted::Unencrypted(writer.finish()?))
+            }
+            inner => inner,
+        };
+        Ok(())
     }
 
-    /// Add a symlink entry.
+    /// Discard the file being written, so that it isn't part of the archive.
     ///
-    /// The zip archive will contain an entry for path `name` which is a symlink to `target`.
+    /// The writer seeks back to the local header of the file, so that what is written next
+    /// overwrites it. The writer isn't truncated: if the archive would end before the end of the
+    /// discarded data, zeros are written before the central directory so that it ends there.
+    /// With [`ZipWriter::new_streaming`], what was written of the file stays in the writer, but
+    /// isn't referenced by the central directory.
     ///
-    /// No validation or normalization of the paths is performed. For best results,
-    /// callers should normalize `\` to `/` and ensure symlinks are relative to other
-    /// paths within the zip archive.
+    /// Fails if no file is being written.
+    pub fn abort_file(&mut self) -> ZipResult<()> {
+        if !self.writing_to_file {
+            return Err(ZipError::Io(io::Error::new(
+                io::ErrorKind::Other,
+                "No file has been started",
+            )));
+        }
+        let file = self.files.pop().unwrap();
+        self.store_if_larger = None;
+        if !self
+            .files
+            .iter()
+            .any(|other| other.file_name == file.file_name)
+        {
+            self.names.remove(&file.file_name);
+        }
+        self.switch_to_plain()?;
+        if !self.streaming {
+            let writer = self.inner.get_plain();
+            self.high_water_mark = self.high_water_mark.max(writer.stream_position()?);
+            writer.seek(io::SeekFrom::Start(file.header_start))?;
+        }
+
+        self.writing_to_file = false;
+        self.writing_to_extra_field = false;
+        self.writing_to_central_extra_field_only = false;
+        // The last file was finished before this one started
+        self.writing_raw = true;
+        Ok(())
+    }
+
+    /// Stop writing the archive without writing its central directory, and return the writer.
+    ///
+    /// The file being written and the files still being compressed on other threads are
+    /// discarded. What was written so far stays in the writer, which doesn't hold a valid
+    /// archive: a temporary file, for instance, can then be deleted. Fails if the archive was
+    /// already finished, or if finishing the compression or encryption of the current file
+    /// fails. Either way, dropping the `ZipWriter` then doesn't finish the archive.
+    pub fn abort(mut self) -> ZipResult<W> {
+        #[cfg(feature = "parallel")]
+        {
+            self.pending = Default::default();
+        }
+        let result = self.switch_to_plain();
+        let inner = mem::replace(&mut self.inner, GenericZipWriter::Closed);
+        result?;
+        Ok(inner.unwrap())
+    }
+
+    /// Whether the archive was finished or aborted, so that nothing more can be written
+    pub fn is_finished(&self) -> bool {
+        self.inner.is_closed()
+    }
+
+    /// Finish the last file and write the central directory, so that what was written so far is
+    /// a complete archive, readable if the rest is never written.
+    ///
+    /// The writer then seeks back to the start of that central directory, so that the next
+    /// file overwrites it. If the archive would end before the end of the checkpoint, for
+    /// instance when files are removed after it, zeros are written before the central directory
+    /// so that it ends there. Fails with [`ZipWriter::new_streaming`], as the writer can't seek.
+    ///
+    /// ```no_run
+    /// use std::fs::File;
+    /// use std::io::Write;
+    /// use zip::write::FileOptions;
+    /// use zip::ZipWriter;
+    ///
+    /// # fn main() -> zip::result::ZipResult<()> {
+    /// let mut zip = ZipWriter::new(File::create("backup.zip")?);
+    /// for i in 0..100 {
+    ///     zip.start_file(format!("part{i}.bin"), FileOptions::default())?;
+    ///     zip.write_all(&[0; 1024])?;
+    ///     zip.checkpoint()?;
+    /// }
+    /// zip.finish()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn checkpoint(&mut self) -> ZipResult<()> {
+        if self.streaming {
+            return Err(ZipError::Io(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "Checkpoints aren't supported without seeking",
+            )));
+        }
+        self.finish_file()?;
+        let central_start = self.inner.get_plain().stream_position()?;
+        self.write_central_directory()?;
+        let writer = self.inner.get_plain();
+        writer.flush()?;
+        self.high_water_mark = writer.stream_position()?;
+        writer.seek(io::SeekFrom::Start(central_start))?;
+        Ok(())
+    }
+
+    /// Finish the last file and write all other zip-structures
+    ///
+    /// This will return the writer, but one should normally not append any data to the end of the file.
+    /// Note that the zipfile will also be finished on drop.
+    pub fn finish(&mut self) -> ZipResult<W> {
+        self.finalize()?;
+        let inner = mem::replace(&mut self.inner, GenericZipWriter::Closed);
+        Ok(inner.unwrap())
+    }
+
+    /// Add a symlink entry.
+    ///
+    /// The zip archive will contain an entry for path `name` which is a symlink to `target`.
+    ///
+    /// The paths aren't normalized, and are only checked for a `name` ending with `/`, which would
+    /// be a directory, and for a `target` containing NUL. For best results, callers should
+    /// normalize `\` to `/` and ensure symlinks are relative to other paths within the zip
+    /// archive: [`ZipArchive::extract`](crate::ZipArchive::extract) refuses other symlinks.
     ///
     /// WARNING: not all zip implementations preserve symlinks on extract. Some zip
     /// implementations may materialize a symlink as a regular file, possibly with the
@@ -808,6 +2843,20 @@ impl<W: Write + io::Seek> ZipWriter<W> {
         N: Into<String>,
         T: Into<String>,
     {
+        let name = name.into();
+        if name.ends_with('/') {
+            return Err(ZipError::Io(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Symlink name can't end with '/'",
+            )));
+        }
+        let target = target.into();
+        if target.contains('\0') {
+            return Err(ZipError::Io(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Symlink target can't contain NUL",
+            )));
+        }
         if options.permissions.is_none() {
             options.permissions = Some(0o777);
         }
@@ -818,64 +2867,73 @@ impl<W: Write + io::Seek> ZipWriter<W> {
 
         self.start_entry(name, options, None)?;
         self.writing_to_file = true;
-        self.write_all(target.into().as_bytes())?;
+        self.write_all(target.as_bytes())?;
         self.writing_to_file = false;
 
         Ok(())
     }
 
+    /// Add the files, directories and symlinks in the directory `src`, recursively.
+    ///
+    /// Their names are their paths relative to `src`, with `/` separators, after the prefix set
+    /// in `options`. Their last modified times and, on Unix, permissions are those of the
+    /// filesystem. The entries of each directory are added in the order of their names, and the
+    /// contents of a directory right after it. Files are added with [`ZipWriter::add_entry`].
+    /// Other file types, such as FIFOs, sockets and devices, are skipped.
+    ///
+    /// Fails if a name or symlink target isn't valid UTF-8. When following symlinks, also fails if
+    /// a symlink is broken, or points to a directory containing it.
+    pub fn add_directory_tree<P: AsRef<std::path::Path>>(
+        &mut self,
+        src: P,
+        options: DirectoryTreeOptions,
+    ) -> ZipResult<()> {
+        options.walk(src.as_ref(), &mut |entry| match entry {
+            TreeEntry::Directory { name, metadata, .. } => {
+                if options.add_directories {
+                    self.add_directory(name, options.file_options_for(&metadata))?;
+                }
+                Ok(())
+            }
+            TreeEntry::File {
+                name,
+                path,
+                metadata,
+            } => self.add_entry(
+                name,
+                std::fs::File::open(path)?,
+                options.file_options_for(&metadata),
+            ),
+            TreeEntry::Symlink {
+                name,
+                target,
+                metadata,
+            } => self.add_symlink(name, target, options.file_options_for(&metadata)),
+        })
+    }
+
     fn finalize(&mut self) -> ZipResult<()> {
         self.finish_file()?;
+        self.write_central_directory()
+    }
 
-        {
-            let writer = self.inner.get_plain();
-
-            let central_start = writer.stream_position()?;
-            for file in self.files.iter() {
-                write_central_directory_header(writer, file)?;
-            }
-            let central_size = writer.stream_position()? - central_start;
-
-            if self.files.len() > spec::ZIP64_ENTRY_THR
-                || central_size.max(central_start) > spec::ZIP64_BYTES_THR
-            {
-                let zip64_footer = spec::Zip64CentralDirectoryEnd {
-                    version_made_by: DEFAULT_VERSION as u16,
-                    version_needed_to_extract: DEFAULT_VERSION as u16,
-                    disk_number: 0,
-                    disk_with_central_directory: 0,
-                    number_of_files_on_this_disk: self.files.len() as u64,
-                    number_of_files: self.files.len() as u64,
-                    central_directory_size: central_size,
-                    central_directory_offset: central_start,
-                };
-
-                zip64_footer.write(writer)?;
-
-                let zip64_footer = spec::Zip64CentralDirectoryEndLocator {
-                    disk_with_central_directory: 0,
-                    end_of_central_directory_offset: central_start + central_size,
-                    number_of_disks: 1,
-                };
-
-                zip64_footer.write(writer)?;
-            }
-
-            let number_of_files = self.files.len().min(spec::ZIP64_ENTRY_THR) as u16;
-            let footer = spec::CentralDirectoryEnd {
-                disk_number: 0,
-                disk_with_central_directory: 0,
-                zip_file_comment: self.comment.clone(),
-                number_of_files_on_this_disk: number_of_files,
-                number_of_files,
-                central_directory_size: central_size.min(spec::ZIP64_BYTES_THR) as u32,
-                central_directory_offset: central_start.min(spec::ZIP64_BYTES_THR) as u32,
-            };
+    /// Write the central directory and its end at the current position
+    fn write_central_directory(&mut self) -> ZipResult<()> {
+        let writer = self.inner.get_plain();
 
-            footer.write(writer)?;
+        let mut files: Vec<&ZipFileData> = self.files.iter().collect();
+        if self.central_directory_sort == SortOrder::ByName {
+            files.sort_by(|a, b| a.file_name.cmp(&b.file_name));
         }
-
-        Ok(())
+        let central_start = writer.stream_position()?;
+        write_central_directory(
+            writer,
+            &files,
+            central_start,
+            self.high_water_mark,
+            &self.comment,
+            self.archive_offset,
+        )
     }
 }
 
@@ -889,7 +2947,7 @@ impl<W: Write + io::Seek> Drop for ZipWriter<W> {
     }
 }
 
-impl<W: Write + io::Seek> GenericZipWriter<W> {
+impl<W: Write> GenericZipWriter<W> {
     fn switch_to(
         &mut self,
         compression: CompressionMethod,
@@ -915,6 +2973,8 @@ impl<W: Write + io::Seek> GenericZipWriter<W> {
                 feature = "deflate-zlib"
             ))]
             GenericZipWriter::Deflater(w) => w.finish()?,
+            #[cfg(any(feature = "deflate", feature = "deflate-miniz"))]
+            GenericZipWriter::DeflaterWithStrategy(w) => w.finish()?,
             #[cfg(feature = "bzip2")]
             GenericZipWriter::Bzip2(w) => w.finish()?,
             #[cfg(feature = "zstd")]
@@ -928,18 +2988,11 @@ impl<W: Write + io::Seek> GenericZipWriter<W> {
             }
         };
 
+        validate_compression_level(compression, compression_level)?;
         *self = {
             #[allow(deprecated)]
             match compression {
-                CompressionMethod::Stored => {
-                    if compression_level.is_some() {
-                        return Err(ZipError::UnsupportedArchive(
-                            "Unsupported compression level",
-                        ));
-                    }
-
-                    GenericZipWriter::Storer(bare)
-                }
+                CompressionMethod::Stored => GenericZipWriter::Storer(bare),
                 #[cfg(any(
                     feature = "deflate",
                     feature = "deflate-miniz",
@@ -947,30 +3000,16 @@ impl<W: Write + io::Seek> GenericZipWriter<W> {
                 ))]
                 CompressionMethod::Deflated => GenericZipWriter::Deflater(DeflateEncoder::new(
                     bare,
-                    flate2::Compression::new(
-                        clamp_opt(
-                            compression_level
-                                .unwrap_or(flate2::Compression::default().level() as i32),
-                            deflate_compression_level_range(),
-                        )
-                        .ok_or(ZipError::UnsupportedArchive(
-                            "Unsupported compression level",
-                        ))? as u32,
-                    ),
+                    compression_level.map_or_else(flate2::Compression::default, |level| {
+                        flate2::Compression::new(level as u32)
+                    }),
                 )),
                 #[cfg(feature = "bzip2")]
                 CompressionMethod::Bzip2 => GenericZipWriter::Bzip2(BzEncoder::new(
                     bare,
-                    bzip2::Compression::new(
-                        clamp_opt(
-                            compression_level
-                                .unwrap_or(bzip2::Compression::default().level() as i32),
-                            bzip2_compression_level_range(),
-                        )
-                        .ok_or(ZipError::UnsupportedArchive(
-                            "Unsupported compression level",
-                        ))? as u32,
-                    ),
+                    compression_level.map_or_else(bzip2::Compression::default, |level| {
+                        bzip2::Compression::new(level as u32)
+                    }),
                 )),
                 CompressionMethod::AES => {
                     return Err(ZipError::UnsupportedArchive(
@@ -981,16 +3020,27 @@ impl<W: Write + io::Seek> GenericZipWriter<W> {
                 CompressionMethod::Zstd => GenericZipWriter::Zstd(
                     ZstdEncoder::new(
                         bare,
-                        clamp_opt(
-                            compression_level.unwrap_or(zstd::DEFAULT_COMPRESSION_LEVEL),
-                            zstd::compression_level_range(),
-                        )
-                        .ok_or(ZipError::UnsupportedArchive(
-                            "Unsupported compression level",
-                        ))?,
+                        compression_level.unwrap_or(zstd::DEFAULT_COMPRESSION_LEVEL),
                     )
                     .unwrap(),
                 ),
+                #[cfg(feature = "legacy-decompress")]
+                CompressionMethod::Shrink
+                | CompressionMethod::Reduce1
+                | CompressionMethod::Reduce2
+                | CompressionMethod::Reduce3
+                | CompressionMethod::Reduce4
+                | CompressionMethod::Implode => {
+                    return Err(ZipError::UnsupportedArchive(
+                        "Legacy compression methods are not supported for writing",
+                    ))
+                }
+                #[cfg(feature = "xz")]
+                CompressionMethod::Xz => {
+                    return Err(ZipError::UnsupportedArchive(
+                        "XZ compression is not supported for writing",
+                    ))
+                }
                 CompressionMethod::Unsupported(..) => {
                     return Err(ZipError::UnsupportedArchive("Unsupported compression"))
                 }
@@ -1000,6 +3050,66 @@ impl<W: Write + io::Seek> GenericZipWriter<W> {
         Ok(())
     }
 
+    /// Switch to the compression method and level of `options`, with their Zstandard dictionary
+    /// or deflate options
+    fn switch_to_options(&mut self, options: &FileOptions) -> ZipResult<()> {
+        #[cfg(any(
+            feature = "deflate",
+            feature = "deflate-miniz",
+            feature = "deflate-zlib"
+        ))]
+        if let Some(deflate_options) = options.deflate_options {
+            debug_assert_eq!(
+                options.compression_method,
+                CompressionMethod::Deflated,
+                "Deflate options are only used with the Deflated method"
+            );
+            if options.compression_method == CompressionMethod::Deflated {
+                let level = Some(deflate_options.level);
+                if deflate_options.strategy == Strategy::Default {
+                    return self.switch_to(CompressionMethod::Deflated, level);
+                }
+                validate_compression_level(CompressionMethod::Deflated, level)?;
+                #[cfg(not(any(feature = "deflate", feature = "deflate-miniz")))]
+                return Err(ZipError::UnsupportedArchive(
+                    "Deflate strategies need the deflate or deflate-miniz feature",
+                ));
+                #[cfg(any(feature = "deflate", feature = "deflate-miniz"))]
+                {
+                    let bare = match mem::replace(self, GenericZipWriter::Closed) {
+                        GenericZipWriter::Storer(w) => w,
+                        _ => panic!("Should have switched to stored beforehand"),
+                    };
+                    *self = GenericZipWriter::DeflaterWithStrategy(deflate::StrategyEncoder::new(
+                        bare,
+                        deflate_options.level as u32,
+                        deflate_options.strategy,
+                    ));
+                    return Ok(());
+                }
+            }
+        }
+        #[cfg(feature = "zstd")]
+        if let (CompressionMethod::Zstd, Some(dictionary)) =
+            (options.compression_method, options.zstd_dictionary)
+        {
+            validate_compression_level(options.compression_method, options.compression_level)?;
+            let bare = match mem::replace(self, GenericZipWriter::Closed) {
+                GenericZipWriter::Storer(w) => w,
+                _ => panic!("Should have switched to stored beforehand"),
+            };
+            *self = GenericZipWriter::Zstd(ZstdEncoder::with_dictionary(
+                bare,
+                options
+                    .compression_level
+                    .unwrap_or(zstd::DEFAULT_COMPRESSION_LEVEL),
+                dictionary,
+            )?);
+            return Ok(());
+        }
+        self.switch_to(options.compression_method, options.compression_level)
+    }
+
     fn ref_mut(&mut self) -> Option<&mut dyn Write> {
         match *self {
             GenericZipWriter::Storer(ref mut w) => Some(w as &mut dyn Write),
@@ -1009,6 +3119,8 @@ impl<W: Write + io::Seek> GenericZipWriter<W> {
                 feature = "deflate-zlib"
             ))]
             GenericZipWriter::Deflater(ref mut w) => Some(w as &mut dyn Write),
+            #[cfg(any(feature = "deflate", feature = "deflate-miniz"))]
+            GenericZipWriter::DeflaterWithStrategy(ref mut w) => Some(w as &mut dyn Write),
             #[cfg(feature = "bzip2")]
             GenericZipWriter::Bzip2(ref mut w) => Some(w as &mut dyn Write),
             #[cfg(feature = "zstd")]
@@ -1021,6 +3133,26 @@ impl<W: Write + io::Seek> GenericZipWriter<W> {
         matches!(*self, GenericZipWriter::Closed)
     }
 
+    /// The writer that the compressor writes to, if it isn't closed
+    fn get_mut(&mut self) -> Option<&mut MaybeEncrypted<W>> {
+        match self {
+            GenericZipWriter::Closed => None,
+            GenericZipWriter::Storer(w) => Some(w),
+            #[cfg(any(
+                feature = "deflate",
+                feature = "deflate-miniz",
+                feature = "deflate-zlib"
+            ))]
+            GenericZipWriter::Deflater(w) => Some(w.get_mut()),
+            #[cfg(any(feature = "deflate", feature = "deflate-miniz"))]
+            GenericZipWriter::DeflaterWithStrategy(w) => Some(w.get_mut()),
+            #[cfg(feature = "bzip2")]
+            GenericZipWriter::Bzip2(w) => Some(w.get_mut()),
+            #[cfg(feature = "zstd")]
+            GenericZipWriter::Zstd(w) => Some(w.get_mut()),
+        }
+    }
+
     fn get_plain(&mut self) -> &mut W {
         match *self {
             GenericZipWriter::Storer(MaybeEncrypted::Unencrypted(ref mut w)) => w,
@@ -1037,6 +3169,8 @@ impl<W: Write + io::Seek> GenericZipWriter<W> {
                 feature = "deflate-zlib"
             ))]
             GenericZipWriter::Deflater(..) => Some(CompressionMethod::Deflated),
+            #[cfg(any(feature = "deflate", feature = "deflate-miniz"))]
+            GenericZipWriter::DeflaterWithStrategy(..) => Some(CompressionMethod::Deflated),
             #[cfg(feature = "bzip2")]
             GenericZipWriter::Bzip2(..) => Some(CompressionMethod::Bzip2),
             #[cfg(feature = "zstd")]
@@ -1053,54 +3187,40 @@ impl<W: Write + io::Seek> GenericZipWriter<W> {
     }
 }
 
-#[cfg(any(
-    feature = "deflate",
-    feature = "deflate-miniz",
-    feature = "deflate-zlib"
-))]
-fn deflate_compression_level_range() -> std::ops::RangeInclusive<i32> {
-    let min = flate2::Compression::none().level() as i32;
-    let max = flate2::Compression::best().level() as i32;
-    min..=max
-}
-
-#[cfg(feature = "bzip2")]
-fn bzip2_compression_level_range() -> std::ops::RangeInclusive<i32> {
-    let min = bzip2::Compression::none().level() as i32;
-    let max = bzip2::Compression::best().level() as i32;
-    min..=max
-}
-
-#[cfg(any(
-    feature = "deflate",
-    feature = "deflate-miniz",
-    feature = "deflate-zlib",
-    feature = "bzip2",
-    feature = "zstd"
-))]
-fn clamp_opt<T: Ord + Copy>(value: T, range: std::ops::RangeInclusive<T>) -> Option<T> {
-    if range.contains(&value) {
-        Some(value)
-    } else {
-        None
+/// Check that `level` is a valid compression level for `method`, before writing anything
+fn validate_compression_level(method: CompressionMethod, level: Option<i32>) -> ZipResult<()> {
+    let level = match level {
+        None => return Ok(()),
+        Some(level) => level,
+    };
+    match method.level_range() {
+        Some(range) if range.contains(&level) => Ok(()),
+        Some(range) => Err(ZipError::Io(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "Compression level {level} is out of range for {method}, which accepts {} to {}",
+                range.start(),
+                range.end()
+            ),
+        ))),
+        None => Err(ZipError::Io(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{method} doesn't take a compression level"),
+        ))),
     }
 }
 
 fn write_local_file_header<T: Write>(writer: &mut T, file: &ZipFileData) -> ZipResult<()> {
+    // Before writing anything, since copied files can have extra data of any length
+    let extra_field_length = local_extra_length(file)?;
     // local file header signature
     writer.write_u32::<LittleEndian>(spec::LOCAL_FILE_HEADER_SIGNATURE)?;
     // version needed to extract
     writer.write_u16::<LittleEndian>(file.version_needed())?;
     // general purpose bit flag
-    let flag = if !file.file_name.is_ascii() {
-        1u16 << 11
-    } else {
-        0
-    } | if file.encrypted { 1u16 << 0 } else { 0 };
-    writer.write_u16::<LittleEndian>(flag)?;
+    writer.write_u16::<LittleEndian>(general_purpose_flags(file))?;
     // Compression method
-    #[allow(deprecated)]
-    writer.write_u16::<LittleEndian>(file.compression_method.to_u16())?;
+    writer.write_u16::<LittleEndian>(file.compression_method_id)?;
     // last mod file time and last mod file date
     writer.write_u16::<LittleEndian>(file.last_modified_time.timepart())?;
     writer.write_u16::<LittleEndian>(file.last_modified_time.datepart())?;
@@ -1115,20 +3235,339 @@ fn write_local_file_header<T: Write>(writer: &mut T, file: &ZipFileData) -> ZipR
         writer.write_u32::<LittleEndian>(file.uncompressed_size as u32)?;
     }
     // file name length
-    writer.write_u16::<LittleEndian>(file.file_name.as_bytes().len() as u16)?;
+    let name = encoded_name(file);
+    writer.write_u16::<LittleEndian>(name.len() as u16)?;
     // extra field length
-    let extra_field_length = if file.large_file { 20 } else { 0 } + file.extra_field.len() as u16;
     writer.write_u16::<LittleEndian>(extra_field_length)?;
     // file name
-    writer.write_all(file.file_name.as_bytes())?;
+    writer.write_all(&name)?;
     // zip64 extra field
     if file.large_file {
         write_local_zip64_extra_field(writer, file)?;
     }
+    // AES extra field
+    write_aes_extra_field(writer, file)?;
+    // extended timestamp extra field
+    write_extended_timestamp_field(writer, file, true)?;
+    // NTFS extra field
+    write_ntfs_field(writer, file)?;
+    // new Unix extra field
+    write_unix_ownership_field(writer, file)?;
+    // extended attributes extra field
+    write_xattrs_field(writer, file)?;
+    // Unicode Path extra field
+    write_unicode_path_field(writer, file)?;
+    // alignment padding
+    writer.write_all(&file.alignment_padding)?;
+    // extra field
+    writer.write_all(&file.extra_field)?;
+
+    Ok(())
+}
+
+/// Length of the extra fields that the writer adds to the local header of a file, which may not
+/// fit in a `u16`
+fn managed_local_extra_length(file: &ZipFileData) -> usize {
+    let zip64_length = if file.large_file { 20 } else { 0 };
+    let aes_length = if file.aes_mode.is_some() { 11 } else { 0 };
+    zip64_length
+        + aes_length
+        + extended_timestamp_length(file, true) as usize
+        + ntfs_length(file) as usize
+        + unix_ownership_length(file) as usize
+        + xattrs_field_length(&file.xattrs)
+        + unicode_path_length(file) as usize
+}
+
+/// Length of all the extra fields of the local header of a file
+///
+/// Fails if it doesn't fit in the `u16` of the header.
+fn local_extra_length(file: &ZipFileData) -> ZipResult<u16> {
+    extra_length(&[
+        managed_local_extra_length(file),
+        file.alignment_padding.len(),
+        file.extra_field.len(),
+    ])
+}
+
+/// Length of all the extra fields of the central header of a file, with a ZIP64 field of
+/// `zip64_length` bytes
+///
+/// Fails if it doesn't fit in the `u16` of the header.
+fn central_extra_length(file: &ZipFileData, zip64_length: usize) -> ZipResult<u16> {
+    let aes_length = if file.aes_mode.is_some() { 11 } else { 0 };
+    extra_length(&[
+        zip64_length,
+        aes_length,
+        extended_timestamp_length(file, false) as usize,
+        ntfs_length(file) as usize,
+        unix_ownership_length(file) as usize,
+        xattrs_field_length(&file.xattrs),
+        unicode_path_length(file) as usize,
+        file.extra_field.len(),
+    ])
+}
+
+/// Sum of the lengths of several extra fields, which must fit in a `u16`
+fn extra_length(lengths: &[usize]) -> ZipResult<u16> {
+    lengths
+        .iter()
+        .try_fold(0usize, |total, &length| total.checked_add(length))
+        .and_then(|length| u16::try_from(length).ok())
+        .ok_or_else(|| {
+            ZipError::Io(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Extra fields are longer than 65535 bytes",
+            ))
+        })
+}
+
+/// Bytes of the name field of a file
+fn encoded_name(file: &ZipFileData) -> Cow<'_, [u8]> {
+    if file.legacy_name_compat {
+        Cow::Owned(crate::cp437::to_cp437(&file.file_name))
+    } else {
+        Cow::Borrowed(file.file_name.as_bytes())
+    }
+}
+
+/// Bytes of the comment field of a file
+fn encoded_comment(file: &ZipFileData) -> Cow<'_, [u8]> {
+    if file.legacy_name_compat {
+        Cow::Owned(crate::cp437::to_cp437(&file.file_comment))
+    } else {
+        Cow::Borrowed(file.file_comment.as_bytes())
+    }
+}
+
+/// Whether the name and comment of a file are written in UTF-8, rather than IBM codepage 437
+fn has_utf8_flag(file: &ZipFileData) -> bool {
+    !file.legacy_name_compat && (!file.file_name.is_ascii() || !file.file_comment.is_ascii())
+}
+
+/// General purpose bit flag of a file, the same in its local and central headers
+fn general_purpose_flags(file: &ZipFileData) -> u16 {
+    let mut flags = 0;
+    if file.encrypted {
+        flags |= 1u16 << 0;
+    }
+    if file.using_data_descriptor {
+        flags |= 1u16 << 3;
+    }
+    if has_utf8_flag(file) {
+        flags |= 1u16 << 11;
+    }
+    flags
+}
+
+/// Length of the Info-ZIP Unicode Path extra field of a file, which is the same in both headers
+fn unicode_path_length(file: &ZipFileData) -> u16 {
+    if file.legacy_name_compat && !file.file_name.is_ascii() {
+        9 + file.file_name.len() as u16
+    } else {
+        0
+    }
+}
+
+/// Write the Info-ZIP Unicode Path extra field of a file, if its name is written in IBM codepage
+/// 437 without being ASCII
+fn write_unicode_path_field<T: Write>(writer: &mut T, file: &ZipFileData) -> ZipResult<()> {
+    let length = unicode_path_length(file);
+    if length > 0 {
+        writer.write_u16::<LittleEndian>(0x7075)?;
+        writer.write_u16::<LittleEndian>(length - 4)?;
+        writer.write_u8(1)?;
+        writer.write_u32::<LittleEndian>(crc32fast::hash(&encoded_name(file)))?;
+        writer.write_all(file.file_name.as_bytes())?;
+    }
+    Ok(())
+}
+
+/// Length of the NTFS extra field of a file, which is the same in both headers
+fn ntfs_length(file: &ZipFileData) -> u16 {
+    if file.ntfs_times.is_some() {
+        36
+    } else {
+        0
+    }
+}
+
+/// Write the NTFS extra field of a file, if it has one
+fn write_ntfs_field<T: Write>(writer: &mut T, file: &ZipFileData) -> ZipResult<()> {
+    if let Some(times) = file.ntfs_times {
+        writer.write_u16::<LittleEndian>(0x000a)?;
+        writer.write_u16::<LittleEndian>(32)?;
+        // reserved
+        writer.write_u32::<LittleEndian>(0)?;
+        // attribute 1, holding the times
+        writer.write_u16::<LittleEndian>(0x0001)?;
+        writer.write_u16::<LittleEndian>(24)?;
+        writer.write_u64::<LittleEndian>(times.mtime)?;
+        writer.write_u64::<LittleEndian>(times.atime)?;
+        writer.write_u64::<LittleEndian>(times.ctime)?;
+    }
+    Ok(())
+}
+
+/// Length of the new Unix extra field of a file, which is the same in both headers
+fn unix_ownership_length(file: &ZipFileData) -> u16 {
+    if file.unix_ownership.is_some() {
+        15
+    } else {
+        0
+    }
+}
+
+/// Length of the extended attributes extra field of a file, which is the same in both headers
+fn xattrs_length(file: &ZipFileData) -> u16 {
+    xattrs_field_length(&file.xattrs) as u16
+}
+
+/// Length of an extended attributes extra field, which may not fit in a `u16`
+fn xattrs_field_length(xattrs: &[(OsString, Vec<u8>)]) -> usize {
+    if xattrs.is_empty() {
+        return 0;
+    }
+    let attributes_length: usize = xattrs
+        .iter()
+        .map(|(name, value)| 4 + os_str_bytes(name).len() + value.len())
+        .sum();
+    4 + attributes_length
+}
+
+/// Write the extended attributes extra field of a file, if it has extended attributes
+fn write_xattrs_field<T: Write>(writer: &mut T, file: &ZipFileData) -> ZipResult<()> {
+    let length = xattrs_length(file);
+    if length > 0 {
+        writer.write_u16::<LittleEndian>(0x7861)?;
+        writer.write_u16::<LittleEndian>(length - 4)?;
+        for (name, value) in &file.xattrs {
+            let name = os_str_bytes(name);
+            writer.write_u16::<LittleEndian>(name.len() as u16)?;
+            writer.write_all(&name)?;
+            writer.write_u16::<LittleEndian>(value.len() as u16)?;
+            writer.write_all(value)?;
+        }
+    }
+    Ok(())
+}
+
+/// Bytes of an extended attribute name: the name itself on Unix, and its UTF-8 encoding elsewhere
+fn os_str_bytes(name: &OsStr) -> Cow<'_, [u8]> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStrExt;
+        Cow::Borrowed(name.as_bytes())
+    }
+    #[cfg(not(unix))]
+    match name.to_string_lossy() {
+        Cow::Borrowed(name) => Cow::Borrowed(name.as_bytes()),
+        Cow::Owned(name) => Cow::Owned(name.into_bytes()),
+    }
+}
+
+/// Write the Info-ZIP new Unix extra field of a file, if it has an owner
+fn write_unix_ownership_field<T: Write>(writer: &mut T, file: &ZipFileData) -> ZipResult<()> {
+    if let Some((uid, gid)) = file.unix_ownership {
+        writer.write_u16::<LittleEndian>(0x7875)?;
+        writer.write_u16::<LittleEndian>(11)?;
+        writer.write_u8(1)?;
+        writer.write_u8(4)?;
+        writer.write_u32::<LittleEndian>(uid)?;
+        writer.write_u8(4)?;
+        writer.write_u32::<LittleEndian>(gid)?;
+    }
+    Ok(())
+}
+
+/// Length of the extended timestamp extra field of a file, in its local or central header
+fn extended_timestamp_length(file: &ZipFileData, local: bool) -> u16 {
+    if file.unix_mtime.is_none() && file.unix_atime.is_none() {
+        return 0;
+    }
+    let mut length = 5;
+    if file.unix_mtime.is_some() {
+        length += 4;
+    }
+    if local && file.unix_atime.is_some() {
+        length += 4;
+    }
+    length
+}
+
+/// Write the extended timestamp extra field of a file, if it has one
+///
+/// The central header only holds the modification time, but its flags are the same as in the
+/// local header.
+fn write_extended_timestamp_field<T: Write>(
+    writer: &mut T,
+    file: &ZipFileData,
+    local: bool,
+) -> ZipResult<()> {
+    let length = extended_timestamp_length(file, local);
+    if length == 0 {
+        return Ok(());
+    }
+    writer.write_u16::<LittleEndian>(0x5455)?;
+    writer.write_u16::<LittleEndian>(length - 4)?;
+    let flags = if file.unix_mtime.is_some() { 0x01 } else { 0 }
+        | if file.unix_atime.is_some() { 0x02 } else { 0 };
+    writer.write_u8(flags)?;
+    if let Some(mtime) = file.unix_mtime {
+        writer.write_i32::<LittleEndian>(mtime as i32)?;
+    }
+    if let (true, Some(atime)) = (local, file.unix_atime) {
+        writer.write_i32::<LittleEndian>(atime as i32)?;
+    }
+    Ok(())
+}
 
+/// Write the AES extra field of a file, if it's encrypted with AES
+fn write_aes_extra_field<T: Write>(writer: &mut T, file: &ZipFileData) -> ZipResult<()> {
+    if let Some((mode, vendor_version)) = file.aes_mode {
+        writer.write_u16::<LittleEndian>(0x9901)?;
+        writer.write_u16::<LittleEndian>(7)?;
+        writer.write_u16::<LittleEndian>(match vendor_version {
+            AesVendorVersion::Ae1 => 0x0001,
+            AesVendorVersion::Ae2 => 0x0002,
+        })?;
+        writer.write_all(b"AE")?;
+        writer.write_u8(match mode {
+            AesMode::Aes128 => 0x01,
+            AesMode::Aes192 => 0x02,
+            AesMode::Aes256 => 0x03,
+        })?;
+        #[allow(deprecated)]
+        writer.write_u16::<LittleEndian>(file.compression_method.to_u16())?;
+    }
     Ok(())
 }
 
+/// Whether `file` has a local ZIP64 extra field, or room reserved for one
+fn can_use_zip64(file: &ZipFileData) -> bool {
+    let padding_length = file.alignment_padding.len();
+    file.large_file || padding_length == 20 || padding_length >= 24
+}
+
+/// Turn the first 20 bytes of the alignment padding of `file`, where [`new_file_data`] reserves
+/// room for a ZIP64 extra field, into that field
+///
+/// The header keeps its length, as the rest of the padding stays a padding field. Gives `false`
+/// if the padding is too short.
+fn use_reserved_zip64(file: &mut ZipFileData) -> bool {
+    let padding_length = file.alignment_padding.len();
+    if padding_length != 20 && padding_length < 24 {
+        return false;
+    }
+    file.alignment_padding.truncate(padding_length - 20);
+    if padding_length > 20 {
+        let data_length = (padding_length - 24) as u16;
+        file.alignment_padding[2..4].copy_from_slice(&data_length.to_le_bytes());
+    }
+    file.large_file = true;
+    true
+}
+
 fn update_local_file_header<T: Write + io::Seek>(
     writer: &mut T,
     file: &ZipFileData,
@@ -1153,44 +3592,108 @@ fn update_local_file_header<T: Write + io::Seek>(
     Ok(())
 }
 
-fn write_central_directory_header<T: Write>(writer: &mut T, file: &ZipFileData) -> ZipResult<()> {
-    // buffer zip64 extra field to determine its variable length
-    let mut zip64_extra_field = [0; 28];
-    let zip64_extra_field_length =
-        write_central_zip64_extra_field(&mut zip64_extra_field.as_mut(), file)?;
+/// Write the data of a file again without compression, if it isn't longer than its compressed
+/// data, and the stored data can end where the compressed data ends
+///
+/// A padding extra field is added at the end of the local header for the difference, so its
+/// length must be 0 or at least 4, and keep the data aligned.
+fn store_if_larger<T: Write + io::Seek>(
+    writer: &mut T,
+    file: &mut ZipFileData,
+    copy: StoreIfLarger,
+) -> ZipResult<()> {
+    if file.compressed_size < file.uncompressed_size
+        || file.uncompressed_size != copy.data.len() as u64
+    {
+        return Ok(());
+    }
+    let padding = file.compressed_size - file.uncompressed_size;
+    let data_start = file.data_start.load();
+    let extra_length = data_start - file.header_start - 30 - encoded_name(file).len() as u64;
+    if (1..4).contains(&padding)
+        || padding % copy.alignment as u64 != 0
+        || extra_length + padding > u16::MAX as u64
+    {
+        return Ok(());
+    }
 
-    // central file header signature
-    writer.write_u32::<LittleEndian>(spec::CENTRAL_DIRECTORY_HEADER_SIGNATURE)?;
-    // version made by
-    let version_made_by = (file.system as u16) << 8 | (file.version_made_by as u16);
-    writer.write_u16::<LittleEndian>(version_made_by)?;
-    // version needed to extract
-    writer.write_u16::<LittleEndian>(file.version_needed())?;
-    // general puprose bit flag
-    let flag = if !file.file_name.is_ascii() {
-        1u16 << 11
-    } else {
-        0
-    } | if file.encrypted { 1u16 << 0 } else { 0 };
-    writer.write_u16::<LittleEndian>(flag)?;
     // compression method
-    #[allow(deprecated)]
-    writer.write_u16::<LittleEndian>(file.compression_method.to_u16())?;
-    // last mod file time + date
-    writer.write_u16::<LittleEndian>(file.last_modified_time.timepart())?;
-    writer.write_u16::<LittleEndian>(file.last_modified_time.datepart())?;
-    // crc-32
-    writer.write_u32::<LittleEndian>(file.crc32)?;
-    // compressed size
-    writer.write_u32::<LittleEndian>(file.compressed_size.min(spec::ZIP64_BYTES_THR) as u32)?;
+    writer.seek(io::SeekFrom::Start(file.header_start + 8))?;
+    writer.write_u16::<LittleEndian>(0)?;
+    // extra field length
+    writer.seek(io::SeekFrom::Start(file.header_start + 28))?;
+    writer.write_u16::<LittleEndian>((extra_length + padding) as u16)?;
+    writer.seek(io::SeekFrom::Start(data_start))?;
+    if padding > 0 {
+        writer.write_u16::<LittleEndian>(copy.alignment_padding_id)?;
+        writer.write_u16::<LittleEndian>(padding as u16 - 4)?;
+        writer.write_all(&vec![0; padding as usize - 4])?;
+    }
+    writer.write_all(&copy.data)?;
+
+    file.compression_method = CompressionMethod::Stored;
+    file.compression_method_id = 0;
+    file.compression_level = None;
+    file.compressed_size = file.uncompressed_size;
+    file.data_start.store(data_start + padding);
+    Ok(())
+}
+
+/// Write the CRC and sizes of a file following its data, for files using a data descriptor
+fn write_data_descriptor<T: Write>(writer: &mut T, file: &ZipFileData) -> ZipResult<()> {
+    writer.write_u32::<LittleEndian>(spec::DATA_DESCRIPTOR_SIGNATURE)?;
+    writer.write_u32::<LittleEndian>(file.crc32)?;
+    if file.large_file {
+        writer.write_u64::<LittleEndian>(file.compressed_size)?;
+        writer.write_u64::<LittleEndian>(file.uncompressed_size)?;
+    } else {
+        writer.write_u32::<LittleEndian>(file.compressed_size as u32)?;
+        writer.write_u32::<LittleEndian>(file.uncompressed_size as u32)?;
+    }
+    Ok(())
+}
+
+/// Write the central directory header of `file`, in an archive starting at `archive_offset`
+fn write_central_directory_header<T: Write>(
+    writer: &mut T,
+    file: &ZipFileData,
+    archive_offset: u64,
+) -> ZipResult<()> {
+    let header_start = file.header_start - archive_offset;
+    // buffer zip64 extra field to determine its variable length
+    let mut zip64_extra_field = [0; 28];
+    let zip64_extra_field_length =
+        write_central_zip64_extra_field(&mut zip64_extra_field.as_mut(), file, header_start)?;
+    let extra_field_length = central_extra_length(file, zip64_extra_field_length as usize)?;
+
+    // central file header signature
+    writer.write_u32::<LittleEndian>(spec::CENTRAL_DIRECTORY_HEADER_SIGNATURE)?;
+    // version made by
+    let version_made_by = (file.system as u16) << 8 | (file.version_made_by as u16);
+    writer.write_u16::<LittleEndian>(version_made_by)?;
+    // version needed to extract
+    writer.write_u16::<LittleEndian>(file.version_needed())?;
+    // general puprose bit flag
+    writer.write_u16::<LittleEndian>(general_purpose_flags(file))?;
+    // compression method
+    writer.write_u16::<LittleEndian>(file.compression_method_id)?;
+    // last mod file time + date
+    writer.write_u16::<LittleEndian>(file.last_modified_time.timepart())?;
+    writer.write_u16::<LittleEndian>(file.last_modified_time.datepart())?;
+    // crc-32
+    writer.write_u32::<LittleEndian>(file.crc32)?;
+    // compressed size
+    writer.write_u32::<LittleEndian>(file.compressed_size.min(spec::ZIP64_BYTES_THR) as u32)?;
     // uncompressed size
     writer.write_u32::<LittleEndian>(file.uncompressed_size.min(spec::ZIP64_BYTES_THR) as u32)?;
     // file name length
-    writer.write_u16::<LittleEndian>(file.file_name.as_bytes().len() as u16)?;
+    let name = encoded_name(file);
+    writer.write_u16::<LittleEndian>(name.len() as u16)?;
     // extra field length
-    writer.write_u16::<LittleEndian>(zip64_extra_field_length + file.extra_field.len() as u16)?;
+    writer.write_u16::<LittleEndian>(extra_field_length)?;
     // file comment length
-    writer.write_u16::<LittleEndian>(0)?;
+    let comment = encoded_comment(file);
+    writer.write_u16::<LittleEndian>(comment.len() as u16)?;
     // disk number start
     writer.write_u16::<LittleEndian>(0)?;
     // internal file attribytes
@@ -1198,22 +3701,125 @@ fn write_central_directory_header<T: Write>(writer: &mut T, file: &ZipFileData)
     // external file attributes
     writer.write_u32::<LittleEndian>(file.external_attributes)?;
     // relative offset of local header
-    writer.write_u32::<LittleEndian>(file.header_start.min(spec::ZIP64_BYTES_THR) as u32)?;
+    writer.write_u32::<LittleEndian>(header_start.min(spec::ZIP64_BYTES_THR) as u32)?;
     // file name
-    writer.write_all(file.file_name.as_bytes())?;
+    writer.write_all(&name)?;
     // zip64 extra field
     writer.write_all(&zip64_extra_field[..zip64_extra_field_length as usize])?;
+    // AES extra field
+    write_aes_extra_field(writer, file)?;
+    // extended timestamp extra field
+    write_extended_timestamp_field(writer, file, false)?;
+    // NTFS extra field
+    write_ntfs_field(writer, file)?;
+    // new Unix extra field
+    write_unix_ownership_field(writer, file)?;
+    // extended attributes extra field
+    write_xattrs_field(writer, file)?;
+    // Unicode Path extra field
+    write_unicode_path_field(writer, file)?;
     // extra field
     writer.write_all(&file.extra_field)?;
     // file comment
-    // <none>
+    writer.write_all(&comment)?;
 
     Ok(())
 }
 
-fn validate_extra_data(file: &ZipFileData) -> ZipResult<()> {
-    let mut data = file.extra_field.as_slice();
+/// Write the central directory headers of `files` and the records following them, at
+/// `central_start` in an archive starting at `archive_offset`
+///
+/// If the archive would end before `min_end`, zeros are written before the central directory,
+/// so that the archive ends there and nothing written before is left after it.
+fn write_central_directory<T: Write>(
+    writer: &mut T,
+    files: &[&ZipFileData],
+    central_start: u64,
+    min_end: u64,
+    comment: &[u8],
+    archive_offset: u64,
+) -> ZipResult<()> {
+    let directory_at = |central_start: u64| -> ZipResult<Vec<u8>> {
+        let mut directory = Vec::new();
+        for file in files {
+            write_central_directory_header(&mut directory, file, archive_offset)?;
+        }
+        let central_size = directory.len() as u64;
+        write_central_directory_end(
+            &mut directory,
+            files.len(),
+            central_start,
+            central_size,
+            comment,
+            archive_offset,
+        )?;
+        Ok(directory)
+    };
+    let mut directory = directory_at(central_start)?;
+    let end = central_start + directory.len() as u64;
+    if end < min_end {
+        // The end records only grow if the padding makes them switch to ZIP64
+        let padding = min_end - end;
+        io::copy(&mut io::repeat(0).take(padding), writer)?;
+        directory = directory_at(central_start + padding)?;
+    }
+    writer.write_all(&directory)?;
+    Ok(())
+}
 
+/// Write the records following the central directory headers, in an archive starting at
+/// `archive_offset`
+fn write_central_directory_end<T: Write>(
+    writer: &mut T,
+    number_of_files: usize,
+    central_start: u64,
+    central_size: u64,
+    comment: &[u8],
+    archive_offset: u64,
+) -> ZipResult<()> {
+    let central_start = central_start - archive_offset;
+    // A count of 0xFFFF is the placeholder telling readers to look for the ZIP64 record
+    if number_of_files >= spec::ZIP64_ENTRY_THR
+        || central_size.max(central_start) >= spec::ZIP64_BYTES_THR
+    {
+        let zip64_footer = spec::Zip64CentralDirectoryEnd {
+            version_made_by: DEFAULT_VERSION as u16,
+            version_needed_to_extract: DEFAULT_VERSION as u16,
+            disk_number: 0,
+            disk_with_central_directory: 0,
+            number_of_files_on_this_disk: number_of_files as u64,
+            number_of_files: number_of_files as u64,
+            central_directory_size: central_size,
+            central_directory_offset: central_start,
+        };
+
+        zip64_footer.write(writer)?;
+
+        let zip64_footer = spec::Zip64CentralDirectoryEndLocator {
+            disk_with_central_directory: 0,
+            end_of_central_directory_offset: central_start + central_size,
+            number_of_disks: 1,
+        };
+
+        zip64_footer.write(writer)?;
+    }
+
+    let number_of_files = number_of_files.min(spec::ZIP64_ENTRY_THR) as u16;
+    let footer = spec::CentralDirectoryEnd {
+        disk_number: 0,
+        disk_with_central_directory: 0,
+        zip_file_comment: comment.to_vec(),
+        number_of_files_on_this_disk: number_of_files,
+        number_of_files,
+        central_directory_size: central_size.min(spec::ZIP64_BYTES_THR) as u32,
+        central_directory_offset: central_start.min(spec::ZIP64_BYTES_THR) as u32,
+    };
+
+    footer.write(writer)?;
+    Ok(())
+}
+
+fn validate_extra_data(mut data: &[u8]) -> ZipResult<()> {
     if data.len() > spec::ZIP64_ENTRY_THR {
         return Err(ZipError::Io(io::Error::new(
             io::ErrorKind::InvalidData,
@@ -1239,10 +3845,16 @@ fn validate_extra_data(file: &ZipFileData) -> ZipResult<()> {
                 "No custom ZIP64 extra data allowed",
             )));
         }
+        if kind == 0x9901 {
+            return Err(ZipError::Io(io::Error::new(
+                io::ErrorKind::Other,
+                "No custom AES extra data allowed",
+            )));
+        }
 
         #[cfg(not(feature = "unreserved"))]
         {
-            if kind <= 31 || EXTRA_FIELD_MAPPING.iter().any(|&mapped| mapped == kind) {
+            if kind <= 31 || EXTRA_FIELD_MAPPING.contains(&kind) {
                 return Err(ZipError::Io(io::Error::new(
                     io::ErrorKind::Other,
                     format!(
@@ -1281,7 +3893,7 @@ fn update_local_zip64_extra_field<T: Write + io::Seek>(
     writer: &mut T,
     file: &ZipFileData,
 ) -> ZipResult<()> {
-    let zip64_extra_field = file.header_start + 30 + file.file_name.as_bytes().len() as u64;
+    let zip64_extra_field = file.header_start + 30 + encoded_name(file).len() as u64;
     writer.seek(io::SeekFrom::Start(zip64_extra_field + 4))?;
     writer.write_u64::<LittleEndian>(file.uncompressed_size)?;
     writer.write_u64::<LittleEndian>(file.compressed_size)?;
@@ -1290,7 +3902,11 @@ fn update_local_zip64_extra_field<T: Write + io::Seek>(
     Ok(())
 }
 
-fn write_central_zip64_extra_field<T: Write>(writer: &mut T, file: &ZipFileData) -> ZipResult<u16> {
+fn write_central_zip64_extra_field<T: Write>(
+    writer: &mut T,
+    file: &ZipFileData,
+    header_start: u64,
+) -> ZipResult<u16> {
     // The order of the fields in the zip64 extended
     // information record is fixed, but the fields MUST
     // only appear if the corresponding Local or Central
@@ -1298,14 +3914,14 @@ fn write_central_zip64_extra_field<T: Write>(writer: &mut T, file: &ZipFileData)
     let mut size = 0;
     let uncompressed_size = file.uncompressed_size > spec::ZIP64_BYTES_THR;
     let compressed_size = file.compressed_size > spec::ZIP64_BYTES_THR;
-    let header_start = file.header_start > spec::ZIP64_BYTES_THR;
+    let large_header_start = header_start >= spec::ZIP64_BYTES_THR;
     if uncompressed_size {
         size += 8;
     }
     if compressed_size {
         size += 8;
     }
-    if header_start {
+    if large_header_start {
         size += 8;
     }
     if size > 0 {
@@ -1319,8 +3935,8 @@ fn write_central_zip64_extra_field<T: Write>(writer: &mut T, file: &ZipFileData)
         if compressed_size {
             writer.write_u64::<LittleEndian>(file.compressed_size)?;
         }
-        if header_start {
-            writer.write_u64::<LittleEndian>(file.header_start)?;
+        if large_header_start {
+            writer.write_u64::<LittleEndian>(header_start)?;
         }
         // Excluded fields:
         // u32: disk start number
@@ -1328,6 +3944,34 @@ fn write_central_zip64_extra_field<T: Write>(writer: &mut T, file: &ZipFileData)
     Ok(size)
 }
 
+/// Convert a relative path to a file name, for [`ZipWriter::start_file_from_path`]
+pub(crate) fn path_to_zip_name(path: &std::path::Path) -> ZipResult<String> {
+    let invalid =
+        |message: &'static str| ZipError::Io(io::Error::new(io::ErrorKind::InvalidInput, message));
+    let path = path
+        .to_str()
+        .ok_or_else(|| invalid("Path is not valid UTF-8"))?;
+    if path.starts_with(['/', '\\']) {
+        return Err(invalid("Path is absolute"));
+    }
+    let bytes = path.as_bytes();
+    if bytes.len() >= 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':' {
+        return Err(invalid("Path starts with a drive"));
+    }
+    let mut components = Vec::new();
+    for component in path.split(['/', '\\']) {
+        match component {
+            "" | "." => {}
+            ".." => return Err(invalid("Path has a '..' component")),
+            _ => components.push(component),
+        }
+    }
+    if components.is_empty() {
+        return Err(invalid("Path is empty"));
+    }
+    Ok(components.join("/"))
+}
+
 fn path_to_string(path: &std::path::Path) -> String {
     let mut path_str = String::new();
     for component in path.components() {
@@ -1341,11 +3985,20 @@ fn path_to_string(path: &std::path::Path) -> String {
     path_str
 }
 
+#[cfg(not(feature = "unreserved"))]
+const EXTRA_FIELD_MAPPING: [u16; 49] = [
+    0x0001, 0x0007, 0x0008, 0x0009, 0x000a, 0x000c, 0x000d, 0x000e, 0x000f, 0x0014, 0x0015, 0x0016,
+    0x0017, 0x0018, 0x0019, 0x0020, 0x0021, 0x0022, 0x0023, 0x0065, 0x0066, 0x4690, 0x07c8, 0x2605,
+    0x2705, 0x2805, 0x334d, 0x4341, 0x4453, 0x4704, 0x470f, 0x4b46, 0x4c41, 0x4d49, 0x4f4c, 0x5356,
+    0x5455, 0x554e, 0x5855, 0x6375, 0x6542, 0x7075, 0x756e, 0x7855, 0xa11e, 0xa220, 0xfd4a, 0x9901,
+    0x9902,
+];
+
 #[cfg(test)]
 mod test {
     use super::{FileOptions, ZipWriter};
     use crate::compression::CompressionMethod;
-    use crate::types::DateTime;
+    use crate::types::{AesVendorVersion, DateTime, System, DEFAULT_VERSION};
     use std::io;
     use std::io::Write;
 
@@ -1457,6 +4110,18 @@ mod test {
         );
     }
 
+    #[test]
+    fn write_symlink_invalid() {
+        let mut writer = ZipWriter::new(io::Cursor::new(Vec::new()));
+        assert!(writer
+            .add_symlink("directory/", "target", FileOptions::default())
+            .is_err());
+        assert!(writer
+            .add_symlink("name", "tar\0get", FileOptions::default())
+            .is_err());
+        assert_eq!(writer.finish().unwrap().into_inner().len(), 22);
+    }
+
     #[test]
     fn write_mimetype_zip() {
         let mut writer = ZipWriter::new(io::Cursor::new(Vec::new()));
@@ -1465,8 +4130,29 @@ mod test {
             compression_level: None,
             last_modified_time: DateTime::default(),
             permissions: Some(33188),
-            large_file: false,
+            dir_permissions: None,
+            large_file: Some(false),
+            reserve_zip64: false,
             encrypt_with: None,
+            aes_encryption: None,
+            aes_vendor_version: AesVendorVersion::Ae2,
+            legacy_zstd_method_id: false,
+            alignment: 1,
+            alignment_padding_id: 0xd935,
+            comment: "",
+            unix_mtime: None,
+            unix_atime: None,
+            ntfs_times: None,
+            unix_ownership: None,
+            xattrs: &[],
+            legacy_name_compat: false,
+            system: System::Unix,
+            version_made_by: DEFAULT_VERSION,
+            dos_attributes: None,
+            zstd_dictionary: None,
+            deflate_options: None,
+            store_if_larger: false,
+            store_if_larger_buffer: super::DEFAULT_STORE_IF_LARGER_BUFFER,
         };
         writer.start_file("mimetype", options).unwrap();
         writer
@@ -1480,6 +4166,336 @@ mod test {
         assert_eq!(result.get_ref(), &v);
     }
 
+    #[cfg(feature = "zstd")]
+    fn zstd_archive(legacy_method_id: bool) -> Vec<u8> {
+        let options = FileOptions::default()
+            .compression_method(CompressionMethod::Zstd)
+            .legacy_zstd_method_id(legacy_method_id);
+        let mut writer = ZipWriter::new(io::Cursor::new(Vec::new()));
+        writer.start_file("zstd", options).unwrap();
+        writer.write_all(b"compressed with zstandard").unwrap();
+        writer.finish().unwrap().into_inner()
+    }
+
+    /// Get the compression method IDs in the local and central headers of a single-file archive
+    #[cfg(feature = "zstd")]
+    fn method_ids(archive: &[u8]) -> (u16, u16) {
+        let central = archive.len() - 22 - 46 - "zstd".len();
+        (
+            u16::from_le_bytes([archive[8], archive[9]]),
+            u16::from_le_bytes([archive[central + 10], archive[central + 11]]),
+        )
+    }
+
+    #[test]
+    #[cfg(feature = "zstd")]
+    fn zstd_method_ids() {
+        use crate::ZipArchive;
+        use std::io::Read;
+
+        for (legacy, id) in [(false, 93), (true, 20)] {
+            let archive = zstd_archive(legacy);
+            assert_eq!(method_ids(&archive), (id, id));
+
+            let mut archive = ZipArchive::new(io::Cursor::new(archive)).unwrap();
+            let mut file = archive.by_index(0).unwrap();
+            assert_eq!(file.compression(), CompressionMethod::Zstd);
+            let mut contents = String::new();
+            file.read_to_string(&mut contents).unwrap();
+            assert_eq!(contents, "compressed with zstandard");
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "zstd")]
+    fn raw_copy_keeps_zstd_method_id() {
+        use crate::ZipArchive;
+
+        for id in [93, 20] {
+            let mut source = ZipArchive::new(io::Cursor::new(zstd_archive(id == 20))).unwrap();
+            let mut writer = ZipWriter::new(io::Cursor::new(Vec::new()));
+            writer.raw_copy_file(source.by_index(0).unwrap()).unwrap();
+            let copy = writer.finish().unwrap().into_inner();
+            assert_eq!(method_ids(&copy), (id, id));
+        }
+    }
+
+    #[test]
+    fn raw_copy_rename() {
+        use crate::ZipArchive;
+        use std::io::Read;
+
+        let mut writer = ZipWriter::new(io::Cursor::new(Vec::new()));
+        for name in ["assets/a.txt", "assets/b.txt"] {
+            writer.start_file(name, FileOptions::default()).unwrap();
+            writer.write_all(name.repeat(100).as_bytes()).unwrap();
+        }
+        let mut source = ZipArchive::new(writer.finish().unwrap()).unwrap();
+
+        let renames = [
+            ("assets/a.txt", "v2/assets/a.txt"),
+            ("assets/b.txt", "b.txt"),
+            ("assets/a.txt", "ressources/é.txt"),
+        ];
+        let mut writer = ZipWriter::new(io::Cursor::new(Vec::new()));
+        for (old_name, new_name) in renames {
+            writer
+                .raw_copy_file_rename(source.by_name(old_name).unwrap(), new_name)
+                .unwrap();
+        }
+        let mut copy = ZipArchive::new(writer.finish().unwrap()).unwrap();
+
+        let raw_data = |file: &mut crate::read::ZipFile| {
+            let mut data = Vec::new();
+            file.get_raw_reader().read_to_end(&mut data).unwrap();
+            data
+        };
+        for (old_name, new_name) in renames {
+            let mut old = source.by_name(old_name).unwrap();
+            let mut new = copy.by_name(new_name).unwrap();
+            assert_eq!(new.crc32(), old.crc32());
+            assert_eq!(new.compression(), old.compression());
+            assert_eq!(raw_data(&mut new), raw_data(&mut old));
+            drop(new);
+
+            let mut contents = String::new();
+            copy.by_name(new_name)
+                .unwrap()
+                .read_to_string(&mut contents)
+                .unwrap();
+            assert_eq!(contents, old_name.repeat(100));
+        }
+
+        // The new name is not ASCII, so the copy needs the UTF-8 flag
+        let header_start = copy.by_index(2).unwrap().header_start() as usize;
+        let archive = copy.into_inner().into_inner();
+        assert_ne!(archive[header_start + 7] & 0b1000, 0);
+    }
+
+    #[test]
+    fn copy_files() {
+        use crate::result::ZipError;
+        use crate::ZipArchive;
+        use std::io::Read;
+
+        let mut writer = ZipWriter::new(io::Cursor::new(Vec::new()));
+        writer
+            .start_file("en/strings.txt", FileOptions::default())
+            .unwrap();
+        writer.write_all(&b"localized strings".repeat(100)).unwrap();
+        writer
+            .deep_copy_file("en/strings.txt", "en-GB/strings.txt")
+            .unwrap();
+        writer
+            .shallow_copy_file("en/strings.txt", "en-US/strings.txt")
+            .unwrap();
+        writer
+            .start_file("fr/strings.txt", FileOptions::default())
+            .unwrap();
+        writer.write_all(b"cha\xc3\xaenes").unwrap();
+        writer
+            .deep_copy_file("fr/strings.txt", "fr-CA/strings.txt")
+            .unwrap();
+
+        assert!(writer
+            .deep_copy_file("en/strings.txt", "fr/strings.txt")
+            .is_err());
+        assert!(writer
+            .shallow_copy_file("en/strings.txt", "fr/strings.txt")
+            .is_err());
+        assert!(matches!(
+            writer.deep_copy_file("de/strings.txt", "de-AT/strings.txt"),
+            Err(ZipError::FileNotFound)
+        ));
+
+        // Files read when appending can be copied too
+        let archive = writer.finish().unwrap();
+        let mut writer = ZipWriter::new_append(archive).unwrap();
+        writer
+            .deep_copy_file("fr/strings.txt", "fr-BE/strings.txt")
+            .unwrap();
+        let archive = writer.finish().unwrap();
+
+        let mut archive = ZipArchive::new(archive).unwrap();
+        let expected = [
+            ("en/strings.txt", "localized strings".repeat(100)),
+            ("en-GB/strings.txt", "localized strings".repeat(100)),
+            ("en-US/strings.txt", "localized strings".repeat(100)),
+            ("fr/strings.txt", "cha\u{ee}nes".to_owned()),
+            ("fr-CA/strings.txt", "cha\u{ee}nes".to_owned()),
+            ("fr-BE/strings.txt", "cha\u{ee}nes".to_owned()),
+        ];
+        assert_eq!(archive.len(), expected.len());
+        for (index, (name, contents)) in expected.iter().enumerate() {
+            let mut file = archive.by_index(index).unwrap();
+            assert_eq!(file.name(), *name);
+            let mut data = String::new();
+            file.read_to_string(&mut data).unwrap();
+            assert_eq!(data, *contents);
+        }
+    }
+
+    #[test]
+    fn copies_keep_alignment() {
+        use crate::ZipArchive;
+        use std::io::Read;
+
+        let stored = FileOptions::default().compression_method(CompressionMethod::Stored);
+        let mut writer = ZipWriter::new(io::Cursor::new(Vec::new()));
+        writer.start_file("first.txt", stored).unwrap();
+        writer.write_all(b"first").unwrap();
+        writer
+            .start_file("aligned.bin", stored.alignment(4096))
+            .unwrap();
+        writer.write_all(&[0xab; 100]).unwrap();
+        writer.start_file("unaligned.bin", stored).unwrap();
+        writer.write_all(&[0xcd; 100]).unwrap();
+        writer
+            .deep_copy_file("aligned.bin", "aligned copy.bin")
+            .unwrap();
+        let mut source = ZipArchive::new(writer.finish().unwrap()).unwrap();
+
+        let mut writer = ZipWriter::new(io::Cursor::new(Vec::new()));
+        writer.start_file("other.txt", stored).unwrap();
+        writer.write_all(b"other").unwrap();
+        for name in ["aligned.bin", "unaligned.bin", "aligned copy.bin"] {
+            writer.raw_copy_file(source.by_name(name).unwrap()).unwrap();
+        }
+        let mut copy = ZipArchive::new(writer.finish().unwrap()).unwrap();
+
+        for archive in [&mut source, &mut copy] {
+            for (name, alignment) in [("aligned.bin", 4096), ("aligned copy.bin", 4096)] {
+                let mut file = archive.by_name(name).unwrap();
+                assert_eq!(file.data_start() % alignment, 0);
+                let mut data = Vec::new();
+                file.read_to_end(&mut data).unwrap();
+                assert_eq!(data, [0xab; 100]);
+            }
+        }
+        // Data that wasn't aligned isn't padded, and the room reserved for a ZIP64 field isn't kept
+        let file = copy.by_name("unaligned.bin").unwrap();
+        assert_eq!(file.data_start() - file.header_start(), 30 + 13);
+    }
+
+    #[test]
+    fn abort_file() {
+        use crate::ZipArchive;
+        use std::io::Read;
+
+        let mut writer = ZipWriter::new(io::Cursor::new(Vec::new()));
+        assert!(writer.abort_file().is_err());
+        writer.start_file("first", FileOptions::default()).unwrap();
+        writer.write_all(b"first contents").unwrap();
+        writer.start_file("broken", FileOptions::default()).unwrap();
+        writer.write_all(&[0xab; 1000]).unwrap();
+        writer.abort_file().unwrap();
+        assert!(writer.abort_file().is_err());
+        writer.start_file("second", FileOptions::default()).unwrap();
+        writer.write_all(&[0xcd; 2000]).unwrap();
+        let archive = writer.finish().unwrap();
+
+        let mut archive = ZipArchive::new(archive).unwrap();
+        assert_eq!(archive.len(), 2);
+        let mut contents = Vec::new();
+        archive
+            .by_index(0)
+            .unwrap()
+            .read_to_end(&mut contents)
+            .unwrap();
+        assert_eq!(contents, b"first contents");
+        let mut file = archive.by_index(1).unwrap();
+        assert_eq!(file.name(), "second");
+        contents.clear();
+        file.read_to_end(&mut contents).unwrap();
+        assert_eq!(contents, [0xcd; 2000]);
+    }
+
+    #[test]
+    fn reserved_zip64_field() {
+        use crate::spec;
+        use byteorder::{ByteOrder, LittleEndian};
+
+        let options = FileOptions::default().compression_method(CompressionMethod::Stored);
+        let write = |size: Option<u64>| {
+            let mut writer = ZipWriter::new(io::Cursor::new(Vec::new()));
+            writer.start_file("file.txt", options).unwrap();
+            writer.write_all(b"data").unwrap();
+            if let Some(size) = size {
+                // Pretend that this much went through, to cross the threshold without writing it
+                writer.stats.bytes_written = size;
+            }
+            writer.finish().unwrap().into_inner()
+        };
+        let extra_start = 30 + "file.txt".len();
+
+        // A small file keeps the room in a padding field
+        let small = write(None);
+        assert_eq!(LittleEndian::read_u16(&small[4..]), 20);
+        assert_eq!(LittleEndian::read_u32(&small[18..]), 4);
+        assert_eq!(LittleEndian::read_u32(&small[22..]), 4);
+        let extra = &small[extra_start..];
+        assert_eq!(LittleEndian::read_u16(&small[28..]), 20);
+        assert_eq!(LittleEndian::read_u16(extra), 0xd935);
+        assert_eq!(LittleEndian::read_u16(&extra[2..]), 16);
+
+        // A large one gets a ZIP64 field in its place, with the data where it was
+        let size = spec::ZIP64_BYTES_THR + 1;
+        let large = write(Some(size));
+        assert_eq!(LittleEndian::read_u16(&large[4..]), 45);
+        assert_eq!(LittleEndian::read_u32(&large[18..]), u32::MAX);
+        assert_eq!(LittleEndian::read_u32(&large[22..]), u32::MAX);
+        assert_eq!(large[28..30], small[28..30]);
+        let extra = &large[extra_start..];
+        assert_eq!(LittleEndian::read_u16(extra), 0x0001);
+        assert_eq!(LittleEndian::read_u16(&extra[2..]), 16);
+        assert_eq!(LittleEndian::read_u64(&extra[4..]), size);
+        assert_eq!(LittleEndian::read_u64(&extra[12..]), 4);
+        let data_start = extra_start + 20;
+        assert_eq!(large[data_start..data_start + 4], *b"data");
+    }
+
+    #[test]
+    fn abort_last_file() {
+        use crate::ZipArchive;
+
+        let mut writer = ZipWriter::new(io::Cursor::new(Vec::new()));
+        writer.start_file("first", FileOptions::default()).unwrap();
+        writer.write_all(b"first contents").unwrap();
+        let options = FileOptions::default().compression_method(CompressionMethod::Stored);
+        writer.start_file("broken", options).unwrap();
+        writer.write_all(&[0xab; 100_000]).unwrap();
+        writer.abort_file().unwrap();
+        let archive = writer.finish().unwrap().into_inner();
+
+        // The archive ends where the discarded data did
+        assert!(archive.len() > 100_000);
+        assert!(!archive[archive.len() - 100..].contains(&0xab));
+        let archive = ZipArchive::new(io::Cursor::new(archive)).unwrap();
+        assert_eq!(archive.len(), 1);
+        assert_eq!(archive.offset(), 0);
+    }
+
+    #[test]
+    fn remove_entry_after_checkpoint() {
+        use crate::ZipArchive;
+
+        let mut writer = ZipWriter::new(io::Cursor::new(Vec::new()));
+        writer.start_file("first", FileOptions::default()).unwrap();
+        writer.write_all(b"first contents").unwrap();
+        writer
+            .start_file("a_long_name_for_the_second_file", FileOptions::default())
+            .unwrap();
+        writer.checkpoint().unwrap();
+        writer
+            .remove_entry("a_long_name_for_the_second_file")
+            .unwrap();
+        let archive = writer.finish().unwrap().into_inner();
+
+        // The end of the checkpoint, which lists both files, was overwritten
+        let archive = ZipArchive::new(io::Cursor::new(archive)).unwrap();
+        assert_eq!(archive.len(), 1);
+    }
+
     #[test]
     fn path_to_string() {
         let mut path = std::path::PathBuf::new();
@@ -1494,13 +4510,951 @@ mod test {
         let path_str = super::path_to_string(&path);
         assert_eq!(path_str, "windows/system32");
     }
-}
 
-#[cfg(not(feature = "unreserved"))]
-const EXTRA_FIELD_MAPPING: [u16; 49] = [
-    0x0001, 0x0007, 0x0008, 0x0009, 0x000a, 0x000c, 0x000d, 0x000e, 0x000f, 0x0014, 0x0015, 0x0016,
-    0x0017, 0x0018, 0x0019, 0x0020, 0x0021, 0x0022, 0x0023, 0x0065, 0x0066, 0x4690, 0x07c8, 0x2605,
-    0x2705, 0x2805, 0x334d, 0x4341, 0x4453, 0x4704, 0x470f, 0x4b46, 0x4c41, 0x4d49, 0x4f4c, 0x5356,
-    0x5455, 0x554e, 0x5855, 0x6375, 0x6542, 0x7075, 0x756e, 0x7855, 0xa11e, 0xa220, 0xfd4a, 0x9901,
-    0x9902,
-];
+    #[test]
+    fn path_to_zip_name() {
+        use std::path::{Path, PathBuf};
+
+        let name = |path: &Path| super::path_to_zip_name(path).ok();
+        assert_eq!(
+            name(&PathBuf::from(r"a\b\c.txt")).as_deref(),
+            Some("a/b/c.txt")
+        );
+        assert_eq!(
+            name(&Path::new("a").join("b").join("c.txt")).as_deref(),
+            Some("a/b/c.txt")
+        );
+        assert_eq!(name(Path::new("./a//b/")).as_deref(), Some("a/b"));
+        for invalid in [
+            "",
+            ".",
+            "/etc/passwd",
+            r"\windows",
+            r"C:\Users\a.txt",
+            "c:a.txt",
+            r"\\server\share\a.txt",
+            "a/../b",
+            r"a\..\b",
+            "..",
+        ] {
+            assert_eq!(name(&PathBuf::from(invalid)), None, "{invalid}");
+        }
+        #[cfg(unix)]
+        {
+            use std::os::unix::ffi::OsStrExt;
+            let path = Path::new(std::ffi::OsStr::from_bytes(b"a\xff.txt"));
+            assert_eq!(name(path), None);
+        }
+    }
+
+    #[test]
+    fn start_file_from_path() {
+        use crate::ZipArchive;
+        use std::path::PathBuf;
+
+        let mut writer = ZipWriter::new(io::Cursor::new(Vec::new()));
+        writer
+            .start_file_from_path(&PathBuf::from(r"dir\file.txt"), FileOptions::default())
+            .unwrap();
+        writer.write_all(b"contents").unwrap();
+        let error = writer
+            .start_file_from_path(&PathBuf::from("../file.txt"), FileOptions::default())
+            .unwrap_err();
+        assert!(error.to_string().contains("'..'"));
+        let mut archive = ZipArchive::new(writer.finish().unwrap()).unwrap();
+        assert_eq!(archive.len(), 1);
+        assert!(archive.by_name("dir/file.txt").is_ok());
+    }
+
+    #[test]
+    fn aligned_files() {
+        use crate::read::ZipArchive;
+
+        let options = FileOptions::default()
+            .compression_method(CompressionMethod::Stored)
+            .alignment(4096);
+        let mut writer = ZipWriter::new(io::Cursor::new(Vec::new()));
+        writer.start_file("first", options).unwrap();
+        writer.write_all(b"first contents").unwrap();
+        writer
+            .start_file("second", options.large_file(true))
+            .unwrap();
+        writer.write_all(b"second contents").unwrap();
+        writer
+            .start_file("third", options.alignment_padding_id(0))
+            .unwrap();
+        writer.write_all(b"third contents").unwrap();
+        let archive = writer.finish().unwrap();
+
+        let mut archive = ZipArchive::new(archive).unwrap();
+        for (index, contents) in ["first", "second", "third"].iter().enumerate() {
+            let mut file = archive.by_index(index).unwrap();
+            assert_eq!(file.data_start() % 4096, 0);
+            assert!(file.extra_data().is_empty());
+            let mut data = String::new();
+            io::Read::read_to_string(&mut file, &mut data).unwrap();
+            assert_eq!(data, format!("{contents} contents"));
+        }
+    }
+
+    #[test]
+    fn invalid_alignment() {
+        let mut writer = ZipWriter::new(io::Cursor::new(Vec::new()));
+        let options = FileOptions::default().alignment(3);
+        assert!(writer.start_file("file", options).is_err());
+        let options = FileOptions::default().alignment(4);
+        assert!(writer.start_file_with_extra_data("file", options).is_err());
+    }
+
+    #[test]
+    fn extra_fields() {
+        use super::ExtraField;
+        use crate::read::ZipArchive;
+
+        let local = [ExtraField::new(0xcafe, b"local".to_vec())];
+        let central = [
+            ExtraField::new(0xcafe, b"central".to_vec()),
+            ExtraField::new(0xbeef, Vec::new()),
+        ];
+        let mut writer = ZipWriter::new(io::Cursor::new(Vec::new()));
+        let data_start = writer
+            .start_file_with_extra_fields("file", FileOptions::default(), &local, &central)
+            .unwrap();
+        writer.write_all(b"contents").unwrap();
+        let archive = writer.finish().unwrap().into_inner();
+
+        let data_start = data_start as usize;
+        assert_eq!(
+            archive[data_start - 9..data_start],
+            [0xfe, 0xca, 5, 0, b'l', b'o', b'c', b'a', b'l']
+        );
+        let mut archive = ZipArchive::new(io::Cursor::new(archive)).unwrap();
+        assert_eq!(
+            archive.by_index(0).unwrap().extra_data(),
+            b"\xfe\xca\x07\x00central\xef\xbe\x00\x00"
+        );
+    }
+
+    #[test]
+    fn invalid_extra_fields() {
+        use super::ExtraField;
+
+        let mut writer = ZipWriter::new(io::Cursor::new(Vec::new()));
+        let invalid = [
+            vec![ExtraField::new(0x0001, vec![0; 8])],
+            vec![ExtraField::new(0x9901, vec![0; 7])],
+            vec![ExtraField::new(0xcafe, vec![0; 0x10000])],
+            vec![
+                ExtraField::new(0xcafe, vec![0; 0x8000]),
+                ExtraField::new(0xbeef, vec![0; 0x8000]),
+            ],
+        ];
+        for fields in &invalid {
+            assert!(writer
+                .start_file_with_extra_fields("file", FileOptions::default(), fields, &[])
+                .is_err());
+            assert!(writer
+                .start_file_with_extra_fields("file", FileOptions::default(), &[], fields)
+                .is_err());
+        }
+        assert!(writer.finish().unwrap().into_inner().len() < 100);
+    }
+
+    #[test]
+    fn file_comments() {
+        use crate::ZipArchive;
+
+        let mut writer = ZipWriter::new(io::Cursor::new(Vec::new()));
+        writer
+            .start_file("a.txt", FileOptions::default().comment("sha256:0123abcd"))
+            .unwrap();
+        writer
+            .add_directory("b", FileOptions::default().comment("répertoire"))
+            .unwrap();
+        writer.start_file("c.txt", FileOptions::default()).unwrap();
+        let long_comment = "x".repeat(u16::MAX as usize + 1);
+        assert!(writer
+            .start_file("d.txt", FileOptions::default().comment(&long_comment))
+            .is_err());
+        let mut archive = ZipArchive::new(writer.finish().unwrap()).unwrap();
+
+        assert_eq!(
+            archive.by_name("a.txt").unwrap().comment(),
+            "sha256:0123abcd"
+        );
+        assert_eq!(archive.by_name("b/").unwrap().comment(), "répertoire");
+        assert_eq!(archive.by_name("c.txt").unwrap().comment(), "");
+        assert!(archive.by_name("d.txt").is_err());
+    }
+
+    #[test]
+    fn finish_into_readable() {
+        use crate::ZipArchive;
+        use std::io::Read;
+
+        let mut writer = ZipWriter::new(io::Cursor::new(Vec::new()));
+        writer.set_comment("archive comment");
+        writer
+            .start_file("b.txt", FileOptions::default().comment("first"))
+            .unwrap();
+        writer.write_all(b"first file").unwrap();
+        writer.add_directory("a", FileOptions::default()).unwrap();
+        writer
+            .start_file(
+                "c.txt",
+                FileOptions::default().compression_method(CompressionMethod::Stored),
+            )
+            .unwrap();
+        writer.write_all(b"last file").unwrap();
+        let mut readable = writer.finish_into_readable().unwrap();
+        let mut parsed =
+            ZipArchive::new(io::Cursor::new(readable.clone().into_inner().into_inner())).unwrap();
+
+        assert_eq!(readable.comment(), b"archive comment");
+        assert_eq!(readable.comment(), parsed.comment());
+        assert_eq!(readable.len(), 3);
+        for i in 0..parsed.len() {
+            let mut expected = parsed.by_index(i).unwrap();
+            let mut file = readable.by_index(i).unwrap();
+            assert_eq!(file.name(), expected.name());
+            assert_eq!(file.comment(), expected.comment());
+            assert_eq!(file.header_start(), expected.header_start());
+            assert_eq!(file.data_start(), expected.data_start());
+            let mut contents = Vec::new();
+            file.read_to_end(&mut contents).unwrap();
+            let mut expected_contents = Vec::new();
+            expected.read_to_end(&mut expected_contents).unwrap();
+            assert_eq!(contents, expected_contents);
+        }
+    }
+
+    #[test]
+    fn extended_timestamps() {
+        use crate::ZipArchive;
+
+        let mtime = 1_700_000_001; // 2023-11-14 22:13:21 UTC
+        let atime = 1_700_000_101;
+        let mut writer = ZipWriter::new(io::Cursor::new(Vec::new()));
+        writer
+            .start_file(
+                "both.txt",
+                FileOptions::default().unix_mtime(mtime).unix_atime(atime),
+            )
+            .unwrap();
+        writer.write_all(b"both").unwrap();
+        writer
+            .start_file(
+                "large.txt",
+                FileOptions::default().unix_mtime(mtime).large_file(true),
+            )
+            .unwrap();
+        writer.write_all(b"large").unwrap();
+        writer
+            .start_file("none.txt", FileOptions::default())
+            .unwrap();
+        assert!(writer
+            .start_file("invalid.txt", FileOptions::default().unix_mtime(1 << 40))
+            .is_err());
+        let archive = writer.finish().unwrap().into_inner();
+
+        let mut zip = ZipArchive::new(io::Cursor::new(archive.as_slice())).unwrap();
+        for name in ["both.txt", "large.txt"] {
+            let file = zip.by_name(name).unwrap();
+            assert_eq!(file.unix_mtime(), Some(mtime));
+            let dos_time = file.last_modified();
+            assert_eq!(
+                (dos_time.year(), dos_time.month(), dos_time.day()),
+                (2023, 11, 14)
+            );
+            assert_eq!(
+                (dos_time.hour(), dos_time.minute(), dos_time.second()),
+                (22, 13, 20)
+            );
+        }
+        assert_eq!(zip.by_name("none.txt").unwrap().unix_mtime(), None);
+
+        // The access time is only in the local header
+        let mut stream = archive.as_slice();
+        let file = crate::read::read_zipfile_from_stream(&mut stream)
+            .unwrap()
+            .unwrap();
+        assert_eq!(file.name(), "both.txt");
+        assert_eq!(file.unix_mtime(), Some(mtime));
+        assert_eq!(file.unix_atime(), Some(atime));
+    }
+
+    #[test]
+    fn ntfs_times() {
+        use crate::types::NtfsTimes;
+        use crate::ZipArchive;
+
+        let times = NtfsTimes {
+            mtime: 0x01d9_a1b2_c3d4_e5f6,
+            atime: u64::MAX,
+            ctime: 1,
+        };
+        let mut writer = ZipWriter::new(io::Cursor::new(Vec::new()));
+        writer
+            .start_file("ntfs.txt", FileOptions::default().ntfs_times(times))
+            .unwrap();
+        writer
+            .start_file(
+                "both.txt",
+                FileOptions::default()
+                    .ntfs_times(times)
+                    .unix_mtime(1_700_000_001),
+            )
+            .unwrap();
+        writer
+            .start_file("none.txt", FileOptions::default())
+            .unwrap();
+        let archive = writer.finish().unwrap().into_inner();
+
+        let mut zip = ZipArchive::new(io::Cursor::new(archive.as_slice())).unwrap();
+        assert_eq!(zip.by_name("ntfs.txt").unwrap().ntfs_times(), Some(times));
+        let both = zip.by_name("both.txt").unwrap();
+        assert_eq!(both.ntfs_times(), Some(times));
+        assert_eq!(both.unix_mtime(), Some(1_700_000_001));
+        drop(both);
+        assert_eq!(zip.by_name("none.txt").unwrap().ntfs_times(), None);
+
+        let mut stream = archive.as_slice();
+        let file = crate::read::read_zipfile_from_stream(&mut stream)
+            .unwrap()
+            .unwrap();
+        assert_eq!(file.ntfs_times(), Some(times));
+    }
+
+    #[test]
+    fn legacy_name_compat() {
+        use crate::read::{FileNameEncoding, ZipReaderConfig};
+        use crate::ZipArchive;
+
+        let names = ["naïve café.txt", "日本.txt", "ascii.txt"];
+        let mut writer = ZipWriter::new(io::Cursor::new(Vec::new()));
+        for name in names {
+            writer
+                .start_file(name, FileOptions::default().legacy_name_compat(true))
+                .unwrap();
+        }
+        let archive = writer.finish().unwrap().into_inner();
+
+        // The local header has no UTF-8 flag, and a name in codepage 437
+        assert_eq!(archive[7] & (1 << 3), 0);
+        assert_eq!(&archive[30..44], b"na\x8bve caf\x82.txt");
+
+        // The Unicode Path extra field gives the name
+        let mut zip = ZipArchive::new(io::Cursor::new(archive.as_slice())).unwrap();
+        for (i, name) in names.iter().enumerate() {
+            assert_eq!(zip.by_index(i).unwrap().name(), *name);
+        }
+
+        // Readers ignoring it decode codepage 437
+        let config = ZipReaderConfig::default().file_name_encoding(FileNameEncoding::Cp437);
+        let mut zip = ZipArchive::with_config(io::Cursor::new(archive.as_slice()), config).unwrap();
+        assert_eq!(zip.by_index(0).unwrap().name(), names[0]);
+        assert_eq!(zip.by_index(1).unwrap().name(), "??.txt");
+        assert_eq!(zip.by_index(2).unwrap().name(), names[2]);
+    }
+
+    #[test]
+    fn last_modified_time_from() {
+        use std::time::{Duration, UNIX_EPOCH};
+
+        let time = |options: FileOptions| {
+            let time = options.last_modified_time;
+            (time.datepart(), time.timepart())
+        };
+        let at = |date: (u16, u8, u8), clock: (u8, u8, u8)| {
+            let dt =
+                DateTime::from_date_and_time(date.0, date.1, date.2, clock.0, clock.1, clock.2)
+                    .unwrap();
+            (dt.datepart(), dt.timepart())
+        };
+        let options = FileOptions::default();
+
+        // 2023-11-14 22:13:21.7 UTC, stored with an even second
+        let modern = UNIX_EPOCH + Duration::from_millis(1_700_000_001_700);
+        assert_eq!(
+            time(options.last_modified_time_from(modern)),
+            at((2023, 11, 14), (22, 13, 20))
+        );
+        assert_eq!(
+            time(options.last_modified_time_from(UNIX_EPOCH)),
+            at((1980, 1, 1), (0, 0, 0))
+        );
+        let before_epoch = UNIX_EPOCH - Duration::from_secs(86400);
+        assert_eq!(
+            time(options.last_modified_time_from(before_epoch)),
+            at((1980, 1, 1), (0, 0, 0))
+        );
+        let after_2107 = UNIX_EPOCH + Duration::from_secs(4_354_819_200);
+        assert_eq!(
+            time(options.last_modified_time_from(after_2107)),
+            at((2107, 12, 31), (23, 59, 58))
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn from_fs_metadata() {
+        use std::fs;
+        use std::os::unix::fs::PermissionsExt;
+
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("file.txt");
+        fs::write(&path, b"contents").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o640)).unwrap();
+        let metadata = fs::metadata(&path).unwrap();
+        let options = FileOptions::from_fs_metadata(&metadata);
+
+        assert_eq!(options.permissions, Some(0o640));
+        let expected = FileOptions::default()
+            .last_modified_time_from(metadata.modified().unwrap())
+            .last_modified_time;
+        assert_eq!(options.last_modified_time.datepart(), expected.datepart());
+        assert_eq!(options.last_modified_time.timepart(), expected.timepart());
+        assert!(options.last_modified_time.year() >= 2020);
+    }
+
+    #[test]
+    fn central_directory_sort() {
+        use super::SortOrder;
+        use crate::ZipArchive;
+        use std::io::Read;
+
+        let mut writer = ZipWriter::new(io::Cursor::new(Vec::new()));
+        writer.set_central_directory_sort(SortOrder::ByName);
+        for name in ["c.txt", "a/", "b.txt", "a/z.txt"] {
+            if name.ends_with('/') {
+                writer.add_directory(name, FileOptions::default()).unwrap();
+            } else {
+                writer.start_file(name, FileOptions::default()).unwrap();
+                writer.write_all(name.repeat(10).as_bytes()).unwrap();
+            }
+        }
+        let mut archive = ZipArchive::new(writer.finish().unwrap()).unwrap();
+
+        let names: Vec<_> = (0..archive.len())
+            .map(|i| archive.by_index(i).unwrap().name().to_owned())
+            .collect();
+        assert_eq!(names, ["a/", "a/z.txt", "b.txt", "c.txt"]);
+
+        // The local headers are still in the order they were written
+        let header_starts: Vec<_> = ["c.txt", "a/", "b.txt", "a/z.txt"]
+            .iter()
+            .map(|name| archive.by_name(name).unwrap().header_start())
+            .collect();
+        assert!(header_starts.windows(2).all(|pair| pair[0] < pair[1]));
+        for name in ["c.txt", "b.txt", "a/z.txt"] {
+            let mut contents = String::new();
+            archive
+                .by_name(name)
+                .unwrap()
+                .read_to_string(&mut contents)
+                .unwrap();
+            assert_eq!(contents, name.repeat(10));
+        }
+    }
+
+    #[test]
+    fn compression_level_boundaries() {
+        let check = |method: CompressionMethod, level: Option<i32>| {
+            let mut writer = ZipWriter::new(io::Cursor::new(Vec::new()));
+            let options = FileOptions::default()
+                .compression_method(method)
+                .compression_level(level);
+            let result = writer.start_file("file", options);
+            if result.is_err() {
+                // Nothing was written
+                assert_eq!(writer.finish().unwrap().position(), 22);
+            }
+            result.map_err(|error| error.to_string())
+        };
+        assert!(check(CompressionMethod::Stored, None).is_ok());
+        let error = check(CompressionMethod::Stored, Some(1)).unwrap_err();
+        assert!(error.contains("Stored"), "{error}");
+        // Empty without compression features
+        #[allow(unused_mut)]
+        let mut ranges: Vec<(CompressionMethod, std::ops::RangeInclusive<i32>)> = vec![];
+        #[cfg(any(
+            feature = "deflate",
+            feature = "deflate-miniz",
+            feature = "deflate-zlib"
+        ))]
+        ranges.push((CompressionMethod::Deflated, 0..=9));
+        #[cfg(feature = "bzip2")]
+        ranges.push((CompressionMethod::Bzip2, 1..=9));
+        #[cfg(feature = "zstd")]
+        ranges.push((CompressionMethod::Zstd, zstd::compression_level_range()));
+        for (method, range) in ranges {
+            assert_eq!(method.level_range(), Some(range.clone()));
+            assert!(check(method, None).is_ok());
+            assert!(check(method, Some(*range.start())).is_ok());
+            assert!(check(method, Some(*range.end())).is_ok());
+            let error = check(method, Some(range.start() - 1)).unwrap_err();
+            assert!(
+                error.contains(&method.to_string())
+                    && error.contains(&format!("{} to {}", range.start(), range.end())),
+                "{error}"
+            );
+            assert!(check(method, Some(range.end() + 1)).is_err());
+        }
+    }
+
+    #[cfg(any(
+        feature = "deflate",
+        feature = "deflate-miniz",
+        feature = "deflate-zlib"
+    ))]
+    #[test]
+    fn deflate_strategies() {
+        use super::{DeflateOptions, Strategy};
+        use crate::ZipArchive;
+        use std::io::Read;
+
+        let data = b"aaaaaaaabbbbbbbbabababab Lorem ipsum dolor sit amet ".repeat(100);
+        let write = |options: FileOptions| {
+            let mut writer = ZipWriter::new(io::Cursor::new(Vec::new()));
+            writer.start_file("file", options).unwrap();
+            writer.write_all(&data).unwrap();
+            let mut archive = ZipArchive::new(writer.finish().unwrap()).unwrap();
+            let mut file = archive.by_index(0).unwrap();
+            let mut contents = Vec::new();
+            file.read_to_end(&mut contents).unwrap();
+            assert_eq!(contents, data);
+            file.compressed_size()
+        };
+        let default = write(FileOptions::default());
+        let options = FileOptions::default().deflate_options(DeflateOptions::default());
+        assert_eq!(write(options), default);
+        #[cfg(any(feature = "deflate", feature = "deflate-miniz"))]
+        {
+            for strategy in [Strategy::Filtered, Strategy::HuffmanOnly, Strategy::Rle] {
+                let options =
+                    FileOptions::default().deflate_options(DeflateOptions { level: 9, strategy });
+                let size = write(options);
+                if strategy == Strategy::HuffmanOnly {
+                    assert!(size > default);
+                }
+            }
+        }
+        // zlib's strategies are out of reach through flate2
+        #[cfg(not(any(feature = "deflate", feature = "deflate-miniz")))]
+        {
+            let options = FileOptions::default().deflate_options(DeflateOptions {
+                level: 9,
+                strategy: Strategy::Rle,
+            });
+            let mut writer = ZipWriter::new(io::Cursor::new(Vec::new()));
+            assert!(writer.start_file("file", options).is_err());
+        }
+
+        // The level of the deflate options takes precedence
+        let options = FileOptions::default()
+            .compression_level(Some(1))
+            .deflate_options(DeflateOptions {
+                level: 10,
+                strategy: Strategy::Default,
+            });
+        let mut writer = ZipWriter::new(io::Cursor::new(Vec::new()));
+        assert!(writer.start_file("file", options).is_err());
+    }
+
+    #[cfg(all(
+        debug_assertions,
+        any(
+            feature = "deflate",
+            feature = "deflate-miniz",
+            feature = "deflate-zlib"
+        )
+    ))]
+    #[test]
+    #[should_panic(expected = "Deflate options are only used with the Deflated method")]
+    fn deflate_options_with_other_method() {
+        use super::DeflateOptions;
+
+        let options = FileOptions::default()
+            .compression_method(CompressionMethod::Stored)
+            .deflate_options(DeflateOptions::default());
+        let mut writer = ZipWriter::new(io::Cursor::new(Vec::new()));
+        let _ = writer.start_file("file", options);
+    }
+
+    #[test]
+    fn directory_permissions() {
+        use crate::ZipArchive;
+
+        let mut writer = ZipWriter::new(io::Cursor::new(Vec::new()));
+        writer
+            .add_directory("default", FileOptions::default())
+            .unwrap();
+        writer
+            .add_directory("files", FileOptions::default().unix_permissions(0o700))
+            .unwrap();
+        let options = FileOptions::default()
+            .unix_permissions(0o644)
+            .dir_permissions(0o40750);
+        writer.add_directory("dirs", options).unwrap();
+        writer.start_file("file", options).unwrap();
+        let mut archive = ZipArchive::new(writer.finish().unwrap()).unwrap();
+
+        for (name, mode) in [
+            ("default/", 0o40755),
+            ("files/", 0o40700),
+            ("dirs/", 0o40750),
+        ] {
+            let file = archive.by_name(name).unwrap();
+            assert!(file.is_dir());
+            assert_eq!(file.unix_mode(), Some(mode));
+        }
+        let file = archive.by_name("file").unwrap();
+        assert!(file.is_file());
+        assert_eq!(file.unix_mode(), Some(0o100644));
+    }
+
+    #[test]
+    fn made_by_dos() {
+        use crate::ZipArchive;
+
+        let options = FileOptions::default()
+            .compression_method(CompressionMethod::Stored)
+            .made_by(System::Dos, 20);
+        let mut writer = ZipWriter::new(io::Cursor::new(Vec::new()));
+        writer.start_file("file", options).unwrap();
+        writer
+            .start_file("read-only", options.unix_permissions(0o444))
+            .unwrap();
+        writer.add_directory("dir", options).unwrap();
+        assert!(writer.add_symlink("link", "file", options).is_err());
+        assert!(writer
+            .start_file("unknown", options.made_by(System::Unknown, 20))
+            .is_err());
+        let mut archive = ZipArchive::new(writer.finish().unwrap()).unwrap();
+        assert_eq!(archive.len(), 3);
+
+        for (name, mode) in [
+            ("file", 0o100664),
+            ("read-only", 0o100444),
+            ("dir/", 0o40775),
+        ] {
+            let file = archive.by_name(name).unwrap();
+            assert_eq!(file.version_made_by(), (2, 0));
+            assert_eq!(file.unix_mode(), Some(mode));
+        }
+    }
+
+    #[test]
+    fn duplicate_policy() {
+        use super::Duplicate;
+        use crate::ZipArchive;
+        use std::io::Read;
+
+        let write = |policy: Duplicate| {
+            let mut writer = ZipWriter::new(io::Cursor::new(Vec::new()));
+            writer.set_duplicate_policy(policy);
+            let options = FileOptions::default().compression_method(CompressionMethod::Stored);
+            writer.start_file("a.txt", options).unwrap();
+            writer.write_all(b"first").unwrap();
+            writer.start_file("b.txt", options).unwrap();
+            let result = writer.start_file("a.txt", options);
+            if result.is_ok() {
+                writer.write_all(b"second").unwrap();
+            }
+            let mut archive = ZipArchive::new(writer.finish().unwrap()).unwrap();
+            let names: Vec<_> = (0..archive.len())
+                .map(|i| archive.by_index(i).unwrap().name().to_owned())
+                .collect();
+            let mut contents = String::new();
+            archive
+                .by_name("a.txt")
+                .unwrap()
+                .read_to_string(&mut contents)
+                .unwrap();
+            (result.is_ok(), names, contents)
+        };
+
+        let (ok, names, contents) = write(Duplicate::Allow);
+        assert!(ok);
+        assert_eq!(names, ["a.txt", "b.txt", "a.txt"]);
+        assert_eq!(contents, "second");
+
+        let (ok, names, contents) = write(Duplicate::Error);
+        assert!(!ok);
+        assert_eq!(names, ["a.txt", "b.txt"]);
+        assert_eq!(contents, "first");
+
+        let (ok, names, contents) = write(Duplicate::Replace);
+        assert!(ok);
+        assert_eq!(names, ["b.txt", "a.txt"]);
+        assert_eq!(contents, "second");
+    }
+
+    #[test]
+    fn append_after_prepended_data() {
+        use crate::ZipArchive;
+        use std::io::Read;
+
+        let options = FileOptions::default().compression_method(CompressionMethod::Stored);
+        let mut writer = ZipWriter::new(io::Cursor::new(Vec::new()));
+        writer.set_comment("Comment");
+        writer.start_file("a.txt", options).unwrap();
+        writer.write_all(b"Lorem ipsum").unwrap();
+        let mut archive = vec![0x5a; 1024];
+        archive.extend(writer.finish().unwrap().into_inner());
+
+        let mut writer = ZipWriter::new_append(io::Cursor::new(archive)).unwrap();
+        writer.start_file("b.txt", options).unwrap();
+        writer.write_all(b"dolor sit amet").unwrap();
+        let archive = writer.finish().unwrap().into_inner();
+        assert_eq!(archive[..1024], [0x5a; 1024]);
+        let mut archive = ZipArchive::new(io::Cursor::new(archive)).unwrap();
+
+        assert_eq!(archive.offset(), 1024);
+        assert_eq!(archive.comment(), b"Comment");
+        for (name, expected) in [("a.txt", "Lorem ipsum"), ("b.txt", "dolor sit amet")] {
+            let mut contents = String::new();
+            archive
+                .by_name(name)
+                .unwrap()
+                .read_to_string(&mut contents)
+                .unwrap();
+            assert_eq!(contents, expected);
+        }
+    }
+
+    #[test]
+    fn entry_stats() {
+        use super::EntryStats;
+        use crate::ZipArchive;
+
+        let mut writer = ZipWriter::new(io::Cursor::new(Vec::new()));
+        let stored = FileOptions::default().compression_method(CompressionMethod::Stored);
+        assert!(writer.last_entry_stats().is_none());
+        assert_eq!(writer.current_offset().unwrap(), 0);
+
+        let mut stats = vec![];
+        writer.start_file("a.txt", stored).unwrap();
+        writer.write_all(b"Lorem ipsum").unwrap();
+        let a_end = writer.current_offset().unwrap();
+        writer.add_directory("dir", stored).unwrap();
+        stats.push(writer.last_entry_stats().unwrap().clone());
+        writer
+            .start_file("dir/b.txt", FileOptions::default())
+            .unwrap();
+        stats.push(writer.last_entry_stats().unwrap().clone());
+        writer.write_all(&b"dolor sit amet ".repeat(100)).unwrap();
+        assert_eq!(writer.entry_count(), 3);
+        let archive = writer.finish().unwrap();
+        stats.push(writer.last_entry_stats().unwrap().clone());
+
+        let mut archive = ZipArchive::new(archive).unwrap();
+        assert_eq!(archive.by_name("dir/").unwrap().header_start(), a_end);
+        let expected: Vec<_> = (0..archive.len())
+            .map(|i| {
+                let file = archive.by_index(i).unwrap();
+                EntryStats {
+                    name: file.name().to_owned(),
+                    header_start: file.header_start(),
+                    data_start: file.data_start(),
+                    compressed_size: file.compressed_size(),
+                    uncompressed_size: file.size(),
+                    crc32: file.crc32(),
+                }
+            })
+            .collect();
+        assert_eq!(stats, expected);
+    }
+
+    #[test]
+    fn use_after_finish() {
+        let mut writer = ZipWriter::new(io::Cursor::new(Vec::new()));
+        writer.start_file("a.txt", FileOptions::default()).unwrap();
+        assert!(!writer.is_finished());
+        writer.finish().unwrap();
+        assert!(writer.is_finished());
+
+        assert!(writer.finish().is_err());
+        assert!(writer.start_file("b.txt", FileOptions::default()).is_err());
+        assert!(writer.write_all(b"data").is_err());
+        assert!(writer.add_directory("dir", FileOptions::default()).is_err());
+        assert!(writer.deep_copy_file("a.txt", "b.txt").is_err());
+        assert!(writer.remove_entry("a.txt").is_err());
+        assert!(writer.abort_file().is_err());
+        assert!(writer.current_offset().is_err());
+        assert!(writer.abort().is_err());
+    }
+
+    #[test]
+    fn abort_and_reuse_writer() {
+        use crate::ZipArchive;
+
+        let mut writer = ZipWriter::new(io::Cursor::new(Vec::new()));
+        writer.start_file("a.txt", FileOptions::default()).unwrap();
+        writer.write_all(b"Lorem ipsum").unwrap();
+        writer.start_file("b.txt", FileOptions::default()).unwrap();
+        writer.write_all(b"dolor sit amet").unwrap();
+        let mut inner = writer.abort().unwrap();
+        assert!(ZipArchive::new(inner.clone()).is_err());
+
+        inner.get_mut().clear();
+        inner.set_position(0);
+        let mut writer = ZipWriter::new(inner);
+        writer.start_file("c.txt", FileOptions::default()).unwrap();
+        let archive = ZipArchive::new(writer.finish().unwrap()).unwrap();
+        assert_eq!(archive.file_names().collect::<Vec<_>>(), ["c.txt"]);
+    }
+
+    #[test]
+    fn dos_attributes() {
+        use crate::types::DosAttributes;
+        use crate::ZipArchive;
+
+        let attributes = DosAttributes {
+            read_only: true,
+            hidden: true,
+            ..DosAttributes::default()
+        };
+        let options = FileOptions::default().dos_attributes(attributes);
+        let mut writer = ZipWriter::new(io::Cursor::new(Vec::new()));
+        writer.start_file("unix", options).unwrap();
+        writer
+            .start_file("dos", options.made_by(System::Dos, 20))
+            .unwrap();
+        writer
+            .add_directory("dir", options.made_by(System::Dos, 20))
+            .unwrap();
+        writer
+            .start_file("default", FileOptions::default())
+            .unwrap();
+        let mut archive = ZipArchive::new(writer.finish().unwrap()).unwrap();
+
+        for (name, expected, mode) in [
+            ("unix", attributes, 0o100644),
+            ("dos", attributes, 0o100444),
+            ("dir/", attributes, 0o40555),
+            ("default", DosAttributes::default(), 0o100644),
+        ] {
+            let file = archive.by_name(name).unwrap();
+            assert_eq!(file.dos_attributes(), expected);
+            assert_eq!(file.unix_mode(), Some(mode));
+        }
+    }
+
+    #[test]
+    fn unix_ownership() {
+        use crate::ZipArchive;
+
+        let mut writer = ZipWriter::new(io::Cursor::new(Vec::new()));
+        writer
+            .start_file(
+                "owned.txt",
+                FileOptions::default()
+                    .unix_ownership(1000, 100)
+                    .unix_mtime(1_700_000_001)
+                    .large_file(true),
+            )
+            .unwrap();
+        writer.write_all(b"owned").unwrap();
+        writer
+            .start_file("none.txt", FileOptions::default())
+            .unwrap();
+        let archive = writer.finish().unwrap().into_inner();
+
+        let mut zip = ZipArchive::new(io::Cursor::new(archive.as_slice())).unwrap();
+        let owned = zip.by_name("owned.txt").unwrap();
+        assert_eq!(owned.unix_uid(), Some(1000));
+        assert_eq!(owned.unix_gid(), Some(100));
+        assert_eq!(owned.unix_mtime(), Some(1_700_000_001));
+        drop(owned);
+        let none = zip.by_name("none.txt").unwrap();
+        assert_eq!(none.unix_uid(), None);
+        assert!(!none.extra_data().windows(2).any(|id| id == [0x75, 0x78]));
+        drop(none);
+
+        let mut stream = archive.as_slice();
+        let mut file = crate::read::read_zipfile_from_stream(&mut stream)
+            .unwrap()
+            .unwrap();
+        assert_eq!(file.unix_uid(), Some(1000));
+        assert_eq!(file.unix_gid(), Some(100));
+        let mut data = String::new();
+        io::Read::read_to_string(&mut file, &mut data).unwrap();
+        assert_eq!(data, "owned");
+    }
+
+    #[cfg(any(
+        feature = "deflate",
+        feature = "deflate-miniz",
+        feature = "deflate-zlib"
+    ))]
+    #[test]
+    fn store_if_larger() {
+        use crate::ZipArchive;
+        use std::io::Read;
+
+        // Incompressible data, from a xorshift generator
+        let mut state = 0x2545_f491_4f6c_dd1du64;
+        let random: Vec<u8> = (0..100_000)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                state as u8
+            })
+            .collect();
+        let text = b"Lorem ipsum dolor sit amet ".repeat(1000);
+        let options = FileOptions::default()
+            .compression_method(CompressionMethod::Deflated)
+            .store_if_larger(true);
+
+        let mut writer = ZipWriter::new(io::Cursor::new(Vec::new()));
+        writer.start_file("random.bin", options).unwrap();
+        writer.write_all(&random).unwrap();
+        writer.start_file("text.txt", options).unwrap();
+        writer.write_all(&text).unwrap();
+        writer
+            .start_file("unbuffered.bin", options.store_if_larger_buffer(1000))
+            .unwrap();
+        writer.write_all(&random).unwrap();
+        writer
+            .start_file("aligned.bin", options.alignment(4096))
+            .unwrap();
+        writer.write_all(&random[..5000]).unwrap();
+        let archive = writer.finish().unwrap().into_inner();
+
+        let mut zip = ZipArchive::new(io::Cursor::new(archive.as_slice())).unwrap();
+        for (name, method, contents) in [
+            ("random.bin", CompressionMethod::Stored, &random[..]),
+            ("text.txt", CompressionMethod::Deflated, &text[..]),
+            ("unbuffered.bin", CompressionMethod::Deflated, &random[..]),
+        ] {
+            let mut file = zip.by_name(name).unwrap();
+            assert_eq!(file.compression(), method, "{name}");
+            let mut data = Vec::new();
+            file.read_to_end(&mut data).unwrap();
+            assert!(data == contents, "{name}");
+        }
+        let aligned = zip.by_name("aligned.bin").unwrap();
+        assert_eq!(aligned.data_start() % 4096, 0);
+        drop(aligned);
+
+        // The local headers match the data that follows them
+        let mut stream = archive.as_slice();
+        let mut file = crate::read::read_zipfile_from_stream(&mut stream)
+            .unwrap()
+            .unwrap();
+        assert_eq!(file.compression(), CompressionMethod::Stored);
+        let mut data = Vec::new();
+        file.read_to_end(&mut data).unwrap();
+        assert!(data == random);
+        drop(file);
+        let mut file = crate::read::read_zipfile_from_stream(&mut stream)
+            .unwrap()
+            .unwrap();
+        assert_eq!(file.name(), "text.txt");
+        let mut data = Vec::new();
+        file.read_to_end(&mut data).unwrap();
+        assert!(data == text);
+    }
+}
@@ -0,0 +1,87 @@
+//! Deflate with a compression strategy, which flate2 doesn't expose
+
+use super::Strategy;
+use miniz_oxide::deflate::core::{
+    compress, create_comp_flags_from_zip_params, CompressionStrategy, CompressorOxide, TDEFLFlush,
+    TDEFLStatus,
+};
+use std::io::{self, Write};
+
+/// Negative window bits select raw deflate, without a zlib header
+const RAW_DEFLATE_WINDOW_BITS: i32 = -15;
+
+/// A raw deflate encoder, like `flate2::write::DeflateEncoder`, with a strategy
+pub(super) struct StrategyEncoder<W: Write> {
+    compressor: Box<CompressorOxide>,
+    writer: W,
+    buffer: Vec<u8>,
+}
+
+impl<W: Write> StrategyEncoder<W> {
+    pub(super) fn new(writer: W, level: u32, strategy: Strategy) -> StrategyEncoder<W> {
+        let strategy = match strategy {
+            Strategy::Default => CompressionStrategy::Default,
+            Strategy::Filtered => CompressionStrategy::Filtered,
+            Strategy::HuffmanOnly => CompressionStrategy::HuffmanOnly,
+            Strategy::Rle => CompressionStrategy::RLE,
+        };
+        let flags = create_comp_flags_from_zip_params(
+            level as i32,
+            RAW_DEFLATE_WINDOW_BITS,
+            strategy as i32,
+        );
+        StrategyEncoder {
+            compressor: Box::new(CompressorOxide::new(flags)),
+            writer,
+            buffer: vec![0; 32 * 1024],
+        }
+    }
+
+    /// Compress `input`, writing the output, and return the status, the input consumed and
+    /// whether the output buffer was filled, so that there may be more output
+    fn compress(
+        &mut self,
+        input: &[u8],
+        flush: TDEFLFlush,
+    ) -> io::Result<(TDEFLStatus, usize, bool)> {
+        let (status, consumed, produced) =
+            compress(&mut self.compressor, input, &mut self.buffer, flush);
+        self.writer.write_all(&self.buffer[..produced])?;
+        match status {
+            TDEFLStatus::BadParam | TDEFLStatus::PutBufFailed => Err(io::Error::new(
+                io::ErrorKind::Other,
+                "Deflate compression failed",
+            )),
+            _ => Ok((status, consumed, produced == self.buffer.len())),
+        }
+    }
+
+    pub(super) fn get_mut(&mut self) -> &mut W {
+        &mut self.writer
+    }
+
+    /// Write the end of the compressed data, and return the underlying writer
+    pub(super) fn finish(mut self) -> io::Result<W> {
+        while self.compress(&[], TDEFLFlush::Finish)?.0 != TDEFLStatus::Done {}
+        Ok(self.writer)
+    }
+}
+
+impl<W: Write> Write for StrategyEncoder<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        loop {
+            let (_, consumed, _) = self.compress(buf, TDEFLFlush::None)?;
+            if consumed > 0 {
+                return Ok(consumed);
+            }
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        while self.compress(&[], TDEFLFlush::Sync)?.2 {}
+        self.writer.flush()
+    }
+}
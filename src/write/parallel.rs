@@ -0,0 +1,183 @@
+//! Compressing files on other threads, for [`ZipWriter::add_entry`]
+//!
+//! Each file is read into memory and compressed on a thread of its own, into memory too. The
+//! files are then written in the order they were added, so the archive is the same as one written
+//! by a single thread.
+
+use super::{
+    new_file_data, use_reserved_zip64, DeflateOptions, FileOptions, GenericZipWriter,
+    MaybeEncrypted, ZipRawValues, ZipWriter,
+};
+use crate::compression::CompressionMethod;
+use crate::result::{ZipError, ZipResult};
+use crate::spec;
+use crate::types::ZipFileData;
+use std::collections::VecDeque;
+use std::io::{self, Read, Seek, Write};
+use std::thread::{self, JoinHandle};
+
+/// Files being compressed, oldest first
+pub(super) struct Pending {
+    pub(super) parallelism: usize,
+    entries: VecDeque<PendingEntry>,
+}
+
+impl Default for Pending {
+    fn default() -> Self {
+        Pending {
+            parallelism: 1,
+            entries: VecDeque::new(),
+        }
+    }
+}
+
+impl Pending {
+    pub(super) fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub(super) fn contains(&self, name: &str) -> bool {
+        self.entries
+            .iter()
+            .any(|entry| entry.file.file_name == name)
+    }
+
+    /// Drop the files named `name`, whose compression carries on unused
+    pub(super) fn remove(&mut self, name: &str) {
+        self.entries.retain(|entry| entry.file.file_name != name);
+    }
+}
+
+struct PendingEntry {
+    file: ZipFileData,
+    job: JoinHandle<ZipResult<Compressed>>,
+}
+
+struct Compressed {
+    data: Vec<u8>,
+    crc32: u32,
+    uncompressed_size: u64,
+}
+
+/// The options used to compress a file, owned so that they can be sent to another thread
+struct CompressionOptions {
+    method: CompressionMethod,
+    level: Option<i32>,
+    deflate_options: Option<DeflateOptions>,
+    zstd_dictionary: Option<Vec<u8>>,
+}
+
+impl CompressionOptions {
+    fn new(options: &FileOptions) -> CompressionOptions {
+        CompressionOptions {
+            method: options.compression_method,
+            level: options.compression_level,
+            deflate_options: options.deflate_options,
+            zstd_dictionary: options.zstd_dictionary.map(<[u8]>::to_vec),
+        }
+    }
+
+    fn file_options(&self) -> FileOptions<'_> {
+        let mut options = FileOptions::default()
+            .compression_method(self.method)
+            .compression_level(self.level);
+        options.deflate_options = self.deflate_options;
+        options.zstd_dictionary = self.zstd_dictionary.as_deref();
+        options
+    }
+}
+
+fn compress(data: Vec<u8>, options: CompressionOptions) -> ZipResult<Compressed> {
+    let mut compressor = GenericZipWriter::Storer(MaybeEncrypted::Unencrypted(Vec::new()));
+    compressor.switch_to_options(&options.file_options())?;
+    compressor.ref_mut().unwrap().write_all(&data)?;
+    compressor.switch_to(CompressionMethod::Stored, None)?;
+    Ok(Compressed {
+        data: compressor.unwrap(),
+        crc32: crc32fast::hash(&data),
+        uncompressed_size: data.len() as u64,
+    })
+}
+
+impl<W: Write + Seek> ZipWriter<W> {
+    /// Whether a file with these options can be compressed on another thread
+    ///
+    /// Encryption, alignment and storing files that don't compress depend on the writer, so
+    /// these files are written in place.
+    pub(super) fn can_queue(&self, options: &FileOptions) -> bool {
+        self.pending.parallelism > 1
+            && options.encrypt_with.is_none()
+            && options.aes_encryption.is_none()
+            && options.alignment == 1
+            && !options.store_if_larger
+    }
+
+    /// Start compressing a file, and write the oldest ones once too many are compressing
+    pub(super) fn queue_entry<R: Read>(
+        &mut self,
+        name: String,
+        mut reader: R,
+        mut options: FileOptions,
+    ) -> ZipResult<()> {
+        if self.pending.entries.is_empty() {
+            self.finish_file()?;
+        }
+        self.check_duplicate(&name)?;
+        // The same header as `ZipWriter::start_file` writes
+        options.reserve_zip64 = true;
+        let file = new_file_data(
+            name,
+            &options,
+            ZipRawValues {
+                crc32: 0,
+                compressed_size: 0,
+                uncompressed_size: 0,
+            },
+            0,
+        )?;
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        let compression = CompressionOptions::new(&options);
+        let job = thread::spawn(move || compress(data, compression));
+        self.pending.entries.push_back(PendingEntry { file, job });
+
+        while self.pending.entries.len() > self.pending.parallelism {
+            self.write_oldest_pending()?;
+        }
+        Ok(())
+    }
+
+    /// Wait for all files being compressed, and write them
+    pub(super) fn write_pending(&mut self) -> ZipResult<()> {
+        while !self.pending.entries.is_empty() {
+            self.write_oldest_pending()?;
+        }
+        Ok(())
+    }
+
+    fn write_oldest_pending(&mut self) -> ZipResult<()> {
+        let PendingEntry { mut file, job } = self.pending.entries.pop_front().unwrap();
+        let compressed = job.join().map_err(|_| {
+            ZipError::Io(io::Error::new(
+                io::ErrorKind::Other,
+                "Compression thread panicked",
+            ))
+        })??;
+        file.crc32 = compressed.crc32;
+        file.compressed_size = compressed.data.len() as u64;
+        file.uncompressed_size = compressed.uncompressed_size;
+        if file.compressed_size.max(file.uncompressed_size) > spec::ZIP64_BYTES_THR
+            && !file.large_file
+            && !use_reserved_zip64(&mut file)
+        {
+            file.large_file = true;
+        }
+        file.header_start = self.inner.get_plain().stream_position()?;
+        self.write_local_header(file)?;
+        self.inner.get_plain().write_all(&compressed.data)?;
+        // The local header already has the CRC and sizes
+        self.writing_raw = true;
+        self.entry_finished()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,582 @@
+//! Writing archives from async code, with [tokio](https://tokio.rs)
+//!
+//! [`ZipWriter`] writes to writers that can seek, and produces the same archive as the
+//! synchronous [`crate::ZipWriter`]. [`ZipStreamWriter`] writes to writers that can't seek, by
+//! following the data of each file with a data descriptor instead of updating its local header.
+//!
+//! The data of each file is written through their [`AsyncWrite`] implementation. Compression
+//! happens in memory, on the task writing the data; only the I/O is asynchronous.
+//!
+//! Unlike the synchronous writer, these writers are not finished when dropped: call `finish`.
+//!
+//! ```
+//! use tokio::io::AsyncWriteExt;
+//! use zip::write::{tokio::ZipWriter, FileOptions};
+//!
+//! # async fn doit() -> zip::result::ZipResult<()> {
+//! let mut zip = ZipWriter::new(std::io::Cursor::new(Vec::new()));
+//! zip.start_file("hello_world.txt", FileOptions::default()).await?;
+//! zip.write_all(b"Hello, World!").await?;
+//! let archive = zip.finish().await?.into_inner();
+//! # Ok(())
+//! # }
+//! ```
+
+use super::{
+    can_use_zip64, new_file_data, use_reserved_zip64, write_central_directory,
+    write_data_descriptor, write_local_file_header, FileOptions, GenericZipWriter, MaybeEncrypted,
+    ZipRawValues, ZipWriterStats,
+};
+use crate::compression::CompressionMethod;
+use crate::result::{ZipError, ZipResult};
+use crate::spec;
+use crate::types::{AesVendorVersion, ZipFileData};
+use ::tokio::io::{AsyncSeek, AsyncSeekExt, AsyncWrite, AsyncWriteExt};
+use std::io::{self, Write};
+use std::mem;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+/// Async ZIP archive generator, for writers that can seek
+///
+/// Each file starts with a local header, which is updated with the CRC and sizes of the file
+/// once it's finished.
+pub struct ZipWriter<W> {
+    core: Core<W>,
+    /// Whether the offset of the archive in `W` is known
+    started: bool,
+}
+
+/// Async ZIP archive generator, for writers that can't seek
+///
+/// The CRC and sizes of each file are written in a data descriptor following its data. Offsets
+/// in the archive are counted from the first byte written, so the writer should be at its start.
+///
+/// As the size of a file isn't known when its local header is written, files are always written
+/// with ZIP64 extra fields and data descriptors, whatever [`FileOptions::large_file`] is set to.
+/// The archive can then grow past 4 GiB, and so can each file.
+///
+/// Files encrypted with the deprecated ZipCrypto method aren't supported.
+pub struct ZipStreamWriter<W> {
+    core: Core<W>,
+}
+
+/// The state shared by both writers
+struct Core<W> {
+    inner: W,
+    /// Offset in the archive of the next byte written to `inner`
+    position: u64,
+    /// Offset past the furthest byte written before seeking back, which the archive must end at
+    /// or after
+    high_water_mark: u64,
+    files: Vec<ZipFileData>,
+    /// Compresses and encrypts the data of the current file, into a buffer
+    compressor: GenericZipWriter<Vec<u8>>,
+    /// Data to write to `inner` before anything else
+    pending: Vec<u8>,
+    /// Number of bytes of `pending` already written
+    written: usize,
+    stats: ZipWriterStats,
+    writing_to_file: bool,
+    comment: Vec<u8>,
+}
+
+impl GenericZipWriter<Vec<u8>> {
+    /// Take the data written to the buffer so far
+    fn take_output(&mut self) -> Vec<u8> {
+        let buffer = match self.get_mut() {
+            Some(buffer) => buffer,
+            None => return Vec::new(),
+        };
+        match buffer {
+            MaybeEncrypted::Unencrypted(buffer) => mem::take(buffer),
+            // Encrypted data is only written once the file is finished
+            MaybeEncrypted::Encrypted(_) => Vec::new(),
+            #[cfg(feature = "aes-crypto")]
+            MaybeEncrypted::Aes(w) => mem::take(w.get_mut()),
+        }
+    }
+}
+
+impl<W> Core<W> {
+    fn new(inner: W) -> Core<W> {
+        Core {
+            inner,
+            position: 0,
+            high_water_mark: 0,
+            files: Vec::new(),
+            compressor: GenericZipWriter::Storer(MaybeEncrypted::Unencrypted(Vec::new())),
+            pending: Vec::new(),
+            written: 0,
+            stats: Default::default(),
+            writing_to_file: false,
+            comment: Vec::new(),
+        }
+    }
+
+    /// Offset in the archive of the next byte added to `pending`
+    fn offset(&self) -> u64 {
+        self.position + (self.pending.len() - self.written) as u64
+    }
+
+    fn start_entry(
+        &mut self,
+        name: String,
+        options: FileOptions,
+        using_data_descriptor: bool,
+    ) -> ZipResult<()> {
+        let mut file = new_file_data(
+            name,
+            &options,
+            ZipRawValues {
+                crc32: 0,
+                compressed_size: 0,
+                uncompressed_size: 0,
+            },
+            self.offset(),
+        )?;
+        file.using_data_descriptor = using_data_descriptor;
+        write_local_file_header(&mut self.pending, &file)?;
+
+        let header_end = self.offset();
+        self.stats.start = header_end;
+        *file.data_start.get_mut() = header_end;
+        self.stats.bytes_written = 0;
+        self.stats.hasher = crc32fast::Hasher::new();
+        self.files.push(file);
+
+        if let Some(keys) = options.encrypt_with {
+            let mut zipwriter = crate::zipcrypto::ZipCryptoWriter {
+                writer: Vec::new(),
+                buffer: vec![],
+                keys: keys.into(),
+            };
+            zipwriter.write_all(&[0u8; 12])?;
+            self.compressor = GenericZipWriter::Storer(MaybeEncrypted::Encrypted(zipwriter));
+        }
+        #[cfg(feature = "aes-crypto")]
+        if let Some((mode, password)) = options.aes_encryption {
+            let aes_writer = crate::aes::AesWriter::new(Vec::new(), mode, password)?;
+            self.compressor = GenericZipWriter::Storer(MaybeEncrypted::Aes(aes_writer));
+        }
+        Ok(())
+    }
+
+    fn start_file(
+        &mut self,
+        name: String,
+        mut options: FileOptions,
+        using_data_descriptor: bool,
+    ) -> ZipResult<()> {
+        if options.permissions.is_none() {
+            options.permissions = Some(0o644);
+        }
+        *options.permissions.as_mut().unwrap() |= 0o100000;
+        options.check_compression_level()?;
+        // Without a data descriptor, the header can be rewritten for a file that turns out large
+        options.reserve_zip64 = !using_data_descriptor;
+        self.start_entry(name, options, using_data_descriptor)?;
+        self.compressor.switch_to_options(&options)?;
+        self.writing_to_file = true;
+        Ok(())
+    }
+
+    fn add_directory(&mut self, name: String, mut options: FileOptions<'_>) -> ZipResult<()> {
+        options.permissions = Some(options.directory_mode());
+        options.compression_method = CompressionMethod::Stored;
+
+        // Append a slash to the filename if it does not end with it.
+        let name = match name.chars().last() {
+            Some('/') | Some('\\') => name,
+            _ => name + "/",
+        };
+        // Directories have no data, so the local header never needs updating
+        self.start_entry(name, options, false)
+    }
+
+    /// Discard the current file, with its data not yet written to `inner`
+    fn abort_file_data(&mut self) -> ZipResult<ZipFileData> {
+        if !self.writing_to_file {
+            return Err(ZipError::Io(io::Error::new(
+                io::ErrorKind::Other,
+                "No file has been started",
+            )));
+        }
+        self.compressor = GenericZipWriter::Storer(MaybeEncrypted::Unencrypted(Vec::new()));
+        self.pending.clear();
+        self.written = 0;
+        self.writing_to_file = false;
+        Ok(self.files.pop().unwrap())
+    }
+
+    /// Finish compressing the current file, and record its CRC and sizes
+    fn finish_file_data(&mut self) -> ZipResult<&ZipFileData> {
+        self.compressor.switch_to(CompressionMethod::Stored, None)?;
+        match mem::replace(&mut self.compressor, GenericZipWriter::Closed) {
+            GenericZipWriter::Storer(MaybeEncrypted::Encrypted(writer)) => {
+                let crc32 = self.stats.hasher.clone().finalize();
+                self.compressor =
+                    GenericZipWriter::Storer(MaybeEncrypted::Unencrypted(writer.finish(crc32)?));
+            }
+            #[cfg(feature = "aes-crypto")]
+            GenericZipWriter::Storer(MaybeEncrypted::Aes(writer)) => {
+                self.compressor =
+                    GenericZipWriter::Storer(MaybeEncrypted::Unencrypted(writer.finish()?));
+            }
+            compressor => self.compressor = compressor,
+        }
+        let output = self.compressor.take_output();
+        self.pending.extend_from_slice(&output);
+        self.writing_to_file = false;
+
+        let file_end = self.offset();
+        let file = self.files.last_mut().unwrap();
+        file.crc32 = match file.aes_mode {
+            Some((_, AesVendorVersion::Ae2)) => 0,
+            _ => self.stats.hasher.clone().finalize(),
+        };
+        file.uncompressed_size = self.stats.bytes_written;
+        file.compressed_size = file_end - self.stats.start;
+        if !file.large_file
+            && file.compressed_size.max(file.uncompressed_size) > spec::ZIP64_BYTES_THR
+            && !file.using_data_descriptor
+        {
+            use_reserved_zip64(file);
+        }
+        // check compressed size as well as it can also be slightly larger than uncompressed size
+        if file.compressed_size > spec::ZIP64_BYTES_THR && !file.large_file {
+            return Err(ZipError::Io(io::Error::new(
+                io::ErrorKind::Other,
+                "Large file option has not been set",
+            )));
+        }
+        Ok(file)
+    }
+
+    /// Queue the central directory, which starts at the current offset
+    fn queue_central_directory(&mut self) -> ZipResult<()> {
+        let central_start = self.offset();
+        let files: Vec<&ZipFileData> = self.files.iter().collect();
+        write_central_directory(
+            &mut self.pending,
+            &files,
+            central_start,
+            self.high_water_mark,
+            &self.comment,
+            0,
+        )
+    }
+}
+
+impl<W: AsyncWrite + Unpin> Core<W> {
+    async fn write_pending(&mut self) -> io::Result<()> {
+        self.inner.write_all(&self.pending[self.written..]).await?;
+        self.position += (self.pending.len() - self.written) as u64;
+        self.pending.clear();
+        self.written = 0;
+        Ok(())
+    }
+
+    fn poll_write_pending(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        while self.written < self.pending.len() {
+            match Pin::new(&mut self.inner).poll_write(cx, &self.pending[self.written..]) {
+                Poll::Ready(Ok(0)) => return Poll::Ready(Err(io::ErrorKind::WriteZero.into())),
+                Poll::Ready(Ok(count)) => {
+                    self.written += count;
+                    self.position += count as u64;
+                }
+                Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+        self.pending.clear();
+        self.written = 0;
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_write(&mut self, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        if !self.writing_to_file {
+            return Poll::Ready(Err(io::Error::new(
+                io::ErrorKind::Other,
+                "No file has been started",
+            )));
+        }
+        // Write the data compressed so far first, so that it doesn't pile up
+        match self.poll_write_pending(cx) {
+            Poll::Ready(Ok(())) => {}
+            Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+            Poll::Pending => return Poll::Pending,
+        }
+        let count = match self.compressor.ref_mut() {
+            Some(w) => w.write(buf)?,
+            None => {
+                return Poll::Ready(Err(io::Error::new(
+                    io::ErrorKind::BrokenPipe,
+                    "ZipWriter was already closed",
+                )))
+            }
+        };
+        self.stats.update(&buf[..count]);
+        if self.stats.bytes_written > spec::ZIP64_BYTES_THR
+            && !can_use_zip64(self.files.last().unwrap())
+        {
+            self.compressor = GenericZipWriter::Closed;
+            return Poll::Ready(Err(io::Error::new(
+                io::ErrorKind::Other,
+                "Large file option has not been set",
+            )));
+        }
+        self.pending = self.compressor.take_output();
+        Poll::Ready(Ok(count))
+    }
+
+    fn poll_flush(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.poll_write_pending(cx) {
+            Poll::Ready(Ok(())) => Pin::new(&mut self.inner).poll_flush(cx),
+            other => other,
+        }
+    }
+}
+
+impl<W: AsyncWrite + AsyncSeek + Unpin> ZipWriter<W> {
+    /// Initializes the archive.
+    ///
+    /// Before writing to this object, the [`ZipWriter::start_file`] function should be called.
+    pub fn new(inner: W) -> ZipWriter<W> {
+        ZipWriter {
+            core: Core::new(inner),
+            started: false,
+        }
+    }
+
+    /// Set ZIP archive comment.
+    pub fn set_comment<S>(&mut self, comment: S)
+    where
+        S: Into<String>,
+    {
+        self.set_raw_comment(comment.into().into())
+    }
+
+    /// Set ZIP archive comment.
+    ///
+    /// This sets the raw bytes of the comment. The comment
+    /// is typically expected to be encoded in UTF-8, and must be shorter than 64 KiB for the
+    /// archive to be finished.
+    pub fn set_raw_comment(&mut self, comment: Vec<u8>) {
+        self.core.comment = comment;
+    }
+
+    /// Get the offset of the archive in the writer, on first use
+    async fn start(&mut self) -> ZipResult<()> {
+        if !self.started {
+            self.core.position = self.core.inner.stream_position().await?;
+            self.started = true;
+        }
+        Ok(())
+    }
+
+    async fn finish_file(&mut self) -> ZipResult<()> {
+        self.start().await?;
+        if !self.core.writing_to_file {
+            return self.core.write_pending().await.map_err(ZipError::from);
+        }
+        let mut header = Vec::new();
+        let header_start = {
+            let file = self.core.finish_file_data()?;
+            write_local_file_header(&mut header, file)?;
+            file.header_start
+        };
+        self.core.write_pending().await?;
+
+        let file_end = self.core.position;
+        let inner = &mut self.core.inner;
+        inner.seek(io::SeekFrom::Start(header_start)).await?;
+        inner.write_all(&header).await?;
+        inner.seek(io::SeekFrom::Start(file_end)).await?;
+        Ok(())
+    }
+
+    /// Create a file in the archive and start writing its contents.
+    ///
+    /// The data should be written using the [`AsyncWrite`] implementation on this [`ZipWriter`]
+    pub async fn start_file<S>(&mut self, name: S, options: FileOptions<'_>) -> ZipResult<()>
+    where
+        S: Into<String>,
+    {
+        self.finish_file().await?;
+        self.core.start_file(name.into(), options, false)?;
+        self.core.write_pending().await?;
+        Ok(())
+    }
+
+    /// Add a directory entry.
+    pub async fn add_directory<S>(&mut self, name: S, options: FileOptions<'_>) -> ZipResult<()>
+    where
+        S: Into<String>,
+    {
+        self.finish_file().await?;
+        self.core.add_directory(name.into(), options)?;
+        self.core.write_pending().await?;
+        Ok(())
+    }
+
+    /// Finish the last file and write all other zip-structures
+    ///
+    /// This returns the writer, after flushing it.
+    pub async fn finish(mut self) -> ZipResult<W> {
+        self.finish_file().await?;
+        self.core.queue_central_directory()?;
+        self.core.write_pending().await?;
+        self.core.inner.flush().await?;
+        Ok(self.core.inner)
+    }
+
+    /// Discard the file being written, so that it isn't part of the archive.
+    ///
+    /// The writer seeks back to the local header of the file, so that what is written next
+    /// overwrites it. Like with [`crate::ZipWriter::abort_file`], the writer isn't truncated, but
+    /// the archive is finished with zeros before its central directory if it would otherwise end
+    /// before the end of the discarded data.
+    ///
+    /// Fails if no file is being written.
+    pub async fn abort_file(&mut self) -> ZipResult<()> {
+        let file = self.core.abort_file_data()?;
+        self.core.high_water_mark = self.core.high_water_mark.max(self.core.position);
+        self.core
+            .inner
+            .seek(io::SeekFrom::Start(file.header_start))
+            .await?;
+        self.core.position = file.header_start;
+        Ok(())
+    }
+}
+
+impl<W: AsyncWrite + AsyncSeek + Unpin> AsyncWrite for ZipWriter<W> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        self.get_mut().core.poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.get_mut().core.poll_flush(cx)
+    }
+
+    /// Flushes the written data, without finishing the archive or shutting down the writer
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.get_mut().core.poll_flush(cx)
+    }
+}
+
+impl<W: AsyncWrite + Unpin> ZipStreamWriter<W> {
+    /// Initializes the archive.
+    ///
+    /// Before writing to this object, the [`ZipStreamWriter::start_file`] function should be
+    /// called.
+    pub fn new(inner: W) -> ZipStreamWriter<W> {
+        ZipStreamWriter {
+            core: Core::new(inner),
+        }
+    }
+
+    /// Set ZIP archive comment.
+    pub fn set_comment<S>(&mut self, comment: S)
+    where
+        S: Into<String>,
+    {
+        self.set_raw_comment(comment.into().into())
+    }
+
+    /// Set ZIP archive comment.
+    ///
+    /// This sets the raw bytes of the comment. The comment
+    /// is typically expected to be encoded in UTF-8, and must be shorter than 64 KiB for the
+    /// archive to be finished.
+    pub fn set_raw_comment(&mut self, comment: Vec<u8>) {
+        self.core.comment = comment;
+    }
+
+    async fn finish_file(&mut self) -> ZipResult<()> {
+        if self.core.writing_to_file {
+            let mut descriptor = Vec::new();
+            write_data_descriptor(&mut descriptor, self.core.finish_file_data()?)?;
+            self.core.pending.extend_from_slice(&descriptor);
+        }
+        self.core.write_pending().await?;
+        Ok(())
+    }
+
+    /// Create a file in the archive and start writing its contents.
+    ///
+    /// The data should be written using the [`AsyncWrite`] implementation on this
+    /// [`ZipStreamWriter`]
+    pub async fn start_file<S>(&mut self, name: S, mut options: FileOptions<'_>) -> ZipResult<()>
+    where
+        S: Into<String>,
+    {
+        if options.encrypt_with.is_some() {
+            return Err(ZipError::UnsupportedArchive(
+                "Encrypted files cannot be written without seeking",
+            ));
+        }
+        options.large_file = Some(true);
+        self.finish_file().await?;
+        self.core.start_file(name.into(), options, true)?;
+        self.core.write_pending().await?;
+        Ok(())
+    }
+
+    /// Add a directory entry.
+    pub async fn add_directory<S>(&mut self, name: S, options: FileOptions<'_>) -> ZipResult<()>
+    where
+        S: Into<String>,
+    {
+        self.finish_file().await?;
+        self.core.add_directory(name.into(), options)?;
+        self.core.write_pending().await?;
+        Ok(())
+    }
+
+    /// Finish the last file and write all other zip-structures
+    ///
+    /// This returns the writer, after flushing it.
+    pub async fn finish(mut self) -> ZipResult<W> {
+        self.finish_file().await?;
+        self.core.queue_central_directory()?;
+        self.core.write_pending().await?;
+        self.core.inner.flush().await?;
+        Ok(self.core.inner)
+    }
+
+    /// Discard the file being written, so that it isn't part of the archive.
+    ///
+    /// What was already written of the file stays in the writer, but isn't referenced by the
+    /// central directory: readers using it skip these bytes.
+    ///
+    /// Fails if no file is being written.
+    pub fn abort_file(&mut self) -> ZipResult<()> {
+        self.core.abort_file_data()?;
+        Ok(())
+    }
+}
+
+impl<W: AsyncWrite + Unpin> AsyncWrite for ZipStreamWriter<W> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        self.get_mut().core.poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.get_mut().core.poll_flush(cx)
+    }
+
+    /// Flushes the written data, without finishing the archive or shutting down the writer
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.get_mut().core.poll_flush(cx)
+    }
+}
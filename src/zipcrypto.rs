@@ -4,17 +4,32 @@
 //! [https://courses.cs.ut.ee/MTAT.07.022/2015_fall/uploads/Main/dmitri-report-f15-16.pdf](https://courses.cs.ut.ee/MTAT.07.022/2015_fall/uploads/Main/dmitri-report-f15-16.pdf)
 
 use std::num::Wrapping;
+#[cfg(feature = "zeroize")]
+use zeroize::Zeroize;
 
-/// A container to hold the current key state
+/// The internal key state of ZipCrypto
+///
+/// Besides being derived from a password, the initial keys can be given directly, for example
+/// when they were recovered by a known-plaintext attack on an archive whose password is lost.
 #[derive(Clone, Copy)]
-pub(crate) struct ZipCryptoKeys {
+pub struct ZipCryptoKeys {
     key_0: Wrapping<u32>,
     key_1: Wrapping<u32>,
     key_2: Wrapping<u32>,
 }
 
 impl ZipCryptoKeys {
-    fn new() -> ZipCryptoKeys {
+    /// Create the key state from the three internal keys, as they are after processing the
+    /// password
+    pub fn new(key_0: u32, key_1: u32, key_2: u32) -> ZipCryptoKeys {
+        ZipCryptoKeys {
+            key_0: Wrapping(key_0),
+            key_1: Wrapping(key_1),
+            key_2: Wrapping(key_2),
+        }
+    }
+
+    fn initial() -> ZipCryptoKeys {
         ZipCryptoKeys {
             key_0: Wrapping(0x12345678),
             key_1: Wrapping(0x23456789),
@@ -50,8 +65,10 @@ impl ZipCryptoKeys {
     fn crc32(crc: Wrapping<u32>, input: u8) -> Wrapping<u32> {
         (crc >> 8) ^ Wrapping(CRCTABLE[((crc & Wrapping(0xff)).0 as u8 ^ input) as usize])
     }
-    pub(crate) fn derive(password: &[u8]) -> ZipCryptoKeys {
-        let mut keys = ZipCryptoKeys::new();
+
+    /// Derive the key state from a password
+    pub fn derive(password: &[u8]) -> ZipCryptoKeys {
+        let mut keys = ZipCryptoKeys::initial();
         for byte in password.iter() {
             keys.update(*byte);
         }
@@ -59,10 +76,60 @@ impl ZipCryptoKeys {
     }
 }
 
+#[cfg(feature = "zeroize")]
+impl Zeroize for ZipCryptoKeys {
+    fn zeroize(&mut self) {
+        self.key_0.zeroize();
+        self.key_1.zeroize();
+        self.key_2.zeroize();
+    }
+}
+
+/// Key state owned by a reader or writer, which is wiped when dropped if the `zeroize` feature
+/// is enabled
+///
+/// `ZipCryptoKeys` itself has to stay `Copy`, since `FileOptions` holds it, so the keys kept by
+/// options and their copies aren't wiped.
+pub(crate) struct SecretKeys(ZipCryptoKeys);
+
+impl From<ZipCryptoKeys> for SecretKeys {
+    fn from(keys: ZipCryptoKeys) -> SecretKeys {
+        SecretKeys(keys)
+    }
+}
+
+impl std::ops::Deref for SecretKeys {
+    type Target = ZipCryptoKeys;
+
+    fn deref(&self) -> &ZipCryptoKeys {
+        &self.0
+    }
+}
+
+impl std::ops::DerefMut for SecretKeys {
+    fn deref_mut(&mut self) -> &mut ZipCryptoKeys {
+        &mut self.0
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl Zeroize for SecretKeys {
+    fn zeroize(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl Drop for SecretKeys {
+    fn drop(&mut self) {
+        self.zeroize();
+    }
+}
+
 /// A ZipCrypto reader with unverified password
 pub struct ZipCryptoReader<R> {
     file: R,
-    keys: ZipCryptoKeys,
+    keys: SecretKeys,
 }
 
 pub enum ZipCryptoValidator {
@@ -80,7 +147,15 @@ impl<R: std::io::Read> ZipCryptoReader<R> {
     pub fn new(file: R, password: &[u8]) -> ZipCryptoReader<R> {
         ZipCryptoReader {
             file,
-            keys: ZipCryptoKeys::derive(password),
+            keys: ZipCryptoKeys::derive(password).into(),
+        }
+    }
+
+    /// Create a reader from keys that were already derived from the password
+    pub fn with_keys(file: R, keys: ZipCryptoKeys) -> ZipCryptoReader<R> {
+        ZipCryptoReader {
+            file,
+            keys: keys.into(),
         }
     }
 
@@ -126,7 +201,7 @@ impl<R: std::io::Read> ZipCryptoReader<R> {
 pub(crate) struct ZipCryptoWriter<W> {
     pub(crate) writer: W,
     pub(crate) buffer: Vec<u8>,
-    pub(crate) keys: ZipCryptoKeys,
+    pub(crate) keys: SecretKeys,
 }
 impl<W: std::io::Write> ZipCryptoWriter<W> {
     pub(crate) fn finish(mut self, crc32: u32) -> std::io::Result<W> {
@@ -208,3 +283,28 @@ static CRCTABLE: [u32; 256] = [
     0xbdbdf21c, 0xcabac28a, 0x53b39330, 0x24b4a3a6, 0xbad03605, 0xcdd70693, 0x54de5729, 0x23d967bf,
     0xb3667a2e, 0xc4614ab8, 0x5d681b02, 0x2a6f2b94, 0xb40bbe37, 0xc30c8ea1, 0x5a05df1b, 0x2d02ef8d,
 ];
+
+#[cfg(all(test, feature = "zeroize"))]
+mod test {
+    use super::{SecretKeys, ZipCryptoKeys};
+    use zeroize::Zeroize;
+
+    #[test]
+    fn zeroize_keys() {
+        let mut keys = ZipCryptoKeys::derive(b"password");
+        keys.zeroize();
+        assert_eq!((keys.key_0.0, keys.key_1.0, keys.key_2.0), (0, 0, 0));
+    }
+
+    #[test]
+    fn copied_keys_are_not_zeroized() {
+        // Like the keys a writer copies out of `FileOptions`
+        let keys = ZipCryptoKeys::derive(b"password");
+        let mut secret = SecretKeys::from(keys);
+        // What dropping the keys of a reader or writer does
+        secret.zeroize();
+        assert_eq!((secret.key_0.0, secret.key_1.0, secret.key_2.0), (0, 0, 0));
+        // The copy it was made from is left as is
+        assert_ne!((keys.key_0.0, keys.key_1.0, keys.key_2.0), (0, 0, 0));
+    }
+}
@@ -15,8 +15,7 @@ fn aes256_encrypted_uncompressed_file() {
 
     let mut file = archive
         .by_name_decrypt("secret_data_256_uncompressed", PASSWORD)
-        .expect("couldn't find file in archive")
-        .expect("invalid password");
+        .expect("couldn't open file in archive");
     assert_eq!("secret_data_256_uncompressed", file.name());
 
     let mut content = String::new();
@@ -33,8 +32,7 @@ fn aes256_encrypted_file() {
 
     let mut file = archive
         .by_name_decrypt("secret_data_256", PASSWORD)
-        .expect("couldn't find file in archive")
-        .expect("invalid password");
+        .expect("couldn't open file in archive");
     assert_eq!("secret_data_256", file.name());
 
     let mut content = String::new();
@@ -51,8 +49,7 @@ fn aes192_encrypted_file() {
 
     let mut file = archive
         .by_name_decrypt("secret_data_192", PASSWORD)
-        .expect("couldn't find file in archive")
-        .expect("invalid password");
+        .expect("couldn't open file in archive");
     assert_eq!("secret_data_192", file.name());
 
     let mut content = String::new();
@@ -69,8 +66,7 @@ fn aes128_encrypted_file() {
 
     let mut file = archive
         .by_name_decrypt("secret_data_128", PASSWORD)
-        .expect("couldn't find file in archive")
-        .expect("invalid password");
+        .expect("couldn't open file in archive");
     assert_eq!("secret_data_128", file.name());
 
     let mut content = String::new();
@@ -78,3 +74,164 @@ fn aes128_encrypted_file() {
         .expect("couldn't read encrypted file");
     assert_eq!(SECRET_CONTENT, content);
 }
+
+/// Find the start and length of an entry's encrypted data, and its central header
+fn entry_layout(v: &[u8], name: &str) -> (usize, usize, usize) {
+    let mut archive =
+        ZipArchive::new(io::Cursor::new(v.to_vec())).expect("couldn't open test zip file");
+    let file = archive
+        .by_name_decrypt(name, PASSWORD)
+        .expect("couldn't open file in archive");
+    (
+        file.data_start() as usize,
+        file.compressed_size() as usize,
+        file.central_header_start() as usize,
+    )
+}
+
+fn read_secret(v: Vec<u8>, name: &str) -> zip::result::ZipResult<String> {
+    let mut archive = ZipArchive::new(io::Cursor::new(v))?;
+    let mut file = archive.by_name_decrypt(name, PASSWORD)?;
+    let mut content = String::new();
+    file.read_to_string(&mut content)?;
+    Ok(content)
+}
+
+#[test]
+fn aes_tampered_data() {
+    for name in ["secret_data_256_uncompressed", "secret_data_256"] {
+        let mut v = include_bytes!("data/aes_archive.zip").to_vec();
+        let (data_start, _, _) = entry_layout(&v, name);
+        // Skip the 16 byte salt and the 2 byte password verification value
+        v[data_start + 18] ^= 1;
+        assert!(read_secret(v, name).is_err(), "{}", name);
+    }
+}
+
+#[test]
+fn aes_tampered_authentication_code() {
+    let name = "secret_data_128";
+    let mut v = include_bytes!("data/aes_archive.zip").to_vec();
+    let (data_start, compressed_size, _) = entry_layout(&v, name);
+    v[data_start + compressed_size - 1] ^= 1;
+    assert!(read_secret(v, name).is_err());
+}
+
+#[test]
+fn aes_compressed_size_too_small() {
+    let name = "secret_data_256";
+    let mut v = include_bytes!("data/aes_archive.zip").to_vec();
+    let (_, _, central_header_start) = entry_layout(&v, name);
+    v[central_header_start + 20..central_header_start + 24].copy_from_slice(&5u32.to_le_bytes());
+    assert!(read_secret(v, name).is_err());
+}
+
+#[test]
+fn aes_encrypted_files_streaming() {
+    let v = include_bytes!("data/aes_archive.zip");
+    let mut stream = &v[..];
+
+    let mut names = Vec::new();
+    while let Some(mut file) =
+        zip::read::read_zipfile_from_stream_decrypt(&mut stream, PASSWORD).unwrap()
+    {
+        let mut content = String::new();
+        file.read_to_string(&mut content)
+            .expect("couldn't read encrypted file");
+        assert_eq!(SECRET_CONTENT, content);
+        names.push(file.name().to_owned());
+    }
+    assert_eq!(names.len(), 4);
+}
+
+#[test]
+fn aes_encrypted_files_raw_copy() {
+    let mut source = ZipArchive::new(io::Cursor::new(
+        include_bytes!("data/aes_archive.zip").to_vec(),
+    ))
+    .unwrap();
+    let mut writer = zip::ZipWriter::new(io::Cursor::new(Vec::new()));
+    for i in 0..source.len() {
+        writer
+            .raw_copy_file(source.by_index_raw(i).unwrap())
+            .unwrap();
+    }
+    let mut copy = ZipArchive::new(writer.finish().unwrap()).unwrap();
+
+    assert_eq!(copy.len(), 4);
+    for i in 0..copy.len() {
+        let name = source.by_index_raw(i).unwrap().name().to_owned();
+        let mut file = copy.by_name_decrypt(&name, PASSWORD).unwrap();
+        let mut content = String::new();
+        file.read_to_string(&mut content)
+            .expect("couldn't read copied encrypted file");
+        assert_eq!(SECRET_CONTENT, content);
+    }
+}
+
+#[test]
+fn aes_encrypted_files_written() {
+    use std::io::Write;
+    use zip::write::FileOptions;
+    use zip::{AesMode, AesVendorVersion, CompressionMethod};
+
+    let modes = [AesMode::Aes128, AesMode::Aes192, AesMode::Aes256];
+    let versions = [AesVendorVersion::Ae1, AesVendorVersion::Ae2];
+    #[allow(unused_mut)]
+    let mut methods = vec![CompressionMethod::Stored];
+    #[cfg(any(
+        feature = "deflate",
+        feature = "deflate-miniz",
+        feature = "deflate-zlib"
+    ))]
+    methods.push(CompressionMethod::Deflated);
+
+    let mut writer = zip::ZipWriter::new(io::Cursor::new(Vec::new()));
+    let mut entries = Vec::new();
+    for (i, &mode) in modes.iter().enumerate() {
+        for &version in &versions {
+            for &method in &methods {
+                let name = format!("secret_{}_{:?}_{}", i, version, method);
+                let options = FileOptions::default()
+                    .compression_method(method)
+                    .encrypt_with_aes(mode, PASSWORD)
+                    .aes_vendor_version(version)
+                    .unix_mtime(1_700_000_001);
+                writer.start_file(name.as_str(), options).unwrap();
+                writer.write_all(SECRET_CONTENT.as_bytes()).unwrap();
+                entries.push((name, mode, version, method));
+            }
+        }
+    }
+    let v = writer.finish().unwrap().into_inner();
+
+    let mut archive = ZipArchive::new(io::Cursor::new(v.clone())).unwrap();
+    for (name, mode, version, method) in entries {
+        {
+            let file = archive.by_name_decrypt(&name, PASSWORD).unwrap();
+            assert_eq!(file.compression(), method, "{}", name);
+            assert_eq!(file.unix_mtime(), Some(1_700_000_001), "{}", name);
+            let crc32 = crc32fast::hash(SECRET_CONTENT.as_bytes());
+            match version {
+                AesVendorVersion::Ae1 => assert_eq!(file.crc32(), crc32, "{}", name),
+                AesVendorVersion::Ae2 => assert_eq!(file.crc32(), 0, "{}", name),
+            }
+            if method == CompressionMethod::Stored {
+                // Salt, password verification value, data and authentication code
+                let salt_length = match mode {
+                    AesMode::Aes128 => 8,
+                    AesMode::Aes192 => 12,
+                    AesMode::Aes256 => 16,
+                };
+                assert_eq!(
+                    file.compressed_size() as usize,
+                    salt_length + 2 + SECRET_CONTENT.len() + 10,
+                    "{}",
+                    name
+                );
+            }
+        }
+        assert_eq!(read_secret(v.clone(), &name).unwrap(), SECRET_CONTENT);
+        assert!(archive.by_name_decrypt(&name, b"wrong password").is_err());
+    }
+}
@@ -0,0 +1,108 @@
+use std::io::{Cursor, Write};
+use zip::read::TreeNode;
+use zip::write::FileOptions;
+use zip::{ZipArchive, ZipWriter};
+
+fn archive(names: &[&str]) -> ZipArchive<Cursor<Vec<u8>>> {
+    let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+    for name in names {
+        if name.ends_with('/') {
+            writer.add_directory(*name, FileOptions::default()).unwrap();
+        } else {
+            writer.start_file(*name, FileOptions::default()).unwrap();
+            writer.write_all(name.as_bytes()).unwrap();
+        }
+    }
+    ZipArchive::new(writer.finish().unwrap()).unwrap()
+}
+
+/// Render a node and its descendants, one per line, with directories ending in `/` and the
+/// entry index in brackets
+fn render(node: TreeNode<'_>, depth: usize, out: &mut String) {
+    for child in node.children() {
+        out.push_str(&"  ".repeat(depth));
+        out.push_str(child.name());
+        if child.is_dir() {
+            out.push('/');
+        }
+        match child.index() {
+            Some(i) => out.push_str(&format!(" [{i}]\n")),
+            None => out.push('\n'),
+        }
+        render(child, depth + 1, out);
+    }
+}
+
+#[test]
+fn explicit_and_implicit_directories() {
+    let archive = archive(&[
+        "docs/",
+        "docs/readme.txt",
+        "src/bin/main.rs",
+        "src/lib.rs",
+        "src\\windows.rs",
+        "top.txt",
+        "docs/guide/intro.txt",
+    ]);
+    let tree = archive.tree();
+    let mut rendered = String::new();
+    render(tree.root(), 0, &mut rendered);
+    assert_eq!(
+        rendered,
+        "docs/ [0]\n  readme.txt [1]\n  guide/\n    intro.txt [6]\n\
+         src/\n  bin/\n    main.rs [2]\n  lib.rs [3]\n  windows.rs [4]\n\
+         top.txt [5]\n"
+    );
+
+    let root = tree.root();
+    assert!(root.is_dir());
+    assert!(root.index().is_none());
+    assert!(root.parent().is_none());
+
+    let main = tree.get("src/bin/main.rs").unwrap();
+    assert_eq!(main.index(), Some(2));
+    assert!(!main.is_dir());
+    assert_eq!(main.children().count(), 0);
+    let bin = main.parent().unwrap();
+    assert_eq!(bin.name(), "bin");
+    assert_eq!(bin.parent().unwrap().name(), "src");
+
+    let names: Vec<&str> = tree.children("src").map(|node| node.name()).collect();
+    assert_eq!(names, ["bin", "lib.rs", "windows.rs"]);
+    assert_eq!(tree.children("src\\bin/").count(), 1);
+    assert_eq!(tree.children("").count(), 3);
+    assert_eq!(tree.children("top.txt").count(), 0);
+    assert_eq!(tree.children("missing").count(), 0);
+    assert!(tree.get("src/missing.rs").is_none());
+    assert!(tree.get("top.txt/inner").is_none());
+}
+
+#[test]
+fn duplicates_and_conflicts() {
+    let archive = archive(&["a.txt", "dir//b.txt", "a.txt", "x", "x/y.txt", "dir/", "/"]);
+    let tree = archive.tree();
+
+    // The last of the duplicates wins, as with `by_name`
+    let names: Vec<&str> = tree.root().children().map(|node| node.name()).collect();
+    assert_eq!(names, ["a.txt", "dir", "x", "x"]);
+    assert_eq!(tree.get("a.txt").unwrap().index(), Some(2));
+
+    // Empty components are skipped, and a later explicit entry fills in an implicit directory
+    let dir = tree.get("dir").unwrap();
+    assert_eq!(dir.index(), Some(5));
+    assert_eq!(tree.get("dir/b.txt").unwrap().index(), Some(1));
+
+    // A file and a directory with the same name are siblings, and lookups find the directory
+    let x = tree.get("x").unwrap();
+    assert!(x.is_dir());
+    assert!(x.index().is_none());
+    assert_eq!(tree.get("x/y.txt").unwrap().index(), Some(4));
+    let file = tree
+        .root()
+        .children()
+        .find(|node| node.name() == "x" && !node.is_dir());
+    assert_eq!(file.unwrap().index(), Some(3));
+
+    // An entry without any component is left out
+    assert!(tree.root().index().is_none());
+}
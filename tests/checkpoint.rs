@@ -0,0 +1,53 @@
+use std::fs;
+use std::io::{Cursor, Read, Write};
+use zip::write::FileOptions;
+use zip::{DateTime, ZipArchive, ZipWriter};
+
+fn options() -> FileOptions<'static> {
+    FileOptions::default()
+        .last_modified_time(DateTime::from_date_and_time(2023, 7, 1, 12, 0, 0).unwrap())
+}
+
+fn write_entry<W: Write + std::io::Seek>(writer: &mut ZipWriter<W>, i: usize) {
+    writer
+        .start_file(format!("entry{i}.txt"), options())
+        .unwrap();
+    writer
+        .write_all(format!("Contents of entry {i}").repeat(100).as_bytes())
+        .unwrap();
+}
+
+#[test]
+fn checkpoint_survives_crash() {
+    let temp = tempfile::tempdir().unwrap();
+    let path = temp.path().join("checkpoint.zip");
+    let mut writer = ZipWriter::new(fs::File::create(&path).unwrap());
+    write_entry(&mut writer, 1);
+    writer.checkpoint().unwrap();
+
+    // A crash now leaves the file as it is on disk
+    let crashed = fs::read(&path).unwrap();
+    let mut archive = ZipArchive::new(Cursor::new(crashed)).unwrap();
+    assert_eq!(archive.len(), 1);
+    let mut data = String::new();
+    archive
+        .by_name("entry1.txt")
+        .unwrap()
+        .read_to_string(&mut data)
+        .unwrap();
+    assert_eq!(data, "Contents of entry 1".repeat(100));
+
+    write_entry(&mut writer, 2);
+    writer.checkpoint().unwrap();
+    writer.checkpoint().unwrap();
+    write_entry(&mut writer, 3);
+    writer.finish().unwrap();
+
+    // Checkpoints leave nothing behind in the finished archive
+    let mut expected = ZipWriter::new(Cursor::new(Vec::new()));
+    for i in 1..=3 {
+        write_entry(&mut expected, i);
+    }
+    let expected = expected.finish().unwrap().into_inner();
+    assert!(fs::read(&path).unwrap() == expected);
+}
@@ -0,0 +1,54 @@
+#![cfg(any(
+    feature = "deflate",
+    feature = "deflate-miniz",
+    feature = "deflate-zlib"
+))]
+
+use std::io::{Cursor, Write};
+use zip::diff::{compare, compare_contents, ArchiveDiff};
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+fn archive(files: &[(&str, &str, CompressionMethod)]) -> ZipArchive<Cursor<Vec<u8>>> {
+    let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+    for (name, contents, method) in files {
+        writer
+            .start_file(*name, FileOptions::default().compression_method(*method))
+            .unwrap();
+        writer.write_all(contents.as_bytes()).unwrap();
+    }
+    ZipArchive::new(writer.finish().unwrap()).unwrap()
+}
+
+#[test]
+fn compare_archives() {
+    let stored = CompressionMethod::Stored;
+    let deflated = CompressionMethod::Deflated;
+    let mut a = archive(&[
+        ("same.txt", "same contents", stored),
+        ("removed.txt", "removed", stored),
+        ("changed.txt", "old contents", stored),
+        ("dir\\windows.txt", "windows", stored),
+        ("recompressed.txt", "recompressed", stored),
+    ]);
+    let mut b = archive(&[
+        ("recompressed.txt", "recompressed", deflated),
+        ("added.txt", "added", stored),
+        ("changed.txt", "new contents", stored),
+        ("dir/windows.txt", "windows", stored),
+        ("same.txt", "same contents", stored),
+    ]);
+
+    let expected = ArchiveDiff {
+        added: vec!["added.txt".to_owned()],
+        removed: vec!["removed.txt".to_owned()],
+        modified: vec!["changed.txt".to_owned()],
+    };
+    assert_eq!(compare(&a, &b), expected);
+    assert_eq!(compare_contents(&mut a, &mut b).unwrap(), expected);
+    assert!(compare(&a, &a).is_empty());
+
+    let reversed = compare(&b, &a);
+    assert_eq!(reversed.added, expected.removed);
+    assert_eq!(reversed.removed, expected.added);
+}
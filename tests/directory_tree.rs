@@ -0,0 +1,176 @@
+use std::fs;
+use std::io::Cursor;
+use std::path::Path;
+use tempfile::TempDir;
+use zip::write::{DirectoryTreeOptions, SymlinkPolicy};
+use zip::{ZipArchive, ZipWriter};
+
+fn create_tree() -> TempDir {
+    let tree = tempfile::tempdir().unwrap();
+    let root = tree.path();
+    fs::create_dir_all(root.join("sub/empty")).unwrap();
+    fs::write(root.join("a.txt"), b"Lorem ipsum").unwrap();
+    fs::write(root.join("sub/b.txt"), b"dolor sit amet").unwrap();
+    fs::write(root.join(".hidden"), b"consectetur").unwrap();
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(root.join("a.txt"), fs::Permissions::from_mode(0o755)).unwrap();
+        std::os::unix::fs::symlink("sub/b.txt", root.join("link")).unwrap();
+        std::os::unix::fs::symlink("sub", root.join("sublink")).unwrap();
+    }
+    tree
+}
+
+fn names(archive: &mut ZipArchive<Cursor<Vec<u8>>>) -> Vec<String> {
+    (0..archive.len())
+        .map(|i| archive.by_index(i).unwrap().name().to_owned())
+        .collect()
+}
+
+/// Assert that the trees at `a` and `b` have the same files, directories and symlinks
+fn assert_same_tree(a: &Path, b: &Path) {
+    let mut a_entries: Vec<_> = fs::read_dir(a).unwrap().map(|e| e.unwrap()).collect();
+    let mut b_entries: Vec<_> = fs::read_dir(b).unwrap().map(|e| e.unwrap()).collect();
+    a_entries.sort_by_key(|entry| entry.file_name());
+    b_entries.sort_by_key(|entry| entry.file_name());
+    assert_eq!(
+        a_entries.iter().map(|e| e.file_name()).collect::<Vec<_>>(),
+        b_entries.iter().map(|e| e.file_name()).collect::<Vec<_>>()
+    );
+    for (a_entry, b_entry) in a_entries.iter().zip(&b_entries) {
+        let a_type = a_entry.file_type().unwrap();
+        let b_type = b_entry.file_type().unwrap();
+        if a_type.is_symlink() {
+            assert!(b_type.is_symlink());
+            assert_eq!(
+                fs::read_link(a_entry.path()).unwrap(),
+                fs::read_link(b_entry.path()).unwrap()
+            );
+        } else if a_type.is_dir() {
+            assert!(b_type.is_dir());
+            assert_same_tree(&a_entry.path(), &b_entry.path());
+        } else {
+            assert_eq!(
+                fs::read(a_entry.path()).unwrap(),
+                fs::read(b_entry.path()).unwrap()
+            );
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::PermissionsExt;
+                assert_eq!(
+                    a_entry.metadata().unwrap().permissions().mode(),
+                    b_entry.metadata().unwrap().permissions().mode()
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn extract_directory_tree() {
+    let src = create_tree();
+    let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+    writer
+        .add_directory_tree(src.path(), DirectoryTreeOptions::default())
+        .unwrap();
+    let mut archive = ZipArchive::new(writer.finish().unwrap()).unwrap();
+
+    let dst = tempfile::tempdir().unwrap();
+    archive.extract(dst.path()).unwrap();
+    assert_same_tree(src.path(), dst.path());
+}
+
+#[test]
+fn directory_tree_options() {
+    let src = create_tree();
+    let filter = |path: &Path| path != Path::new("sub").join("empty");
+    let options = DirectoryTreeOptions::default()
+        .prefix("root")
+        .include_hidden(false)
+        .add_directories(false)
+        .symlinks(SymlinkPolicy::Follow)
+        .filter(&filter);
+    let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+    writer.add_directory_tree(src.path(), options).unwrap();
+    let mut archive = ZipArchive::new(writer.finish().unwrap()).unwrap();
+
+    #[cfg(unix)]
+    assert_eq!(
+        names(&mut archive),
+        [
+            "root/a.txt",
+            "root/link",
+            "root/sub/b.txt",
+            "root/sublink/b.txt"
+        ]
+    );
+    #[cfg(not(unix))]
+    assert_eq!(names(&mut archive), ["root/a.txt", "root/sub/b.txt"]);
+}
+
+#[cfg(unix)]
+#[test]
+fn directory_tree_symlink_loop() {
+    let src = create_tree();
+    std::os::unix::fs::symlink("..", src.path().join("sub/parent")).unwrap();
+
+    let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+    writer
+        .add_directory_tree(src.path(), DirectoryTreeOptions::default())
+        .unwrap();
+    assert!(writer
+        .add_directory_tree(
+            src.path(),
+            DirectoryTreeOptions::default()
+                .prefix("followed")
+                .symlinks(SymlinkPolicy::Follow)
+        )
+        .is_err());
+}
+
+#[cfg(unix)]
+#[test]
+fn directory_tree_unix_ownership() {
+    use std::os::unix::fs::MetadataExt;
+
+    let src = create_tree();
+    let metadata = fs::metadata(src.path().join("a.txt")).unwrap();
+    let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+    writer
+        .add_directory_tree(
+            src.path(),
+            DirectoryTreeOptions::default().unix_ownership(true),
+        )
+        .unwrap();
+    writer
+        .add_directory_tree(src.path(), DirectoryTreeOptions::default().prefix("plain"))
+        .unwrap();
+    let mut archive = ZipArchive::new(writer.finish().unwrap()).unwrap();
+
+    let file = archive.by_name("a.txt").unwrap();
+    assert_eq!(file.unix_uid(), Some(metadata.uid()));
+    assert_eq!(file.unix_gid(), Some(metadata.gid()));
+    drop(file);
+    assert_eq!(archive.by_name("plain/a.txt").unwrap().unix_uid(), None);
+}
+
+#[cfg(unix)]
+#[test]
+fn directory_tree_special_files() {
+    use std::os::unix::ffi::OsStringExt;
+
+    let src = create_tree();
+    let fifo = std::ffi::CString::new(src.path().join("fifo").into_os_string().into_vec()).unwrap();
+    assert_eq!(unsafe { libc::mkfifo(fifo.as_ptr(), 0o644) }, 0);
+    let _socket = std::os::unix::net::UnixListener::bind(src.path().join("socket")).unwrap();
+
+    let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+    writer
+        .add_directory_tree(src.path(), DirectoryTreeOptions::default())
+        .unwrap();
+    let mut archive = ZipArchive::new(writer.finish().unwrap()).unwrap();
+    let names = names(&mut archive);
+    assert!(!names.iter().any(|name| name == "fifo" || name == "socket"));
+    assert!(names.iter().any(|name| name == "a.txt"));
+}
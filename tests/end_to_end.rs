@@ -3,6 +3,7 @@ use std::collections::HashSet;
 use std::io::prelude::*;
 use std::io::{Cursor, Seek};
 use std::iter::FromIterator;
+use zip::result::ZipError;
 use zip::write::FileOptions;
 use zip::{CompressionMethod, SUPPORTED_COMPRESSION_METHODS};
 
@@ -85,6 +86,50 @@ fn append() {
     }
 }
 
+// This test asserts that entries can be removed and renamed when appending, both those already
+// in the archive and those added since it was opened.
+#[test]
+fn append_remove_rename() {
+    let mut file = &mut Cursor::new(Vec::new());
+    write_test_archive(file, CompressionMethod::Stored).expect("Couldn't write to test file");
+
+    {
+        let mut zip = zip::ZipWriter::new_append(&mut file).unwrap();
+        zip.remove_entry("test/☃.txt").unwrap();
+        zip.rename_entry(ENTRY_NAME, COPY_ENTRY_NAME).unwrap();
+        zip.start_file("added.txt", FileOptions::default()).unwrap();
+        zip.write_all(LOREM_IPSUM).unwrap();
+        zip.start_file("removed.txt", FileOptions::default())
+            .unwrap();
+        zip.rename_entry("added.txt", "test/añadido.txt").unwrap();
+        zip.remove_entry("removed.txt").unwrap();
+        assert!(matches!(
+            zip.remove_entry("test/☃.txt"),
+            Err(ZipError::FileNotFound)
+        ));
+        assert!(matches!(
+            zip.rename_entry(ENTRY_NAME, "other.txt"),
+            Err(ZipError::FileNotFound)
+        ));
+        zip.finish().unwrap();
+    }
+
+    let mut zip = zip::ZipArchive::new(&mut file).unwrap();
+    let file_names = zip.file_names().collect::<HashSet<_>>();
+    let expected_file_names = [
+        "test/",
+        "test_with_extra_data/🐢.txt",
+        COPY_ENTRY_NAME,
+        "test/añadido.txt",
+    ];
+    assert_eq!(
+        file_names,
+        HashSet::from_iter(expected_file_names.iter().copied())
+    );
+    check_archive_file_contents(&mut zip, COPY_ENTRY_NAME, LOREM_IPSUM);
+    check_archive_file_contents(&mut zip, "test/añadido.txt", LOREM_IPSUM);
+}
+
 // Write a test zip archive to buffer.
 fn write_test_archive(
     file: &mut Cursor<Vec<u8>>,
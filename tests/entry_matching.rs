@@ -0,0 +1,73 @@
+use std::io::{Cursor, Write};
+use zip::write::FileOptions;
+use zip::{ZipArchive, ZipWriter};
+
+fn archive() -> ZipArchive<Cursor<Vec<u8>>> {
+    let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+    for name in [
+        "assets/",
+        "assets/textures/",
+        "assets/textures/wall.png",
+        "assets/textures/wall.txt",
+        "assets/textures/hd/floor.png",
+        "assets/sounds/step.ogg",
+        "assets_old/textures/wall.png",
+        "readme.png",
+    ] {
+        if name.ends_with('/') {
+            writer.add_directory(name, FileOptions::default()).unwrap();
+        } else {
+            writer.start_file(name, FileOptions::default()).unwrap();
+            writer.write_all(name.as_bytes()).unwrap();
+        }
+    }
+    ZipArchive::new(writer.finish().unwrap()).unwrap()
+}
+
+fn names(archive: &mut ZipArchive<Cursor<Vec<u8>>>, indices: Vec<usize>) -> Vec<String> {
+    indices
+        .into_iter()
+        .map(|i| archive.by_index(i).unwrap().name().to_owned())
+        .collect()
+}
+
+#[test]
+fn indices_with_prefix() {
+    let mut archive = archive();
+    let indices = archive.indices_with_prefix("assets/textures/").collect();
+    assert_eq!(
+        names(&mut archive, indices),
+        [
+            "assets/textures/",
+            "assets/textures/wall.png",
+            "assets/textures/wall.txt",
+            "assets/textures/hd/floor.png",
+        ]
+    );
+    assert_eq!(archive.indices_with_prefix("").count(), archive.len());
+    assert_eq!(archive.indices_with_prefix("missing/").count(), 0);
+}
+
+#[cfg(feature = "glob")]
+#[test]
+fn indices_matching() {
+    let mut archive = archive();
+    let indices = archive
+        .indices_matching("assets/textures/*.png")
+        .unwrap()
+        .collect();
+    assert_eq!(names(&mut archive, indices), ["assets/textures/wall.png"]);
+
+    let indices = archive
+        .indices_matching("assets/**/*.png")
+        .unwrap()
+        .collect();
+    assert_eq!(
+        names(&mut archive, indices),
+        ["assets/textures/wall.png", "assets/textures/hd/floor.png"]
+    );
+
+    let indices = archive.indices_matching("*.png").unwrap().collect();
+    assert_eq!(names(&mut archive, indices), ["readme.png"]);
+    assert!(archive.indices_matching("assets/[").is_err());
+}
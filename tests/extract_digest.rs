@@ -0,0 +1,50 @@
+#![cfg(feature = "sha2")]
+
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::io::{Cursor, Write};
+use zip::read::{ExtractOptions, HashAlgorithm};
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+#[test]
+fn extract_with_sha256_digests() {
+    let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+    writer
+        .add_directory("dir/", FileOptions::default())
+        .unwrap();
+    writer
+        .start_file(
+            "dir/stored.txt",
+            FileOptions::default().compression_method(CompressionMethod::Stored),
+        )
+        .unwrap();
+    writer.write_all(b"Stored contents").unwrap();
+    writer
+        .start_file("compressed.txt", FileOptions::default())
+        .unwrap();
+    writer
+        .write_all("Compressed contents\n".repeat(1000).as_bytes())
+        .unwrap();
+    let mut archive = ZipArchive::new(writer.finish().unwrap()).unwrap();
+
+    let temp = tempfile::tempdir().unwrap();
+    let directory = temp.path();
+    let options = ExtractOptions::default().hasher(HashAlgorithm::Sha256);
+    let summary = archive.extract_with_options(directory, &options).unwrap();
+
+    assert_eq!(summary.digests.len(), 2);
+    for name in ["dir/stored.txt", "compressed.txt"] {
+        let extracted = fs::read(directory.join(name)).unwrap();
+        assert_eq!(summary.digests[name], Sha256::digest(&extracted).to_vec());
+    }
+    assert_eq!(
+        fs::read(directory.join("compressed.txt")).unwrap(),
+        "Compressed contents\n".repeat(1000).into_bytes()
+    );
+
+    let summary = archive
+        .extract_with_options(directory, &ExtractOptions::default())
+        .unwrap();
+    assert!(summary.digests.is_empty());
+}
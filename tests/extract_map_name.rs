@@ -0,0 +1,65 @@
+use std::fs;
+use std::io::{Cursor, Write};
+use std::path::{Path, PathBuf};
+use zip::read::ExtractOptions;
+use zip::write::FileOptions;
+use zip::{ZipArchive, ZipWriter};
+
+fn archive() -> ZipArchive<Cursor<Vec<u8>>> {
+    let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+    writer
+        .add_directory("docs/guide/", FileOptions::default())
+        .unwrap();
+    for name in [
+        "docs/guide/intro.md",
+        "docs/index.md",
+        "bin/tool",
+        "bin/tool.pdb",
+    ] {
+        writer.start_file(name, FileOptions::default()).unwrap();
+        writer.write_all(name.as_bytes()).unwrap();
+    }
+    ZipArchive::new(writer.finish().unwrap()).unwrap()
+}
+
+#[test]
+fn extract_map_name() {
+    let temp = tempfile::tempdir().unwrap();
+    let directory = temp.path();
+    let options = ExtractOptions::default().map_name(|path| {
+        if path
+            .extension()
+            .map_or(false, |extension| extension == "pdb")
+        {
+            return None;
+        }
+        match path.strip_prefix("docs") {
+            Ok(doc) => Some(Path::new("share/doc").join(doc)),
+            Err(_) => Some(path.to_path_buf()),
+        }
+    });
+    archive().extract_with_options(directory, &options).unwrap();
+
+    assert!(directory.join("share/doc/guide").is_dir());
+    for (path, name) in [
+        ("share/doc/guide/intro.md", "docs/guide/intro.md"),
+        ("share/doc/index.md", "docs/index.md"),
+        ("bin/tool", "bin/tool"),
+    ] {
+        assert_eq!(fs::read_to_string(directory.join(path)).unwrap(), name);
+    }
+    assert!(!directory.join("docs").exists());
+    assert!(!directory.join("bin/tool.pdb").exists());
+}
+
+#[test]
+fn extract_map_name_escaping() {
+    for mapped in ["../escaped", "/absolute", "."] {
+        let directory = tempfile::tempdir().unwrap();
+        let mapped = PathBuf::from(mapped);
+        let options = ExtractOptions::default().map_name(move |_| Some(mapped.clone()));
+        assert!(archive()
+            .extract_with_options(directory.path(), &options)
+            .is_err());
+    }
+}
@@ -0,0 +1,48 @@
+use std::fs;
+use std::io::{Cursor, Write};
+use zip::read::ExtractOptions;
+use zip::write::FileOptions;
+use zip::{ZipArchive, ZipWriter};
+
+const IMAGE_LENGTH: usize = 10 * 1024 * 1024;
+
+fn disk_image() -> Vec<u8> {
+    let mut image = vec![0; IMAGE_LENGTH];
+    image[..16].copy_from_slice(b"boot sector data");
+    image[5 * 1024 * 1024 + 100] = 1;
+    image
+}
+
+#[test]
+fn extract_sparse() {
+    let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+    writer
+        .start_file("disk.img", FileOptions::default())
+        .unwrap();
+    writer.write_all(&disk_image()).unwrap();
+    // Ends with a hole
+    writer
+        .start_file("zeros.img", FileOptions::default())
+        .unwrap();
+    writer.write_all(&vec![0; IMAGE_LENGTH]).unwrap();
+    let mut archive = ZipArchive::new(writer.finish().unwrap()).unwrap();
+
+    let temp = tempfile::tempdir().unwrap();
+    let directory = temp.path();
+    archive
+        .extract_with_options(directory, &ExtractOptions::default().sparse(true))
+        .unwrap();
+
+    assert!(fs::read(directory.join("disk.img")).unwrap() == disk_image());
+    assert!(fs::read(directory.join("zeros.img")).unwrap() == vec![0; IMAGE_LENGTH]);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        // Filesystems without sparse files allocate every block
+        let metadata = fs::metadata(directory.join("zeros.img")).unwrap();
+        if metadata.blocks() * 512 < metadata.len() {
+            let metadata = fs::metadata(directory.join("disk.img")).unwrap();
+            assert!(metadata.blocks() * 512 < metadata.len());
+        }
+    }
+}
@@ -0,0 +1,54 @@
+use std::fs;
+use std::io::{Cursor, Write};
+use zip::read::ExtractOptions;
+use zip::write::FileOptions;
+use zip::{ZipArchive, ZipWriter};
+
+#[test]
+fn extract_strip_components() {
+    let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+    writer
+        .add_directory("pkg-1.0/", FileOptions::default())
+        .unwrap();
+    writer
+        .add_directory("pkg-1.0/src/", FileOptions::default())
+        .unwrap();
+    for name in ["pkg-1.0/README.md", "pkg-1.0/src/lib.rs"] {
+        writer.start_file(name, FileOptions::default()).unwrap();
+        writer.write_all(name.as_bytes()).unwrap();
+    }
+    let mut archive = ZipArchive::new(writer.finish().unwrap()).unwrap();
+
+    let temp = tempfile::tempdir().unwrap();
+    let directory = temp.path();
+    archive
+        .extract_with_options(directory, &ExtractOptions::default().strip_components(1))
+        .unwrap();
+
+    assert!(!directory.join("pkg-1.0").exists());
+    assert_eq!(
+        fs::read_to_string(directory.join("README.md")).unwrap(),
+        "pkg-1.0/README.md"
+    );
+    assert_eq!(
+        fs::read_to_string(directory.join("src/lib.rs")).unwrap(),
+        "pkg-1.0/src/lib.rs"
+    );
+    let mut names: Vec<_> = fs::read_dir(directory)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .collect();
+    names.sort();
+    assert_eq!(names, ["README.md", "src"]);
+
+    // Only the files under src/ are deep enough
+    fs::remove_dir_all(directory).unwrap();
+    archive
+        .extract_with_options(directory, &ExtractOptions::default().strip_components(2))
+        .unwrap();
+    let names: Vec<_> = fs::read_dir(directory)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name())
+        .collect();
+    assert_eq!(names, ["lib.rs"]);
+}
@@ -0,0 +1,61 @@
+#![cfg(feature = "legacy-decompress")]
+
+use std::io::{self, Read};
+use zip::{CompressionMethod, ZipArchive};
+
+fn check_archive(data: &[u8], methods: &[(&str, CompressionMethod)]) {
+    let mut archive =
+        ZipArchive::new(io::Cursor::new(data.to_vec())).expect("couldn't open test zip file");
+    assert_eq!(archive.len(), methods.len());
+
+    for &(name, method) in methods {
+        let mut file = archive
+            .by_name(name)
+            .expect("couldn't find file in archive");
+        assert_eq!(file.compression(), method);
+
+        let mut content = Vec::new();
+        file.read_to_end(&mut content)
+            .expect("couldn't decompress file");
+        assert_eq!(
+            content,
+            include_bytes!("data/legacy.txt").as_ref(),
+            "{}",
+            name
+        );
+    }
+}
+
+#[test]
+fn decompress_shrink() {
+    check_archive(
+        include_bytes!("data/shrink.zip"),
+        &[("shrink.txt", CompressionMethod::Shrink)],
+    );
+}
+
+// Info-ZIP's unzip is built without Reduce, so these were checked with another decoder instead:
+// the `legacy-zip` feature of zip 8.6.0 decompresses all four entries to legacy.txt.
+#[test]
+fn decompress_reduce() {
+    check_archive(
+        include_bytes!("data/reduce.zip"),
+        &[
+            ("reduce1.txt", CompressionMethod::Reduce1),
+            ("reduce2.txt", CompressionMethod::Reduce2),
+            ("reduce3.txt", CompressionMethod::Reduce3),
+            ("reduce4.txt", CompressionMethod::Reduce4),
+        ],
+    );
+}
+
+#[test]
+fn decompress_implode() {
+    check_archive(
+        include_bytes!("data/implode.zip"),
+        &[
+            ("implode_4k_2trees.txt", CompressionMethod::Implode),
+            ("implode_8k_3trees.txt", CompressionMethod::Implode),
+        ],
+    );
+}
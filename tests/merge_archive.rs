@@ -0,0 +1,84 @@
+use std::fs;
+use std::io::{Cursor, Write};
+use std::path::Path;
+use zip::write::{Duplicate, FileOptions};
+use zip::{ZipArchive, ZipWriter};
+
+fn archive(files: &[(&str, &str)]) -> ZipArchive<Cursor<Vec<u8>>> {
+    let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+    for (name, contents) in files {
+        if name.ends_with('/') {
+            writer.add_directory(*name, FileOptions::default()).unwrap();
+        } else {
+            writer.start_file(*name, FileOptions::default()).unwrap();
+            writer.write_all(contents.as_bytes()).unwrap();
+        }
+    }
+    ZipArchive::new(writer.finish().unwrap()).unwrap()
+}
+
+fn read_tree(root: &Path, dir: &Path, entries: &mut Vec<(String, Option<Vec<u8>>)>) {
+    let mut children: Vec<_> = fs::read_dir(dir).unwrap().map(|e| e.unwrap()).collect();
+    children.sort_by_key(|entry| entry.file_name());
+    for child in children {
+        let path = child.path();
+        let name = path
+            .strip_prefix(root)
+            .unwrap()
+            .to_string_lossy()
+            .into_owned();
+        if child.file_type().unwrap().is_dir() {
+            entries.push((name, None));
+            read_tree(root, &path, entries);
+        } else {
+            entries.push((name, Some(fs::read(&path).unwrap())));
+        }
+    }
+}
+
+fn extract(archives: &mut [ZipArchive<Cursor<Vec<u8>>>]) -> Vec<(String, Option<Vec<u8>>)> {
+    let dir = tempfile::tempdir().unwrap();
+    for archive in archives {
+        archive.extract(dir.path()).unwrap();
+    }
+    let mut entries = Vec::new();
+    read_tree(dir.path(), dir.path(), &mut entries);
+    entries
+}
+
+#[test]
+fn merge_archives() {
+    let mut a = archive(&[
+        ("a/", ""),
+        ("a/one.txt", "Lorem ipsum"),
+        ("top.txt", "dolor"),
+    ]);
+    let mut b = archive(&[("b/two.txt", "sit amet"), ("b/three.txt", "consectetur")]);
+
+    let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+    assert_eq!(
+        writer.merge_archive(&mut a).unwrap(),
+        ["a/", "a/one.txt", "top.txt"]
+    );
+    assert_eq!(
+        writer.merge_archive(&mut b).unwrap(),
+        ["b/two.txt", "b/three.txt"]
+    );
+    let merged = ZipArchive::new(writer.finish().unwrap()).unwrap();
+    assert_eq!(merged.len(), 5);
+
+    assert_eq!(extract(&mut [merged]), extract(&mut [a, b]));
+}
+
+#[test]
+fn merge_archive_name_collision() {
+    let mut a = archive(&[("one.txt", "Lorem ipsum")]);
+    let mut b = archive(&[("two.txt", "dolor"), ("one.txt", "sit amet")]);
+
+    let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+    writer.set_duplicate_policy(Duplicate::Error);
+    writer.merge_archive(&mut a).unwrap();
+    assert!(writer.merge_archive(&mut b).is_err());
+    let merged = ZipArchive::new(writer.finish().unwrap()).unwrap();
+    assert_eq!(merged.len(), 1);
+}
@@ -0,0 +1,82 @@
+#![cfg(feature = "parallel")]
+
+use std::io::{Cursor, Read, Write};
+use zip::unstable::write::FileOptionsExt;
+use zip::write::FileOptions;
+use zip::{CompressionMethod, DateTime, ZipArchive, ZipWriter};
+
+fn options(method: CompressionMethod) -> FileOptions<'static> {
+    FileOptions::default()
+        .compression_method(method)
+        .last_modified_time(DateTime::from_date_and_time(2023, 7, 1, 12, 0, 0).unwrap())
+}
+
+fn contents(i: usize) -> Vec<u8> {
+    format!("File {i}: Lorem ipsum dolor sit amet, consectetur adipiscing elit\n")
+        .repeat(i * 50)
+        .into_bytes()
+}
+
+fn write_archive(mut writer: ZipWriter<Cursor<Vec<u8>>>) -> Vec<u8> {
+    for i in 0..20 {
+        let method = if i % 2 == 0 {
+            CompressionMethod::Stored
+        } else {
+            CompressionMethod::Deflated
+        };
+        let name = format!("file{i}.txt");
+        if i == 7 {
+            writer.add_directory("dir/", options(method)).unwrap();
+        }
+        if i == 11 {
+            // Written in place, after the files before it
+            writer
+                .start_file(
+                    &name,
+                    options(method).with_deprecated_encryption(b"password"),
+                )
+                .unwrap();
+            writer.write_all(&contents(i)).unwrap();
+        } else {
+            writer
+                .add_entry(name, contents(i).as_slice(), options(method))
+                .unwrap();
+        }
+    }
+    writer.finish().unwrap().into_inner()
+}
+
+#[test]
+fn same_archive_as_serial() {
+    let serial = write_archive(ZipWriter::new(Cursor::new(Vec::new())));
+    let parallel = write_archive(ZipWriter::new(Cursor::new(Vec::new())).with_parallelism(4));
+    assert!(serial == parallel);
+
+    let mut archive = ZipArchive::new(Cursor::new(parallel)).unwrap();
+    assert_eq!(archive.len(), 21);
+    for i in 0..20 {
+        let name = format!("file{i}.txt");
+        let mut file = if i == 11 {
+            archive.by_name_decrypt(&name, b"password").unwrap()
+        } else {
+            archive.by_name(&name).unwrap()
+        };
+        let mut data = Vec::new();
+        file.read_to_end(&mut data).unwrap();
+        assert_eq!(data, contents(i));
+    }
+}
+
+#[test]
+fn compression_error() {
+    let mut writer = ZipWriter::new(Cursor::new(Vec::new())).with_parallelism(2);
+    assert!(writer
+        .add_entry(
+            "a.txt",
+            &b"a"[..],
+            options(CompressionMethod::Deflated).compression_level(Some(100)),
+        )
+        .is_err());
+    let archive = ZipArchive::new(writer.finish().unwrap()).unwrap();
+    assert!(archive.is_empty());
+}
@@ -0,0 +1,135 @@
+use std::io::{Cursor, Write};
+use zip::read::ParseDiagnostic;
+use zip::write::FileOptions;
+use zip::{ZipArchive, ZipWriter};
+
+const CONTENTS: &[u8] = b"contents";
+
+/// An archive with one stored file, whose name has the general purpose `flags`, and whose
+/// central header has the extra data `extra`
+fn fixture(name: &[u8], flags: u16, extra: &[u8]) -> Vec<u8> {
+    let crc32 = crc32fast::hash(CONTENTS);
+    let mut data = Vec::new();
+    data.extend_from_slice(&0x04034b50u32.to_le_bytes());
+    data.extend_from_slice(&20u16.to_le_bytes());
+    data.extend_from_slice(&flags.to_le_bytes());
+    data.extend_from_slice(&[0; 6]);
+    data.extend_from_slice(&crc32.to_le_bytes());
+    data.extend_from_slice(&(CONTENTS.len() as u32).to_le_bytes());
+    data.extend_from_slice(&(CONTENTS.len() as u32).to_le_bytes());
+    data.extend_from_slice(&(name.len() as u16).to_le_bytes());
+    data.extend_from_slice(&0u16.to_le_bytes());
+    data.extend_from_slice(name);
+    data.extend_from_slice(CONTENTS);
+
+    let central_start = data.len();
+    data.extend_from_slice(&0x02014b50u32.to_le_bytes());
+    data.extend_from_slice(&[20, 3, 20, 0]);
+    data.extend_from_slice(&flags.to_le_bytes());
+    data.extend_from_slice(&[0; 6]);
+    data.extend_from_slice(&crc32.to_le_bytes());
+    data.extend_from_slice(&(CONTENTS.len() as u32).to_le_bytes());
+    data.extend_from_slice(&(CONTENTS.len() as u32).to_le_bytes());
+    data.extend_from_slice(&(name.len() as u16).to_le_bytes());
+    data.extend_from_slice(&(extra.len() as u16).to_le_bytes());
+    data.extend_from_slice(&[0; 10]);
+    data.extend_from_slice(&0u32.to_le_bytes());
+    data.extend_from_slice(name);
+    data.extend_from_slice(extra);
+
+    let central_size = data.len() - central_start;
+    data.extend_from_slice(&0x06054b50u32.to_le_bytes());
+    data.extend_from_slice(&[0, 0, 0, 0, 1, 0, 1, 0]);
+    data.extend_from_slice(&(central_size as u32).to_le_bytes());
+    data.extend_from_slice(&(central_start as u32).to_le_bytes());
+    data.extend_from_slice(&0u16.to_le_bytes());
+    data
+}
+
+#[test]
+fn no_diagnostics() {
+    let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+    writer.start_file("é.txt", FileOptions::default()).unwrap();
+    writer.write_all(CONTENTS).unwrap();
+    let data = writer.finish().unwrap().into_inner();
+    let (archive, diagnostics) = ZipArchive::new_with_diagnostics(Cursor::new(data)).unwrap();
+    assert_eq!(archive.len(), 1);
+    assert_eq!(diagnostics, []);
+}
+
+#[test]
+fn truncated_extra_field() {
+    // An extended timestamp field that should hold a modification time, but ends after its flags
+    let data = fixture(b"file.txt", 0, &[0x55, 0x54, 5, 0, 1]);
+    let (mut archive, diagnostics) =
+        ZipArchive::new_with_diagnostics(Cursor::new(data.clone())).unwrap();
+    assert_eq!(
+        diagnostics,
+        [ParseDiagnostic::TruncatedExtraField { index: 0 }]
+    );
+    assert_eq!(
+        diagnostics[0].to_string(),
+        "extra field truncated in entry 0"
+    );
+    assert!(archive.by_index(0).unwrap().unix_mtime().is_none());
+
+    // The normal constructor still reads it the same way
+    let mut archive = ZipArchive::new(Cursor::new(data)).unwrap();
+    assert_eq!(archive.by_index(0).unwrap().name(), "file.txt");
+}
+
+#[test]
+fn unknown_extra_field() {
+    let data = fixture(b"file.txt", 0, &[0xfe, 0xca, 0, 0, 0x55, 0x54, 1, 0, 0]);
+    let (_, diagnostics) = ZipArchive::new_with_diagnostics(Cursor::new(data)).unwrap();
+    assert_eq!(
+        diagnostics,
+        [ParseDiagnostic::UnknownExtraField {
+            index: 0,
+            id: 0xcafe,
+        }]
+    );
+}
+
+#[test]
+fn cp437_name() {
+    let data = fixture(b"caf\x82.txt", 0, &[]);
+    let (mut archive, diagnostics) = ZipArchive::new_with_diagnostics(Cursor::new(data)).unwrap();
+    assert_eq!(diagnostics, [ParseDiagnostic::Cp437Name { index: 0 }]);
+    assert_eq!(archive.by_index(0).unwrap().name(), "café.txt");
+
+    // Names marked as UTF-8, and ASCII names, decode the same in both encodings
+    let data = fixture(b"caf\xc3\xa9.txt", 1 << 11, &[]);
+    let (_, diagnostics) = ZipArchive::new_with_diagnostics(Cursor::new(data)).unwrap();
+    assert_eq!(diagnostics, []);
+    let data = fixture(b"cafe.txt", 0, &[]);
+    let (_, diagnostics) = ZipArchive::new_with_diagnostics(Cursor::new(data)).unwrap();
+    assert_eq!(diagnostics, []);
+
+    // Nor are names given by an Info-ZIP Unicode Path field
+    let name = b"caf\x82.txt";
+    let mut extra = vec![0x75, 0x70, 14, 0, 1];
+    extra.extend_from_slice(&crc32fast::hash(name).to_le_bytes());
+    extra.extend_from_slice("café.txt".as_bytes());
+    let data = fixture(name, 0, &extra);
+    let (mut archive, diagnostics) = ZipArchive::new_with_diagnostics(Cursor::new(data)).unwrap();
+    assert_eq!(diagnostics, []);
+    assert_eq!(archive.by_index(0).unwrap().name(), "café.txt");
+}
+
+#[test]
+fn entry_count_mismatch() {
+    let mut data = fixture(b"file.txt", 0, &[]);
+    // The total number of entries, after the number of entries on this disk
+    let count = data.len() - 12;
+    data[count] = 3;
+    let (archive, diagnostics) = ZipArchive::new_with_diagnostics(Cursor::new(data)).unwrap();
+    assert_eq!(archive.len(), 1);
+    assert_eq!(
+        diagnostics,
+        [ParseDiagnostic::EntryCountMismatch {
+            recorded: 3,
+            parsed: 1,
+        }]
+    );
+}
@@ -0,0 +1,133 @@
+use std::io::{Cursor, Read, Write};
+use zip::result::ZipError;
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+fn source_archive() -> ZipArchive<Cursor<Vec<u8>>> {
+    let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+    writer.set_comment("mod pack");
+    writer
+        .start_file(
+            "stored.txt",
+            FileOptions::default().compression_method(CompressionMethod::Stored),
+        )
+        .unwrap();
+    writer.write_all(b"stored contents").unwrap();
+    writer
+        .add_directory("textures", FileOptions::default())
+        .unwrap();
+    writer
+        .start_file("textures/texture.png", FileOptions::default())
+        .unwrap();
+    writer.write_all(&b"old texture ".repeat(100)).unwrap();
+    writer
+        .start_file("deflated.txt", FileOptions::default())
+        .unwrap();
+    writer
+        .write_all(&b"deflated contents ".repeat(100))
+        .unwrap();
+    ZipArchive::new(writer.finish().unwrap()).unwrap()
+}
+
+/// The names and contents of the files of an archive, in order
+fn extract_all(archive: &mut ZipArchive<Cursor<Vec<u8>>>) -> Vec<(String, Vec<u8>)> {
+    (0..archive.len())
+        .map(|i| {
+            let mut file = archive.by_index(i).unwrap();
+            let mut data = Vec::new();
+            file.read_to_end(&mut data).unwrap();
+            (file.name().to_owned(), data)
+        })
+        .collect()
+}
+
+#[test]
+fn replace_entry() {
+    let mut source = source_archive();
+    let patched = zip::patch::replace_entry(
+        &mut source,
+        Cursor::new(Vec::new()),
+        "textures/texture.png",
+        &b"new texture"[..],
+        FileOptions::default().compression_method(CompressionMethod::Stored),
+    )
+    .unwrap();
+    let mut patched = ZipArchive::new(patched).unwrap();
+
+    let mut expected = extract_all(&mut source);
+    expected[2].1 = b"new texture".to_vec();
+    assert_eq!(extract_all(&mut patched), expected);
+    assert_eq!(patched.comment(), b"mod pack");
+    assert_eq!(
+        patched
+            .by_name("textures/texture.png")
+            .unwrap()
+            .compression(),
+        CompressionMethod::Stored
+    );
+    // The other files keep their compressed data
+    for name in ["stored.txt", "deflated.txt"] {
+        let old = source.by_name(name).unwrap();
+        let (method, size, crc32) = (old.compression(), old.compressed_size(), old.crc32());
+        drop(old);
+        let new = patched.by_name(name).unwrap();
+        assert_eq!(
+            (new.compression(), new.compressed_size(), new.crc32()),
+            (method, size, crc32)
+        );
+    }
+}
+
+#[test]
+fn replace_missing_entry() {
+    let mut source = source_archive();
+    let mut dst = Cursor::new(Vec::new());
+    assert!(matches!(
+        zip::patch::replace_entry(
+            &mut source,
+            &mut dst,
+            "missing.txt",
+            &b""[..],
+            FileOptions::default()
+        ),
+        Err(ZipError::FileNotFound)
+    ));
+    assert!(dst.get_ref().is_empty());
+}
+
+#[test]
+fn replace_duplicate_entry() {
+    let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+    for contents in [&b"first"[..], b"second"] {
+        writer
+            .start_file("duplicate.txt", FileOptions::default())
+            .unwrap();
+        writer.write_all(contents).unwrap();
+    }
+    let mut source = ZipArchive::new(writer.finish().unwrap()).unwrap();
+    let patched = zip::patch::replace_entry(
+        &mut source,
+        Cursor::new(Vec::new()),
+        "duplicate.txt",
+        &b"new"[..],
+        FileOptions::default(),
+    )
+    .unwrap();
+    let mut patched = ZipArchive::new(patched).unwrap();
+
+    // The entry that by_name reads is the one replaced
+    assert_eq!(
+        extract_all(&mut patched),
+        [
+            ("duplicate.txt".to_owned(), b"first".to_vec()),
+            ("duplicate.txt".to_owned(), b"new".to_vec()),
+        ]
+    );
+    let mut contents = String::new();
+    patched
+        .by_name("duplicate.txt")
+        .unwrap()
+        .read_to_string(&mut contents)
+        .unwrap();
+    assert_eq!(contents, "new");
+}
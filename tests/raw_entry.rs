@@ -0,0 +1,90 @@
+#![cfg(feature = "deflate")]
+
+use std::io::{Cursor, Read, Write};
+use zip::unstable::write::FileOptionsExt;
+use zip::write::{FileOptions, ZipRawValues};
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+const CONTENTS: &[u8] =
+    b"Lorem ipsum dolor sit amet, consectetur adipiscing elit. Lorem ipsum dolor sit amet";
+
+fn deflate(data: &[u8]) -> Vec<u8> {
+    let mut encoder =
+        flate2::write::DeflateEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(data).unwrap();
+    encoder.finish().unwrap()
+}
+
+fn raw_values(compressed: &[u8]) -> ZipRawValues {
+    ZipRawValues {
+        crc32: crc32fast::hash(CONTENTS),
+        compressed_size: compressed.len() as u64,
+        uncompressed_size: CONTENTS.len() as u64,
+    }
+}
+
+#[test]
+fn add_pre_deflated_entry() {
+    let compressed = deflate(CONTENTS);
+    let options = FileOptions::default().compression_method(CompressionMethod::Deflated);
+    let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+    writer
+        .add_raw_entry(
+            "blob.txt",
+            options,
+            raw_values(&compressed),
+            &compressed[..],
+        )
+        .unwrap();
+    writer.start_file("after.txt", options).unwrap();
+    writer.write_all(b"after").unwrap();
+    let mut archive = ZipArchive::new(writer.finish().unwrap()).unwrap();
+
+    let mut file = archive.by_name("blob.txt").unwrap();
+    assert_eq!(file.compression(), CompressionMethod::Deflated);
+    assert_eq!(file.compressed_size(), compressed.len() as u64);
+    let mut data = Vec::new();
+    file.read_to_end(&mut data).unwrap();
+    assert_eq!(data, CONTENTS);
+    drop(file);
+
+    let mut data = String::new();
+    archive
+        .by_name("after.txt")
+        .unwrap()
+        .read_to_string(&mut data)
+        .unwrap();
+    assert_eq!(data, "after");
+}
+
+#[test]
+fn add_raw_entry_errors() {
+    let compressed = deflate(CONTENTS);
+    let options = FileOptions::default().compression_method(CompressionMethod::Deflated);
+    let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+
+    // Data shorter than its compressed size is discarded
+    let short = &compressed[..compressed.len() - 1];
+    assert!(writer
+        .add_raw_entry("short.txt", options, raw_values(&compressed), short)
+        .is_err());
+    assert!(writer
+        .add_raw_entry(
+            "stored.txt",
+            options.compression_method(CompressionMethod::Stored),
+            raw_values(&compressed),
+            &compressed[..],
+        )
+        .is_err());
+    assert!(writer
+        .add_raw_entry(
+            "encrypted.txt",
+            options.with_deprecated_encryption(b"password"),
+            raw_values(&compressed),
+            &compressed[..],
+        )
+        .is_err());
+
+    let archive = ZipArchive::new(writer.finish().unwrap()).unwrap();
+    assert!(archive.is_empty());
+}
@@ -0,0 +1,135 @@
+use std::io::{Cursor, Read, Write};
+use zip::write::FileOptions;
+use zip::{ZipArchive, ZipWriter};
+
+fn contents(i: usize) -> String {
+    format!("Contents of entry {i}\n").repeat(50)
+}
+
+fn read_entry(archive: &mut ZipArchive<Cursor<Vec<u8>>>, i: usize) -> String {
+    let mut data = String::new();
+    archive
+        .by_name(&format!("entry{i}.txt"))
+        .unwrap()
+        .read_to_string(&mut data)
+        .unwrap();
+    data
+}
+
+#[test]
+fn resume_interrupted_archive() {
+    let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+    writer
+        .add_directory("dir/", FileOptions::default())
+        .unwrap();
+    for i in 1..=4 {
+        writer
+            .start_file(format!("entry{i}.txt"), FileOptions::default())
+            .unwrap();
+        writer.write_all(contents(i).as_bytes()).unwrap();
+    }
+    // Cut short in the middle of entry 4, without a central directory
+    let mut partial = writer.abort().unwrap().into_inner();
+    partial.truncate(partial.len() - 10);
+
+    let mut writer = ZipWriter::resume(Cursor::new(partial)).unwrap();
+    assert_eq!(writer.entry_count(), 4);
+    writer
+        .start_file("entry5.txt", FileOptions::default())
+        .unwrap();
+    writer.write_all(contents(5).as_bytes()).unwrap();
+    let mut archive = ZipArchive::new(writer.finish().unwrap()).unwrap();
+
+    assert_eq!(archive.len(), 5);
+    assert!(archive.by_name("dir/").unwrap().is_dir());
+    assert!(archive.by_name("entry4.txt").is_err());
+    for i in [1, 2, 3, 5] {
+        assert_eq!(read_entry(&mut archive, i), contents(i));
+    }
+}
+
+#[test]
+fn resume_large_file_entries() {
+    let options = FileOptions::default().large_file(true);
+    let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+    for i in 1..=3 {
+        writer.start_file(format!("entry{i}.txt"), options).unwrap();
+        writer.write_all(contents(i).as_bytes()).unwrap();
+    }
+    let mut partial = writer.abort().unwrap().into_inner();
+    partial.truncate(partial.len() - 10);
+
+    let mut writer = ZipWriter::resume(Cursor::new(partial)).unwrap();
+    assert_eq!(writer.entry_count(), 2);
+    writer.start_file("entry4.txt", options).unwrap();
+    writer.write_all(contents(4).as_bytes()).unwrap();
+    let mut archive = ZipArchive::new(writer.finish().unwrap()).unwrap();
+
+    assert_eq!(archive.len(), 3);
+    for i in [1, 2, 4] {
+        assert_eq!(read_entry(&mut archive, i), contents(i));
+    }
+}
+
+#[test]
+fn resume_without_new_files() {
+    let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+    for i in 1..=2 {
+        writer
+            .start_file(format!("entry{i}.txt"), FileOptions::default())
+            .unwrap();
+        writer.write_all(contents(i).as_bytes()).unwrap();
+    }
+    let stored = FileOptions::default().compression_method(zip::CompressionMethod::Stored);
+    writer.start_file("entry3.txt", stored).unwrap();
+    writer.write_all(&[0xab; 100_000]).unwrap();
+    let mut partial = writer.abort().unwrap().into_inner();
+    partial.truncate(partial.len() - 10);
+    let length = partial.len();
+
+    let mut writer = ZipWriter::resume(Cursor::new(partial)).unwrap();
+    let archive = writer.finish().unwrap().into_inner();
+    // The central directory is written over the end of the file that was cut short
+    assert!(archive.len() >= length);
+    assert!(!archive[length - 100..].contains(&0xab));
+    let mut archive = ZipArchive::new(Cursor::new(archive)).unwrap();
+    assert_eq!(archive.len(), 2);
+    assert_eq!(archive.offset(), 0);
+    for i in 1..=2 {
+        assert_eq!(read_entry(&mut archive, i), contents(i));
+    }
+}
+
+#[test]
+fn resume_with_data_descriptor() {
+    // A stored file with a data descriptor, as written by streaming writers
+    let data = contents(1).into_bytes();
+    let crc32 = crc32fast::hash(&data);
+    let mut partial = Vec::new();
+    partial.extend_from_slice(&0x04034b50u32.to_le_bytes());
+    partial.extend_from_slice(&[20, 0, 8, 0, 0, 0, 0, 0, 0x21, 0]);
+    partial.extend_from_slice(&[0; 12]);
+    partial.extend_from_slice(&10u16.to_le_bytes());
+    partial.extend_from_slice(&0u16.to_le_bytes());
+    partial.extend_from_slice(b"entry1.txt");
+    partial.extend_from_slice(&data);
+    partial.extend_from_slice(&0x08074b50u32.to_le_bytes());
+    partial.extend_from_slice(&crc32.to_le_bytes());
+    partial.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    partial.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    // The local header of a file that was cut short
+    partial.extend_from_slice(&0x04034b50u32.to_le_bytes());
+    partial.extend_from_slice(&[20, 0, 8, 0]);
+
+    let mut writer = ZipWriter::resume(Cursor::new(partial)).unwrap();
+    assert_eq!(writer.entry_count(), 1);
+    writer
+        .start_file("entry2.txt", FileOptions::default())
+        .unwrap();
+    writer.write_all(contents(2).as_bytes()).unwrap();
+    let mut archive = ZipArchive::new(writer.finish().unwrap()).unwrap();
+
+    assert_eq!(archive.len(), 2);
+    assert_eq!(read_entry(&mut archive, 1), contents(1));
+    assert_eq!(read_entry(&mut archive, 2), contents(2));
+}
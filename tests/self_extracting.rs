@@ -0,0 +1,41 @@
+use getrandom::getrandom;
+use std::io::{Cursor, Read, Write};
+use zip::write::FileOptions;
+use zip::{ZipArchive, ZipWriter};
+
+#[test]
+fn write_with_prefix() {
+    let mut stub = vec![0; 4096];
+    getrandom(&mut stub).unwrap();
+
+    let mut writer = ZipWriter::new_with_prefix(Cursor::new(Vec::new()), stub.as_slice()).unwrap();
+    writer.add_directory("dir", FileOptions::default()).unwrap();
+    writer
+        .start_file("dir/a.txt", FileOptions::default())
+        .unwrap();
+    writer.write_all(b"Lorem ipsum").unwrap();
+    writer.start_file("b.txt", FileOptions::default()).unwrap();
+    writer.write_all(b"dolor sit amet").unwrap();
+    let archive = writer.finish().unwrap().into_inner();
+    assert_eq!(archive[..stub.len()], stub[..]);
+
+    let mut archive = ZipArchive::new(Cursor::new(archive)).unwrap();
+    assert_eq!(archive.offset(), stub.len() as u64);
+    assert_eq!(archive.len(), 3);
+    for (name, expected) in [("dir/a.txt", "Lorem ipsum"), ("b.txt", "dolor sit amet")] {
+        let mut contents = String::new();
+        archive
+            .by_name(name)
+            .unwrap()
+            .read_to_string(&mut contents)
+            .unwrap();
+        assert_eq!(contents, expected);
+    }
+
+    // Appending keeps the prefix and the offset
+    let mut writer = ZipWriter::new_append(archive.into_inner()).unwrap();
+    writer.start_file("c.txt", FileOptions::default()).unwrap();
+    let archive = ZipArchive::new(writer.finish().unwrap()).unwrap();
+    assert_eq!(archive.offset(), stub.len() as u64);
+    assert_eq!(archive.len(), 4);
+}
@@ -0,0 +1,144 @@
+use std::io::{Cursor, Read, Write};
+use zip::unstable::write::FileOptionsExt;
+use zip::write::FileOptions;
+use zip::{CompressionMethod, DateTime, ZipArchive, ZipWriter};
+
+const CONTENTS: &[u8] =
+    b"Lorem ipsum dolor sit amet, consectetur adipiscing elit, sed do eiusmod tempor";
+
+fn options() -> FileOptions<'static> {
+    FileOptions::default()
+        .last_modified_time(DateTime::from_date_and_time(2023, 5, 1, 12, 30, 0).unwrap())
+}
+
+fn read_file<R: Read + std::io::Seek>(archive: &mut ZipArchive<R>, name: &str) -> Vec<u8> {
+    let mut contents = Vec::new();
+    archive
+        .by_name(name)
+        .unwrap()
+        .read_to_end(&mut contents)
+        .unwrap();
+    contents
+}
+
+#[test]
+fn streaming() {
+    let mut zip = ZipWriter::new_streaming(Vec::new());
+    zip.start_file(
+        "stored.txt",
+        options().compression_method(CompressionMethod::Stored),
+    )
+    .unwrap();
+    zip.write_all(CONTENTS).unwrap();
+    zip.add_directory("directory", options()).unwrap();
+    zip.start_file("deflated.txt", options()).unwrap();
+    for _ in 0..100 {
+        zip.write_all(CONTENTS).unwrap();
+    }
+    zip.add_symlink("link", "stored.txt", options()).unwrap();
+    zip.start_file("large.txt", options().large_file(false))
+        .unwrap();
+    zip.write_all(CONTENTS).unwrap();
+    let archive = zip.finish().unwrap().into_inner();
+
+    let mut archive = ZipArchive::new(Cursor::new(archive)).unwrap();
+    let names: Vec<_> = (0..archive.len())
+        .map(|i| archive.by_index(i).unwrap().name().to_owned())
+        .collect();
+    assert_eq!(
+        names,
+        [
+            "stored.txt",
+            "directory/",
+            "deflated.txt",
+            "link",
+            "large.txt"
+        ]
+    );
+    assert_eq!(read_file(&mut archive, "stored.txt"), CONTENTS);
+    assert_eq!(
+        read_file(&mut archive, "deflated.txt"),
+        CONTENTS.repeat(100)
+    );
+    assert_eq!(read_file(&mut archive, "link"), b"stored.txt");
+    assert_eq!(read_file(&mut archive, "large.txt"), CONTENTS);
+    assert!(archive.by_name("directory/").unwrap().is_dir());
+    assert!(archive.by_name("link").unwrap().is_symlink());
+}
+
+#[test]
+fn zip64_headers_and_descriptors() {
+    let mut zip = ZipWriter::new_streaming(Vec::new());
+    zip.start_file("first.txt", options()).unwrap();
+    zip.write_all(CONTENTS).unwrap();
+    zip.add_directory("directory", options()).unwrap();
+    let archive = zip.finish().unwrap().into_inner();
+
+    let mut zip = ZipArchive::new(Cursor::new(archive.as_slice())).unwrap();
+    let first = zip.by_index(0).unwrap();
+    // The data descriptor flag, and a ZIP64 extra field first
+    assert_eq!(archive[6] & 1 << 3, 1 << 3);
+    let name_end = 30 + first.name().len();
+    assert_eq!(archive[28..30], [20, 0]);
+    assert_eq!(archive[name_end..name_end + 4], [1, 0, 16, 0]);
+    // Signature, CRC and 8 byte sizes
+    let descriptor_start = (first.data_start() + first.compressed_size()) as usize;
+    drop(first);
+    assert_eq!(
+        archive[descriptor_start..descriptor_start + 4],
+        *b"PK\x07\x08"
+    );
+    let directory = zip.by_index(1).unwrap();
+    assert_eq!(directory.header_start() as usize, descriptor_start + 24);
+    // Directories have no data, so their local header is complete
+    let header = &archive[descriptor_start + 24..];
+    assert_eq!(header[6] & 1 << 3, 0);
+    assert_eq!(header[28..30], [0, 0]);
+}
+
+#[test]
+fn abort_file() {
+    let mut zip = ZipWriter::new_streaming(Vec::new());
+    zip.start_file("first.txt", options()).unwrap();
+    zip.write_all(CONTENTS).unwrap();
+    zip.start_file("aborted.txt", options()).unwrap();
+    zip.write_all(CONTENTS).unwrap();
+    zip.abort_file().unwrap();
+    zip.start_file("second.txt", options()).unwrap();
+    zip.write_all(CONTENTS).unwrap();
+    let archive = zip.finish().unwrap().into_inner();
+
+    let mut archive = ZipArchive::new(Cursor::new(archive)).unwrap();
+    let names: Vec<_> = (0..archive.len())
+        .map(|i| archive.by_index(i).unwrap().name().to_owned())
+        .collect();
+    assert_eq!(names, ["first.txt", "second.txt"]);
+    assert_eq!(read_file(&mut archive, "first.txt"), CONTENTS);
+    assert_eq!(read_file(&mut archive, "second.txt"), CONTENTS);
+}
+
+#[test]
+fn streaming_rejects_seeking() {
+    let mut zip = ZipWriter::new_streaming(Vec::new());
+    let result = zip.start_file(
+        "secret.txt",
+        options().with_deprecated_encryption(b"password"),
+    );
+    assert!(matches!(
+        result,
+        Err(zip::result::ZipError::UnsupportedArchive(_))
+    ));
+
+    zip.start_file("first.txt", options()).unwrap();
+    zip.write_all(CONTENTS).unwrap();
+    assert!(zip.checkpoint().is_err());
+    assert!(zip
+        .start_file_with_extra_data("extra.txt", options())
+        .is_err());
+
+    // Nothing was written by the failed calls
+    let archive = zip.finish().unwrap().into_inner();
+    let mut archive = ZipArchive::new(Cursor::new(archive)).unwrap();
+    assert_eq!(archive.len(), 1);
+    assert_eq!(read_file(&mut archive, "first.txt"), CONTENTS);
+}
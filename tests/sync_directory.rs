@@ -0,0 +1,121 @@
+use std::fs;
+use std::io::{Cursor, Read, Write};
+use std::path::Path;
+use zip::update::{sync_directory, SyncOptions, SyncReport};
+use zip::write::FileOptions;
+use zip::{ZipArchive, ZipWriter};
+
+fn sync(
+    archive: &mut ZipArchive<Cursor<Vec<u8>>>,
+    src: &Path,
+    options: SyncOptions,
+) -> (ZipArchive<Cursor<Vec<u8>>>, SyncReport) {
+    let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+    let report = sync_directory(archive, &mut writer, src, options).unwrap();
+    (ZipArchive::new(writer.finish().unwrap()).unwrap(), report)
+}
+
+fn raw_data(archive: &mut ZipArchive<Cursor<Vec<u8>>>, name: &str) -> Vec<u8> {
+    for i in 0..archive.len() {
+        let mut file = archive.by_index_raw(i).unwrap();
+        if file.name() == name {
+            let mut data = Vec::new();
+            file.read_to_end(&mut data).unwrap();
+            return data;
+        }
+    }
+    panic!("{name} not found");
+}
+
+fn names(names: &[&str]) -> Vec<String> {
+    names.iter().map(|name| name.to_string()).collect()
+}
+
+#[test]
+fn sync_directory_copies_unchanged_files() {
+    let temp = tempfile::tempdir().unwrap();
+    let src = temp.path();
+    fs::create_dir_all(src.join("dir")).unwrap();
+    fs::write(src.join("a.txt"), "Contents of a\n".repeat(100)).unwrap();
+    fs::write(src.join("dir/b.txt"), "Contents of b\n".repeat(100)).unwrap();
+    fs::write(src.join("dir/c.txt"), "Contents of c\n".repeat(100)).unwrap();
+
+    let empty = ZipWriter::new(Cursor::new(Vec::new())).finish().unwrap();
+    let (mut old, report) = sync(
+        &mut ZipArchive::new(empty).unwrap(),
+        src,
+        SyncOptions::default(),
+    );
+    assert_eq!(
+        report.added,
+        names(&["a.txt", "dir/", "dir/b.txt", "dir/c.txt"])
+    );
+
+    fs::write(src.join("a.txt"), "New contents of a\n".repeat(100)).unwrap();
+    fs::remove_file(src.join("dir/c.txt")).unwrap();
+    fs::write(src.join("d.txt"), "Contents of d\n".repeat(100)).unwrap();
+    let (mut new, report) = sync(&mut old, src, SyncOptions::default());
+    assert_eq!(
+        report,
+        SyncReport {
+            copied: names(&["dir/", "dir/b.txt"]),
+            updated: names(&["a.txt"]),
+            added: names(&["d.txt"]),
+            removed: names(&["dir/c.txt"]),
+        }
+    );
+    assert_eq!(new.len(), 4);
+    assert_eq!(
+        raw_data(&mut new, "dir/b.txt"),
+        raw_data(&mut old, "dir/b.txt")
+    );
+    let mut data = String::new();
+    new.by_name("a.txt")
+        .unwrap()
+        .read_to_string(&mut data)
+        .unwrap();
+    assert_eq!(data, "New contents of a\n".repeat(100));
+}
+
+#[test]
+fn sync_directory_force_crc_check() {
+    let temp = tempfile::tempdir().unwrap();
+    let src = temp.path();
+    let contents = "Contents of a\n".repeat(100);
+    fs::write(src.join("a.txt"), &contents).unwrap();
+
+    // The same contents with another modification time
+    let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+    writer
+        .start_file("a.txt", FileOptions::default().unix_mtime(0))
+        .unwrap();
+    writer.write_all(contents.as_bytes()).unwrap();
+    let mut old = ZipArchive::new(writer.finish().unwrap()).unwrap();
+
+    let (_, report) = sync(&mut old, src, SyncOptions::default());
+    assert_eq!(report.updated, names(&["a.txt"]));
+    let (mut new, report) = sync(&mut old, src, SyncOptions::default().force_crc_check(true));
+    assert_eq!(report.copied, names(&["a.txt"]));
+    assert_eq!(raw_data(&mut new, "a.txt"), raw_data(&mut old, "a.txt"));
+}
+
+#[cfg(unix)]
+#[test]
+fn sync_directory_skips_special_files() {
+    use std::os::unix::ffi::OsStringExt;
+
+    let temp = tempfile::tempdir().unwrap();
+    let src = temp.path();
+    fs::write(src.join("a.txt"), "Contents of a\n").unwrap();
+    std::os::unix::fs::symlink("a.txt", src.join("link")).unwrap();
+    let fifo = std::ffi::CString::new(src.join("fifo").into_os_string().into_vec()).unwrap();
+    assert_eq!(unsafe { libc::mkfifo(fifo.as_ptr(), 0o644) }, 0);
+
+    let empty = ZipWriter::new(Cursor::new(Vec::new())).finish().unwrap();
+    let (_, report) = sync(
+        &mut ZipArchive::new(empty).unwrap(),
+        src,
+        SyncOptions::default(),
+    );
+    assert_eq!(report.added, names(&["a.txt"]));
+}
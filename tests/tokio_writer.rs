@@ -0,0 +1,501 @@
+#![cfg(feature = "tokio")]
+
+use std::io::{Cursor, Read, Write};
+use tokio::io::AsyncWriteExt;
+use zip::unstable::write::FileOptionsExt;
+use zip::write::tokio::{ZipStreamWriter, ZipWriter as AsyncZipWriter};
+use zip::write::FileOptions;
+use zip::{CompressionMethod, DateTime, ZipArchive};
+
+const CONTENTS: &[u8] =
+    b"Lorem ipsum dolor sit amet, consectetur adipiscing elit, sed do eiusmod tempor";
+
+fn options() -> FileOptions<'static> {
+    FileOptions::default()
+        .last_modified_time(DateTime::from_date_and_time(2023, 5, 1, 12, 30, 0).unwrap())
+}
+
+fn block_on<F: std::future::Future>(future: F) -> F::Output {
+    tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap()
+        .block_on(future)
+}
+
+fn sync_archive() -> Vec<u8> {
+    let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+    zip.set_comment("archive comment");
+    zip.start_file(
+        "stored.txt",
+        options()
+            .compression_method(CompressionMethod::Stored)
+            .alignment(64),
+    )
+    .unwrap();
+    zip.write_all(CONTENTS).unwrap();
+    zip.add_directory("directory", options()).unwrap();
+    zip.start_file("deflated.txt", options()).unwrap();
+    for _ in 0..100 {
+        zip.write_all(CONTENTS).unwrap();
+    }
+    zip.start_file("large.txt", options().large_file(true))
+        .unwrap();
+    zip.write_all(CONTENTS).unwrap();
+    zip.start_file("empty.txt", options()).unwrap();
+    zip.finish().unwrap().into_inner()
+}
+
+#[test]
+fn same_archive_as_sync_writer() {
+    let archive = block_on(async {
+        let mut zip = AsyncZipWriter::new(Cursor::new(Vec::new()));
+        zip.set_comment("archive comment");
+        zip.start_file(
+            "stored.txt",
+            options()
+                .compression_method(CompressionMethod::Stored)
+                .alignment(64),
+        )
+        .await?;
+        zip.write_all(CONTENTS).await?;
+        zip.add_directory("directory", options()).await?;
+        zip.start_file("deflated.txt", options()).await?;
+        for _ in 0..100 {
+            zip.write_all(CONTENTS).await?;
+        }
+        zip.start_file("large.txt", options().large_file(true))
+            .await?;
+        zip.write_all(CONTENTS).await?;
+        zip.start_file("empty.txt", options()).await?;
+        zip.finish().await
+    })
+    .unwrap()
+    .into_inner();
+
+    assert_eq!(archive, sync_archive());
+}
+
+#[test]
+fn encrypted_file() {
+    let archive = block_on(async {
+        let mut zip = AsyncZipWriter::new(Cursor::new(Vec::new()));
+        zip.start_file(
+            "secret.txt",
+            options().with_deprecated_encryption(b"password"),
+        )
+        .await?;
+        zip.write_all(CONTENTS).await?;
+        zip.finish().await
+    })
+    .unwrap();
+
+    let mut archive = ZipArchive::new(archive).unwrap();
+    let mut contents = Vec::new();
+    archive
+        .by_name_decrypt("secret.txt", b"password")
+        .unwrap()
+        .read_to_end(&mut contents)
+        .unwrap();
+    assert_eq!(contents, CONTENTS);
+}
+
+#[test]
+fn streaming() {
+    let archive = block_on(async {
+        let mut zip = ZipStreamWriter::new(Vec::new());
+        zip.start_file(
+            "stored.txt",
+            options().compression_method(CompressionMethod::Stored),
+        )
+        .await?;
+        zip.write_all(CONTENTS).await?;
+        zip.add_directory("directory", options()).await?;
+        zip.start_file("deflated.txt", options()).await?;
+        for _ in 0..100 {
+            zip.write_all(CONTENTS).await?;
+        }
+        zip.start_file("large.txt", options().large_file(true))
+            .await?;
+        zip.write_all(CONTENTS).await?;
+        zip.finish().await
+    })
+    .unwrap();
+
+    let mut archive = ZipArchive::new(Cursor::new(archive)).unwrap();
+    let names: Vec<_> = (0..archive.len())
+        .map(|i| archive.by_index(i).unwrap().name().to_owned())
+        .collect();
+    assert_eq!(
+        names,
+        ["stored.txt", "directory/", "deflated.txt", "large.txt"]
+    );
+    for (name, repeat) in [("stored.txt", 1), ("deflated.txt", 100), ("large.txt", 1)] {
+        let mut contents = Vec::new();
+        archive
+            .by_name(name)
+            .unwrap()
+            .read_to_end(&mut contents)
+            .unwrap();
+        assert_eq!(contents, CONTENTS.repeat(repeat));
+    }
+    assert!(archive.by_name("directory/").unwrap().is_dir());
+}
+
+#[test]
+fn same_archive_as_sync_streaming() {
+    let archive = block_on(async {
+        let mut zip = ZipStreamWriter::new(Vec::new());
+        zip.start_file(
+            "stored.txt",
+            options().compression_method(CompressionMethod::Stored),
+        )
+        .await?;
+        zip.write_all(CONTENTS).await?;
+        zip.add_directory("directory", options()).await?;
+        zip.start_file("deflated.txt", options()).await?;
+        for _ in 0..100 {
+            zip.write_all(CONTENTS).await?;
+        }
+        zip.finish().await
+    })
+    .unwrap();
+
+    let mut zip = zip::ZipWriter::new_streaming(Vec::new());
+    zip.start_file(
+        "stored.txt",
+        options().compression_method(CompressionMethod::Stored),
+    )
+    .unwrap();
+    zip.write_all(CONTENTS).unwrap();
+    zip.add_directory("directory", options()).unwrap();
+    zip.start_file("deflated.txt", options()).unwrap();
+    for _ in 0..100 {
+        zip.write_all(CONTENTS).unwrap();
+    }
+    assert_eq!(archive, zip.finish().unwrap().into_inner());
+}
+
+#[test]
+fn raw_copy_from_stream() {
+    let archive = block_on(async {
+        let mut zip = ZipStreamWriter::new(Vec::new());
+        zip.start_file("deflated.txt", options()).await?;
+        zip.write_all(CONTENTS).await?;
+        zip.start_file("large.txt", options().large_file(true))
+            .await?;
+        zip.write_all(CONTENTS).await?;
+        zip.finish().await
+    })
+    .unwrap();
+
+    let mut source = ZipArchive::new(Cursor::new(archive)).unwrap();
+    let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+    for i in 0..source.len() {
+        zip.raw_copy_file(source.by_index_raw(i).unwrap()).unwrap();
+    }
+    let copy = zip.finish().unwrap().into_inner();
+    let mut archive = ZipArchive::new(Cursor::new(copy.as_slice())).unwrap();
+    for name in ["deflated.txt", "large.txt"] {
+        let mut contents = Vec::new();
+        archive
+            .by_name(name)
+            .unwrap()
+            .read_to_end(&mut contents)
+            .unwrap();
+        assert_eq!(contents, CONTENTS);
+    }
+
+    // The data descriptors are kept
+    for i in 0..archive.len() {
+        let file = archive.by_index_raw(i).unwrap();
+        let descriptor_start = (file.data_start() + file.compressed_size()) as usize;
+        assert_eq!(copy[descriptor_start..descriptor_start + 4], *b"PK\x07\x08");
+    }
+}
+
+#[test]
+fn abort_file() {
+    fn check(archive: Vec<u8>) {
+        let mut archive = ZipArchive::new(Cursor::new(archive)).unwrap();
+        assert_eq!(archive.len(), 2);
+        for (i, name) in ["first.txt", "second.txt"].iter().enumerate() {
+            let mut file = archive.by_index(i).unwrap();
+            assert_eq!(file.name(), *name);
+            let mut contents = Vec::new();
+            file.read_to_end(&mut contents).unwrap();
+            assert_eq!(contents, CONTENTS);
+        }
+    }
+
+    let archive = block_on(async {
+        let mut zip = AsyncZipWriter::new(Cursor::new(Vec::new()));
+        assert!(zip.abort_file().await.is_err());
+        zip.start_file("first.txt", options()).await?;
+        zip.write_all(CONTENTS).await?;
+        zip.start_file("broken.txt", options()).await?;
+        zip.write_all(&CONTENTS.repeat(100)).await?;
+        zip.abort_file().await?;
+        zip.start_file("second.txt", options()).await?;
+        zip.write_all(CONTENTS).await?;
+        zip.finish().await
+    })
+    .unwrap();
+    check(archive.into_inner());
+
+    let archive = block_on(async {
+        let mut zip = ZipStreamWriter::new(Vec::new());
+        assert!(zip.abort_file().is_err());
+        zip.start_file("first.txt", options()).await?;
+        zip.write_all(CONTENTS).await?;
+        zip.start_file("broken.txt", options()).await?;
+        zip.write_all(&CONTENTS.repeat(100)).await?;
+        zip.abort_file()?;
+        zip.start_file("second.txt", options()).await?;
+        zip.write_all(CONTENTS).await?;
+        zip.finish().await
+    })
+    .unwrap();
+    check(archive);
+}
+
+#[test]
+fn abort_last_file() {
+    let archive = block_on(async {
+        let mut zip = AsyncZipWriter::new(Cursor::new(Vec::new()));
+        zip.start_file("first.txt", options()).await?;
+        zip.write_all(CONTENTS).await?;
+        let stored = options().compression_method(CompressionMethod::Stored);
+        zip.start_file("broken.txt", stored).await?;
+        zip.write_all(&[0xab; 100_000]).await?;
+        // Data that isn't flushed yet is never written
+        zip.flush().await?;
+        zip.abort_file().await?;
+        zip.finish().await
+    })
+    .unwrap()
+    .into_inner();
+
+    // The archive ends where the discarded data did
+    assert!(archive.len() > 100_000);
+    assert!(!archive[archive.len() - 100..].contains(&0xab));
+    let archive = ZipArchive::new(Cursor::new(archive)).unwrap();
+    assert_eq!(archive.len(), 1);
+    assert_eq!(archive.offset(), 0);
+}
+
+#[test]
+fn resume_streamed_archive() {
+    // Small files, whose data descriptors have ZIP64 sizes all the same
+    let stored = options().compression_method(CompressionMethod::Stored);
+    let mut partial = block_on(async {
+        let mut zip = ZipStreamWriter::new(Vec::new());
+        for i in 0..3 {
+            zip.start_file(format!("file{i}.bin"), stored).await?;
+            zip.write_all(&[i as u8; 1000]).await?;
+        }
+        zip.finish().await
+    })
+    .unwrap();
+    let third_start = partial
+        .windows(4)
+        .rposition(|window| window == b"PK\x03\x04")
+        .unwrap();
+    partial.truncate(third_start + 50);
+
+    let mut zip = zip::ZipWriter::resume(Cursor::new(partial)).unwrap();
+    assert_eq!(zip.entry_count(), 2);
+    let mut archive = ZipArchive::new(zip.finish().unwrap()).unwrap();
+    assert_eq!(archive.len(), 2);
+    for i in 0..2 {
+        let mut file = archive.by_index(i).unwrap();
+        assert_eq!(file.name(), format!("file{i}.bin"));
+        let mut contents = Vec::new();
+        file.read_to_end(&mut contents).unwrap();
+        assert_eq!(contents, [i as u8; 1000]);
+    }
+}
+
+#[test]
+fn streaming_rejects_encryption() {
+    let result = block_on(async {
+        let mut zip = ZipStreamWriter::new(Vec::new());
+        zip.start_file(
+            "secret.txt",
+            options().with_deprecated_encryption(b"password"),
+        )
+        .await
+    });
+    assert!(matches!(
+        result,
+        Err(zip::result::ZipError::UnsupportedArchive(_))
+    ));
+}
+
+#[cfg(feature = "aes-crypto")]
+#[test]
+fn aes_encrypted_file() {
+    let archive = block_on(async {
+        let mut zip = ZipStreamWriter::new(Vec::new());
+        zip.start_file(
+            "secret.txt",
+            options().encrypt_with_aes(zip::AesMode::Aes256, b"password"),
+        )
+        .await?;
+        for _ in 0..100 {
+            zip.write_all(CONTENTS).await?;
+        }
+        zip.finish().await
+    })
+    .unwrap();
+
+    let mut archive = ZipArchive::new(Cursor::new(archive)).unwrap();
+    let mut contents = Vec::new();
+    archive
+        .by_name_decrypt("secret.txt", b"password")
+        .unwrap()
+        .read_to_end(&mut contents)
+        .unwrap();
+    assert_eq!(contents, CONTENTS.repeat(100));
+}
+
+#[test]
+fn streaming_zip64_descriptors() {
+    let archive = block_on(async {
+        let mut zip = ZipStreamWriter::new(Vec::new());
+        zip.start_file("first.txt", options()).await?;
+        zip.write_all(CONTENTS).await?;
+        zip.start_file("second.txt", options()).await?;
+        zip.write_all(CONTENTS).await?;
+        zip.finish().await
+    })
+    .unwrap();
+
+    let mut zip = ZipArchive::new(Cursor::new(archive.as_slice())).unwrap();
+    let first = zip.by_index(0).unwrap();
+    let descriptor_start = (first.data_start() + first.compressed_size()) as usize;
+    drop(first);
+    // Signature, CRC and 8 byte sizes
+    assert_eq!(
+        archive[descriptor_start..descriptor_start + 4],
+        *b"PK\x07\x08"
+    );
+    let second_start = zip.by_index(1).unwrap().header_start() as usize;
+    assert_eq!(second_start, descriptor_start + 24);
+}
+
+/// Archive keeping runs of zeroes as holes, to write files larger than memory
+#[derive(Default)]
+struct SparseArchive {
+    /// Non-zero chunks, by offset
+    chunks: Vec<(u64, Vec<u8>)>,
+    len: u64,
+    position: u64,
+}
+
+impl tokio::io::AsyncWrite for SparseArchive {
+    fn poll_write(
+        mut self: std::pin::Pin<&mut Self>,
+        _: &mut std::task::Context<'_>,
+        buf: &[u8],
+    ) -> std::task::Poll<std::io::Result<usize>> {
+        let len = self.len;
+        if buf.iter().any(|&b| b != 0) {
+            match self.chunks.last_mut() {
+                Some((offset, chunk)) if *offset + chunk.len() as u64 == len => {
+                    chunk.extend_from_slice(buf)
+                }
+                _ => self.chunks.push((len, buf.to_vec())),
+            }
+        }
+        self.len += buf.len() as u64;
+        std::task::Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(
+        self: std::pin::Pin<&mut Self>,
+        _: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<std::io::Result<()>> {
+        std::task::Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(
+        self: std::pin::Pin<&mut Self>,
+        _: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<std::io::Result<()>> {
+        std::task::Poll::Ready(Ok(()))
+    }
+}
+
+impl Read for SparseArchive {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let position = self.position;
+        let end = self.len.min(position + buf.len() as u64);
+        if end <= position {
+            return Ok(0);
+        }
+        let buf = &mut buf[..(end - position) as usize];
+        buf.iter_mut().for_each(|b| *b = 0);
+        for (offset, chunk) in &self.chunks {
+            let chunk_end = offset + chunk.len() as u64;
+            if chunk_end <= position || *offset >= end {
+                continue;
+            }
+            let start = position.max(*offset);
+            let stop = end.min(chunk_end);
+            buf[(start - position) as usize..(stop - position) as usize]
+                .copy_from_slice(&chunk[(start - offset) as usize..(stop - offset) as usize]);
+        }
+        self.position = end;
+        Ok(buf.len())
+    }
+}
+
+impl std::io::Seek for SparseArchive {
+    fn seek(&mut self, pos: std::io::SeekFrom) -> std::io::Result<u64> {
+        self.position = match pos {
+            std::io::SeekFrom::Start(offset) => offset,
+            std::io::SeekFrom::End(offset) => (self.len as i64 + offset) as u64,
+            std::io::SeekFrom::Current(offset) => (self.position as i64 + offset) as u64,
+        };
+        Ok(self.position)
+    }
+}
+
+#[test]
+#[ignore = "writes and reads 5 GiB"]
+fn streaming_large_archive() {
+    const LARGE_SIZE: u64 = 5 << 30;
+    let zeroes = vec![0; 1 << 20];
+
+    let archive = block_on(async {
+        let mut zip = ZipStreamWriter::new(SparseArchive::default());
+        zip.start_file(
+            "large.bin",
+            options().compression_method(CompressionMethod::Stored),
+        )
+        .await?;
+        for _ in 0..LARGE_SIZE / zeroes.len() as u64 {
+            zip.write_all(&zeroes).await?;
+        }
+        zip.start_file("small.txt", options()).await?;
+        zip.write_all(CONTENTS).await?;
+        zip.finish().await
+    })
+    .unwrap();
+    assert!(archive.len > LARGE_SIZE);
+
+    let mut archive = ZipArchive::new(archive).unwrap();
+    let mut large = archive.by_name("large.bin").unwrap();
+    assert_eq!(large.size(), LARGE_SIZE);
+    assert_eq!(
+        std::io::copy(&mut large, &mut std::io::sink()).unwrap(),
+        LARGE_SIZE
+    );
+    drop(large);
+
+    let mut small = archive.by_name("small.txt").unwrap();
+    assert!(small.header_start() > LARGE_SIZE);
+    let mut contents = Vec::new();
+    small.read_to_end(&mut contents).unwrap();
+    assert_eq!(contents, CONTENTS);
+}
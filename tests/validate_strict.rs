@@ -0,0 +1,140 @@
+use std::io::{Cursor, Write};
+use zip::read::{HeaderField, ValidationWarning, ZipReaderConfig};
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+/// An archive of two stored files, and the offsets of the central header of each
+fn fixture() -> (Vec<u8>, [usize; 2]) {
+    let options = FileOptions::default().compression_method(CompressionMethod::Stored);
+    let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+    for name in ["first.txt", "second.txt"] {
+        writer.start_file(name, options).unwrap();
+        writer.write_all(name.as_bytes()).unwrap();
+    }
+    let data = writer.finish().unwrap().into_inner();
+    let mut archive = ZipArchive::new(Cursor::new(data.clone())).unwrap();
+    let central = [0, 1].map(|i| archive.by_index(i).unwrap().central_header_start() as usize);
+    (data, central)
+}
+
+fn validate(data: Vec<u8>) -> Vec<ValidationWarning> {
+    ZipArchive::new(Cursor::new(data))
+        .unwrap()
+        .validate_strict()
+        .unwrap()
+}
+
+#[test]
+fn valid_archive() {
+    let (data, _) = fixture();
+    assert_eq!(validate(data), []);
+}
+
+#[test]
+fn local_crc_mismatch() {
+    let (mut data, _) = fixture();
+    // The CRC32 of the local header of the first file
+    data[14] ^= 0xFF;
+    let warnings = validate(data);
+    assert_eq!(
+        warnings,
+        [ValidationWarning::HeaderMismatch {
+            index: 0,
+            field: HeaderField::Crc32,
+        }]
+    );
+    assert_eq!(
+        warnings[0].to_string(),
+        "entry 0 has a different Crc32 in its local and central headers"
+    );
+}
+
+#[test]
+fn strong_encryption_flag() {
+    let (mut data, central) = fixture();
+    // Set the strong encryption and encryption flags in both headers of the second file, and
+    // make it need version 6.2 to extract
+    let local = u32::from_le_bytes(data[central[1] + 42..central[1] + 46].try_into().unwrap());
+    data[local as usize + 6] |= 0x41;
+    data[central[1] + 8] |= 0x41;
+    data[central[1] + 6] = 62;
+    assert_eq!(
+        validate(data.clone()),
+        [ValidationWarning::UnsupportedFlags {
+            index: 1,
+            flags: 0x40,
+        }]
+    );
+
+    data[central[1] + 6] = 64;
+    let warnings = validate(data);
+    assert_eq!(
+        warnings[1],
+        ValidationWarning::VersionNeeded {
+            index: 1,
+            version: 64,
+        }
+    );
+    assert_eq!(
+        warnings[1].to_string(),
+        "entry 1 needs version 6.4 to extract"
+    );
+}
+
+#[test]
+fn local_name_and_method_mismatch() {
+    let (mut data, _) = fixture();
+    data[8] = 8;
+    data[30] = b'F';
+    assert_eq!(
+        validate(data),
+        [
+            ValidationWarning::HeaderMismatch {
+                index: 0,
+                field: HeaderField::CompressionMethod,
+            },
+            ValidationWarning::HeaderMismatch {
+                index: 0,
+                field: HeaderField::FileName,
+            },
+        ]
+    );
+}
+
+#[test]
+fn unreadable_local_header() {
+    let (mut data, central) = fixture();
+    // Break the signature of the first local header, and point the second one past the end
+    data[0] = 0;
+    let end = data.len() as u32;
+    data[central[1] + 42..central[1] + 46].copy_from_slice(&end.to_le_bytes());
+    let warnings = validate(data);
+    assert_eq!(
+        warnings,
+        [
+            ValidationWarning::UnreadableLocalHeader { index: 0 },
+            ValidationWarning::UnreadableLocalHeader { index: 1 },
+        ]
+    );
+    assert_eq!(
+        warnings[0].to_string(),
+        "entry 0 has no readable local header"
+    );
+}
+
+#[test]
+fn central_directory_size_mismatch() {
+    let (mut data, central) = fixture();
+    let end = data.len() - 22;
+    let size = (end - central[0]) as u64;
+    data[end + 12] += 1;
+    let config = ZipReaderConfig::default().trust_central_directory_offsets(true);
+    let mut archive = ZipArchive::with_config(Cursor::new(data), config).unwrap();
+    assert_eq!(
+        archive.validate_strict().unwrap(),
+        [ValidationWarning::CentralDirectorySize {
+            recorded: size + 1,
+            actual: size,
+        }]
+    );
+}
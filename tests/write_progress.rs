@@ -0,0 +1,105 @@
+use std::io::{Cursor, Read, Write};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::{Arc, Mutex};
+use zip::write::{FileOptions, WriteProgress};
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+#[derive(Debug, PartialEq)]
+struct Event {
+    name: String,
+    uncompressed_bytes: u64,
+    compressed_bytes: u64,
+    finished_entry: bool,
+}
+
+fn record(writer: &mut ZipWriter<Cursor<Vec<u8>>>) -> Arc<Mutex<Vec<Event>>> {
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let recorded = events.clone();
+    writer.set_progress_callback(move |progress: WriteProgress| {
+        recorded.lock().unwrap().push(Event {
+            name: progress.name.to_owned(),
+            uncompressed_bytes: progress.uncompressed_bytes,
+            compressed_bytes: progress.compressed_bytes,
+            finished_entry: progress.finished_entry,
+        })
+    });
+    events
+}
+
+#[test]
+fn progress_events() {
+    let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+    let events = record(&mut writer);
+    let options = FileOptions::default().compression_method(CompressionMethod::Stored);
+    writer.start_file("large", options).unwrap();
+    for _ in 0..10 {
+        writer.write_all(&[b'a'; 300 * 1024]).unwrap();
+    }
+    writer.add_directory("dir", options).unwrap();
+    writer.start_file("small", options).unwrap();
+    writer.write_all(b"Lorem ipsum").unwrap();
+    let mut archive = ZipArchive::new(writer.finish().unwrap()).unwrap();
+
+    let progress = |name: &str, bytes: u64, finished_entry: bool| Event {
+        name: name.to_owned(),
+        uncompressed_bytes: bytes,
+        compressed_bytes: bytes,
+        finished_entry,
+    };
+    assert_eq!(
+        *events.lock().unwrap(),
+        [
+            progress("large", 1200 * 1024, false),
+            progress("large", 2100 * 1024, false),
+            progress("large", 3000 * 1024, true),
+            progress("dir/", 0, true),
+            progress("small", 11, true),
+        ]
+    );
+
+    // Raw copies are reported once finished
+    let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+    let events = record(&mut writer);
+    writer.merge_archive(&mut archive).unwrap();
+    assert_eq!(events.lock().unwrap().len(), 3);
+    assert!(events
+        .lock()
+        .unwrap()
+        .iter()
+        .all(|event| event.finished_entry));
+}
+
+#[test]
+fn panicking_callback() {
+    let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+    let mut panicked = false;
+    writer.set_progress_callback(move |progress: WriteProgress| {
+        if !panicked {
+            panicked = true;
+            panic!("Progress of {}", progress.name);
+        }
+    });
+    let options = FileOptions::default().compression_method(CompressionMethod::Stored);
+    writer.start_file("aborted", options).unwrap();
+    let result = catch_unwind(AssertUnwindSafe(|| {
+        writer.write_all(&[b'a'; 2 << 20]).unwrap();
+    }));
+    assert!(result.is_err());
+    writer.abort_file().unwrap();
+
+    writer.start_file("file", options).unwrap();
+    writer.write_all(b"Lorem ipsum").unwrap();
+    let mut archive = writer.finish().unwrap();
+    // The aborted data is left after the archive
+    let end = archive.position() as usize;
+    archive.get_mut().truncate(end);
+    let mut archive = ZipArchive::new(archive).unwrap();
+    assert_eq!(archive.len(), 1);
+    let mut data = String::new();
+    archive
+        .by_name("file")
+        .unwrap()
+        .read_to_string(&mut data)
+        .unwrap();
+    assert_eq!(data, "Lorem ipsum");
+}
@@ -0,0 +1,174 @@
+use std::ffi::OsString;
+use std::io::{self, Cursor, Write};
+use zip::result::ZipError;
+use zip::write::{ExtraField, FileOptions};
+use zip::{ZipArchive, ZipWriter};
+
+fn xattrs() -> Vec<(OsString, Vec<u8>)> {
+    vec![
+        (
+            OsString::from("user.origin"),
+            b"https://example.com".to_vec(),
+        ),
+        (OsString::from("user.empty"), Vec::new()),
+    ]
+}
+
+fn archive_with_xattrs(xattrs: &[(OsString, Vec<u8>)]) -> ZipArchive<Cursor<Vec<u8>>> {
+    let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+    writer
+        .start_file("file.txt", FileOptions::default().xattrs(xattrs))
+        .unwrap();
+    writer.write_all(b"contents").unwrap();
+    writer
+        .add_directory("dir/", FileOptions::default().xattrs(xattrs))
+        .unwrap();
+    ZipArchive::new(writer.finish().unwrap()).unwrap()
+}
+
+#[test]
+fn xattrs_round_trip() {
+    let xattrs = xattrs();
+    let mut archive = archive_with_xattrs(&xattrs);
+    assert_eq!(archive.by_name("file.txt").unwrap().xattrs(), xattrs);
+    assert_eq!(archive.by_name("dir/").unwrap().xattrs(), xattrs);
+
+    // Copied files keep a single field
+    let extra_length = archive.by_name("file.txt").unwrap().extra_data().len();
+    let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+    writer
+        .raw_copy_file(archive.by_name("file.txt").unwrap())
+        .unwrap();
+    let mut copy = ZipArchive::new(writer.finish().unwrap()).unwrap();
+    let file = copy.by_name("file.txt").unwrap();
+    assert_eq!(file.xattrs(), xattrs);
+    assert_eq!(file.extra_data().len(), extra_length);
+}
+
+#[test]
+fn xattrs_too_large() {
+    let xattrs = vec![(OsString::from("user.large"), vec![0; 0xFFF0])];
+    let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+    assert!(writer
+        .start_file("file.txt", FileOptions::default().xattrs(&xattrs))
+        .is_err());
+    let xattrs = vec![(OsString::from("user.large"), vec![0; 0xF000])];
+    writer
+        .start_file("file.txt", FileOptions::default().xattrs(&xattrs))
+        .unwrap();
+
+    // Along with other extra data, the total must fit in the header
+    let extra = [ExtraField::new(0xcafe, vec![0; 0x1000])];
+    let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+    let error = writer
+        .start_file_with_extra_fields(
+            "file.txt",
+            FileOptions::default().xattrs(&xattrs),
+            &extra,
+            &[],
+        )
+        .unwrap_err();
+    assert!(matches!(error, ZipError::Io(e) if e.kind() == io::ErrorKind::InvalidInput));
+
+    // The largest field that leaves room for a ZIP64 field in the central header, but not for
+    // alignment padding, without the room reserved for a local ZIP64 field taking its place
+    let xattrs = vec![(OsString::from("user.large"), vec![0; 0xFFFF - 28 - 18])];
+    let options = FileOptions::default().xattrs(&xattrs).large_file(false);
+    let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+    writer.start_file("file.txt", options).unwrap();
+    let error = writer
+        .start_file("file.txt", options.alignment(64))
+        .unwrap_err();
+    assert!(matches!(error, ZipError::Io(e) if e.kind() == io::ErrorKind::InvalidInput));
+}
+
+#[cfg(target_os = "linux")]
+fn get_xattr(path: &std::path::Path, name: &str) -> Option<Vec<u8>> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let path = CString::new(path.as_os_str().as_bytes()).unwrap();
+    let name = CString::new(name).unwrap();
+    let mut value = vec![0u8; 256];
+    let length = unsafe {
+        libc::getxattr(
+            path.as_ptr(),
+            name.as_ptr(),
+            value.as_mut_ptr() as *mut libc::c_void,
+            value.len(),
+        )
+    };
+    if length < 0 {
+        let e = std::io::Error::last_os_error();
+        assert_eq!(e.raw_os_error(), Some(libc::ENODATA), "{e}");
+        return None;
+    }
+    value.truncate(length as usize);
+    Some(value)
+}
+
+/// Extract `archive` with `options` into a new directory, or return `None` if the filesystem of
+/// the temporary directory doesn't support user attributes
+#[cfg(target_os = "linux")]
+fn extract(
+    archive: &mut ZipArchive<Cursor<Vec<u8>>>,
+    options: &zip::read::ExtractOptions,
+) -> Option<tempfile::TempDir> {
+    let directory = tempfile::tempdir().unwrap();
+    let summary = archive
+        .extract_with_options(directory.path(), options)
+        .unwrap();
+    if let Some((_, e)) = summary.xattr_errors.first() {
+        assert_eq!(e.raw_os_error(), Some(libc::ENOTSUP), "{e}");
+        return None;
+    }
+    Some(directory)
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn restore_xattrs() {
+    use zip::read::ExtractOptions;
+
+    let mut xattrs = xattrs();
+    // These would grant privileges when extracting as root, and fail to be set otherwise
+    xattrs.push((OsString::from("security.capability"), vec![1; 20]));
+    xattrs.push((OsString::from("trusted.origin"), b"trusted".to_vec()));
+    let mut archive = archive_with_xattrs(&xattrs);
+    let options = ExtractOptions::default().restore_xattrs(true);
+    let directory = match extract(&mut archive, &options) {
+        Some(directory) => directory,
+        None => return,
+    };
+    for name in ["file.txt", "dir"] {
+        let path = directory.path().join(name);
+        assert_eq!(
+            get_xattr(&path, "user.origin").unwrap(),
+            b"https://example.com"
+        );
+        assert_eq!(get_xattr(&path, "user.empty").unwrap(), b"");
+        assert_eq!(get_xattr(&path, "security.capability"), None);
+        assert_eq!(get_xattr(&path, "trusted.origin"), None);
+    }
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn restore_xattrs_filter() {
+    use zip::read::ExtractOptions;
+
+    let mut archive = archive_with_xattrs(&xattrs());
+    let options = ExtractOptions::default()
+        .restore_xattrs(true)
+        .xattr_filter(|name| name == "user.origin");
+    let directory = match extract(&mut archive, &options) {
+        Some(directory) => directory,
+        None => return,
+    };
+    let path = directory.path().join("file.txt");
+    assert_eq!(
+        get_xattr(&path, "user.origin").unwrap(),
+        b"https://example.com"
+    );
+    assert_eq!(get_xattr(&path, "user.empty"), None);
+}
@@ -0,0 +1,38 @@
+#![cfg(feature = "xz")]
+
+use std::io::{self, Read};
+use zip::{CompressionMethod, ZipArchive};
+
+#[test]
+fn decompress_xz() {
+    let mut v = Vec::new();
+    v.extend_from_slice(include_bytes!("data/xz.zip"));
+    let mut archive = ZipArchive::new(io::Cursor::new(v)).expect("couldn't open test zip file");
+
+    let mut file = archive
+        .by_name("hello.txt")
+        .expect("couldn't find file in archive");
+    assert_eq!(file.compression(), CompressionMethod::Xz);
+
+    let mut content = String::new();
+    file.read_to_string(&mut content)
+        .expect("couldn't read xz-compressed file");
+    assert_eq!(
+        content,
+        "This file was compressed with XZ (method 95).\n".repeat(40)
+    );
+}
+
+#[test]
+fn extract_xz() {
+    let mut v = Vec::new();
+    v.extend_from_slice(include_bytes!("data/xz.zip"));
+    let mut archive = ZipArchive::new(io::Cursor::new(v)).expect("couldn't open test zip file");
+
+    let directory = tempfile::tempdir().unwrap();
+    archive
+        .extract(directory.path())
+        .expect("couldn't extract archive");
+    let content = std::fs::read_to_string(directory.path().join("hello.txt")).unwrap();
+    assert_eq!(content.len(), 1840);
+}
@@ -0,0 +1,104 @@
+use std::io::Cursor;
+use zip::read::{ParseDiagnostic, ZipReaderConfig};
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+const ZIP64_LOCATOR_SIGNATURE: [u8; 4] = [0x50, 0x4b, 0x06, 0x07];
+
+fn write_archive(count: usize) -> Vec<u8> {
+    let options = FileOptions::default().compression_method(CompressionMethod::Stored);
+    let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+    for i in 0..count {
+        writer.start_file(format!("{i}"), options).unwrap();
+    }
+    writer.finish().unwrap().into_inner()
+}
+
+/// Assert that the archive has `count` files, with a ZIP64 end of central directory
+fn assert_zip64_count(archive: Vec<u8>, count: usize) {
+    let end = archive.len() - 22;
+    // The 16-bit counts of the end of central directory record are placeholders
+    assert_eq!(archive[end + 8..end + 12], [0xff; 4]);
+    assert_eq!(archive[end - 20..end - 16], ZIP64_LOCATOR_SIGNATURE);
+
+    let mut archive = ZipArchive::new(Cursor::new(archive)).unwrap();
+    assert_eq!(archive.len(), count);
+    for i in 0..count {
+        assert_eq!(archive.by_index(i).unwrap().name(), i.to_string());
+    }
+}
+
+#[test]
+fn more_than_65535_entries() {
+    assert_zip64_count(write_archive(70_000), 70_000);
+}
+
+#[test]
+fn exactly_65535_entries() {
+    assert_zip64_count(write_archive(65_535), 65_535);
+}
+
+#[test]
+fn wrapped_count_without_zip64() {
+    // Replace the ZIP64 records with a regular end of central directory whose 16-bit counts
+    // wrapped around, as some old writers do
+    let mut archive = write_archive(70_000);
+    let end = archive.len() - 22;
+    let zip64_end = u64::from_le_bytes(archive[end - 12..end - 4].try_into().unwrap()) as usize;
+    let directory_size =
+        u64::from_le_bytes(archive[zip64_end + 40..zip64_end + 48].try_into().unwrap());
+    let directory_start =
+        u64::from_le_bytes(archive[zip64_end + 48..zip64_end + 56].try_into().unwrap());
+    archive.truncate(zip64_end);
+    archive.extend_from_slice(&[0x50, 0x4b, 0x05, 0x06, 0, 0, 0, 0]);
+    archive.extend_from_slice(&(70_000u32 as u16).to_le_bytes());
+    archive.extend_from_slice(&(70_000u32 as u16).to_le_bytes());
+    archive.extend_from_slice(&(directory_size as u32).to_le_bytes());
+    archive.extend_from_slice(&(directory_start as u32).to_le_bytes());
+    archive.extend_from_slice(&0u16.to_le_bytes());
+
+    let mut zip = ZipArchive::new(Cursor::new(archive.clone())).unwrap();
+    assert_eq!(zip.len(), 70_000);
+    assert_eq!(zip.by_index(69_999).unwrap().name(), "69999");
+    assert!(zip.by_name("4464").is_ok());
+    let (_, diagnostics) = ZipArchive::new_with_diagnostics(Cursor::new(archive.clone())).unwrap();
+    assert_eq!(
+        diagnostics,
+        [ParseDiagnostic::EntryCountMismatch {
+            recorded: 4_464,
+            parsed: 70_000,
+        }]
+    );
+
+    let config = ZipReaderConfig::default().max_entries(Some(65_535));
+    assert!(ZipArchive::with_config(Cursor::new(archive), config).is_err());
+}
+
+#[test]
+fn fewer_entries_without_zip64() {
+    let archive = write_archive(65_534);
+    let end = archive.len() - 22;
+    assert_eq!(archive[end + 8..end + 10], 65_534u16.to_le_bytes());
+    assert_ne!(archive[end - 20..end - 16], ZIP64_LOCATOR_SIGNATURE);
+    assert_eq!(ZipArchive::new(Cursor::new(archive)).unwrap().len(), 65_534);
+}
+
+#[cfg(feature = "tokio")]
+#[test]
+fn more_than_65535_entries_streaming() {
+    use zip::write::tokio::ZipStreamWriter;
+
+    let archive = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap()
+        .block_on(async {
+            let options = FileOptions::default().compression_method(CompressionMethod::Stored);
+            let mut writer = ZipStreamWriter::new(Vec::new());
+            for i in 0..70_000 {
+                writer.start_file(format!("{i}"), options).await?;
+            }
+            writer.finish().await
+        })
+        .unwrap();
+    assert_zip64_count(archive, 70_000);
+}
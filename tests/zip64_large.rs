@@ -53,7 +53,7 @@
 // 22c400260  00 00 50 4b 05 06 00 00  00 00 03 00 03 00 27 01  |..PK..........'.|
 // 22c400270  00 00 ff ff ff ff 00 00                           |........|
 // 22c400278
-use std::io::{self, Read, Seek, SeekFrom};
+use std::io::{self, Read, Seek, SeekFrom, Write};
 
 const BLOCK1_LENGTH: u64 = 0x60;
 const BLOCK1: [u8; BLOCK1_LENGTH as usize] = [
@@ -209,3 +209,277 @@ fn zip64_large() {
         };
     }
 }
+
+/// A file starting with `base` zero bytes, which aren't stored, followed by the written data
+struct SparseFile {
+    base: u64,
+    data: Vec<u8>,
+    pointer: u64,
+}
+
+impl SparseFile {
+    fn new(base: u64) -> Self {
+        SparseFile {
+            base,
+            data: Vec::new(),
+            pointer: base,
+        }
+    }
+
+    fn len(&self) -> u64 {
+        self.base + self.data.len() as u64
+    }
+}
+
+impl Seek for SparseFile {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let pointer = match pos {
+            SeekFrom::Start(offset) => offset as i64,
+            SeekFrom::End(offset) => self.len() as i64 + offset,
+            SeekFrom::Current(offset) => self.pointer as i64 + offset,
+        };
+        if pointer < 0 {
+            return Err(io::Error::new(io::ErrorKind::Other, "Invalid seek offset"));
+        }
+        self.pointer = pointer as u64;
+        Ok(self.pointer)
+    }
+}
+
+impl Read for SparseFile {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let length = if self.pointer < self.base {
+            let length = buf.len().min((self.base - self.pointer) as usize);
+            buf[..length].fill(0);
+            length
+        } else {
+            let start = ((self.pointer - self.base) as usize).min(self.data.len());
+            let length = buf.len().min(self.data.len() - start);
+            buf[..length].copy_from_slice(&self.data[start..start + length]);
+            length
+        };
+        self.pointer += length as u64;
+        Ok(length)
+    }
+}
+
+impl Write for SparseFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.pointer < self.base {
+            return Err(io::Error::new(io::ErrorKind::Other, "Writing in the zeros"));
+        }
+        let start = (self.pointer - self.base) as usize;
+        if self.data.len() < start + buf.len() {
+            self.data.resize(start + buf.len(), 0);
+        }
+        self.data[start..start + buf.len()].copy_from_slice(buf);
+        self.pointer += buf.len() as u64;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn central_directory_beyond_4_gib() {
+    // Each file takes 139 bytes, so that with the first base, the central directory starts at
+    // 0xFFFFFFFF, which is the placeholder for ZIP64
+    for base in [0xffff_ffff - 4 * 139, 0xffff_ff00, 0x1_0000_0000] {
+        let mut writer = zip::ZipWriter::new(SparseFile::new(base));
+        for i in 0..4 {
+            writer
+                .start_file(
+                    format!("file{i}.txt"),
+                    zip::write::FileOptions::default()
+                        .compression_method(zip::CompressionMethod::Stored),
+                )
+                .unwrap();
+            writer.write_all(&[b'a' + i; 100]).unwrap();
+        }
+        let file = writer.finish().unwrap();
+        let end = file.data.len() - 22;
+        assert_eq!(file.data[end + 16..end + 20], [0xff; 4]);
+        assert_eq!(file.data[end - 20..end - 16], [0x50, 0x4b, 0x06, 0x07]);
+
+        let mut archive = zip::ZipArchive::new(file).unwrap();
+        assert_eq!(archive.len(), 4);
+        for i in 0..4 {
+            let mut data = Vec::new();
+            archive
+                .by_name(&format!("file{i}.txt"))
+                .unwrap()
+                .read_to_end(&mut data)
+                .unwrap();
+            assert_eq!(data, [b'a' + i; 100]);
+        }
+    }
+}
+
+/// A file that only keeps the data written in short writes, and reads zeros everywhere else
+///
+/// Long writes must be zeros, which isn't checked. The later writes win where they overlap, so
+/// that headers can be patched.
+#[derive(Default)]
+struct ZeroFilledFile {
+    writes: Vec<(u64, Vec<u8>)>,
+    len: u64,
+    pointer: u64,
+}
+
+impl ZeroFilledFile {
+    /// The length from which writes aren't kept
+    const LONG_WRITE: usize = 4096;
+}
+
+impl Seek for ZeroFilledFile {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let pointer = match pos {
+            SeekFrom::Start(offset) => offset as i64,
+            SeekFrom::End(offset) => self.len as i64 + offset,
+            SeekFrom::Current(offset) => self.pointer as i64 + offset,
+        };
+        if pointer < 0 {
+            return Err(io::Error::new(io::ErrorKind::Other, "Invalid seek offset"));
+        }
+        self.pointer = pointer as u64;
+        Ok(self.pointer)
+    }
+}
+
+impl Read for ZeroFilledFile {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let length = buf
+            .len()
+            .min(self.len.saturating_sub(self.pointer) as usize);
+        let (start, end) = (self.pointer, self.pointer + length as u64);
+        buf[..length].fill(0);
+        for (offset, data) in &self.writes {
+            let data_end = offset + data.len() as u64;
+            if *offset < end && data_end > start {
+                let from = offset.max(&start);
+                let to = data_end.min(end);
+                buf[(from - start) as usize..(to - start) as usize]
+                    .copy_from_slice(&data[(from - offset) as usize..(to - offset) as usize]);
+            }
+        }
+        self.pointer = end;
+        Ok(length)
+    }
+}
+
+impl Write for ZeroFilledFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if buf.len() < Self::LONG_WRITE {
+            self.writes.push((self.pointer, buf.to_vec()));
+        }
+        self.pointer += buf.len() as u64;
+        self.len = self.len.max(self.pointer);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Write 4 GiB of zeros, one byte more than fits in 32 bits, to `writer`
+fn write_4_gib<W: Write>(writer: &mut W) -> io::Result<()> {
+    let zeros = vec![0; 1 << 20];
+    for _ in 0..1 << 12 {
+        writer.write_all(&zeros)?;
+    }
+    Ok(())
+}
+
+/// Write a file of 4 GiB and a small one with `options`, and read them back without seeking
+fn check_large_file_streamed(options: zip::write::FileOptions) {
+    let mut writer = zip::ZipWriter::new(ZeroFilledFile::default());
+    writer.start_file("zeros", options).unwrap();
+    write_4_gib(&mut writer).unwrap();
+    writer.start_file("small.txt", options).unwrap();
+    writer.write_all(b"small").unwrap();
+    let mut file = writer.finish().unwrap();
+
+    file.seek(SeekFrom::Start(0)).unwrap();
+    let mut reader = io::BufReader::with_capacity(1 << 20, file);
+    let mut large = zip::read::read_zipfile_from_stream(&mut reader)
+        .unwrap()
+        .unwrap();
+    assert_eq!(large.name(), "zeros");
+    assert_eq!(large.size(), 1 << 32);
+    assert_eq!(io::copy(&mut large, &mut io::sink()).unwrap(), 1 << 32);
+    drop(large);
+    let mut small = zip::read::read_zipfile_from_stream(&mut reader)
+        .unwrap()
+        .unwrap();
+    let mut contents = String::new();
+    small.read_to_string(&mut contents).unwrap();
+    assert_eq!(contents, "small");
+    drop(small);
+
+    let mut archive = zip::ZipArchive::new(reader.into_inner()).unwrap();
+    assert_eq!(archive.by_name("zeros").unwrap().size(), 1 << 32);
+    contents.clear();
+    archive
+        .by_name("small.txt")
+        .unwrap()
+        .read_to_string(&mut contents)
+        .unwrap();
+    assert_eq!(contents, "small");
+}
+
+#[test]
+fn large_file_streamed() {
+    let options =
+        zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Stored);
+    check_large_file_streamed(options.large_file(true));
+}
+
+#[test]
+fn large_file_switched_to_zip64() {
+    // The room reserved in the local header becomes its ZIP64 extra field
+    let options =
+        zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Stored);
+    check_large_file_streamed(options);
+}
+
+#[test]
+fn large_file_without_seeking() {
+    let options =
+        zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Stored);
+    let mut writer = zip::ZipWriter::new_streaming(ZeroFilledFile::default());
+    writer.start_file("zeros", options).unwrap();
+    write_4_gib(&mut writer).unwrap();
+    writer.start_file("small.txt", options).unwrap();
+    writer.write_all(b"small").unwrap();
+    let file = writer.finish().unwrap().into_inner();
+
+    // The second file and the central directory start past 4 GiB
+    let mut archive = zip::ZipArchive::new(file).unwrap();
+    let mut large = archive.by_name("zeros").unwrap();
+    assert_eq!(large.size(), 1 << 32);
+    assert_eq!(io::copy(&mut large, &mut io::sink()).unwrap(), 1 << 32);
+    drop(large);
+    let mut small = archive.by_name("small.txt").unwrap();
+    assert!(small.header_start() > 1 << 32);
+    let mut contents = String::new();
+    small.read_to_string(&mut contents).unwrap();
+    assert_eq!(contents, "small");
+}
+
+#[test]
+fn large_file_option_false() {
+    let mut writer = zip::ZipWriter::new(ZeroFilledFile::default());
+    writer
+        .start_file(
+            "zeros",
+            zip::write::FileOptions::default()
+                .compression_method(zip::CompressionMethod::Stored)
+                .large_file(false),
+        )
+        .unwrap();
+    let error = write_4_gib(&mut writer).unwrap_err();
+    assert_eq!(error.to_string(), "Large file option has not been set");
+}
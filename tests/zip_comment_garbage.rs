@@ -17,8 +17,10 @@
 // 00000020  6e 74 20 62 6c 61 20 62  6c 61 20 62 6c 61        |nt bla bla bla|
 // 0000002e
 
-use std::io;
-use zip::ZipArchive;
+use std::io::{self, Read, Write};
+use zip::read::{ParseDiagnostic, ZipReaderConfig};
+use zip::write::FileOptions;
+use zip::{ZipArchive, ZipWriter};
 
 #[test]
 fn correctly_handle_zip_with_garbage_after_comment() {
@@ -28,3 +30,54 @@ fn correctly_handle_zip_with_garbage_after_comment() {
 
     assert_eq!(archive.comment(), "short.".as_bytes());
 }
+
+/// Random bytes, as appended by padded uploads or signing tools, which happen to contain an end
+/// of central directory signature
+const TRAILING: [u8; 16] = [
+    0x3d, 0x8a, 0x50, 0x4b, 0x05, 0x06, 0xe1, 0x07, 0x9c, 0x42, 0x00, 0xb6, 0x1f, 0xd4, 0x6e, 0x93,
+];
+
+fn archive_with_trailing_data(comment: &str) -> Vec<u8> {
+    let mut writer = ZipWriter::new(io::Cursor::new(Vec::new()));
+    writer.set_comment(comment);
+    writer
+        .start_file("file.txt", FileOptions::default())
+        .unwrap();
+    writer.write_all(b"contents").unwrap();
+    let mut v = writer.finish().unwrap().into_inner();
+    v.extend_from_slice(&TRAILING);
+    v
+}
+
+#[test]
+fn trailing_random_bytes() {
+    for comment in ["", "archive comment"] {
+        let v = archive_with_trailing_data(comment);
+        let (mut archive, diagnostics) =
+            ZipArchive::new_with_diagnostics(io::Cursor::new(v)).unwrap();
+        assert_eq!(archive.comment(), comment.as_bytes());
+        assert_eq!(diagnostics, [ParseDiagnostic::TrailingData { length: 16 }]);
+        let mut data = String::new();
+        archive
+            .by_name("file.txt")
+            .unwrap()
+            .read_to_string(&mut data)
+            .unwrap();
+        assert_eq!(data, "contents");
+    }
+
+    let mut v = include_bytes!("../tests/data/files_and_dirs.zip").to_vec();
+    v.extend_from_slice(&TRAILING);
+    let archive = ZipArchive::new(io::Cursor::new(v)).unwrap();
+    assert_eq!(archive.len(), 3);
+    assert_eq!(archive.comment(), b"");
+}
+
+#[test]
+fn trailing_bytes_limit() {
+    let v = archive_with_trailing_data("archive comment");
+    let config = ZipReaderConfig::default().max_trailing_bytes(Some(16));
+    assert!(ZipArchive::with_config(io::Cursor::new(v.clone()), config).is_ok());
+    let config = ZipReaderConfig::default().max_trailing_bytes(Some(15));
+    assert!(ZipArchive::with_config(io::Cursor::new(v), config).is_err());
+}
@@ -22,20 +22,24 @@ use std::io::Read;
 
 #[test]
 fn encrypting_file() {
-    use zip::unstable::write::FileOptionsExt;
     use std::io::{Read, Write};
+    use zip::unstable::write::FileOptionsExt;
     let mut buf = vec![0; 2048];
     let mut archive = zip::write::ZipWriter::new(std::io::Cursor::new(&mut buf));
-    archive.start_file("name", zip::write::FileOptions::default().with_deprecated_encryption(b"password")).unwrap();
+    archive
+        .start_file(
+            "name",
+            zip::write::FileOptions::default().with_deprecated_encryption(b"password"),
+        )
+        .unwrap();
     archive.write_all(b"test").unwrap();
     archive.finish().unwrap();
     drop(archive);
     let mut archive = zip::ZipArchive::new(std::io::Cursor::new(&mut buf)).unwrap();
-    let mut file = archive.by_index_decrypt(0, b"password").unwrap().unwrap();
+    let mut file = archive.by_index_decrypt(0, b"password").unwrap();
     let mut buf = Vec::new();
     file.read_to_end(&mut buf).unwrap();
     assert_eq!(buf, b"test");
-
 }
 #[test]
 fn encrypted_file() {
@@ -78,20 +82,17 @@ fn encrypted_file() {
         // Wrong password
         let file = archive.by_index_decrypt(0, b"wrong password");
         match file {
-            Ok(Err(zip::result::InvalidPassword)) => (),
+            Err(zip::result::ZipError::InvalidPassword) => (),
             Err(_) => panic!(
                 "Expected InvalidPassword error when opening encrypted file with wrong password"
             ),
-            Ok(Ok(_)) => panic!("Error: Successfully opened encrypted file with wrong password?!"),
+            Ok(_) => panic!("Error: Successfully opened encrypted file with wrong password?!"),
         }
     }
 
     {
         // Correct password, read contents
-        let mut file = archive
-            .by_index_decrypt(0, "test".as_bytes())
-            .unwrap()
-            .unwrap();
+        let mut file = archive.by_index_decrypt(0, "test".as_bytes()).unwrap();
         let file_name = file.enclosed_name().unwrap();
         assert_eq!(file_name, std::path::PathBuf::from("test.txt"));
 
@@ -100,3 +101,211 @@ fn encrypted_file() {
         assert_eq!(data, "abcdefghijklmnopqrstuvwxyz123456789".as_bytes());
     }
 }
+
+#[test]
+fn encrypted_file_any_password() {
+    // Same archive as in `encrypted_file`
+    let zip_file_bytes = Cursor::new(vec![
+        0x50, 0x4b, 0x03, 0x04, 0x14, 0x00, 0x01, 0x00, 0x00, 0x00, 0x54, 0xbd, 0xb5, 0x50, 0x2f,
+        0x20, 0x79, 0x55, 0x2f, 0x00, 0x00, 0x00, 0x23, 0x00, 0x00, 0x00, 0x08, 0x00, 0x00, 0x00,
+        0x74, 0x65, 0x73, 0x74, 0x2e, 0x74, 0x78, 0x74, 0xca, 0x2d, 0x1d, 0x27, 0x19, 0x19, 0x63,
+        0x43, 0x77, 0x9a, 0x71, 0x76, 0xc9, 0xec, 0xd1, 0x6f, 0xd9, 0xf5, 0x22, 0x67, 0xb3, 0x8f,
+        0x52, 0xb5, 0x41, 0xbc, 0x5c, 0x36, 0xf2, 0x1d, 0x84, 0xc3, 0xc0, 0x28, 0x3b, 0xfd, 0xe1,
+        0x70, 0xc2, 0xcc, 0x0c, 0x11, 0x0c, 0xc5, 0x95, 0x2f, 0xa4, 0x50, 0x4b, 0x01, 0x02, 0x3f,
+        0x00, 0x14, 0x00, 0x01, 0x00, 0x00, 0x00, 0x54, 0xbd, 0xb5, 0x50, 0x2f, 0x20, 0x79, 0x55,
+        0x2f, 0x00, 0x00, 0x00, 0x23, 0x00, 0x00, 0x00, 0x08, 0x00, 0x24, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x20, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x74, 0x65, 0x73, 0x74,
+        0x2e, 0x74, 0x78, 0x74, 0x0a, 0x00, 0x20, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x18,
+        0x00, 0x31, 0xb2, 0x3b, 0xbf, 0xb8, 0x2f, 0xd6, 0x01, 0x31, 0xb2, 0x3b, 0xbf, 0xb8, 0x2f,
+        0xd6, 0x01, 0xa8, 0xc4, 0x45, 0xbd, 0xb8, 0x2f, 0xd6, 0x01, 0x50, 0x4b, 0x05, 0x06, 0x00,
+        0x00, 0x00, 0x00, 0x01, 0x00, 0x01, 0x00, 0x5a, 0x00, 0x00, 0x00, 0x55, 0x00, 0x00, 0x00,
+        0x00, 0x00,
+    ]);
+    let mut archive = zip::ZipArchive::new(zip_file_bytes).unwrap();
+
+    // `tj` is wrong, but passes the check against the encryption header
+    assert!(archive.by_index_decrypt(0, b"tj").is_ok());
+
+    let passwords: &[&[u8]] = &[b"wrong password", b"tj", b"test"];
+    assert_eq!(archive.find_password(0, passwords).unwrap(), Some(2));
+    assert_eq!(archive.find_password(0, &passwords[..2]).unwrap(), None);
+
+    let mut file = archive.by_name_decrypt_any("test.txt", passwords).unwrap();
+    let mut data = Vec::new();
+    file.read_to_end(&mut data).unwrap();
+    assert_eq!(data, "abcdefghijklmnopqrstuvwxyz123456789".as_bytes());
+    drop(file);
+
+    assert!(matches!(
+        archive.by_index_decrypt_any(0, &passwords[..2]),
+        Err(zip::result::ZipError::InvalidPassword)
+    ));
+}
+
+#[test]
+fn encrypted_file_owned_password() {
+    use std::cell::Cell;
+    use std::rc::Rc;
+    use zip::unstable::write::FileOptionsExt;
+
+    /// A password wrapper in the style of secret-holding types, which records when it is dropped
+    struct Password {
+        bytes: Vec<u8>,
+        dropped: Rc<Cell<bool>>,
+    }
+
+    impl AsRef<[u8]> for Password {
+        fn as_ref(&self) -> &[u8] {
+            &self.bytes
+        }
+    }
+
+    impl Drop for Password {
+        fn drop(&mut self) {
+            self.dropped.set(true);
+        }
+    }
+
+    let mut buf = Vec::new();
+    let mut archive = zip::write::ZipWriter::new(Cursor::new(&mut buf));
+    archive
+        .start_file(
+            "name",
+            zip::write::FileOptions::default().with_deprecated_encryption(b"password"),
+        )
+        .unwrap();
+    std::io::Write::write_all(&mut archive, b"test").unwrap();
+    archive.finish().unwrap();
+    drop(archive);
+
+    let dropped = Rc::new(Cell::new(false));
+    let password = Password {
+        bytes: b"password".to_vec(),
+        dropped: dropped.clone(),
+    };
+    let mut archive = zip::ZipArchive::new(Cursor::new(&mut buf)).unwrap();
+    let mut file = archive.by_index_decrypt(0, password).unwrap();
+    // The password is not kept around once the entry is decrypting
+    assert!(dropped.get());
+
+    let mut data = Vec::new();
+    file.read_to_end(&mut data).unwrap();
+    assert_eq!(data, b"test");
+}
+
+#[test]
+fn encrypted_file_with_keys() {
+    use std::io::Write;
+    use zip::read::ZipCryptoKeys;
+    use zip::unstable::write::FileOptionsExt;
+
+    let mut buf = Vec::new();
+    let mut archive = zip::write::ZipWriter::new(Cursor::new(&mut buf));
+    archive
+        .start_file(
+            "name",
+            zip::write::FileOptions::default().with_deprecated_encryption(b"test"),
+        )
+        .unwrap();
+    archive
+        .write_all(b"decrypted without the password")
+        .unwrap();
+    archive.finish().unwrap();
+    drop(archive);
+    let mut archive = zip::ZipArchive::new(Cursor::new(&mut buf)).unwrap();
+
+    // The internal keys after processing the password `test`
+    let recovered = ZipCryptoKeys::new(0x7712f9de, 0x485afd7b, 0x5ded1e32);
+    for keys in [ZipCryptoKeys::derive(b"test"), recovered] {
+        let mut file = archive.by_index_decrypt_with_keys(0, keys).unwrap();
+        let mut data = Vec::new();
+        file.read_to_end(&mut data).unwrap();
+        assert_eq!(data, b"decrypted without the password");
+    }
+
+    let wrong = ZipCryptoKeys::new(0x12345678, 0x23456789, 0x34567890);
+    assert!(matches!(
+        archive.by_index_decrypt_with_keys(0, wrong),
+        Err(zip::result::ZipError::InvalidPassword)
+    ));
+}
+
+#[test]
+fn encrypted_file_streaming() {
+    use std::io::Write;
+    use zip::read::{read_zipfile_from_stream, read_zipfile_from_stream_decrypt};
+    use zip::unstable::write::FileOptionsExt;
+
+    let mut buf = Vec::new();
+    let mut archive = zip::write::ZipWriter::new(Cursor::new(&mut buf));
+    let options = zip::write::FileOptions::default();
+    archive
+        .start_file("encrypted", options.with_deprecated_encryption(b"password"))
+        .unwrap();
+    archive.write_all(b"encrypted contents").unwrap();
+    archive.start_file("plain", options).unwrap();
+    archive.write_all(b"plain contents").unwrap();
+    archive.finish().unwrap();
+    drop(archive);
+
+    let mut stream = buf.as_slice();
+    for expected in [&b"encrypted contents"[..], b"plain contents"] {
+        let mut file = read_zipfile_from_stream_decrypt(&mut stream, b"password")
+            .unwrap()
+            .unwrap();
+        let mut data = Vec::new();
+        file.read_to_end(&mut data).unwrap();
+        assert_eq!(data, expected);
+    }
+    assert!(read_zipfile_from_stream(&mut stream).unwrap().is_none());
+
+    let mut stream = buf.as_slice();
+    assert!(matches!(
+        read_zipfile_from_stream_decrypt(&mut stream, b"wrong"),
+        Err(zip::result::ZipError::InvalidPassword)
+    ));
+    let mut stream = buf.as_slice();
+    assert!(read_zipfile_from_stream(&mut stream).is_err());
+}
+
+#[test]
+fn encrypted_file_raw_copy() {
+    // Same archive as in `encrypted_file`
+    let zip_file_bytes = Cursor::new(vec![
+        0x50, 0x4b, 0x03, 0x04, 0x14, 0x00, 0x01, 0x00, 0x00, 0x00, 0x54, 0xbd, 0xb5, 0x50, 0x2f,
+        0x20, 0x79, 0x55, 0x2f, 0x00, 0x00, 0x00, 0x23, 0x00, 0x00, 0x00, 0x08, 0x00, 0x00, 0x00,
+        0x74, 0x65, 0x73, 0x74, 0x2e, 0x74, 0x78, 0x74, 0xca, 0x2d, 0x1d, 0x27, 0x19, 0x19, 0x63,
+        0x43, 0x77, 0x9a, 0x71, 0x76, 0xc9, 0xec, 0xd1, 0x6f, 0xd9, 0xf5, 0x22, 0x67, 0xb3, 0x8f,
+        0x52, 0xb5, 0x41, 0xbc, 0x5c, 0x36, 0xf2, 0x1d, 0x84, 0xc3, 0xc0, 0x28, 0x3b, 0xfd, 0xe1,
+        0x70, 0xc2, 0xcc, 0x0c, 0x11, 0x0c, 0xc5, 0x95, 0x2f, 0xa4, 0x50, 0x4b, 0x01, 0x02, 0x3f,
+        0x00, 0x14, 0x00, 0x01, 0x00, 0x00, 0x00, 0x54, 0xbd, 0xb5, 0x50, 0x2f, 0x20, 0x79, 0x55,
+        0x2f, 0x00, 0x00, 0x00, 0x23, 0x00, 0x00, 0x00, 0x08, 0x00, 0x24, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x20, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x74, 0x65, 0x73, 0x74,
+        0x2e, 0x74, 0x78, 0x74, 0x0a, 0x00, 0x20, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x18,
+        0x00, 0x31, 0xb2, 0x3b, 0xbf, 0xb8, 0x2f, 0xd6, 0x01, 0x31, 0xb2, 0x3b, 0xbf, 0xb8, 0x2f,
+        0xd6, 0x01, 0xa8, 0xc4, 0x45, 0xbd, 0xb8, 0x2f, 0xd6, 0x01, 0x50, 0x4b, 0x05, 0x06, 0x00,
+        0x00, 0x00, 0x00, 0x01, 0x00, 0x01, 0x00, 0x5a, 0x00, 0x00, 0x00, 0x55, 0x00, 0x00, 0x00,
+        0x00, 0x00,
+    ]);
+    let mut source = zip::ZipArchive::new(zip_file_bytes).unwrap();
+    let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+    writer
+        .raw_copy_file(source.by_index_raw(0).unwrap())
+        .unwrap();
+    let mut copy = zip::ZipArchive::new(writer.finish().unwrap()).unwrap();
+
+    let source = source.by_index_raw(0).unwrap();
+    let mut file = copy.by_index_decrypt(0, b"test").unwrap();
+    assert_eq!(file.name(), "test.txt");
+    assert_eq!(file.crc32(), source.crc32());
+    assert_eq!(
+        file.last_modified().timepart(),
+        source.last_modified().timepart()
+    );
+    // The NTFS timestamps are kept
+    assert_eq!(file.extra_data(), source.extra_data());
+
+    let mut data = Vec::new();
+    file.read_to_end(&mut data).unwrap();
+    assert_eq!(data, "abcdefghijklmnopqrstuvwxyz123456789".as_bytes());
+}
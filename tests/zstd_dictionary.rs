@@ -0,0 +1,85 @@
+#![cfg(feature = "zstd")]
+
+use std::io::{Cursor, Read, Write};
+use std::sync::Arc;
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+fn samples() -> Vec<Vec<u8>> {
+    (0..500)
+        .map(|i| {
+            format!(
+                r#"{{"id": {i}, "name": "user-{}", "email": "user{}@example.com", "active": {}, "roles": ["reader", "writer"], "created": "2023-07-{:02}T12:00:00Z"}}"#,
+                i * 7919 % 1000,
+                i * 104729 % 10000,
+                i % 3 == 0,
+                i % 28 + 1
+            )
+            .into_bytes()
+        })
+        .collect()
+}
+
+fn write_archive(samples: &[Vec<u8>], dictionary: Option<&[u8]>) -> Vec<u8> {
+    let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+    let mut options = FileOptions::default().compression_method(CompressionMethod::Zstd);
+    if let Some(dictionary) = dictionary {
+        options = options.zstd_dictionary(dictionary);
+    }
+    for (i, sample) in samples.iter().enumerate() {
+        writer.start_file(format!("{i}.json"), options).unwrap();
+        writer.write_all(sample).unwrap();
+    }
+    writer.finish().unwrap().into_inner()
+}
+
+fn compressed_size(archive: &mut ZipArchive<Cursor<Vec<u8>>>) -> u64 {
+    (0..archive.len())
+        .map(|i| archive.by_index_raw(i).unwrap().compressed_size())
+        .sum()
+}
+
+#[test]
+fn zstd_dictionary_round_trip() {
+    let samples = samples();
+    let dictionary = zstd::dict::from_samples(&samples, 4096).unwrap();
+
+    let plain = write_archive(&samples, None);
+    let with_dictionary = write_archive(&samples, Some(&dictionary));
+    let mut plain = ZipArchive::new(Cursor::new(plain)).unwrap();
+    let mut archive = ZipArchive::new(Cursor::new(with_dictionary)).unwrap();
+    assert!(compressed_size(&mut archive) * 2 < compressed_size(&mut plain));
+
+    // Without the dictionary, the entries can't be decompressed
+    let mut data = Vec::new();
+    assert!(archive
+        .by_name("0.json")
+        .unwrap()
+        .read_to_end(&mut data)
+        .is_err());
+
+    archive
+        .set_zstd_dictionary(Arc::from(dictionary.as_slice()))
+        .unwrap();
+    for (i, sample) in samples.iter().enumerate() {
+        let mut data = Vec::new();
+        archive
+            .by_name(&format!("{i}.json"))
+            .unwrap()
+            .read_to_end(&mut data)
+            .unwrap();
+        assert_eq!(&data, sample);
+    }
+
+    // Entries compressed without a dictionary can still be read
+    plain
+        .set_zstd_dictionary(Arc::from(dictionary.as_slice()))
+        .unwrap();
+    let mut data = Vec::new();
+    plain
+        .by_name("0.json")
+        .unwrap()
+        .read_to_end(&mut data)
+        .unwrap();
+    assert_eq!(data, samples[0]);
+}